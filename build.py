@@ -148,6 +148,7 @@ def build_windows(name: str, target: str):
 
     # Copy files
     copy_bin(staging_path, target, f"{bin_name}.exe")
+    copy_bin(staging_path, target, f"{bin_name}w.exe")
 
     # Create archive
     create_zip(f"{app_name}-{version}-{name}", staging_path)
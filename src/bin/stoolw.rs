@@ -0,0 +1,82 @@
+//! Background launcher, for use as a launch wrapper such as in Steam launch options:
+//! `stoolw <game> %command%` runs `stool run-game --background <game> %command%`.
+//! It is built for the Windows GUI subsystem, so unlike stool itself,
+//! no console window is ever shown. Errors are shown in a message box instead.
+
+#![cfg_attr(windows, windows_subsystem = "windows")]
+
+use std::{
+    env,
+    path::PathBuf,
+    process::{Command, ExitCode, Stdio},
+};
+
+fn main() -> ExitCode {
+    let output = stool_command()
+        .and_then(|mut command| {
+            command
+                .args(["run-game", "--background"])
+                .args(env::args_os().skip(1))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .output()
+        })
+        .map_err(|err| err.to_string());
+
+    let error = match &output {
+        Ok(output) if output.status.success() || output.stderr.is_empty() => None,
+        Ok(output) => Some(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+        Err(err) => Some(format!("Error running stool: {err}")),
+    };
+
+    if let Some(error) = error {
+        notify_error(&error);
+    }
+
+    match output {
+        Ok(output) => ExitCode::from(output.status.code().map_or(1, |code| u8::try_from(code).unwrap_or(1))),
+        Err(_) => ExitCode::FAILURE,
+    }
+}
+
+/// Command running the stool executable beside this one, without a console window
+fn stool_command() -> Result<Command, std::io::Error> {
+    let exe_path: PathBuf = env::current_exe()?.with_file_name(format!("stool{}", env::consts::EXE_SUFFIX));
+
+    #[allow(unused_mut)]
+    let mut command = Command::new(exe_path);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    Ok(command)
+}
+
+#[cfg(windows)]
+fn notify_error(message: &str) {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR, MB_OK};
+
+    let wide = |s: &str| s.encode_utf16().chain(std::iter::once(0)).collect::<Vec<u16>>();
+    let (message, caption) = (wide(message), wide("stool"));
+
+    // SAFETY: Both strings are null-terminated and outlive the call.
+    unsafe {
+        MessageBoxW(
+            std::ptr::null_mut(),
+            message.as_ptr(),
+            caption.as_ptr(),
+            MB_OK | MB_ICONERROR,
+        );
+    }
+}
+
+#[cfg(not(windows))]
+fn notify_error(message: &str) {
+    eprintln!("{message}");
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use crate::{
+    engine::{adopt, fsck, EngineArgs},
+    headless::LogUiHandler,
+    internal::sync,
+};
+
+pub fn adopt(engine_args: EngineArgs, dir: &Path, password_file: Option<&Path>) -> Result<(), anyhow::Error> {
+    // Backups appearing while the engine is running could interfere with pruning
+    if fsck::engine_is_running(&engine_args) {
+        return Err(anyhow::anyhow!(
+            "Engine is running for '{}', not adopting",
+            engine_args.name
+        ));
+    }
+
+    if let Some(password_file) = password_file {
+        super::use_password_file(&engine_args, password_file)?;
+    }
+
+    let report = sync::with_sync_ui(LogUiHandler::new(), |events| {
+        adopt::adopt_backups(&engine_args, dir, events)
+    })?;
+
+    for name in report.adopted.iter() {
+        println!("Adopted: {name}");
+    }
+
+    for skipped in report.skipped.iter() {
+        eprintln!("{skipped}");
+    }
+
+    println!(
+        "{} backups adopted, {} skipped.",
+        report.adopted.len(),
+        report.skipped.len()
+    );
+
+    Ok(())
+}
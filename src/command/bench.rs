@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use crate::{
+    engine::{bench, EngineArgs},
+    headless::LogUiHandler,
+    internal::{format::format_bytes, sync},
+};
+
+pub fn bench(engine_args: EngineArgs) -> Result<(), anyhow::Error> {
+    let report = sync::with_sync_ui(LogUiHandler::new(), |events| bench::run(&engine_args, events))?;
+
+    println!(
+        "Save data: {} files, {}",
+        report.file_count,
+        format_bytes(report.total_size)
+    );
+    println!("Scan:      {}", format_time(report.scan_time));
+    println!(
+        "Copy:      {} ({})",
+        format_time(report.copy_time),
+        throughput(report.total_size, report.copy_time)
+    );
+    println!(
+        "Hash:      {} ({})",
+        format_time(report.hash_time),
+        throughput(report.total_size, report.hash_time)
+    );
+    println!();
+    println!("Compression:");
+
+    for c in report.compression.iter() {
+        match &c.result {
+            Ok((time, size)) => println!(
+                "  {:<4} level {:>2}: {:>10} in {:>8}, ratio {:.1}%",
+                c.format,
+                c.level,
+                format_bytes(*size),
+                format_time(*time),
+                ratio(*size, report.total_size)
+            ),
+            Err(err) => println!("  {:<4} level {:>2}: failed: {err}", c.format, c.level),
+        }
+    }
+
+    if let Some(c) = report.recommendation() {
+        println!();
+        println!("Recommended: {} level {}", c.format, c.level);
+        println!(
+            "Set it in the main config with:\n\n[archive]\nformat = \"{}\"\nlevel = {}",
+            c.format, c.level
+        );
+    }
+
+    Ok(())
+}
+
+fn throughput(bytes: u64, time: Duration) -> String {
+    let secs = time.as_secs_f64();
+
+    if secs == 0.0 {
+        return "-".to_owned();
+    }
+
+    format!("{}/s", format_bytes((bytes as f64 / secs) as u64))
+}
+
+fn ratio(size: u64, total_size: u64) -> f64 {
+    if total_size == 0 {
+        return 0.0;
+    }
+
+    size as f64 / total_size as f64 * 100.0
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.2}s", time.as_secs_f64())
+}
@@ -0,0 +1,26 @@
+use std::process::ExitCode;
+
+use crate::{config::game::GameConfig, engine::EngineArgs};
+
+pub fn check(engine_args: EngineArgs) -> Result<ExitCode, anyhow::Error> {
+    let (config, unknown_keys) = GameConfig::from_file_with_unknown_keys(&engine_args.game_config_file_path())?;
+
+    let issues: Vec<String> = unknown_keys
+        .iter()
+        .map(|k| k.to_string())
+        .chain(config.warnings())
+        .collect();
+
+    if issues.is_empty() {
+        println!("No issues found.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for issue in issues.iter() {
+        println!("{issue}");
+    }
+
+    println!("{} issues found.", issues.len());
+
+    Ok(ExitCode::FAILURE)
+}
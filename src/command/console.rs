@@ -0,0 +1,144 @@
+//! Simple line-based commands driving a headless engine, read from standard input or a named pipe
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use tracing::{error, info};
+
+use crate::engine::{self, BackupKind, BackupRequest, EngineArgs, EngineControl, EngineState};
+
+use super::status::print_status;
+
+/// Command source standing for standard input
+pub const STDIN_SOURCE: &str = "-";
+
+const USAGE: &str = "Commands: backup <description>, toggle-auto, status";
+
+/// Read commands from a file or named pipe, or from standard input, until the source is exhausted.
+/// Named pipes are reopened whenever their writer closes them, until the engine shuts down.
+pub fn spawn_command_reader(source: PathBuf, engine_args: EngineArgs, control: EngineControl) {
+    std::thread::spawn(move || {
+        let result = if source == Path::new(STDIN_SOURCE) {
+            read_commands(io::stdin().lock(), &engine_args, &control)
+        } else {
+            loop {
+                let file = match fs::File::open(&source) {
+                    Ok(file) => file,
+                    Err(err) => break Err(err.into()),
+                };
+
+                if let Err(err) = read_commands(BufReader::new(file), &engine_args, &control) {
+                    break Err(err);
+                }
+
+                if !is_fifo(&source) || control.state() == EngineState::ShutDown {
+                    break Ok(());
+                }
+            }
+        };
+
+        if let Err(err) = result {
+            error!("Error reading commands from {}: {err}", source.display());
+        }
+    });
+}
+
+fn read_commands(reader: impl BufRead, engine_args: &EngineArgs, control: &EngineControl) -> Result<(), anyhow::Error> {
+    for line in reader.lines() {
+        if let Err(err) = run_command(line?.trim(), engine_args, control) {
+            error!("{err}");
+        }
+    }
+
+    Ok(())
+}
+
+fn run_command(line: &str, engine_args: &EngineArgs, control: &EngineControl) -> Result<(), anyhow::Error> {
+    let (command, arg) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(command, arg)| (command, arg.trim()));
+
+    match command {
+        "" => {}
+        "backup" => {
+            if arg.is_empty() {
+                return Err(anyhow::anyhow!("Usage: backup <description>"));
+            }
+
+            control.send(BackupRequest::CreateBackup {
+                archive_name: engine::make_backup_filename(arg, engine_args.archiver.extension()),
+                kind: BackupKind::Manual,
+            })?;
+        }
+        "toggle-auto" => {
+            let autobackup = !control.get_autobackup();
+            control.set_autobackup(autobackup);
+
+            info!("Auto-backup {}", if autobackup { "on" } else { "off" });
+        }
+        "status" => print_status(engine_args, &control.snapshot())?,
+        _ => return Err(anyhow::anyhow!("Unknown command: {command}. {USAGE}")),
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::engine::testing::{stop, Fixture, UiEvent};
+
+    #[test]
+    fn commands_drive_the_engine() {
+        let fixture = Fixture::new();
+        fixture.write_save("slot1.sav", "one");
+        let (engine, ui) = fixture.start();
+        let control = engine.control();
+        let autobackup = control.get_autobackup();
+
+        // Invalid commands are reported without stopping the reader
+        let commands = "bogus\n\nbackup\n  backup   Before boss  \ntoggle-auto\n";
+        read_commands(Cursor::new(commands), &fixture.args, &control).unwrap();
+
+        ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+        let UiEvent::BeginBackup(name) = &ui.events()[0] else {
+            panic!("Unexpected events: {:?}", ui.events());
+        };
+        assert!(name.ends_with(" Before boss.7z"), "{name}");
+        assert_eq!(control.get_autobackup(), !autobackup);
+
+        stop(engine);
+
+        // The backup command without a description was rejected
+        let backups = ui.events().into_iter().filter(|e| matches!(e, UiEvent::BeginBackup(_)));
+        assert_eq!(backups.count(), 1);
+    }
+
+    #[test]
+    fn unknown_commands_list_the_known_ones() {
+        let fixture = Fixture::new();
+        let (engine, _ui) = fixture.start();
+
+        let err = run_command("restore latest", &fixture.args, &engine.control()).unwrap_err();
+        assert_eq!(err.to_string(), format!("Unknown command: restore. {USAGE}"));
+
+        stop(engine);
+    }
+}
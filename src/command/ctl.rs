@@ -0,0 +1,44 @@
+use time::{format_description::BorrowedFormatItem, macros::format_description, UtcOffset};
+
+use crate::engine::{
+    control::{self, ControlRequest, ControlResponse},
+    EngineArgs,
+};
+
+const EVENT_TIME_FORMAT: &[BorrowedFormatItem<'static>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum CtlCommand {
+    #[clap(about = "List watched save paths with their watcher backend and event counts")]
+    Watches,
+}
+
+pub fn ctl(engine_args: EngineArgs, command: CtlCommand) -> Result<(), anyhow::Error> {
+    let output_path = engine_args.output_path();
+
+    match command {
+        CtlCommand::Watches => {
+            let ControlResponse::Watches(watches) = control::request(&output_path, ControlRequest::Watches)? else {
+                return Err(anyhow::anyhow!("Unexpected response from engine"));
+            };
+
+            let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+
+            for watch in watches.iter() {
+                let last_event = match watch.last_event_at {
+                    Some(at) => at.to_offset(offset).format(EVENT_TIME_FORMAT)?,
+                    None => "never".to_owned(),
+                };
+
+                println!("{}", watch.path.display());
+                println!(
+                    "  backend: {}, events: {}, ignored: {}, errors: {}, dropped: {}, last event: {last_event}",
+                    watch.backend, watch.events, watch.ignored, watch.errors, watch.dropped
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use crate::engine::{diff, EngineArgs};
+
+pub fn diff(engine_args: EngineArgs, old_archive: &str, new_archive: &str) -> Result<(), anyhow::Error> {
+    let diff = diff::diff_backups(&engine_args, old_archive, new_archive)?;
+
+    for item in diff.items.iter() {
+        println!("{}", item.describe());
+    }
+
+    println!("{}", diff.summary());
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::game::GameConfig,
+    engine::{backups, extract, EngineArgs},
+    headless::LogUiHandler,
+    internal::{format::format_bytes, sync},
+};
+
+pub fn extract(
+    engine_args: EngineArgs,
+    archive: &str,
+    dst: Option<PathBuf>,
+    password_file: Option<&Path>,
+) -> Result<(), anyhow::Error> {
+    if let Some(password_file) = password_file {
+        super::use_password_file(&engine_args, password_file)?;
+    }
+
+    let gcfg = GameConfig::from_file(&engine_args.game_config_file_path(), engine_args.strict_config)?;
+    let temp_path = engine_args.temp_path(&gcfg);
+    engine_args.archiver.set_temp_path(&temp_path);
+
+    let dst = match dst {
+        Some(dst) => dst,
+        None => {
+            let stem = backups::strip_archive_extension(archive).unwrap_or(archive);
+
+            temp_path.join(format!("stool-{}-{stem}", engine_args.name))
+        }
+    };
+
+    let report = sync::with_sync_ui(LogUiHandler::new(), |events| {
+        extract::extract_backup(&engine_args, archive, &dst, events)
+    })?;
+
+    println!("Extracted to: {}", dst.display());
+
+    let Some(manifest) = report.manifest else {
+        println!("Backup has no manifest, contents were not verified.");
+        return Ok(());
+    };
+
+    if !report.mismatches.is_empty() {
+        for mismatch in report.mismatches.iter() {
+            eprintln!("{mismatch}");
+        }
+
+        return Err(anyhow::anyhow!(
+            "{} of {} files failed verification",
+            report.mismatches.len(),
+            manifest.files.len()
+        ));
+    }
+
+    println!(
+        "Verified {} files ({})",
+        manifest.files.len(),
+        format_bytes(manifest.total_size())
+    );
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use tracing::error;
+
+use crate::engine::{fsck, EngineArgs};
+
+pub fn fsck(engine_args: EngineArgs, repair: bool) -> Result<(), anyhow::Error> {
+    let issues = fsck::check(&engine_args)?;
+
+    if issues.is_empty() {
+        println!("No issues found.");
+        return Ok(());
+    }
+
+    for issue in issues.iter() {
+        println!("{issue}");
+    }
+
+    if !repair {
+        println!("{} issues found. Run with --repair to fix them.", issues.len());
+        return Ok(());
+    }
+
+    // Repairing while the engine is running could interfere with an ongoing backup
+    if fsck::engine_is_running(&engine_args) {
+        return Err(anyhow::anyhow!(
+            "Engine is running for '{}', not repairing",
+            engine_args.name
+        ));
+    }
+
+    let mut repaired = 0;
+
+    for issue in issues.iter().filter(|i| i.is_repairable()) {
+        match issue.repair(&engine_args) {
+            Ok(_) => repaired += 1,
+            Err(err) => error!("Error repairing issue ({issue}): {err}"),
+        }
+    }
+
+    println!("Repaired {repaired} of {} issues.", issues.len());
+
+    Ok(())
+}
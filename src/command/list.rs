@@ -0,0 +1,19 @@
+use crate::{
+    engine::{backups, EngineArgs},
+    internal::format::format_bytes,
+};
+
+pub fn list(engine_args: EngineArgs, search: Option<&str>) -> Result<(), anyhow::Error> {
+    let backups = match search {
+        Some(term) => backups::search_game_backups(&engine_args, term)?,
+        None => backups::list_game_backups(&engine_args)?,
+    };
+
+    for backup in backups.iter() {
+        let size = backup.path.metadata().map(|m| m.len()).unwrap_or(0);
+
+        println!("{}  ({})", backup.name, format_bytes(size));
+    }
+
+    Ok(())
+}
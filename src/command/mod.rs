@@ -1,7 +1,126 @@
+mod adopt;
+mod bench;
+mod check;
+mod console;
+mod ctl;
+mod diff;
+mod extract;
+mod fsck;
+mod list;
 mod new;
+mod prompt;
+mod report;
+mod restore;
 mod rungame;
+mod schema;
+#[cfg(feature = "self-update")]
+mod self_update;
+mod stats;
+mod status;
 mod tui;
+mod verify;
 
+pub use self::adopt::*;
+pub use self::bench::*;
+pub use self::check::*;
+pub use self::ctl::*;
+pub use self::diff::*;
+pub use self::extract::*;
+pub use self::fsck::*;
+pub use self::list::*;
 pub use self::new::*;
+pub use self::prompt::*;
+pub use self::report::*;
+pub use self::restore::*;
 pub use self::rungame::*;
+pub use self::schema::*;
+#[cfg(feature = "self-update")]
+pub use self::self_update::*;
+pub use self::stats::*;
+pub use self::status::*;
 pub use self::tui::*;
+pub use self::verify::*;
+
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use tracing::{error, info};
+
+use crate::{engine::EngineArgs, internal::shutdown::Shutdown};
+
+/// Read password-protected archives with the password in a file, ignoring a trailing line break
+fn use_password_file(engine_args: &EngineArgs, path: &Path) -> Result<(), anyhow::Error> {
+    let password =
+        fs::read_to_string(path).with_context(|| format!("Error reading password file: {}", path.display()))?;
+
+    engine_args
+        .archiver
+        .set_password(Some(password.trim_end_matches(['\r', '\n']).to_owned()));
+
+    Ok(())
+}
+
+/// Shutdown signal, set when the user presses Ctrl-C, or the session ends.
+/// That is on SIGTERM or SIGHUP, or on Windows when the console window is closed, the user logs out
+/// or the system shuts down, so that the exit backup is created and locks are released.
+fn shutdown_on_signals() -> Shutdown {
+    let shutdown = Shutdown::new();
+
+    ctrlc::set_handler({
+        let shutdown = shutdown.clone();
+
+        move || {
+            info!("Shutdown requested.");
+            shutdown.request();
+        }
+    })
+    .unwrap_or_else(|err| error!("Error setting Ctrl-C handler: {}", err));
+
+    #[cfg(windows)]
+    delay_session_end(shutdown.clone());
+
+    shutdown
+}
+
+/// Keep Windows from terminating the process as soon as the console is closed, the user logs out
+/// or the system shuts down, using the few seconds it allows to shut down cleanly
+#[cfg(windows)]
+fn delay_session_end(shutdown: Shutdown) {
+    use std::sync::OnceLock;
+
+    use windows_sys::Win32::{
+        Foundation::BOOL,
+        System::Console::{SetConsoleCtrlHandler, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT},
+    };
+
+    static SHUTDOWN: OnceLock<Shutdown> = OnceLock::new();
+
+    unsafe extern "system" fn handler(ctrl_type: u32) -> BOOL {
+        // Ctrl-C and Ctrl-Break are left to the next handler
+        if !matches!(ctrl_type, CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT) {
+            return 0;
+        }
+
+        if let Some(shutdown) = SHUTDOWN.get() {
+            info!("Session ending, shutting down.");
+            shutdown.request();
+        }
+
+        // The process is terminated as soon as this returns, so wait for it to exit on its own instead
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    }
+
+    if SHUTDOWN.set(shutdown).is_err() {
+        return;
+    }
+
+    // SAFETY: The handler only accesses a static that is set before it is installed.
+    if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+        error!(
+            "Error setting console close handler: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
@@ -39,6 +39,7 @@ pub fn new(game_config_path: &Path) -> Result<(), anyhow::Error> {
             save_files.push(GameSaveFile {
                 path,
                 staging_subdirectory: None,
+                grace_time: None,
             });
         } else {
             let name: String = dialoguer::Input::new().with_prompt("Name").interact_text()?;
@@ -49,6 +50,9 @@ pub fn new(game_config_path: &Path) -> Result<(), anyhow::Error> {
                     path,
                     include: Default::default(),
                     ignore: Default::default(),
+                    per_user: false,
+                    streaming: false,
+                    grace_time: None,
                 },
             );
         }
@@ -64,30 +68,71 @@ pub fn new(game_config_path: &Path) -> Result<(), anyhow::Error> {
         .default(10)
         .interact_text()?;
 
-    let copy_latest_to_path: String = dialoguer::Input::new()
-        .with_prompt("Copy latest backup to path (blank for none)")
-        .allow_empty(true)
-        .interact_text()?;
+    let mut copy_latest_to_paths: Vec<PathBuf> = Vec::new();
 
-    let copy_latest_to_path: Option<PathBuf> = if !copy_latest_to_path.is_empty() {
-        Some(copy_latest_to_path.into())
-    } else {
-        None
-    };
+    loop {
+        let path: String = dialoguer::Input::new()
+            .with_prompt("Copy latest backup to path (blank to proceed without adding)")
+            .allow_empty(true)
+            .interact_text()?;
+
+        if path.is_empty() {
+            break;
+        }
+
+        copy_latest_to_paths.push(path.into());
+    }
 
     let auto_backup = AutoBackup {
         enabled: true,
         min_interval,
+        snapshot_every_save: false,
+        keep_last: None,
+        collapse_sessions_after_days: None,
+        adaptive_interval: None,
+        description_template: None,
     };
 
     let game_config = GameConfig {
+        version: GameConfig::CURRENT_VERSION,
+
         grace_time,
-        copy_latest_to_path,
+        max_backup_duration: None,
+        copy_latest_to_paths,
+        copy_latest_keep: None,
+        copy_latest_name: None,
+        copy_latest_subfolder: false,
+        targets: Default::default(),
+        inspect: None,
+        staging: Default::default(),
+        keep_staging: false,
+        temp_path: None,
+        verify: Default::default(),
+        cloud_placeholders: Default::default(),
+        overwrite_read_only: false,
+        deleted_files: Default::default(),
+        atomic_restore: false,
+        recycle_keep_days: None,
+        description_templates: Vec::new(),
+        trigger_file: None,
+        steam_app_id: None,
+        server: None,
+        rcon: None,
+        partial_backups: false,
+        delta: None,
+
+        command: None,
+        working_dir: None,
+        args_file: None,
+        env: Default::default(),
+        wait_for_process: None,
 
         auto_backup,
 
         save_dirs,
         save_files,
+        save_registry_keys: Vec::new(),
+        change_indicator_paths: Vec::new(),
     };
 
     fs::create_dir_all(game_config_path)?;
@@ -0,0 +1,212 @@
+//! Line-based interactive menu driving an engine, for terminals the TUI renders poorly in, such as over SSH
+
+use std::time::Duration;
+
+use dialoguer::{Input, Select};
+use tracing::error;
+
+use crate::{
+    config::game::GameConfig,
+    engine::{
+        self, backups,
+        descriptions::DescriptionHistory,
+        restore::{self, ConflictResolution},
+        BackupKind, BackupRequest, EngineArgs, EngineControl, EngineState,
+    },
+    headless::LogUiHandler,
+    internal::shutdown::Shutdown,
+};
+
+use super::status::print_status;
+
+const WAIT_SLEEP_DURATION: Duration = Duration::from_millis(100);
+const NEW_DESCRIPTION_ITEM: &str = "[New description]";
+
+enum Action {
+    CreateBackup,
+    RestoreBackup,
+    ToggleAutoBackup,
+    Status,
+    Quit,
+}
+
+const ACTIONS: &[(&str, Action)] = &[
+    ("Create backup", Action::CreateBackup),
+    ("Restore backup", Action::RestoreBackup),
+    ("Toggle auto-backup", Action::ToggleAutoBackup),
+    ("Status", Action::Status),
+    ("Quit", Action::Quit),
+];
+
+pub fn prompt(engine_args: EngineArgs) -> Result<(), anyhow::Error> {
+    crate::headless::init_logging(None)?;
+
+    let gcfg = GameConfig::from_file(&engine_args.game_config_file_path(), engine_args.strict_config)?;
+
+    let shutdown = super::shutdown_on_signals();
+
+    let engine = engine::run(engine_args.clone(), shutdown.clone(), LogUiHandler::new())?;
+    let mut engine_control = engine.control();
+
+    // Wait for engine to start up
+    while engine_control.state() == EngineState::Starting {
+        std::thread::sleep(WAIT_SLEEP_DURATION);
+    }
+
+    // The engine keeps backing up in the background while waiting for input
+    let result = run_menu(&engine_args, &gcfg, &engine_control, &shutdown);
+
+    engine_control.shutdown();
+    engine.join();
+
+    result
+}
+
+fn run_menu(
+    engine_args: &EngineArgs,
+    gcfg: &GameConfig,
+    engine_control: &EngineControl,
+    shutdown: &Shutdown,
+) -> Result<(), anyhow::Error> {
+    let items: Vec<&str> = ACTIONS.iter().map(|(label, _)| *label).collect();
+
+    while !shutdown.is_requested() && engine_control.state() == EngineState::Running {
+        let Some(ix) = Select::new()
+            .with_prompt(engine_args.name.as_str())
+            .items(&items)
+            .default(0)
+            .interact_opt()?
+        else {
+            break;
+        };
+
+        let result = match ACTIONS[ix].1 {
+            Action::CreateBackup => create_backup(engine_args, gcfg, engine_control),
+            Action::RestoreBackup => restore_backup(engine_args, engine_control),
+            Action::ToggleAutoBackup => {
+                let autobackup = !engine_control.get_autobackup();
+                engine_control.set_autobackup(autobackup);
+
+                println!("Auto-backup {}", if autobackup { "on" } else { "off" });
+                Ok(())
+            }
+            Action::Status => print_status(engine_args, &engine_control.snapshot()),
+            Action::Quit => break,
+        };
+
+        if let Err(err) = result {
+            error!("{err}");
+        }
+    }
+
+    Ok(())
+}
+
+fn create_backup(
+    engine_args: &EngineArgs,
+    gcfg: &GameConfig,
+    engine_control: &EngineControl,
+) -> Result<(), anyhow::Error> {
+    let mut history = DescriptionHistory::load(&engine_args.output_path())?;
+    let suggestions = history.suggestions(&gcfg.description_templates);
+
+    let description = if suggestions.is_empty() {
+        input_description()?
+    } else {
+        let items: Vec<&str> = std::iter::once(NEW_DESCRIPTION_ITEM)
+            .chain(suggestions.iter().map(String::as_str))
+            .collect();
+
+        match Select::new()
+            .with_prompt("Description")
+            .items(&items)
+            .default(0)
+            .interact_opt()?
+        {
+            None => return Ok(()),
+            Some(0) => input_description()?,
+            Some(ix) => suggestions[ix - 1].clone(),
+        }
+    };
+
+    if description.is_empty() {
+        return Ok(());
+    }
+
+    engine_control.send(BackupRequest::CreateBackup {
+        archive_name: engine::make_backup_filename(&description, engine_args.archiver.extension()),
+        kind: BackupKind::Manual,
+    })?;
+
+    history.record(&description);
+    history.save()?;
+
+    Ok(())
+}
+
+fn input_description() -> Result<String, anyhow::Error> {
+    let description: String = Input::new().with_prompt("Description").interact_text()?;
+
+    Ok(description.trim().to_owned())
+}
+
+fn restore_backup(engine_args: &EngineArgs, engine_control: &EngineControl) -> Result<(), anyhow::Error> {
+    let backups = backups::list_game_backups(engine_args)?;
+
+    if backups.is_empty() {
+        println!("No backups found.");
+        return Ok(());
+    }
+
+    let items: Vec<&str> = backups.iter().map(|b| b.name.as_str()).collect();
+
+    let Some(ix) = Select::new()
+        .with_prompt("Backup to restore")
+        .items(&items)
+        .default(0)
+        .max_length(20)
+        .interact_opt()?
+    else {
+        return Ok(());
+    };
+
+    let archive_name = backups[ix].name.clone();
+    let conflicts = restore::find_conflicts(engine_args, &archive_name, None)?;
+
+    // The same resolution is applied to every newer save file
+    let resolution = if conflicts.is_empty() {
+        ConflictResolution::Overwrite
+    } else {
+        println!("Save files newer than in {archive_name}:");
+
+        for conflict in conflicts.iter() {
+            println!("  {conflict}");
+        }
+
+        let resolutions = [
+            ("Overwrite them", ConflictResolution::Overwrite),
+            ("Keep them", ConflictResolution::Keep),
+            ("Back up first, then overwrite them", ConflictResolution::BackUpFirst),
+        ];
+        let items: Vec<&str> = resolutions.iter().map(|(label, _)| *label).collect();
+
+        let Some(ix) = Select::new()
+            .with_prompt("Newer save files")
+            .items(&items)
+            .default(0)
+            .interact_opt()?
+        else {
+            return Ok(());
+        };
+
+        resolutions[ix].1
+    };
+
+    let resolved: Vec<_> = conflicts.into_iter().map(|c| (c, resolution)).collect();
+
+    for request in restore::restore_requests(archive_name, None, &resolved, engine_args.archiver.extension()) {
+        engine_control.send(request)?;
+    }
+
+    Ok(())
+}
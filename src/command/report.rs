@@ -0,0 +1,206 @@
+//! Bundles of logs, history and config for bug reports.
+//! They are only written locally, for the user to review and attach to an issue themselves.
+
+use std::{
+    fs,
+    io::{BufWriter, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use time::{macros::format_description, OffsetDateTime};
+
+use crate::{
+    config::main::CONFIG_FILENAME,
+    engine::{backups::list_game_backups, fsck, history::HISTORY_FILENAME, EngineArgs},
+    internal::zip::ZipWriter,
+};
+
+use super::LOG_FILENAME;
+
+/// Largest part of the end of the log file to include
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+/// Number of most recent history entries to include
+const HISTORY_ENTRIES: usize = 100;
+const REDACTED: &str = "<redacted>";
+
+pub fn report(engine_args: EngineArgs, config_path: &Path, output: Option<PathBuf>) -> Result<PathBuf, anyhow::Error> {
+    let output_path = engine_args.output_path();
+    let sanitizer = Sanitizer::new();
+
+    let report_path = output.unwrap_or_else(|| {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let timestamp = now
+            .format(format_description!("[year][month][day]-[hour][minute][second]"))
+            .unwrap();
+
+        PathBuf::from(format!("stool-report-{}-{timestamp}.zip", engine_args.name))
+    });
+
+    let file = fs::File::create(&report_path)
+        .with_context(|| format!("Error creating report file {}", report_path.display()))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+
+    zip.add_file(
+        "environment.txt",
+        sanitizer.apply(&environment(&engine_args)).as_bytes(),
+    )?;
+
+    let configs = [
+        ("config.toml", config_path.join(CONFIG_FILENAME)),
+        ("game.toml", engine_args.game_config_file_path()),
+    ];
+
+    for (name, path) in configs {
+        if let Ok(toml) = fs::read_to_string(&path) {
+            zip.add_file(name, sanitizer.apply(&redact_secrets(&toml)).as_bytes())?;
+        }
+    }
+
+    if let Some(log) = read_tail(&output_path.join(LOG_FILENAME), MAX_LOG_SIZE)? {
+        zip.add_file(LOG_FILENAME, sanitizer.apply(&log).as_bytes())?;
+    }
+
+    if let Ok(history) = fs::read_to_string(output_path.join(HISTORY_FILENAME)) {
+        let lines: Vec<_> = history.lines().collect();
+        let recent = lines[lines.len().saturating_sub(HISTORY_ENTRIES)..].join("\n");
+
+        zip.add_file(HISTORY_FILENAME, sanitizer.apply(&recent).as_bytes())?;
+    }
+
+    zip.finish()?;
+
+    Ok(report_path)
+}
+
+/// Version, platform and state of the game's data
+fn environment(engine_args: &EngineArgs) -> String {
+    let seven_zip = std::process::Command::new("7z").output().is_ok();
+    let backups = list_game_backups(engine_args).map_or_else(|err| format!("error: {err}"), |b| b.len().to_string());
+
+    [
+        format!("stool {}", env!("CARGO_PKG_VERSION")),
+        format!(
+            "OS: {} ({}), {}",
+            std::env::consts::OS,
+            std::env::consts::FAMILY,
+            std::env::consts::ARCH
+        ),
+        format!("Data path: {}", engine_args.data_path.display()),
+        format!("Archive format: {}", engine_args.archiver.extension()),
+        format!("7z: {}", if seven_zip { "available" } else { "not found" }),
+        format!("Index: {}", if engine_args.use_index { "on" } else { "off" }),
+        format!("Engine running: {}", fsck::engine_is_running(engine_args)),
+        format!("Backups: {backups}"),
+    ]
+    .join("\n")
+}
+
+/// Replace values that may be secret, such as RCON passwords and environment variables passed to the game.
+/// Configs that cannot be parsed are left as they are, as they may be what the report is about.
+fn redact_secrets(toml: &str) -> String {
+    let Ok(mut doc) = toml.parse::<toml_edit::DocumentMut>() else {
+        return toml.to_owned();
+    };
+
+    if let Some(password) = doc
+        .get_mut("rcon")
+        .and_then(|rcon| rcon.as_table_like_mut())
+        .and_then(|rcon| rcon.get_mut("password"))
+    {
+        *password = toml_edit::value(REDACTED);
+    }
+
+    if let Some(env) = doc.get_mut("env").and_then(|env| env.as_table_like_mut()) {
+        for (_, value) in env.iter_mut() {
+            *value = toml_edit::value(REDACTED);
+        }
+    }
+
+    doc.to_string()
+}
+
+/// Read the end of a file, if it exists
+fn read_tail(path: &Path, max_size: u64) -> Result<Option<String>, anyhow::Error> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let size = file.metadata()?.len();
+    file.seek(SeekFrom::Start(size.saturating_sub(max_size)))?;
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+}
+
+/// Removes the user's name and home directory from paths
+struct Sanitizer {
+    home: Option<String>,
+    user: Option<String>,
+}
+
+impl Sanitizer {
+    /// Shortest user name replaced, as shorter ones are likely to occur in unrelated text
+    const MIN_USER_LEN: usize = 3;
+
+    fn new() -> Self {
+        Self {
+            home: dirs::home_dir().map(|home| home.to_string_lossy().into_owned()),
+            user: ["USER", "USERNAME"]
+                .into_iter()
+                .find_map(|var| std::env::var(var).ok())
+                .filter(|user| user.len() >= Self::MIN_USER_LEN),
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut text = text.to_owned();
+
+        if let Some(home) = self.home.as_deref().filter(|home| home.len() > 1) {
+            // Paths in TOML strings have their backslashes escaped
+            text = text.replace(&home.replace('\\', "\\\\"), "~").replace(home, "~");
+        }
+
+        if let Some(user) = self.user.as_deref() {
+            text = text.replace(user, "<user>");
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::game::RconConfig, engine::testing::Fixture};
+
+    use super::*;
+
+    #[test]
+    fn report_bundle_redacts_secrets() {
+        let fixture = Fixture::with_config(|config| {
+            config.env.insert("API_TOKEN".to_owned(), "hunter2".to_owned());
+            config.rcon = Some(RconConfig {
+                address: "localhost:25575".to_owned(),
+                password: "swordfish".to_owned(),
+                save_command: "save-all".to_owned(),
+                save_delay: 0,
+            });
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let report_path = dir.path().join("report.zip");
+        report(fixture.args.clone(), dir.path(), Some(report_path.clone())).unwrap();
+
+        // Files are stored uncompressed, so their contents can be searched directly
+        let report = String::from_utf8_lossy(&std::fs::read(&report_path).unwrap()).into_owned();
+
+        assert!(report.contains("save-command = \"save-all\""));
+        assert!(report.contains("API_TOKEN"));
+        assert!(!report.contains("hunter2"));
+        assert!(!report.contains("swordfish"));
+    }
+}
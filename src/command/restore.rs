@@ -0,0 +1,99 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    engine::{
+        self,
+        restore::{self as engine_restore, ConflictResolution},
+        EngineArgs, EngineState,
+    },
+    headless::LogUiHandler,
+};
+
+const WAIT_SLEEP_DURATION: Duration = Duration::from_millis(100);
+
+/// What to do about save files that are newer than their copies in the backup being restored
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Replace them with the copies in the backup
+    Overwrite,
+    /// Leave them as they are
+    Keep,
+    /// Back up the save files first, then replace them
+    Backup,
+}
+
+impl From<OnConflict> for ConflictResolution {
+    fn from(on_conflict: OnConflict) -> Self {
+        match on_conflict {
+            OnConflict::Overwrite => Self::Overwrite,
+            OnConflict::Keep => Self::Keep,
+            OnConflict::Backup => Self::BackUpFirst,
+        }
+    }
+}
+
+pub fn restore(
+    engine_args: EngineArgs,
+    archive_name: String,
+    only: Option<PathBuf>,
+    on_conflict: Option<OnConflict>,
+    password_file: Option<&Path>,
+) -> Result<(), anyhow::Error> {
+    crate::headless::init_logging(None)?;
+
+    if let Some(password_file) = password_file {
+        super::use_password_file(&engine_args, password_file)?;
+    }
+
+    let conflicts = engine_restore::find_conflicts(&engine_args, &archive_name, only.as_deref())?;
+
+    let resolution = match on_conflict {
+        Some(on_conflict) => ConflictResolution::from(on_conflict),
+        None if conflicts.is_empty() => ConflictResolution::Overwrite,
+        None => {
+            for conflict in conflicts.iter() {
+                println!("{conflict}");
+            }
+
+            return Err(anyhow::anyhow!(
+                "{} save files are newer than in the backup. Pass --on-conflict to overwrite, keep or back them up first",
+                conflicts.len()
+            ));
+        }
+    };
+
+    let resolved: Vec<_> = conflicts.into_iter().map(|c| (c, resolution)).collect();
+    let requests = engine_restore::restore_requests(archive_name, only, &resolved, engine_args.archiver.extension());
+
+    let shutdown = super::shutdown_on_signals();
+
+    let engine = engine::run(engine_args, shutdown, LogUiHandler::new().with_heartbeat())?;
+    let mut engine_control = engine.control();
+
+    // Wait for engine to start up
+    while engine_control.state() != EngineState::Running {
+        std::thread::sleep(WAIT_SLEEP_DURATION);
+    }
+
+    // No automatic backups should be made while restoring
+    engine_control.set_autobackup(false);
+
+    for request in requests {
+        engine_control.send(request)?;
+    }
+
+    // Queued requests are completed before the engine shuts down
+    engine_control.shutdown();
+    engine.join();
+
+    let summary = engine_control.session_summary();
+
+    if let Some(error) = summary.errors.first() {
+        return Err(anyhow::anyhow!("Restore failed: {error}"));
+    }
+
+    Ok(())
+}
@@ -1,43 +1,92 @@
 use std::{
-    env,
-    process::Stdio,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
-    },
-    time::Duration,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{ExitCode, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
-use tracing::{error, info};
+use tracing::info;
 
 use crate::{
-    engine::{self, EngineArgs, EngineState},
+    config::game::GameConfig,
+    engine::{self, ui::MultiUiHandler, EngineArgs, EngineState},
+    headless::LogUiHandler,
+    internal::{process::ProcessMatcher, shutdown::Shutdown},
     tui::{AppState, TuiUiHandler},
 };
 
+use super::console::{spawn_command_reader, STDIN_SOURCE};
+
 const STOOL_PASSTHROUGH_PREFIX: &str = "STOOL_PASSTHROUGH_";
+const COMMAND_PLACEHOLDER: &str = "%command%";
 const WAIT_SLEEP_DURATION: Duration = Duration::from_secs(1);
+/// Log file of games run in the background, in their data directory
+pub const LOG_FILENAME: &str = "stool.log";
+const DEFAULT_PROCESS_GRACE_PERIOD: Duration = Duration::from_secs(30);
+/// Exit code when the game exited successfully, but backups were created with warnings
+const BACKUP_WARNINGS_EXIT_CODE: u8 = 3;
 
-pub fn rungame(engine_args: EngineArgs, game_command: Vec<String>) -> Result<(), anyhow::Error> {
-    // Shutdown signal
-    let shutdown = Arc::new(AtomicBool::new(false));
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunGameMode {
+    /// Run with TUI
+    Tui,
+    /// Run without TUI, passing the game's standard streams through
+    NoTui,
+    /// Run without TUI, logging to file. Started by stoolw, which shows no console window.
+    Background,
+}
 
-    // Set break (Ctrl-C) handler.
-    ctrlc::set_handler({
-        let shutdown = shutdown.clone();
+pub fn rungame(
+    engine_args: EngineArgs,
+    game_command: Vec<String>,
+    mode: RunGameMode,
+    commands: Option<PathBuf>,
+) -> Result<ExitCode, anyhow::Error> {
+    let gcfg = GameConfig::from_file(&engine_args.game_config_file_path(), engine_args.strict_config)?;
+    let game_command = resolve_game_command(&gcfg, &engine_args, game_command)?;
 
-        move || {
-            info!("Shutdown requested by user.");
-            shutdown.store(true, Ordering::Release);
-        }
-    })
-    .unwrap_or_else(|err| error!("Error setting Ctrl-C handler: {}", err));
+    // Game process started by the launcher, followed once the launcher exits
+    let game_process = gcfg
+        .wait_for_process
+        .as_ref()
+        .map(|wfp| -> Result<_, anyhow::Error> {
+            let matcher = ProcessMatcher::new(&wfp.name).context("Invalid wait-for-process name")?;
+            let grace_period = wfp
+                .grace_period
+                .map_or(DEFAULT_PROCESS_GRACE_PERIOD, Duration::from_secs);
+
+            Ok((matcher, grace_period))
+        })
+        .transpose()?;
+
+    // Without a TUI, log messages go to standard error or a log file instead
+    match mode {
+        RunGameMode::Tui => crate::tui::init_logging()?,
+        RunGameMode::NoTui => crate::headless::init_logging(None)?,
+        RunGameMode::Background => crate::headless::init_logging(Some(&engine_args.output_path().join(LOG_FILENAME)))?,
+    }
+
+    let shutdown = super::shutdown_on_signals();
+
+    // Commands read from standard input take it over from the game
+    let commands_on_stdin = commands.as_deref() == Some(Path::new(STDIN_SOURCE));
 
     let app_state = Arc::new(Mutex::new(AppState::default()));
-    let ui = TuiUiHandler::new(app_state.clone());
 
-    let engine = engine::run(engine_args, shutdown.clone(), ui)?;
+    let engine = if mode == RunGameMode::Tui {
+        let ui = MultiUiHandler::new(TuiUiHandler::new(app_state.clone()), LogUiHandler::new());
+
+        engine::run(engine_args.clone(), shutdown.clone(), ui)?
+    } else {
+        engine::run(
+            engine_args.clone(),
+            shutdown.clone(),
+            LogUiHandler::new().with_heartbeat(),
+        )?
+    };
+
     let engine_control = engine.control();
 
     // Wait for engine to start up
@@ -45,6 +94,10 @@ pub fn rungame(engine_args: EngineArgs, game_command: Vec<String>) -> Result<(),
         std::thread::sleep(WAIT_SLEEP_DURATION);
     }
 
+    if let Some(commands) = commands {
+        spawn_command_reader(commands, engine_args, engine_control.clone());
+    }
+
     // Run game
     let game_join_handle = {
         let shutdown = shutdown.clone();
@@ -69,32 +122,211 @@ pub fn rungame(engine_args: EngineArgs, game_command: Vec<String>) -> Result<(),
                 .unwrap_or(true)
         });
 
-        std::thread::spawn(move || -> Result<(), anyhow::Error> {
+        let working_dir = gcfg.working_dir;
+        let game_env_vars = gcfg.env;
+
+        // When there is no TUI occupying the terminal, the game's standard streams
+        // are passed through so launchers can observe the game's output.
+        let stdio = move || {
+            if mode == RunGameMode::NoTui {
+                Stdio::inherit()
+            } else {
+                Stdio::null()
+            }
+        };
+        let stdin = move || if commands_on_stdin { Stdio::null() } else { stdio() };
+
+        std::thread::spawn(move || -> Result<ExitStatus, anyhow::Error> {
             let (program, args) = game_command.split_first().context("Couldn't split game command")?;
 
+            let mut command = std::process::Command::new(program);
+
+            if let Some(working_dir) = working_dir {
+                command.current_dir(working_dir);
+            }
+
             // Run game
-            let result = std::process::Command::new(program)
+            let result = command
                 .args(args)
                 .env_clear()
                 .envs(env_vars)
                 .envs(passthrough_env_vars)
-                .stdin(Stdio::null())
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
+                .envs(game_env_vars)
+                .stdin(stdin())
+                .stdout(stdio())
+                .stderr(stdio())
                 .status();
 
-            shutdown.store(true, Ordering::Release);
+            if let (Ok(_), Some((mut matcher, grace_period))) = (result.as_ref(), game_process) {
+                info!("Launcher exited, following game process");
+                follow_game_process(&mut matcher, grace_period, &shutdown);
+            }
+
+            shutdown.request();
 
-            result?;
-            Ok(())
+            Ok(result?)
         })
     };
 
-    // Run TUI
-    crate::tui::run(engine, app_state, shutdown)?;
+    if mode == RunGameMode::Tui {
+        crate::tui::run(engine, app_state, shutdown)?;
+    } else {
+        crate::headless::run(engine)?;
+    }
 
     // Wait for run game thread to finish
-    game_join_handle.join().unwrap()?;
+    let status = game_join_handle.join().unwrap()?;
+
+    let summary = engine_control.session_summary();
+    if mode == RunGameMode::Background {
+        info!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+
+    if status.success() && summary.backups_with_warnings > 0 {
+        return Ok(ExitCode::from(BACKUP_WARNINGS_EXIT_CODE));
+    }
+
+    Ok(exit_code_from_status(status))
+}
 
-    Ok(())
+/// Wait until no game process has run for the grace period, or stool is shut down
+fn follow_game_process(matcher: &mut ProcessMatcher, grace_period: Duration, shutdown: &Shutdown) {
+    let mut last_seen_at = Instant::now();
+    let mut seen = false;
+
+    while !shutdown.is_requested() && last_seen_at.elapsed() < grace_period {
+        if matcher.is_running() {
+            if !seen {
+                info!("Game process found");
+                seen = true;
+            }
+
+            last_seen_at = Instant::now();
+        }
+
+        shutdown.wait_timeout(WAIT_SLEEP_DURATION);
+    }
+
+    if seen {
+        info!("Game process exited");
+    } else {
+        info!("Game process not found");
+    }
+}
+
+/// Convert the exit status of the game process into an exit code for stool
+fn exit_code_from_status(status: ExitStatus) -> ExitCode {
+    if let Some(code) = status.code() {
+        return ExitCode::from(clamp_exit_code(code));
+    }
+
+    // Process was terminated by a signal
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return ExitCode::from(128u8.wrapping_add(signal as u8));
+        }
+    }
+
+    ExitCode::FAILURE
+}
+
+/// Exit codes that do not fit in a byte (possible on Windows) are reported as a failure,
+/// since truncating them could turn a failure into success
+fn clamp_exit_code(code: i32) -> u8 {
+    u8::try_from(code).unwrap_or(1)
+}
+
+/// Build the final game command from the game config and the command given on the command line.
+fn resolve_game_command(
+    gcfg: &GameConfig,
+    engine_args: &EngineArgs,
+    game_command: Vec<String>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut command = match gcfg.command.as_ref() {
+        // Launcher template - insert the command line command in place of the placeholder
+        Some(template) if template.iter().any(|arg| arg == COMMAND_PLACEHOLDER) => {
+            if game_command.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Game config command contains {COMMAND_PLACEHOLDER}, but no game command was specified"
+                ));
+            }
+
+            let mut command = Vec::with_capacity(template.len() + game_command.len());
+
+            for arg in template.iter() {
+                if arg == COMMAND_PLACEHOLDER {
+                    command.extend(game_command.iter().cloned());
+                } else {
+                    command.push(arg.clone());
+                }
+            }
+
+            command
+        }
+        Some(template) if game_command.is_empty() => template.clone(),
+        _ => game_command,
+    };
+
+    if let Some(args_file) = gcfg.args_file.as_ref() {
+        // Relative paths are resolved relative to the game config directory
+        let args_file = engine_args.game_config_path.join(args_file);
+
+        let args = fs::read_to_string(&args_file)
+            .with_context(|| format!("Error reading arguments file: {}", args_file.display()))?;
+
+        command.extend(
+            args.lines()
+                .map(str::trim)
+                .filter(|arg| !arg.is_empty())
+                .map(str::to_owned),
+        );
+    }
+
+    if command.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No game command specified on the command line or in the game config"
+        ));
+    }
+
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_exit_codes_are_failures() {
+        assert_eq!(clamp_exit_code(0), 0);
+        assert_eq!(clamp_exit_code(3), 3);
+        assert_eq!(clamp_exit_code(255), 255);
+        assert_eq!(clamp_exit_code(256), 1);
+        assert_eq!(clamp_exit_code(-1073741819), 1);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn exit_status_above_255_is_not_success() {
+        use std::os::windows::process::ExitStatusExt;
+
+        let code = exit_code_from_status(ExitStatus::from_raw(256));
+        assert_eq!(format!("{code:?}"), format!("{:?}", ExitCode::FAILURE));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exit_status_of_signal_is_128_plus_signal() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let code = exit_code_from_status(ExitStatus::from_raw(9));
+        assert_eq!(format!("{code:?}"), format!("{:?}", ExitCode::from(137)));
+
+        let code = exit_code_from_status(ExitStatus::from_raw(3 << 8));
+        assert_eq!(format!("{code:?}"), format!("{:?}", ExitCode::from(3)));
+    }
 }
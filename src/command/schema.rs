@@ -0,0 +1,20 @@
+use crate::config::{game::GameConfig, main::MainConfig};
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// Game config
+    Game,
+    /// Main config
+    Main,
+}
+
+pub fn schema(kind: SchemaKind) -> Result<(), anyhow::Error> {
+    let schema = match kind {
+        SchemaKind::Game => schemars::schema_for!(GameConfig),
+        SchemaKind::Main => schemars::schema_for!(MainConfig),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use tracing::{debug, info};
+
+use crate::internal::update::{self, current_version};
+
+pub fn self_update(check: bool) -> Result<(), anyhow::Error> {
+    let release = update::latest_release()?;
+
+    if !release.is_newer() {
+        println!("stool {} is up to date", current_version());
+        return Ok(());
+    }
+
+    println!(
+        "stool {} is available (current: {}): {}",
+        release.version,
+        current_version(),
+        release.url
+    );
+
+    if check {
+        return Ok(());
+    }
+
+    release.install()?;
+    println!("Updated to {}", release.version);
+
+    Ok(())
+}
+
+/// Log when a newer release is available, checking in the background so as not to hold up startup
+pub fn spawn_update_check() {
+    std::thread::spawn(|| match update::latest_release() {
+        Ok(release) if release.is_newer() => info!(
+            "stool {} is available (current: {}). Run `stool self-update` to update.",
+            release.version,
+            current_version()
+        ),
+        Ok(_) => {}
+        Err(err) => debug!("Error checking for updates: {err}"),
+    });
+}
@@ -0,0 +1,51 @@
+use crate::{
+    engine::{stats, EngineArgs},
+    internal::format::format_bytes,
+};
+
+pub fn stats(engine_args: EngineArgs) -> Result<(), anyhow::Error> {
+    let stats = stats::backup_stats(&engine_args)?;
+
+    println!("{}:", engine_args.name);
+    println!("  Backups:       {}", stats.backups);
+    println!("  Physical size: {}", format_bytes(stats.physical_size));
+
+    match stats.ratio() {
+        Some(ratio) => {
+            println!("  Logical size:  {}", format_bytes(stats.logical_size));
+            println!("  Ratio:         {ratio:.2}x");
+        }
+        None => println!("  Logical size:  unknown"),
+    }
+
+    if stats.without_manifest > 0 {
+        println!(
+            "  Without manifest: {} (not counted in logical size)",
+            stats.without_manifest
+        );
+    }
+
+    // Archives are stored whole, so there are no shared chunks to report
+    println!("  Deduplication: none (archive backend)");
+
+    if !stats.months.is_empty() {
+        println!("  Growth:");
+
+        let mut total = 0;
+
+        for month in stats.months.iter() {
+            total += month.physical_size;
+
+            println!(
+                "    {}-{:02}: {} backups, +{} ({} total)",
+                month.year,
+                month.month as u8,
+                month.backups,
+                format_bytes(month.physical_size),
+                format_bytes(total)
+            );
+        }
+    }
+
+    Ok(())
+}
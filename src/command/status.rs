@@ -0,0 +1,121 @@
+use crate::{
+    engine::{
+        backups,
+        control::{self, ControlRequest, ControlResponse, NotRunning},
+        current::CurrentSaves,
+        BackupRequest, EngineArgs, EngineSnapshot, EngineState,
+    },
+    internal::format::{format_bytes, format_duration},
+};
+
+pub fn status(engine_args: EngineArgs) -> Result<(), anyhow::Error> {
+    let status = match control::request(&engine_args.output_path(), ControlRequest::Snapshot) {
+        Ok(ControlResponse::Snapshot(status)) => status,
+        Ok(_) => return Err(anyhow::anyhow!("Unexpected response from engine")),
+        Err(err) if err.is::<NotRunning>() => {
+            println!("{}: not running", engine_args.name);
+            print_latest_backup(&engine_args)?;
+
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
+
+    print_status(&engine_args, &status)
+}
+
+/// Print the status of a running engine, followed by the latest backup of the game
+pub(super) fn print_status(engine_args: &EngineArgs, status: &EngineSnapshot) -> Result<(), anyhow::Error> {
+    let state = match status.state {
+        EngineState::Starting => "starting",
+        EngineState::Running => "running",
+        EngineState::ShuttingDown => "shutting down",
+        EngineState::ShutDown => "shut down",
+    };
+
+    println!("{}: {state}", engine_args.name);
+    println!("  Auto-backup:     {}", if status.autobackup { "on" } else { "off" });
+
+    match status.action.as_ref() {
+        Some(action) => {
+            println!(
+                "  Action:          {} ({:.0}%, {} elapsed, {} processed)",
+                action.description,
+                action.progress * 100.,
+                format_duration(action.elapsed),
+                format_bytes(action.bytes_processed)
+            );
+
+            if let Some(stage) = action.stage.as_ref() {
+                println!("    {stage}");
+            }
+
+            if let Some(file) = action.file.as_ref() {
+                println!("    {file}");
+            }
+        }
+        None => println!("  Action:          idle"),
+    }
+
+    for request in status.queue.iter() {
+        match request {
+            BackupRequest::CreateBackup { archive_name, .. } => println!("  Queued:          backup {archive_name}"),
+            BackupRequest::RestoreBackup { archive_name, .. } => println!("  Queued:          restore {archive_name}"),
+        }
+    }
+
+    match status.pending_changes {
+        Some(pending) => match pending.auto_backup_in {
+            Some(auto_backup_in) => println!(
+                "  Unsaved changes: {} ago, auto-backup in {}",
+                format_duration(pending.since),
+                format_duration(auto_backup_in)
+            ),
+            None => println!("  Unsaved changes: {} ago", format_duration(pending.since)),
+        },
+        None => println!("  Unsaved changes: none"),
+    }
+
+    let session = &status.session;
+    println!(
+        "  Backups created: {} ({} auto, {} manual, {} exit), {} archived",
+        session.backups(),
+        session.auto_backups,
+        session.manual_backups,
+        session.exit_backups,
+        format_bytes(session.bytes_archived)
+    );
+
+    if !session.warnings.is_empty() {
+        println!("  Warnings:        {}", session.warnings.len());
+    }
+
+    if !session.errors.is_empty() {
+        println!("  Errors:          {}", session.errors.len());
+    }
+
+    print_latest_backup(engine_args)?;
+
+    Ok(())
+}
+
+/// Print the latest backup of the game, whether or not the engine created it,
+/// and the backup the live save files correspond to
+fn print_latest_backup(engine_args: &EngineArgs) -> Result<(), anyhow::Error> {
+    let backups = backups::list_game_backups(engine_args)?;
+
+    match backups.iter().max_by_key(|b| b.modified) {
+        Some(backup) => {
+            let size = backup.path.metadata().map(|m| m.len()).unwrap_or(0);
+            println!("  Latest backup:   {} ({})", backup.name, format_bytes(size));
+        }
+        None => println!("  Latest backup:   none"),
+    }
+
+    match CurrentSaves::load(&engine_args.output_path())? {
+        Some(current) => println!("  Current saves:   {current}"),
+        None => println!("  Current saves:   unknown"),
+    }
+
+    Ok(())
+}
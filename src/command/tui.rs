@@ -1,32 +1,24 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
-};
-
-use tracing::{error, info};
+use std::sync::{Arc, Mutex};
 
 use crate::{
-    engine::{self, EngineArgs},
+    engine::{self, remote::RemoteEngine, ui::MultiUiHandler, EngineArgs},
+    headless::LogUiHandler,
     tui::{AppState, TuiUiHandler},
 };
 
-pub fn tui(engine_args: EngineArgs) -> Result<(), anyhow::Error> {
-    // Shutdown signal
-    let shutdown = Arc::new(AtomicBool::new(false));
-
-    // Set break (Ctrl-C) handler.
-    ctrlc::set_handler({
-        let shutdown = shutdown.clone();
+pub fn tui(engine_args: EngineArgs, attach: bool) -> Result<(), anyhow::Error> {
+    crate::tui::init_logging()?;
 
-        move || {
-            info!("Shutdown requested by user.");
-            shutdown.store(true, Ordering::Release);
-        }
-    })
-    .unwrap_or_else(|err| error!("Error setting Ctrl-C handler: {}", err));
+    let shutdown = super::shutdown_on_signals();
 
     let app_state = Arc::new(Mutex::new(AppState::default()));
-    let ui = TuiUiHandler::new(app_state.clone());
+    let ui = MultiUiHandler::new(TuiUiHandler::new(app_state.clone()), LogUiHandler::new());
+
+    if attach {
+        let remote = RemoteEngine::attach(&engine_args.output_path(), ui)?;
+
+        return crate::tui::run_attached(remote, engine_args, app_state, shutdown);
+    }
 
     let engine = engine::run(engine_args, shutdown.clone(), ui)?;
 
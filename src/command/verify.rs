@@ -0,0 +1,48 @@
+use crate::engine::{fsck, verify, EngineArgs};
+
+pub fn verify(engine_args: EngineArgs, archive: Option<&str>, repair: bool) -> Result<(), anyhow::Error> {
+    // Repairing while the engine is running could interfere with an ongoing backup
+    if repair && fsck::engine_is_running(&engine_args) {
+        return Err(anyhow::anyhow!(
+            "Engine is running for '{}', not repairing",
+            engine_args.name
+        ));
+    }
+
+    let results = verify::verify_backups(&engine_args, archive, repair)?;
+
+    let mut damaged = 0;
+
+    for result in results.iter() {
+        match &result.outcome {
+            Ok(outcome) => {
+                println!("{}: {outcome}", result.name);
+
+                if outcome.is_damaged() {
+                    damaged += 1;
+                }
+            }
+            Err(err) => {
+                println!("{}: error: {err}", result.name);
+                damaged += 1;
+            }
+        }
+    }
+
+    if damaged > 0 {
+        let hint = if repair {
+            ""
+        } else {
+            " Run with --repair to fix repairable ones."
+        };
+
+        return Err(anyhow::anyhow!(
+            "{damaged} of {} backups are damaged or could not be verified.{hint}",
+            results.len()
+        ));
+    }
+
+    println!("Verified {} backups.", results.len());
+
+    Ok(())
+}
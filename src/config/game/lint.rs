@@ -0,0 +1,111 @@
+//! Checks for settings that are valid on their own, but make no sense together or would surprise at runtime
+
+use std::path::Path;
+
+use crate::internal::{filter, sync};
+
+use super::GameConfig;
+
+/// Paths of the kinds found in save directories, none of which an ignore list should match in full
+const PROBE_PATHS: &[&str] = &["save.sav", "Save1", "slot/data.dat", "profiles/1/.hidden"];
+
+impl GameConfig {
+    /// Describe settings that are likely mistakes
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.save_dirs.is_empty() && self.save_files.is_empty() && self.save_registry_keys.is_empty() {
+            warnings.push("No save dirs, save files or registry keys are configured, so backups are empty".to_owned());
+        }
+
+        let auto_backup = &self.auto_backup;
+
+        if auto_backup.enabled && !auto_backup.snapshot_every_save && self.grace_time >= auto_backup.min_interval {
+            warnings.push(format!(
+                "grace-time ({}s) is not shorter than auto-backup min-interval ({}s), so the interval has no effect",
+                self.grace_time, auto_backup.min_interval
+            ));
+        }
+
+        if !auto_backup.enabled && auto_backup.snapshot_every_save {
+            warnings.push("snapshot-every-save has no effect while auto-backup is disabled".to_owned());
+        }
+
+        if auto_backup.keep_last == Some(0) {
+            warnings.push("auto-backup keep-last is 0, so every auto-backup is deleted right away".to_owned());
+        }
+
+        if let Some(copy_latest_name) = self.copy_latest_name.as_ref() {
+            if !copy_latest_name.ends_with("{name}") {
+                warnings.push(
+                    "copy-latest-name does not end with {name}, so copies are not recognized as backups and never pruned"
+                        .to_owned(),
+                );
+            }
+        }
+
+        for (name, save_dir) in self.save_dirs.iter() {
+            if save_dir.include.as_ref().is_some_and(Vec::is_empty) {
+                warnings.push(format!(
+                    "Save dir [{name}] has an empty include list, so no files are backed up"
+                ));
+            }
+
+            match save_dir.ignore.as_deref().map(filter::build_globset).transpose() {
+                Ok(Some(ignore)) if PROBE_PATHS.iter().all(|path| ignore.is_match(path)) => {
+                    warnings.push(format!("Save dir [{name}] ignores all files, so none are backed up"));
+                }
+                Ok(_) => {}
+                Err(err) => warnings.push(format!("Save dir [{name}] has an invalid ignore pattern: {err}")),
+            }
+
+            if let Err(err) = save_dir.include.as_deref().map(filter::build_globset).transpose() {
+                warnings.push(format!("Save dir [{name}] has an invalid include pattern: {err}"));
+            }
+
+            let overlapping = self
+                .save_dirs
+                .iter()
+                .filter(|(other_name, _)| *other_name != name)
+                .find(|(_, other)| is_inside(&save_dir.path, &other.path));
+
+            if let Some((other_name, _)) = overlapping {
+                warnings.push(format!(
+                    "Save dir [{name}] is inside save dir [{other_name}], so its files are backed up twice"
+                ));
+            }
+
+            for path in self.copy_latest_to_paths.iter() {
+                if is_inside(path, &save_dir.path) {
+                    warnings.push(format!(
+                        "Copy-latest path {} is inside save dir [{name}], so copies end up in later backups",
+                        path.display()
+                    ));
+                }
+            }
+
+            for path in self.change_indicator_paths.iter() {
+                if is_inside(path, &save_dir.path) {
+                    warnings.push(format!(
+                        "Change indicator path {} is inside save dir [{name}], so it is backed up after all",
+                        path.display()
+                    ));
+                }
+            }
+
+            for (target_name, target) in self.targets.iter() {
+                if is_inside(&target.path, &save_dir.path) {
+                    warnings.push(format!(
+                        "Target [{target_name}] is inside save dir [{name}], so uploads end up in later backups"
+                    ));
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+fn is_inside(path: &Path, dir: &Path) -> bool {
+    sync::resolve_path(path).starts_with(sync::resolve_path(dir))
+}
@@ -5,47 +5,428 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::Context;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
+use tracing::warn;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+use crate::internal::proton;
+
+use super::migrate::{self, GAME_CONFIG_VERSION};
+use super::unknown_keys::{self, UnknownKey};
+
+mod lint;
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct GameSaveDir {
+    /// Path to the save directory
     pub path: PathBuf,
+    /// Glob patterns of files to include. All files are included if omitted.
     pub include: Option<Vec<String>>,
+    /// Glob patterns of files to ignore
     pub ignore: Option<Vec<String>>,
+    /// Back up a directory for each user, with `{user}` in the path matching the name of each user's directory,
+    /// such as `/srv/game/players/{user}/saves`. Each user's directory is backed up as `<name>/<user>`.
+    #[serde(default)]
+    pub per_user: bool,
+    /// Copy files while walking the directory, rather than scanning it in full first.
+    /// Keeps memory use low for directories of millions of files, at the cost of progress showing no total.
+    #[serde(default)]
+    pub streaming: bool,
+    /// Grace time of changes in this directory, in seconds. The game's grace-time if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grace_time: Option<u64>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct GameSaveFile {
+    /// Path to the save file
     pub path: PathBuf,
+    /// Subdirectory in the backup to place the file in
     pub staging_subdirectory: Option<PathBuf>,
+    /// Grace time of changes to this file, in seconds. The game's grace-time if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grace_time: Option<u64>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct AutoBackup {
+    /// Create backups automatically when save files change
     pub enabled: bool,
+    /// Minimum time between auto-backups, in seconds
     pub min_interval: u64,
+    /// Create an auto-backup after every detected save, ignoring the minimum interval
+    #[serde(default)]
+    pub snapshot_every_save: bool,
+    /// Number of auto-backups to keep. Older auto-backups are deleted.
+    pub keep_last: Option<usize>,
+    /// Delete all but the final backup of sessions that ended more than this many days ago.
+    /// Pinned backups are kept.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_sessions_after_days: Option<u64>,
+    /// Grow the minimum interval when backups take long, so that no more than this percentage of time
+    /// is spent creating backups. Based on how long recent backups took.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adaptive_interval: Option<u32>,
+    /// Describe auto-backups with metadata extracted by the save inspector, such as `{Character} lvl {Level}`,
+    /// where each `{label}` is replaced with the value of the field of that label.
+    /// Backups are named "Auto - <description>", or just "Auto" if any field has no value.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_template: Option<String>,
+}
+
+/// Where the staging directory of a game is placed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum StagingLocation {
+    /// In the data path, unless it is on another volume than the saves
+    #[default]
+    Auto,
+    /// Always in the data path
+    Data,
+    /// Next to the save directories, so that staging copies stay on the same volume
+    NearSaves,
+}
+
+impl StagingLocation {
+    fn is_auto(&self) -> bool {
+        *self == Self::Auto
+    }
+}
+
+/// How thoroughly copies of save files are checked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerifyMode {
+    /// Tell changed files by size and modification time only, without checksums
+    Fast,
+    /// Verify checksums of copied files
+    #[default]
+    Standard,
+    /// Also compare checksums of files that appear unchanged, on every backup and restore
+    Paranoid,
+}
+
+impl VerifyMode {
+    fn is_standard(&self) -> bool {
+        *self == Self::Standard
+    }
+}
+
+/// Where save files deleted when restoring go
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeletedFiles {
+    /// Deleted for good
+    #[default]
+    Remove,
+    /// Moved to the trash of the operating system
+    Trash,
+    /// Moved to the recycle directory in the data directory of the game
+    Recycle,
+}
+
+impl DeletedFiles {
+    fn is_remove(&self) -> bool {
+        *self == Self::Remove
+    }
+}
+
+/// How save files that are cloud placeholders are backed up,
+/// such as OneDrive Files On-Demand files whose contents are not kept on this device
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloudPlaceholders {
+    /// Downloaded while copying them, which can be slow
+    #[default]
+    Hydrate,
+    /// Left as they were in the previous backup, or out of the backup if they were not in it, with a warning
+    Skip,
+}
+
+impl CloudPlaceholders {
+    fn is_hydrate(&self) -> bool {
+        *self == Self::Hydrate
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackupTarget {
+    /// Directory to upload backups to, e.g. a mounted network share or a cloud sync folder
+    pub path: PathBuf,
+    /// Size of upload chunks in MiB, 8 if omitted.
+    /// An interrupted upload resumes from the last complete chunk.
+    pub chunk_size_mib: Option<u64>,
+    /// Age public keys (`age1...`) to encrypt uploaded copies to.
+    /// Copies are uploaded unencrypted if empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+
+    /// Only upload manual backups
+    #[serde(default)]
+    pub manual_only: bool,
+    /// Only upload every Nth backup
+    pub every: Option<u32>,
+    /// Postpone uploads while the network connection is metered
+    #[serde(default)]
+    pub skip_metered: bool,
+    /// Only upload between these local times, as "HH:MM-HH:MM". The range may wrap around midnight.
+    pub hours: Option<String>,
+}
+
+/// Coordination with a dedicated game server, which keeps its world in memory and only writes it to disk from time to time
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServerConfig {
+    /// Command forcing the server to write its world to disk, run before save files are copied.
+    /// For example an RCON client sending `save-all flush`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub save_command: Vec<String>,
+    /// Time to wait after the save command for the server to finish writing, in seconds
+    #[serde(default)]
+    pub save_delay: u64,
+    /// Command telling whether the server is busy, such as with players joining or fighting.
+    /// Auto-backups are postponed while it exits successfully.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub busy_command: Vec<String>,
+    /// Longest time to postpone an auto-backup while the server is busy, in seconds. 1 hour if omitted.
+    pub max_postpone: Option<u64>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Storage of large save files that change little between backups, such as single-file worlds,
+/// as binary deltas against their latest full copy
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct DeltaConfig {
+    /// Smallest file to store as a delta, in bytes. 64 MiB if omitted.
+    pub min_size: Option<u64>,
+    /// Number of backups to store as deltas before storing a full copy again. 10 if omitted.
+    /// Restoring needs the backup holding the full copy, which is kept as long as deltas against it are.
+    pub full_every: Option<u32>,
+}
+
+/// Remote console of a dedicated game server, used to have it write its world to disk before backups
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct RconConfig {
+    /// Address of the RCON port, such as `localhost:25575`
+    pub address: String,
+    /// RCON password
+    pub password: String,
+    /// Command writing the world to disk, such as `save-all flush` for Minecraft
+    pub save_command: String,
+    /// Time to wait after the save command for the server to finish writing, in seconds
+    #[serde(default)]
+    pub save_delay: u64,
+}
+
+/// Game process started by a launcher that exits right away, which is followed instead of the launcher
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct WaitForProcess {
+    /// Regular expression matching the name of the game process, such as `^Game(\.exe)?$`
+    pub name: String,
+    /// Time to wait for the game process to start once the launcher exits, or to start again after it exits,
+    /// in seconds. 30 if omitted.
+    pub grace_period: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct GameConfig {
+    /// Config schema version
+    #[serde(default)]
+    pub version: u32,
+
+    /// Time to wait after the last change to save files before backing up, in seconds
     pub grace_time: u64,
-    pub copy_latest_to_path: Option<PathBuf>,
+    /// Time a backup may take before the rest of the files are archived without compression, in seconds.
+    /// Backups are never cut short if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_backup_duration: Option<u64>,
+    /// Copy the latest backup of a session to these paths
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub copy_latest_to_paths: Vec<PathBuf>,
+    /// Number of backup copies to keep in each copy-latest path. Older copies are deleted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_latest_keep: Option<usize>,
+    /// Name of backup copies in copy-latest paths, with `{game}` replaced by the game name
+    /// and `{name}` by the file name of the backup, such as `{game} - {name}`.
+    /// `{name}` if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_latest_name: Option<String>,
+    /// Place backup copies in a subfolder named after the game in each copy-latest path
+    #[serde(default)]
+    pub copy_latest_subfolder: bool,
+    /// Destinations every new backup is uploaded to, by name.
+    /// Uploads that fail are retried when the engine next starts.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub targets: BTreeMap<String, BackupTarget>,
+    /// Extract display metadata, such as character name or level, from save files into backup manifests
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inspect: Option<SaveInspect>,
+    /// Where to place the staging directory that saves are copied to before archiving
+    #[serde(default)]
+    #[serde(skip_serializing_if = "StagingLocation::is_auto")]
+    pub staging: StagingLocation,
+    /// Keep the staging directory between runs, so that the first backup of a session only copies changed files.
+    /// It takes up as much disk space as the saves.
+    #[serde(default)]
+    pub keep_staging: bool,
+    /// Directory for temporary files when extracting, decrypting or adopting backups of this game.
+    /// The one in the main config if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_path: Option<PathBuf>,
+    /// How thoroughly copies of save files are checked
+    #[serde(default)]
+    #[serde(skip_serializing_if = "VerifyMode::is_standard")]
+    pub verify: VerifyMode,
+    /// How save files that are cloud placeholders, not kept on this device, are backed up
+    #[serde(default)]
+    #[serde(skip_serializing_if = "CloudPlaceholders::is_hydrate")]
+    pub cloud_placeholders: CloudPlaceholders,
+    /// Clear read-only and hidden attributes of save files that are overwritten or deleted,
+    /// such as on restore. Otherwise, such files cannot be replaced.
+    #[serde(default)]
+    pub overwrite_read_only: bool,
+    /// Where save files deleted when restoring go, as a safety net
+    #[serde(default)]
+    #[serde(skip_serializing_if = "DeletedFiles::is_remove")]
+    pub deleted_files: DeletedFiles,
+    /// Restore each save directory by building the restored files beside it, then swapping the directories,
+    /// so that a failed restore leaves the saves as they were. It takes up as much extra disk space as the saves.
+    /// Save directories on their own volume are restored in place.
+    #[serde(default)]
+    pub atomic_restore: bool,
+    /// Days to keep files in the recycle directory. 30 if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recycle_keep_days: Option<u64>,
+    /// Descriptions offered when creating a backup in the TUI, after previously used ones
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub description_templates: Vec<String>,
+    /// File that in-game scripts or macros can create to request a backup, using its contents as description.
+    /// The file is deleted once picked up, and is never backed up itself.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_file: Option<PathBuf>,
+    /// Steam app ID of a Windows game run through Proton.
+    /// Save paths starting with `%proton%` are then resolved to the `drive_c` directory of its Proton prefix,
+    /// such as `%proton%/users/steamuser/AppData/Roaming/Game`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steam_app_id: Option<u32>,
+    /// Coordination with a dedicated game server
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerConfig>,
+    /// Remote console of a dedicated game server, whose save command is issued before save files are copied
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rcon: Option<RconConfig>,
+    /// Leave save dirs unchanged since the previous backup of a session out of backups.
+    /// Restoring such a backup needs the earlier backups holding them, which are kept as long as it is.
+    #[serde(default)]
+    pub partial_backups: bool,
+    /// Store large save files as deltas against their latest full copy
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<DeltaConfig>,
+
+    /// Command used to launch the game.
+    /// An element consisting of `%command%` is replaced with the command given on the command line.
+    pub command: Option<Vec<String>>,
+    /// Working directory for the game
+    pub working_dir: Option<PathBuf>,
+    /// File containing additional arguments, one per line
+    pub args_file: Option<PathBuf>,
+    /// Environment variables to set for the game
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Follow a game process started by the launcher, rather than stopping when the launcher exits
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_for_process: Option<WaitForProcess>,
 
     pub auto_backup: AutoBackup,
 
+    /// Save directories to back up, by name
     #[serde(default)]
     pub save_dirs: BTreeMap<String, GameSaveDir>,
+    /// Individual save files to back up
     #[serde(default)]
     #[serde(rename = "save-file")]
     pub save_files: Vec<GameSaveFile>,
+    /// Registry keys to back up, such as `HKEY_CURRENT_USER\Software\Game`.
+    /// They are exported to .reg files in backups, and imported on restore.
+    /// Only supported on Windows, in builds with the `registry` feature.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub save_registry_keys: Vec<String>,
+    /// Files or directories whose changes count as changes to save files, without being backed up,
+    /// such as marker files a game touches outside its save directory once it has finished saving
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub change_indicator_paths: Vec<PathBuf>,
+}
+
+/// Extraction of display metadata from save files
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct SaveInspect {
+    /// Name of the inspector: `json`, or `regex` for other formats
+    pub inspector: String,
+    /// Glob patterns of save files to inspect, relative to the staging directory.
+    /// All files are inspected if empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// Metadata to extract, as display labels with inspector-specific locations,
+    /// such as JSON pointers or regular expressions whose first capture group is the value
+    pub fields: BTreeMap<String, String>,
 }
 
 impl GameConfig {
-    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+    pub const CURRENT_VERSION: u32 = GAME_CONFIG_VERSION;
+
+    /// Read config from file.
+    /// In strict mode, unknown keys are an error instead of a warning.
+    pub fn from_file(path: &Path, strict: bool) -> Result<Self, anyhow::Error> {
+        let (config, unknown_keys) = Self::from_file_with_unknown_keys(path)?;
+
+        if strict {
+            unknown_keys::deny(&unknown_keys).with_context(|| format!("Error in {}", path.display()))?;
+        }
+
+        for unknown_key in unknown_keys {
+            warn!("{}: {unknown_key}", path.display());
+        }
+
+        Ok(config)
+    }
+
+    /// Read config from file, along with the keys in it that do not correspond to any setting
+    pub fn from_file_with_unknown_keys(path: &Path) -> Result<(Self, Vec<UnknownKey>), anyhow::Error> {
         use std::io::Read;
 
         let mut file = fs::File::open(path).context("Error opening config file")?;
@@ -54,7 +435,58 @@ impl GameConfig {
         file.read_to_string(&mut toml_str)
             .context("Error reading config file")?;
 
-        Self::from_str(&toml_str)
+        let (table, upgraded_from) = migrate::migrate_game_config(&toml_str)?;
+        let (mut config, unknown_keys): (Self, _) = unknown_keys::deserialize(table.clone(), &toml_str)?;
+
+        // Write back upgraded config, keeping the original.
+        // The table is written rather than the config, so that unknown keys are preserved for the user to fix.
+        if let Some(version) = upgraded_from {
+            migrate::write_upgraded(path, version, &table)?;
+        }
+
+        config.resolve_proton_paths(&proton::steam_roots())?;
+
+        Ok((config, unknown_keys))
+    }
+
+    /// Resolve save paths inside the Proton prefix of the game, looking for it in the given Steam installations.
+    /// The prefix is looked up on every load, so that configs keep working when it is recreated or moved.
+    pub fn resolve_proton_paths(&mut self, steam_roots: &[PathBuf]) -> Result<(), anyhow::Error> {
+        let uses_prefix = self.save_dirs.values().any(|sd| proton::is_in_prefix(&sd.path))
+            || self.save_files.iter().any(|sf| proton::is_in_prefix(&sf.path))
+            || self
+                .change_indicator_paths
+                .iter()
+                .any(|path| proton::is_in_prefix(path));
+
+        if !uses_prefix {
+            return Ok(());
+        }
+
+        let app_id = self.steam_app_id.with_context(|| {
+            format!(
+                "Save paths starting with {} require steam-app-id",
+                proton::PREFIX_PLACEHOLDER
+            )
+        })?;
+        let drive_c = proton::find_drive_c(app_id, steam_roots).with_context(|| {
+            format!("Proton prefix of Steam app {app_id} not found. Has the game been run through Proton?")
+        })?;
+
+        let paths = self
+            .save_dirs
+            .values_mut()
+            .map(|sd| &mut sd.path)
+            .chain(self.save_files.iter_mut().map(|sf| &mut sf.path))
+            .chain(self.change_indicator_paths.iter_mut());
+
+        for path in paths {
+            if let Some(expanded) = proton::expand_path(path, &drive_c) {
+                *path = expanded;
+            }
+        }
+
+        Ok(())
     }
 
     pub fn write(&self, path: &Path) -> Result<(), anyhow::Error> {
@@ -78,3 +510,30 @@ impl FromStr for GameConfig {
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::testing::{Fixture, SAVE_DIR_NAME};
+
+    #[test]
+    fn config_warnings_point_out_likely_mistakes() {
+        assert!(Fixture::new().config().warnings().is_empty());
+
+        let fixture = Fixture::with_config(|config| {
+            config.grace_time = 60;
+            config.auto_backup.enabled = true;
+            config.auto_backup.min_interval = 30;
+
+            let save_dir = config.save_dirs.get_mut(SAVE_DIR_NAME).unwrap();
+            save_dir.ignore = Some(vec!["*".to_owned()]);
+            config.copy_latest_to_paths = vec![save_dir.path.join("latest")];
+        });
+
+        let warnings = fixture.config().warnings();
+
+        assert_eq!(warnings.len(), 3, "{warnings:?}");
+        assert!(warnings[0].contains("grace-time (60s)"));
+        assert!(warnings[1].contains("ignores all files"));
+        assert!(warnings[2].contains("Copy-latest path"));
+    }
+}
@@ -1,19 +1,156 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::Context;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
-use tracing::error;
+use tracing::{error, warn};
+
+use super::migrate::{self, MAIN_CONFIG_VERSION};
+use super::unknown_keys;
 
 pub const CONFIG_DIR_NAME: &str = "stool";
 pub const CONFIG_FILENAME: &str = "config.toml";
+/// Directory beside the executable holding config and data in portable mode
+pub const PORTABLE_DIR_NAME: &str = "stool-data";
+/// Data directory in portable mode, relative to the portable directory
+const PORTABLE_DATA_DIR_NAME: &str = "data";
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct MainConfig {
+    /// Config schema version
+    #[serde(default)]
+    pub version: u32,
+
+    /// Directory where backups and other data are stored.
+    /// A relative path is relative to the config directory.
     pub data_path: PathBuf,
+    /// Keep an SQLite index of backups, for faster listing and searching
+    #[serde(default)]
+    pub use_index: bool,
+    /// Treat unknown keys in config files as errors instead of warnings
+    #[serde(default)]
+    pub strict_config: bool,
+    /// Log when a newer version of stool is released, checking when a game is run.
+    /// Only supported in builds with the `self-update` feature.
+    #[serde(default)]
+    pub check_for_updates: bool,
+    /// Keep `overlay.txt` and `overlay.json` in the data directory of running games up to date
+    /// with the ongoing backup or restore and the latest backup, for streaming overlays such as OBS text sources
+    #[serde(default)]
+    pub overlay_status: bool,
+    /// Age identity file, for restoring and extracting encrypted backup copies (`.age` files).
+    /// A relative path is relative to the config directory.
+    pub age_identity_file: Option<PathBuf>,
+    /// Directory for temporary files when extracting, decrypting or adopting backups.
+    /// A relative path is relative to the config directory. The system temp directory if omitted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_path: Option<PathBuf>,
+
+    /// Additional named data roots, e.g. on different drives.
+    /// A relative path is relative to the config directory.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub data_roots: BTreeMap<String, PathBuf>,
+    /// Data roots to store different kinds of data in
+    #[serde(default)]
+    #[serde(skip_serializing_if = "StorageRoots::is_empty")]
+    pub storage: StorageRoots,
+
+    /// Backup archive settings
+    #[serde(default)]
+    pub archive: ArchiveSettings,
+    /// Intervals at which running games are checked on
+    #[serde(default)]
+    pub engine: EngineSettings,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EngineSettings {
+    /// Interval at which due auto-backups and the trigger file are checked for, in milliseconds
+    pub check_interval_ms: u64,
+    /// Interval at which save paths are scanned for changes when watching falls back to polling, in milliseconds
+    pub poll_interval_ms: u64,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            check_interval_ms: 1000,
+            poll_interval_ms: 2000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ArchiveSettings {
+    /// Format of new backup archives. Backups in other formats remain readable.
+    pub format: ArchiveFormat,
+    /// Compression level, from 0 (no compression) to 9 (best compression) for 7z, or from 1 to 22 for zstd
+    pub level: u32,
+    /// Number of zstd compression threads, 0 for one per CPU core
+    pub threads: u32,
+    /// Use zstd long-distance matching, which compresses large saves better at the cost of memory
+    pub long_distance_matching: bool,
+    /// Generate parity data of this size, as a percentage of the archive size, from 1 to 100.
+    /// Allows repairing damaged archives with `stool verify --repair`.
+    pub parity_percent: Option<u32>,
+    /// Glob patterns of files to store without compression, such as videos or `.pak` files
+    /// that are already compressed. Only supported by the zstd format.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub store_only: Vec<String>,
+}
+
+impl Default for ArchiveSettings {
+    fn default() -> Self {
+        Self {
+            format: ArchiveFormat::default(),
+            level: 9,
+            threads: 0,
+            long_distance_matching: true,
+            parity_percent: None,
+            store_only: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArchiveFormat {
+    /// 7z archives, created with the external 7z command
+    #[default]
+    #[serde(rename = "7z")]
+    SevenZip,
+    /// Zstd-compressed tar archives, created natively
+    Zstd,
+}
+
+/// Names of data roots to use for different kinds of data.
+/// The data path is used for anything not assigned a root.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct StorageRoots {
+    /// Root for staging directories
+    pub staging: Option<String>,
+    /// Root for backup archives
+    pub backups: Option<String>,
+    /// Root for old backup archives, moved there after `cold-after-days`
+    pub cold: Option<String>,
+    /// Age in days after which backups are moved to the cold root
+    pub cold_after_days: Option<u64>,
+}
+
+impl StorageRoots {
+    fn is_empty(&self) -> bool {
+        self.staging.is_none() && self.backups.is_none() && self.cold.is_none() && self.cold_after_days.is_none()
+    }
 }
 
 impl MainConfig {
@@ -26,7 +163,24 @@ impl MainConfig {
         file.read_to_string(&mut toml_str)
             .context("Error reading config file")?;
 
-        Self::from_str(&toml_str)
+        let (table, upgraded_from) = migrate::migrate_main_config(&toml_str)?;
+        let (config, unknown_keys): (Self, _) = unknown_keys::deserialize(table.clone(), &toml_str)?;
+
+        if config.strict_config {
+            unknown_keys::deny(&unknown_keys).with_context(|| format!("Error in {}", path.display()))?;
+        }
+
+        for unknown_key in unknown_keys {
+            warn!("{}: {unknown_key}", path.display());
+        }
+
+        // Write back upgraded config, keeping the original.
+        // The table is written rather than the config, so that unknown keys are preserved for the user to fix.
+        if let Some(version) = upgraded_from {
+            migrate::write_upgraded(path, version, &table)?;
+        }
+
+        Ok(config)
     }
 
     pub fn write(&self, path: &Path) -> Result<(), anyhow::Error> {
@@ -46,17 +200,35 @@ impl MainConfig {
 
     /// Load configuration from default location,
     /// creating it if it is missing.
-    pub fn load_or_write_default_from_location(config_location: &Path) -> Result<Self, anyhow::Error> {
+    /// In portable mode, a newly created config keeps data inside the config directory.
+    pub fn load_or_write_default_from_location(config_location: &Path, portable: bool) -> Result<Self, anyhow::Error> {
         let config_file_path = Self::path_from_location(config_location)?;
 
         if config_file_path.exists() {
             Ok(Self::from_file(&config_file_path)?)
         } else {
-            let data_path = dirs::data_local_dir()
-                .context("Get local data directory")?
-                .join(CONFIG_DIR_NAME);
+            let data_path = if portable {
+                PathBuf::from(PORTABLE_DATA_DIR_NAME)
+            } else {
+                dirs::data_local_dir()
+                    .context("Get local data directory")?
+                    .join(CONFIG_DIR_NAME)
+            };
 
-            let config = MainConfig { data_path };
+            let config = MainConfig {
+                version: MAIN_CONFIG_VERSION,
+                data_path,
+                use_index: false,
+                strict_config: false,
+                check_for_updates: false,
+                overlay_status: false,
+                age_identity_file: None,
+                temp_path: None,
+                data_roots: BTreeMap::new(),
+                storage: StorageRoots::default(),
+                archive: ArchiveSettings::default(),
+                engine: EngineSettings::default(),
+            };
 
             // Create parent directory if needed
             fs::create_dir_all(config_location)?;
@@ -67,6 +239,21 @@ impl MainConfig {
             Ok(config)
         }
     }
+
+    /// Data path, with a relative path resolved against the config directory
+    pub fn resolved_data_path(&self, config_location: &Path) -> PathBuf {
+        config_location.join(&self.data_path)
+    }
+
+    /// Path of a named data root, with a relative path resolved against the config directory
+    pub fn resolved_data_root(&self, name: &str, config_location: &Path) -> Result<PathBuf, anyhow::Error> {
+        let path = self
+            .data_roots
+            .get(name)
+            .with_context(|| format!("Data root '{name}' is not defined in data-roots"))?;
+
+        Ok(config_location.join(path))
+    }
 }
 
 impl FromStr for MainConfig {
@@ -88,3 +275,55 @@ pub fn get_default_config_path() -> Option<PathBuf> {
 
     config_path
 }
+
+/// Get the portable directory beside the executable
+pub fn get_portable_config_path() -> Result<PathBuf, anyhow::Error> {
+    let exe_path = std::env::current_exe().context("Getting executable path")?;
+    let exe_dir = exe_path.parent().context("Getting executable directory")?;
+
+    Ok(exe_dir.join(PORTABLE_DIR_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portable_config_keeps_data_in_config_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join(PORTABLE_DIR_NAME);
+
+        let config = MainConfig::load_or_write_default_from_location(&config_path, true).unwrap();
+        assert_eq!(config.data_path, Path::new(PORTABLE_DATA_DIR_NAME));
+        assert_eq!(
+            config.resolved_data_path(&config_path),
+            config_path.join(PORTABLE_DATA_DIR_NAME)
+        );
+
+        // The relative data path is kept when the config is read back, so the directory can be moved
+        let config = MainConfig::load_or_write_default_from_location(&config_path, true).unwrap();
+        assert!(config.data_path.is_relative());
+    }
+
+    #[test]
+    fn data_roots_are_resolved_by_name() {
+        let hdd_path = std::env::temp_dir().join("hdd");
+        let config: MainConfig = format!(
+            "data-path = 'data'\n[data-roots]\nhdd = '{}'\nssd = 'fast'\n",
+            hdd_path.display()
+        )
+        .parse()
+        .unwrap();
+        let config_path = Path::new("config");
+
+        // Relative roots are inside the config directory, absolute ones are used as-is
+        assert_eq!(
+            config.resolved_data_root("ssd", config_path).unwrap(),
+            config_path.join("fast")
+        );
+        assert_eq!(config.resolved_data_root("hdd", config_path).unwrap(), hdd_path);
+
+        let err = config.resolved_data_root("nvme", config_path).unwrap_err();
+        assert!(err.to_string().contains("'nvme' is not defined"), "{err}");
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use toml::{Table, Value};
+use tracing::info;
+
+pub const MAIN_CONFIG_VERSION: u32 = 1;
+pub const GAME_CONFIG_VERSION: u32 = 2;
+
+/// A migration step, upgrading a config from the version preceding its index to the next
+type Migration = fn(&mut Table) -> Result<(), anyhow::Error>;
+
+const MAIN_CONFIG_MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: Versioning introduced
+    |_| Ok(()),
+];
+
+const GAME_CONFIG_MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: Top-level backup interval replaced by the auto-backup section
+    |table| {
+        let interval = table
+            .remove("backup-interval")
+            .or_else(|| table.remove("backup_interval"));
+
+        if let Some(interval) = interval {
+            if !table.contains_key("auto-backup") {
+                let mut auto_backup = Table::new();
+                auto_backup.insert("enabled".to_owned(), Value::Boolean(true));
+                auto_backup.insert("min-interval".to_owned(), interval);
+
+                table.insert("auto-backup".to_owned(), Value::Table(auto_backup));
+            }
+        }
+
+        Ok(())
+    },
+    // 1 -> 2: Single copy-latest path replaced by a list of paths
+    |table| {
+        let path = table
+            .remove("copy-latest-to-path")
+            .or_else(|| table.remove("copy_latest_to_path"));
+
+        if let Some(path) = path {
+            if !table.contains_key("copy-latest-to-paths") {
+                table.insert("copy-latest-to-paths".to_owned(), Value::Array(vec![path]));
+            }
+        }
+
+        Ok(())
+    },
+];
+
+/// Parse a main config, upgrading it to the current version if needed.
+/// Returns the config table, and the version it was upgraded from if an upgrade was performed.
+pub fn migrate_main_config(toml_str: &str) -> Result<(Table, Option<u32>), anyhow::Error> {
+    migrate(toml_str, MAIN_CONFIG_MIGRATIONS)
+}
+
+/// Parse a game config, upgrading it to the current version if needed.
+/// Returns the config table, and the version it was upgraded from if an upgrade was performed.
+pub fn migrate_game_config(toml_str: &str) -> Result<(Table, Option<u32>), anyhow::Error> {
+    migrate(toml_str, GAME_CONFIG_MIGRATIONS)
+}
+
+fn migrate(toml_str: &str, migrations: &[Migration]) -> Result<(Table, Option<u32>), anyhow::Error> {
+    let mut table: Table = toml::from_str(toml_str).context("Error parsing config")?;
+
+    // Configs from before versioning was introduced have no version field
+    let version = table.get("version").and_then(Value::as_integer).unwrap_or(0);
+    let version = u32::try_from(version).context("Invalid config version")?;
+
+    let current_version = migrations.len() as u32;
+
+    if version > current_version {
+        return Err(anyhow::anyhow!(
+            "Config version {version} is newer than the newest supported version {current_version}"
+        ));
+    }
+
+    if version == current_version {
+        return Ok((table, None));
+    }
+
+    for migration in migrations[version as usize..].iter() {
+        migration(&mut table)?;
+    }
+
+    table.insert("version".to_owned(), Value::Integer(current_version.into()));
+
+    Ok((table, Some(version)))
+}
+
+/// Write an upgraded config table, keeping a copy of the config file as it was before being upgraded
+pub fn write_upgraded(path: &Path, version: u32, table: &Table) -> Result<(), anyhow::Error> {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(format!(".v{version}.bak"));
+
+    fs::copy(path, &backup_path).context("Error backing up config file before upgrade")?;
+
+    let toml_str = toml::to_string_pretty(table)?;
+    fs::write(path, toml_str).context("Error writing upgraded config file")?;
+
+    info!(
+        "Upgraded config file {} from version {version}, original saved as {}",
+        path.display(),
+        Path::new(&backup_path).display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_game_config_is_upgraded() {
+        let (table, upgraded_from) = migrate_game_config("backup-interval = 300\n").unwrap();
+
+        assert_eq!(upgraded_from, Some(0));
+        assert_eq!(table["version"].as_integer(), Some(GAME_CONFIG_VERSION.into()));
+        assert!(!table.contains_key("backup-interval"));
+        assert_eq!(table["auto-backup"]["enabled"].as_bool(), Some(true));
+        assert_eq!(table["auto-backup"]["min-interval"].as_integer(), Some(300));
+    }
+
+    #[test]
+    fn single_copy_latest_path_becomes_a_list() {
+        let toml_str = "version = 1\ncopy-latest-to-path = 'D:/Backups'\n";
+        let (table, upgraded_from) = migrate_game_config(toml_str).unwrap();
+
+        assert_eq!(upgraded_from, Some(1));
+        assert!(!table.contains_key("copy-latest-to-path"));
+        assert_eq!(
+            table["copy-latest-to-paths"],
+            Value::Array(vec![Value::String("D:/Backups".to_owned())])
+        );
+    }
+
+    #[test]
+    fn current_config_is_left_as_is() {
+        let toml_str = format!("version = {GAME_CONFIG_VERSION}\nbackup-interval = 300\n");
+        let (table, upgraded_from) = migrate_game_config(&toml_str).unwrap();
+
+        assert_eq!(upgraded_from, None);
+        assert_eq!(table["backup-interval"].as_integer(), Some(300));
+    }
+
+    #[test]
+    fn newer_config_is_rejected() {
+        let toml_str = format!("version = {}\n", MAIN_CONFIG_VERSION + 1);
+        let err = migrate_main_config(&toml_str).unwrap_err();
+
+        assert!(err.to_string().contains("newer than"), "{err}");
+    }
+
+    #[test]
+    fn upgraded_config_is_written_back_keeping_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.toml");
+        let original = "backup-interval = 300\n";
+        fs::write(&path, original).unwrap();
+
+        let (table, upgraded_from) = migrate_game_config(original).unwrap();
+        write_upgraded(&path, upgraded_from.unwrap(), &table).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("game.toml.v0.bak")).unwrap(),
+            original
+        );
+
+        let (written, upgraded_from) = migrate_game_config(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(upgraded_from, None);
+        assert_eq!(written, table);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod game;
 pub mod main;
+mod migrate;
+mod unknown_keys;
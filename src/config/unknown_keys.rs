@@ -0,0 +1,228 @@
+use std::{fmt, ops::Range};
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use toml::Table;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+/// A key in a config file that does not correspond to any setting
+#[derive(Clone, Debug)]
+pub struct UnknownKey {
+    pub key: String,
+    /// Line number in the config file, if the key could be located
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "unknown key '{}' on line {line}", self.key),
+            None => write!(f, "unknown key '{}'", self.key),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Deserialize a config table, collecting keys that were ignored.
+/// Line numbers are looked up in the original config text.
+pub fn deserialize<T: DeserializeOwned>(table: Table, toml_str: &str) -> Result<(T, Vec<UnknownKey>), anyhow::Error> {
+    let mut ignored = Vec::new();
+
+    let config = serde_ignored::deserialize(toml::Value::Table(table), |path| {
+        let mut segments = Vec::new();
+        collect_segments(&path, &mut segments);
+        ignored.push(segments);
+    })
+    .context("Error parsing config")?;
+
+    let document = ImDocument::parse(toml_str).ok();
+
+    let unknown_keys = ignored
+        .into_iter()
+        .map(|segments| {
+            let line = document
+                .as_ref()
+                .and_then(|doc| find_key_span(doc.as_table(), &segments))
+                .map(|span| line_number(toml_str, span));
+
+            UnknownKey {
+                key: format_segments(&segments),
+                line,
+            }
+        })
+        .collect();
+
+    Ok((config, unknown_keys))
+}
+
+/// Fail if unknown keys were found
+pub fn deny(unknown_keys: &[UnknownKey]) -> Result<(), anyhow::Error> {
+    if unknown_keys.is_empty() {
+        return Ok(());
+    }
+
+    let list = unknown_keys
+        .iter()
+        .map(|k| format!("  {k}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Err(anyhow::anyhow!("Config contains unknown keys (strict mode):\n{list}"))
+}
+
+fn collect_segments(path: &serde_ignored::Path<'_>, segments: &mut Vec<Segment>) {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => {}
+        Path::Seq { parent, index } => {
+            collect_segments(parent, segments);
+            segments.push(Segment::Index(*index));
+        }
+        Path::Map { parent, key } => {
+            collect_segments(parent, segments);
+            segments.push(Segment::Key(key.clone()));
+        }
+        Path::Some { parent } | Path::NewtypeStruct { parent } | Path::NewtypeVariant { parent } => {
+            collect_segments(parent, segments);
+        }
+    }
+}
+
+fn format_segments(segments: &[Segment]) -> String {
+    let mut s = String::new();
+
+    for segment in segments {
+        match segment {
+            Segment::Key(key) => {
+                if !s.is_empty() {
+                    s.push('.');
+                }
+                s.push_str(key);
+            }
+            Segment::Index(index) => s.push_str(&format!("[{index}]")),
+        }
+    }
+
+    s
+}
+
+fn find_key_span(table: &dyn TableLike, segments: &[Segment]) -> Option<Range<usize>> {
+    match segments {
+        [Segment::Key(key)] => table.get_key_value(key).and_then(|(key, _)| key.span()),
+        [Segment::Key(key), rest @ ..] => find_key_span_in_item(table.get(key)?, rest),
+        _ => None,
+    }
+}
+
+fn find_key_span_in_item(item: &Item, segments: &[Segment]) -> Option<Range<usize>> {
+    match segments.first()? {
+        Segment::Index(index) => match item {
+            Item::ArrayOfTables(tables) => find_key_span(tables.get(*index)?, &segments[1..]),
+            Item::Value(Value::Array(array)) => match array.get(*index)? {
+                Value::InlineTable(table) => find_key_span(table, &segments[1..]),
+                _ => None,
+            },
+            _ => None,
+        },
+        Segment::Key(_) => find_key_span(item.as_table_like()?, segments),
+    }
+}
+
+fn line_number(s: &str, span: Range<usize>) -> usize {
+    s[..span.start].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    #[allow(dead_code)]
+    struct Config {
+        name: String,
+        #[serde(default)]
+        dirs: Vec<Dir>,
+        #[serde(default)]
+        engine: Option<Engine>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Dir {
+        path: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    #[allow(dead_code)]
+    struct Engine {
+        check_interval: u64,
+    }
+
+    fn unknown_keys(toml_str: &str) -> Vec<String> {
+        let table = toml::from_str(toml_str).unwrap();
+        let (_, unknown_keys): (Config, _) = deserialize(table, toml_str).unwrap();
+
+        // Keys are found in the order of the parsed table, not the file
+        let mut unknown_keys: Vec<_> = unknown_keys.iter().map(|k| k.to_string()).collect();
+        unknown_keys.sort();
+
+        unknown_keys
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_with_line_numbers() {
+        let toml_str = "\
+name = 'game'
+nmae = 'typo'
+
+[engine]
+check-interval = 1
+check-intreval = 2
+
+[[dirs]]
+path = 'a'
+
+[[dirs]]
+path = 'b'
+pth = 'c'
+";
+
+        assert_eq!(
+            unknown_keys(toml_str),
+            [
+                "unknown key 'dirs[1].pth' on line 13",
+                "unknown key 'engine.check-intreval' on line 6",
+                "unknown key 'nmae' on line 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_in_inline_tables_are_located() {
+        let toml_str = "name = 'game'\ndirs = [\n  { path = 'a' },\n  { path = 'b', extra = 1 },\n]\n";
+
+        assert_eq!(unknown_keys(toml_str), ["unknown key 'dirs[1].extra' on line 4"]);
+    }
+
+    #[test]
+    fn strict_mode_denies_unknown_keys() {
+        assert!(deny(&[]).is_ok());
+
+        let err = deny(&[UnknownKey {
+            key: "nmae".to_owned(),
+            line: Some(2),
+        }])
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown key 'nmae' on line 2"), "{err}");
+    }
+}
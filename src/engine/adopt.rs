@@ -0,0 +1,273 @@
+//! Import of backups made before stool, from a folder of loose save folders and archives
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use filetime::FileTime;
+use regex::Regex;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+use crate::{
+    config::game::GameConfig,
+    internal::{
+        archive::{Archiver, SevenZip},
+        sync::{self, CopyOptions, Deletion, SyncEvents, SyncOptions},
+    },
+};
+
+use super::{
+    backups::{sanitize_description, strip_archive_extension, BackupInfo},
+    index::BackupIndex,
+    manifest::{manifest_path, Manifest},
+    BackupKind, EngineArgs, ARCHIVE_DATE_FORMAT,
+};
+
+/// Prefix of the description of adopted backups, followed by the name they had
+const ADOPTED_DESCRIPTION: &str = "Adopted";
+
+/// Date, and optionally time, in the names of backups made by hand, such as "2023-05-01_21-30" or "20230501"
+static TIMESTAMP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d{4})[-_.]?(\d{2})[-_.]?(\d{2})(?:[ _T.-]?(\d{2})[-_.:h]?(\d{2})(?:[-_.:m]?(\d{2}))?)?").unwrap()
+});
+
+/// Backup made by hand, found in the folder being adopted
+enum Source {
+    Folder,
+    /// Archive in a format stool reads
+    Archive,
+    /// Zip archive, read with 7z
+    Zip,
+}
+
+/// Backup that was left alone, with the reason
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Default)]
+pub struct AdoptReport {
+    /// Names of the backups created
+    pub adopted: Vec<String>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Skipped {}: {}", self.path.display(), self.reason)
+    }
+}
+
+/// Import the folders and archives in a directory as backups, timestamped by the date in their names,
+/// or by when they were last modified.
+/// Backups already adopted are skipped, so that adopting can be repeated after adding more.
+pub fn adopt_backups(args: &EngineArgs, dir: &Path, events: &SyncEvents) -> Result<AdoptReport, anyhow::Error> {
+    let gcfg = GameConfig::from_file(&args.game_config_file_path(), args.strict_config)?;
+    let save_dir_names: Vec<&str> = gcfg.save_dirs.keys().map(String::as_str).collect();
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Error reading {}", dir.display()))?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    let backup_path = args.backup_path();
+    fs::create_dir_all(&backup_path)?;
+
+    // Backups are unpacked and laid out here while adopting them
+    let temp_path = args.temp_path(&gcfg);
+    args.archiver.set_temp_path(&temp_path);
+    let work_path = temp_path.join(format!("stool-adopt-{}-{}", args.name, std::process::id()));
+    let mut report = AdoptReport::default();
+
+    for path in entries {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let (source, stem) = if path.is_dir() {
+            (Source::Folder, file_name.as_str())
+        } else if let Some(stem) = strip_archive_extension(&file_name) {
+            (Source::Archive, stem)
+        } else if let Some(stem) = file_name.strip_suffix(".zip") {
+            (Source::Zip, stem)
+        } else {
+            report.skipped.push(Skipped {
+                path,
+                reason: "Not a folder or archive".to_owned(),
+            });
+            continue;
+        };
+
+        let timestamp = match parse_timestamp(stem) {
+            Some(timestamp) => timestamp,
+            None => modified_at(&path)?,
+        };
+
+        let description = sanitize_description(&format!("{ADOPTED_DESCRIPTION} {stem}"));
+        let archive_name = format!(
+            "{} {description}.{}",
+            timestamp.format(ARCHIVE_DATE_FORMAT)?,
+            args.archiver.extension()
+        );
+        let archive_path = backup_path.join(&archive_name);
+
+        if archive_path.exists() {
+            report.skipped.push(Skipped {
+                path,
+                reason: format!("Already adopted as {archive_name}"),
+            });
+            continue;
+        }
+
+        if work_path.exists() {
+            fs::remove_dir_all(&work_path)?;
+        }
+
+        let result = adopt_backup(args, &path, source, &work_path, &save_dir_names, events).and_then(|content_path| {
+            let Some(content_path) = content_path else {
+                return Ok(false);
+            };
+
+            let mut manifest = Manifest::build(&args.name, BackupKind::Manual, &content_path, None, events)?;
+            manifest.created_at = timestamp;
+
+            args.archiver.create(&content_path, &archive_path)?;
+            manifest.write(&manifest_path(&archive_path))?;
+
+            // Backups are ordered by modification time, which should be when they were made
+            filetime::set_file_mtime(&archive_path, FileTime::from_system_time(SystemTime::from(timestamp)))?;
+
+            if args.use_index {
+                let backup = BackupInfo {
+                    name: archive_name.clone(),
+                    path: archive_path.clone(),
+                    modified: fs::metadata(&archive_path)?.modified()?,
+                };
+
+                BackupIndex::open(&args.output_path())?.add_backup(&backup, Some(&manifest))?;
+            }
+
+            Ok(true)
+        });
+
+        fs::remove_dir_all(&work_path).ok();
+
+        match result {
+            Ok(true) => report.adopted.push(archive_name),
+            Ok(false) => report.skipped.push(Skipped {
+                path,
+                reason: "Could not tell which save dir its files belong to".to_owned(),
+            }),
+            Err(err) => {
+                // A partly written archive would pass for an adopted backup on the next run
+                fs::remove_file(&archive_path).ok();
+
+                report.skipped.push(Skipped {
+                    path,
+                    reason: format!("{err:#}"),
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Lay out the files of a backup made by hand like those of a stool backup, with a directory per save dir.
+/// Returns the directory they are laid out in, or none if it cannot be told which save dir they belong to.
+fn adopt_backup(
+    args: &EngineArgs,
+    path: &Path,
+    source: Source,
+    work_path: &Path,
+    save_dir_names: &[&str],
+    events: &SyncEvents,
+) -> Result<Option<PathBuf>, anyhow::Error> {
+    let unpacked_path = work_path.join("unpacked");
+    let content_path = work_path.join("content");
+
+    let files_path = match source {
+        Source::Folder => path.to_owned(),
+        Source::Archive => {
+            fs::create_dir_all(&unpacked_path)?;
+            args.archiver.unpack(path, &unpacked_path)?;
+
+            unpacked_path
+        }
+        Source::Zip => {
+            fs::create_dir_all(&unpacked_path)?;
+            SevenZip::new(0).unpack(path, &unpacked_path)?;
+
+            unpacked_path
+        }
+    };
+
+    let top_level: Vec<PathBuf> = fs::read_dir(&files_path)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    let is_save_dir = |p: &PathBuf| {
+        p.file_name()
+            .is_some_and(|name| save_dir_names.iter().any(|n| name == *n))
+    };
+
+    let (src_path, dst_path) = if top_level.iter().any(is_save_dir) {
+        // Already laid out like a stool backup
+        (files_path, content_path.clone())
+    } else if let [save_dir_name] = save_dir_names {
+        // Archives made by hand often hold the save folder itself, rather than its contents
+        let src_path = match top_level.as_slice() {
+            [only] if only.is_dir() => only.clone(),
+            _ => files_path,
+        };
+
+        (src_path, content_path.join(save_dir_name))
+    } else {
+        return Ok(None);
+    };
+
+    let options = SyncOptions {
+        include_globset: None,
+        ignore_globset: None,
+        exclude: &[],
+        filter_in_dst: false,
+        streaming: false,
+        copy: CopyOptions::default(),
+        deletion: Deletion::Remove,
+    };
+
+    sync::sync_dir(&src_path, &dst_path, options, events)?;
+
+    Ok(Some(content_path))
+}
+
+/// Date and time in a backup name, taken to be local time, or midnight if it has only a date
+fn parse_timestamp(name: &str) -> Option<OffsetDateTime> {
+    TIMESTAMP_REGEX.captures_iter(name).find_map(|captures| {
+        let number = |ix: usize| captures.get(ix).map_or(Some(0), |m| m.as_str().parse::<u8>().ok());
+
+        let year = captures[1].parse().ok()?;
+        let month = Month::try_from(captures[2].parse::<u8>().ok()?).ok()?;
+        let date = Date::from_calendar_date(year, month, captures[3].parse().ok()?).ok()?;
+        let time = Time::from_hms(number(4)?, number(5)?, number(6)?).ok()?;
+
+        Some(PrimitiveDateTime::new(date, time).assume_offset(local_offset()))
+    })
+}
+
+/// When a folder or archive was last modified, in local time
+fn modified_at(path: &Path) -> Result<OffsetDateTime, anyhow::Error> {
+    let modified = fs::metadata(path)?.modified()?;
+
+    Ok(OffsetDateTime::from(modified).to_offset(local_offset()))
+}
+
+fn local_offset() -> UtcOffset {
+    UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)
+}
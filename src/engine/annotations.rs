@@ -0,0 +1,89 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde_derive::{Deserialize, Serialize};
+
+pub const ANNOTATIONS_FILENAME: &str = "annotations.json";
+
+/// User-provided information about a backup
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Annotation {
+    /// Pinned backups are never deleted by retention
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Annotation {
+    fn is_empty(&self) -> bool {
+        !self.pinned && self.note.is_none()
+    }
+}
+
+/// Pins and notes of the backups of a game, by archive name
+pub struct Annotations {
+    path: PathBuf,
+    backups: BTreeMap<String, Annotation>,
+}
+
+impl Annotations {
+    pub fn load(output_path: &Path) -> Result<Self, anyhow::Error> {
+        let path = output_path.join(ANNOTATIONS_FILENAME);
+
+        let backups = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).context("Error parsing annotations")?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self { path, backups })
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first, so that a crash never leaves truncated annotations
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&self.backups)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, archive_name: &str) -> Option<&Annotation> {
+        self.backups.get(archive_name)
+    }
+
+    pub fn is_pinned(&self, archive_name: &str) -> bool {
+        self.get(archive_name).is_some_and(|a| a.pinned)
+    }
+
+    pub fn set_pinned(&mut self, archive_name: &str, pinned: bool) {
+        self.update(archive_name, |a| a.pinned = pinned);
+    }
+
+    /// Set the note of a backup. An empty note removes it.
+    pub fn set_note(&mut self, archive_name: &str, note: &str) {
+        let note = note.trim();
+
+        self.update(archive_name, |a| a.note = (!note.is_empty()).then(|| note.to_owned()));
+    }
+
+    fn update(&mut self, archive_name: &str, f: impl FnOnce(&mut Annotation)) {
+        let annotation = self.backups.entry(archive_name.to_owned()).or_default();
+        f(annotation);
+
+        if annotation.is_empty() {
+            self.backups.remove(archive_name);
+        }
+    }
+}
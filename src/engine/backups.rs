@@ -0,0 +1,236 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use time::PrimitiveDateTime;
+
+use crate::internal::{archive::ArchiveEntry, parity::parity_path};
+
+use super::{
+    delta,
+    index::BackupIndex,
+    manifest::{manifest_path, Manifest},
+    partial, EngineArgs, ARCHIVE_DATE_FORMAT,
+};
+
+/// Extensions of supported archive formats
+pub const ARCHIVE_EXTENSIONS: &[&str] = &["7z", "tar.zst"];
+
+/// Description used for automatically created backups
+pub const AUTO_BACKUP_DESCRIPTION: &str = "Auto";
+
+/// Separates the description of auto-backups from details taken from save metadata
+const AUTO_BACKUP_DETAIL_SEPARATOR: &str = " - ";
+
+/// Description used for backups created when the engine shuts down
+pub const EXIT_BACKUP_DESCRIPTION: &str = "Exit";
+
+/// Description used for backups requested through an empty trigger file
+pub const TRIGGER_BACKUP_DESCRIPTION: &str = "Trigger";
+
+/// Description used for manual backups created with a single key press
+pub const QUICK_BACKUP_DESCRIPTION: &str = "Quick";
+
+#[derive(Clone, Debug)]
+pub struct BackupInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+impl BackupInfo {
+    /// Timestamp and description parsed from the archive name
+    pub fn parse_name(&self) -> Option<(PrimitiveDateTime, &str)> {
+        parse_backup_name(&self.name)
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.parse_name().map(|(_, description)| description)
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.description().is_some_and(|description| {
+            description == AUTO_BACKUP_DESCRIPTION
+                || description
+                    .strip_prefix(AUTO_BACKUP_DESCRIPTION)
+                    .is_some_and(|rest| rest.starts_with(AUTO_BACKUP_DETAIL_SEPARATOR))
+        })
+    }
+}
+
+/// List backup archives in a directory, newest first
+pub fn list_backups(backup_path: &Path) -> Result<Vec<BackupInfo>, anyhow::Error> {
+    if !backup_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<_> = fs::read_dir(backup_path)?
+        .filter_map(Result::ok)
+        .filter_map(|e| {
+            let path = e.path();
+
+            let name = path.file_name()?.to_string_lossy().to_string();
+
+            if !path.is_file() || strip_archive_extension(&name).is_none() {
+                return None;
+            }
+
+            let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+
+            Some(BackupInfo { name, path, modified })
+        })
+        .collect();
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.modified));
+
+    Ok(backups)
+}
+
+/// List backup archives in several directories, newest first
+pub fn list_backups_in(backup_paths: &[PathBuf]) -> Result<Vec<BackupInfo>, anyhow::Error> {
+    let mut backups = Vec::new();
+
+    for backup_path in backup_paths {
+        backups.extend(list_backups(backup_path)?);
+    }
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.modified));
+
+    Ok(backups)
+}
+
+/// List the backups of a game, newest first.
+/// Uses the backup index if it is enabled, otherwise scans the backup directories.
+pub fn list_game_backups(args: &EngineArgs) -> Result<Vec<BackupInfo>, anyhow::Error> {
+    if args.use_index {
+        return BackupIndex::open(&args.output_path())?.list();
+    }
+
+    list_backups_in(&args.backup_paths())
+}
+
+/// Find backups of a game whose name, or the path of any file they contain, includes the search term
+pub fn search_game_backups(args: &EngineArgs, term: &str) -> Result<Vec<BackupInfo>, anyhow::Error> {
+    if args.use_index {
+        return BackupIndex::open(&args.output_path())?.search(term);
+    }
+
+    let term = term.to_lowercase();
+
+    let backups = list_backups_in(&args.backup_paths())?
+        .into_iter()
+        .filter(|b| {
+            if b.name.to_lowercase().contains(&term) {
+                return true;
+            }
+
+            let Ok(Some(manifest)) = Manifest::load_for_archive(&b.path) else {
+                return false;
+            };
+
+            manifest
+                .files
+                .iter()
+                .any(|f| f.path.to_string_lossy().to_lowercase().contains(&term))
+        })
+        .collect();
+
+    Ok(backups)
+}
+
+/// Delete a backup of a game, removing it from the backup index if it is enabled
+pub fn delete_game_backup(args: &EngineArgs, backup: &BackupInfo) -> Result<(), anyhow::Error> {
+    delta::check_deletable(args, &backup.name)?;
+    partial::check_deletable(args, &backup.name)?;
+    delete_backup(backup)?;
+
+    if args.use_index {
+        BackupIndex::open(&args.output_path())?.remove_backup(&backup.name)?;
+    }
+
+    Ok(())
+}
+
+/// Delete a backup archive along with its manifest and parity data
+pub fn delete_backup(backup: &BackupInfo) -> Result<(), anyhow::Error> {
+    fs::remove_file(&backup.path)?;
+
+    for path in [manifest_path(&backup.path), parity_path(&backup.path)] {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve an archive name to the path of the archive in one of the backup directories.
+/// Paths to existing archive files outside the backup directories are accepted as-is.
+pub fn resolve_archive(backup_paths: &[PathBuf], archive: &str) -> Result<PathBuf, anyhow::Error> {
+    for backup_path in backup_paths {
+        let archive_path = backup_path.join(archive);
+
+        if archive_path.is_file() {
+            return Ok(archive_path);
+        }
+    }
+
+    let archive_path = PathBuf::from(archive);
+
+    if archive_path.is_file() {
+        return Ok(archive_path);
+    }
+
+    Err(anyhow::anyhow!("Backup not found: {archive}"))
+}
+
+/// List the contents of a backup as restoring it leaves them, without unpacking it
+pub fn list_backup_contents(args: &EngineArgs, archive: &str) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+    partial::list_entries(args, &resolve_archive(&args.backup_paths(), archive)?)
+}
+
+/// Archive name without its extension, if it has the extension of a supported archive format
+pub fn strip_archive_extension(name: &str) -> Option<&str> {
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext)?.strip_suffix('.'))
+}
+
+/// Description of an auto-backup, with details such as the character name
+pub fn auto_backup_description(details: &str) -> String {
+    format!("{AUTO_BACKUP_DESCRIPTION}{AUTO_BACKUP_DETAIL_SEPARATOR}{details}")
+}
+
+/// Replace the description in an archive name, keeping its timestamp and extension
+pub fn with_description(name: &str, description: &str) -> Option<String> {
+    let stem = strip_archive_extension(name)?;
+    let extension = &name[stem.len()..];
+
+    let (date, rest) = stem.split_once(' ')?;
+    let time = rest.split_once(' ').map_or(rest, |(time, _)| time);
+
+    Some(format!("{date} {time} {description}{extension}"))
+}
+
+/// Remove characters that cannot be used in filenames from a backup description
+pub fn sanitize_description(description: &str) -> String {
+    description
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect()
+}
+
+/// Split an archive name into its timestamp and description
+pub fn parse_backup_name(name: &str) -> Option<(PrimitiveDateTime, &str)> {
+    let stem = strip_archive_extension(name)?;
+
+    // The timestamp consists of a date and a time, separated by a space
+    let (date, rest) = stem.split_once(' ')?;
+    let (time, description) = rest.split_once(' ').unwrap_or((rest, ""));
+
+    let timestamp = PrimitiveDateTime::parse(&format!("{date} {time}"), ARCHIVE_DATE_FORMAT).ok()?;
+
+    Some((timestamp, description))
+}
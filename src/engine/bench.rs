@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::game::GameConfig,
+    internal::{
+        archive::{Archiver, SevenZip},
+        hash::hash_crc32,
+        sync::{self, CopyOptions, SyncEvents},
+        tar_zstd::TarZstd,
+    },
+};
+
+use super::{copy_options, dryrun::BackupPlan, EngineArgs, InternalGameSaveDir};
+
+/// Results of benchmarking the backup pipeline on a game's save data
+pub struct BenchReport {
+    pub file_count: usize,
+    pub total_size: u64,
+    pub scan_time: Duration,
+    pub copy_time: Duration,
+    pub hash_time: Duration,
+    pub compression: Vec<CompressionResult>,
+}
+
+/// Result of compressing the save data with one archive format and level
+pub struct CompressionResult {
+    pub format: &'static str,
+    pub level: u32,
+    /// Compression time and archive size
+    pub result: Result<(Duration, u64), anyhow::Error>,
+}
+
+impl BenchReport {
+    /// Pick the fastest setting whose archive is at most 5% larger than the smallest one
+    pub fn recommendation(&self) -> Option<&CompressionResult> {
+        let successful = || {
+            self.compression
+                .iter()
+                .filter_map(|c| c.result.as_ref().ok().map(|(time, size)| (c, *time, *size)))
+        };
+
+        let smallest = successful().map(|(_, _, size)| size).min()?;
+        let threshold = smallest + smallest / 20;
+
+        successful()
+            .filter(|(_, _, size)| *size <= threshold)
+            .min_by_key(|(_, time, _)| *time)
+            .map(|(c, _, _)| c)
+    }
+}
+
+/// Archivers to compare, as format name, level and archiver.
+/// Zstd uses all CPU cores and long-distance matching.
+fn candidates() -> Vec<(&'static str, u32, Box<dyn Archiver>)> {
+    let seven_zip = [1, 3, 5, 7, 9]
+        .into_iter()
+        .map(|level| ("7z", level, Box::new(SevenZip::new(level)) as Box<dyn Archiver>));
+
+    let zstd = [1, 3, 9, 15, 19].into_iter().map(|level| {
+        let archiver = TarZstd {
+            level,
+            threads: 0,
+            long_distance_matching: true,
+            store_only: Vec::new(),
+        };
+
+        ("zstd", level, Box::new(archiver) as Box<dyn Archiver>)
+    });
+
+    seven_zip.chain(zstd).collect()
+}
+
+/// Measure scanning, copying, hashing and compressing the save data of a game.
+/// Save data is copied to a temporary directory, which is removed afterwards.
+pub fn run(args: &EngineArgs, events: &SyncEvents) -> Result<BenchReport, anyhow::Error> {
+    let gcfg = GameConfig::from_file(&args.game_config_file_path(), args.strict_config)?;
+    let save_dirs = InternalGameSaveDir::from_config(&gcfg)?;
+    let own_paths = args.own_paths();
+    InternalGameSaveDir::check_own_paths(&save_dirs, &own_paths)?;
+
+    let bench_path = args
+        .temp_path(&gcfg)
+        .join(format!("stool-bench-{}-{}", args.name, std::process::id()));
+    let staging_path = bench_path.join("staging");
+
+    let result = (|| {
+        let started_at = Instant::now();
+        let plan = BackupPlan::scan(&save_dirs, &gcfg.save_files, &[])?;
+        let scan_time = started_at.elapsed();
+
+        let started_at = Instant::now();
+        stage(
+            &save_dirs,
+            &gcfg.save_files,
+            &staging_path,
+            &own_paths,
+            copy_options(&gcfg),
+            events,
+        )?;
+        let copy_time = started_at.elapsed();
+
+        let started_at = Instant::now();
+        for file in staged_files(&staging_path) {
+            hash_crc32(&file, |_| {})?;
+        }
+        let hash_time = started_at.elapsed();
+
+        let compression = candidates()
+            .into_iter()
+            .map(|(format, level, archiver)| {
+                let archive_path = bench_path.join(format!("bench-{format}-{level}.{}", archiver.extension()));
+
+                let started_at = Instant::now();
+                let result = archiver.create(&staging_path, &archive_path).and_then(|_| {
+                    let time = started_at.elapsed();
+                    let size = fs::metadata(&archive_path)?.len();
+                    fs::remove_file(&archive_path)?;
+
+                    Ok((time, size))
+                });
+
+                CompressionResult { format, level, result }
+            })
+            .collect();
+
+        Ok(BenchReport {
+            file_count: plan.files.len(),
+            total_size: plan.total_size(),
+            scan_time,
+            copy_time,
+            hash_time,
+            compression,
+        })
+    })();
+
+    fs::remove_dir_all(&bench_path).ok();
+
+    result
+}
+
+/// Copy save data into a staging directory, laid out like in a backup
+fn stage(
+    save_dirs: &[InternalGameSaveDir],
+    save_files: &[crate::config::game::GameSaveFile],
+    staging_path: &Path,
+    own_paths: &[PathBuf],
+    copy: CopyOptions,
+    events: &SyncEvents,
+) -> Result<(), anyhow::Error> {
+    for gsp in save_dirs.iter().filter(|gsp| gsp.path.exists()) {
+        sync::sync_dir(
+            &gsp.path,
+            &staging_path.join(&gsp.name),
+            gsp.sync_options(own_paths, false, copy),
+            events,
+        )?;
+    }
+
+    for gsf in save_files.iter().filter(|gsf| gsf.path.is_file()) {
+        let staging_dir_path = match gsf.staging_subdirectory.as_ref() {
+            Some(staging_subdir) => staging_path.join(staging_subdir),
+            None => staging_path.to_owned(),
+        };
+
+        sync::sync_file(&gsf.path, &staging_dir_path, copy, events)?;
+    }
+
+    Ok(())
+}
+
+fn staged_files(staging_path: &Path) -> impl Iterator<Item = PathBuf> {
+    walkdir::WalkDir::new(staging_path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressed(level: u32, millis: u64, size: u64) -> CompressionResult {
+        CompressionResult {
+            format: "zstd",
+            level,
+            result: Ok((Duration::from_millis(millis), size)),
+        }
+    }
+
+    fn failed(level: u32) -> CompressionResult {
+        CompressionResult {
+            format: "zstd",
+            level,
+            result: Err(anyhow::anyhow!("Compression failed")),
+        }
+    }
+
+    fn report(compression: Vec<CompressionResult>) -> BenchReport {
+        BenchReport {
+            file_count: 1,
+            total_size: 1000,
+            scan_time: Duration::ZERO,
+            copy_time: Duration::ZERO,
+            hash_time: Duration::ZERO,
+            compression,
+        }
+    }
+
+    #[test]
+    fn fastest_setting_close_to_the_smallest_archive_is_recommended() {
+        let report = report(vec![
+            compressed(1, 10, 200),
+            compressed(3, 20, 104),
+            compressed(9, 50, 102),
+            compressed(19, 400, 100),
+            failed(22),
+        ]);
+
+        assert_eq!(report.recommendation().map(|c| c.level), Some(3));
+    }
+
+    #[test]
+    fn nothing_is_recommended_if_every_setting_failed() {
+        let report = report(vec![failed(1), failed(9)]);
+
+        assert!(report.recommendation().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Control socket, allowing other stool processes to query and drive a running engine.
+//!
+//! The engine listens on a local TCP port, whose address is written along with an access token
+//! to a file in the data directory of the game. Each connection carries a single request and response,
+//! as lines of JSON, except for subscriptions, which receive UI events until either side disconnects.
+
+use std::{
+    fmt, fs,
+    hash::{BuildHasher, RandomState},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::mpsc::Receiver,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use serde_derive::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use super::{ui::UiEvent, watch::WatchStatus, BackupRequest, EngineControl, EngineSnapshot, EngineState};
+
+pub const CONTROL_FILENAME: &str = "control.json";
+
+/// Interval at which the server checks for new connections and engine shutdown
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a running engine can be reached
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ControlInfo {
+    addr: SocketAddr,
+    token: String,
+}
+
+/// Request to a running engine
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "command")]
+pub enum ControlRequest {
+    Watches,
+    Snapshot,
+    /// Receive UI events as they happen
+    Subscribe,
+    Send {
+        request: BackupRequest,
+    },
+    SetAutobackup {
+        enabled: bool,
+    },
+}
+
+/// Response from a running engine
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlResponse {
+    Watches(Vec<WatchStatus>),
+    Snapshot(Box<EngineSnapshot>),
+    Event(UiEvent),
+    Ok,
+    Error(String),
+}
+
+/// No engine is running for the game, or it exited without cleaning up
+#[derive(Debug)]
+pub struct NotRunning;
+
+impl fmt::Display for NotRunning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Engine is not running")
+    }
+}
+
+impl std::error::Error for NotRunning {}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Envelope {
+    token: String,
+    request: ControlRequest,
+}
+
+/// Generate a token, so that only processes able to read the data directory can control the engine
+fn generate_token() -> String {
+    // The standard hasher is randomly keyed by the OS, and serves as a source of randomness here
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().hash_one(SystemTime::now())))
+        .collect()
+}
+
+/// Start listening for control requests, until the engine has shut down
+pub fn spawn_control_server(output_path: &Path, control: EngineControl) -> Result<(), anyhow::Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).context("Binding control socket")?;
+    listener.set_nonblocking(true)?;
+
+    let info = ControlInfo {
+        addr: listener.local_addr()?,
+        token: generate_token(),
+    };
+
+    let info_path = output_path.join(CONTROL_FILENAME);
+    fs::write(&info_path, serde_json::to_vec(&info)?)?;
+
+    std::thread::spawn(move || {
+        while control.state() != EngineState::ShutDown {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(err) = handle_connection(stream, &info.token, &control) {
+                        debug!("Control connection error: {err}");
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_INTERVAL),
+                Err(err) => {
+                    error!("Control socket error: {err}");
+                    break;
+                }
+            }
+        }
+
+        fs::remove_file(&info_path).ok();
+    });
+
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, token: &str, control: &EngineControl) -> Result<(), anyhow::Error> {
+    // Accepted sockets inherit non-blocking mode from the listener on some platforms
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match serde_json::from_str::<Envelope>(&line) {
+        Ok(envelope) if envelope.token != token => ControlResponse::Error("Invalid token".to_owned()),
+        Ok(envelope) => match envelope.request {
+            ControlRequest::Watches => ControlResponse::Watches(control.watches()),
+            ControlRequest::Snapshot => ControlResponse::Snapshot(Box::new(control.snapshot())),
+            ControlRequest::Subscribe => {
+                // Streamed on its own thread, so that other connections are still served
+                let events = control.subscribe();
+                std::thread::spawn(move || {
+                    if let Err(err) = stream_events(stream, events) {
+                        debug!("Control subscription ended: {err}");
+                    }
+                });
+
+                return Ok(());
+            }
+            ControlRequest::Send { request } => match control.send(request) {
+                Ok(()) => ControlResponse::Ok,
+                Err(err) => ControlResponse::Error(err.to_string()),
+            },
+            ControlRequest::SetAutobackup { enabled } => {
+                control.set_autobackup(enabled);
+                ControlResponse::Ok
+            }
+        },
+        Err(err) => ControlResponse::Error(format!("Invalid request: {err}")),
+    };
+
+    write_response(&stream, &response)
+}
+
+fn stream_events(stream: TcpStream, events: Receiver<UiEvent>) -> Result<(), anyhow::Error> {
+    for event in events {
+        write_response(&stream, &ControlResponse::Event(event))?;
+    }
+
+    Ok(())
+}
+
+fn write_response(mut stream: &TcpStream, response: &ControlResponse) -> Result<(), anyhow::Error> {
+    serde_json::to_writer(stream, response)?;
+    stream.write_all(b"\n")?;
+
+    Ok(())
+}
+
+/// Send a request to the engine running for a game
+pub fn request(output_path: &Path, request: ControlRequest) -> Result<ControlResponse, anyhow::Error> {
+    let stream = connect(output_path, request)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    match serde_json::from_str(&line).context("Error parsing control response")? {
+        ControlResponse::Error(err) => Err(anyhow::anyhow!("Engine: {err}")),
+        response => Ok(response),
+    }
+}
+
+/// Follow the UI events of the engine running for a game.
+/// The iterator ends once the engine shuts down.
+pub fn subscribe(output_path: &Path) -> Result<impl Iterator<Item = UiEvent>, anyhow::Error> {
+    let stream = connect(output_path, ControlRequest::Subscribe)?;
+
+    // Events only arrive while the engine is doing something
+    stream.set_read_timeout(None)?;
+
+    Ok(BufReader::new(stream)
+        .lines()
+        .map_while(Result::ok)
+        .map_while(|line| match serde_json::from_str(&line) {
+            Ok(ControlResponse::Event(event)) => Some(event),
+            Ok(response) => {
+                debug!("Unexpected response to subscription: {response:?}");
+                None
+            }
+            Err(err) => {
+                debug!("Error parsing UI event: {err}");
+                None
+            }
+        }))
+}
+
+/// Connect to the engine running for a game, and send a request
+fn connect(output_path: &Path, request: ControlRequest) -> Result<TcpStream, anyhow::Error> {
+    let info_path = output_path.join(CONTROL_FILENAME);
+    let info: ControlInfo = match fs::read(&info_path) {
+        Ok(data) => serde_json::from_slice(&data).context("Error parsing control info")?,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Err(NotRunning.into()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut stream = match TcpStream::connect_timeout(&info.addr, TIMEOUT) {
+        Ok(stream) => stream,
+        // Control info left behind by an engine that did not shut down cleanly
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => return Err(NotRunning.into()),
+        Err(err) => return Err(anyhow::Error::new(err).context("Connecting to engine")),
+    };
+    stream.set_read_timeout(Some(TIMEOUT))?;
+
+    serde_json::to_writer(
+        &mut stream,
+        &Envelope {
+            token: info.token,
+            request,
+        },
+    )?;
+    stream.write_all(b"\n")?;
+
+    Ok(stream)
+}
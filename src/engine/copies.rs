@@ -0,0 +1,98 @@
+//! Copies of the latest backup in copy-latest paths, which several games may share
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::config::game::GameConfig;
+
+/// Placeholder for the file name of the copied backup in copy-latest name templates
+pub const NAME_PLACEHOLDER: &str = "{name}";
+/// Placeholder for the game name in copy-latest name templates
+pub const GAME_PLACEHOLDER: &str = "{game}";
+
+/// Where and under which name the latest backup of a game is copied to
+pub struct CopyNaming {
+    /// Name of the copies up to the backup file name
+    prefix: String,
+    /// Name of the copies after the backup file name
+    suffix: String,
+    /// Name of the subfolder copies are placed in, if any
+    subfolder: Option<String>,
+}
+
+impl CopyNaming {
+    pub fn new(gcfg: &GameConfig, game: &str) -> Self {
+        let template = gcfg.copy_latest_name.as_deref().unwrap_or(NAME_PLACEHOLDER);
+        let (prefix, suffix) = template.split_once(NAME_PLACEHOLDER).unwrap_or((template, ""));
+
+        Self {
+            prefix: prefix.replace(GAME_PLACEHOLDER, game),
+            suffix: suffix.replace(GAME_PLACEHOLDER, game),
+            subfolder: gcfg.copy_latest_subfolder.then(|| game.to_owned()),
+        }
+    }
+
+    /// Directory copies are placed in within a copy-latest path
+    pub fn dir(&self, copy_latest_path: &Path) -> PathBuf {
+        match self.subfolder.as_ref() {
+            Some(subfolder) => copy_latest_path.join(subfolder),
+            None => copy_latest_path.to_owned(),
+        }
+    }
+
+    /// Name of the copy of a backup archive
+    pub fn copy_name(&self, archive_name: &str) -> String {
+        format!("{}{archive_name}{}", self.prefix, self.suffix)
+    }
+
+    /// Name of the backup archive a file is a copy of, if it is named like a copy of this game
+    pub fn archive_name<'a>(&self, copy_name: &'a str) -> Option<&'a str> {
+        copy_name.strip_prefix(&self.prefix)?.strip_suffix(&self.suffix)
+    }
+}
+
+/// Copy a backup archive into a copy-latest path, returning the path of the copy
+pub fn copy_latest(
+    archive_path: &Path,
+    copy_latest_path: &Path,
+    naming: &CopyNaming,
+) -> Result<PathBuf, anyhow::Error> {
+    let archive_name = archive_path.file_name().context("Archive path has no file name")?;
+
+    let dst_dir = naming.dir(copy_latest_path);
+    fs::create_dir_all(&dst_dir)?;
+
+    let copy_path = dst_dir.join(naming.copy_name(&archive_name.to_string_lossy()));
+    fs::copy(archive_path, &copy_path)?;
+
+    Ok(copy_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::Fixture;
+
+    #[test]
+    fn copy_latest_creates_missing_directories_and_fails_on_blocked_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("2025-01-01 00-00-00 Manual.7z");
+        fs::write(&archive_path, "archive").unwrap();
+
+        let naming = CopyNaming::new(&Fixture::new().config(), "game");
+
+        // Missing directories are created
+        let copy_path = copy_latest(&archive_path, &dir.path().join("usb/stool"), &naming).unwrap();
+        assert_eq!(copy_path, dir.path().join("usb/stool/2025-01-01 00-00-00 Manual.7z"));
+        assert_eq!(fs::read_to_string(copy_path).unwrap(), "archive");
+
+        // A path that cannot be a directory fails on its own
+        let blocked_path = dir.path().join("blocked");
+        fs::write(&blocked_path, "").unwrap();
+        assert!(copy_latest(&archive_path, &blocked_path, &naming).is_err());
+    }
+}
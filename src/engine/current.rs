@@ -0,0 +1,126 @@
+//! Backup the live save files of a game currently correspond to, kept across runs
+//! so that users can tell what they are playing on after several restores
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde_derive::{Deserialize, Serialize};
+use tracing::error;
+
+pub const CURRENT_SAVES_FILENAME: &str = "current-saves.json";
+
+/// Changes seen this soon after a restore are taken to be the restore's own writes
+const RESTORE_SETTLE_TIME: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CurrentSaves {
+    /// Backup the live save files were last restored from or backed up to
+    pub archive: String,
+    /// Whether they have changed since
+    pub changed: bool,
+}
+
+impl CurrentSaves {
+    pub fn load(output_path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        match fs::read(output_path.join(CURRENT_SAVES_FILENAME)) {
+            Ok(data) => Ok(Some(
+                serde_json::from_slice(&data).context("Error parsing current saves")?,
+            )),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl fmt::Display for CurrentSaves {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "derived from '{}'", self.archive)?;
+
+        if self.changed {
+            write!(f, " + changes")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps the current saves of a game up to date as backups, restores and changes happen
+pub struct CurrentSavesTracker {
+    path: PathBuf,
+    current: Option<CurrentSaves>,
+    /// Changes are ignored until then, following a restore
+    settle_until: Option<Instant>,
+    /// Nothing is written if false, such as on a dry run
+    persist: bool,
+}
+
+impl CurrentSavesTracker {
+    pub fn load(output_path: &Path, persist: bool) -> Self {
+        let current = CurrentSaves::load(output_path).unwrap_or_else(|err| {
+            error!("{err}");
+            None
+        });
+
+        Self {
+            path: output_path.join(CURRENT_SAVES_FILENAME),
+            current,
+            settle_until: None,
+            persist,
+        }
+    }
+
+    /// Record that the live save files were backed up to an archive
+    pub fn record_backup(&mut self, archive: &str, changed_since: bool) {
+        self.set(archive, changed_since);
+    }
+
+    /// Record that the live save files were restored from an archive.
+    /// A partial restore leaves them a mix of the backup and what they were before.
+    pub fn record_restore(&mut self, archive: &str, partial: bool, now: Instant) {
+        self.settle_until = Some(now + RESTORE_SETTLE_TIME);
+        self.set(archive, partial);
+    }
+
+    /// Record that the live save files changed
+    pub fn record_change(&mut self, now: Instant) {
+        if self.settle_until.is_some_and(|settle_until| now < settle_until) {
+            return;
+        }
+
+        let Some(current) = self.current.as_mut() else {
+            return;
+        };
+
+        if !current.changed {
+            current.changed = true;
+            self.save();
+        }
+    }
+
+    fn set(&mut self, archive: &str, changed: bool) {
+        self.current = Some(CurrentSaves {
+            archive: archive.to_owned(),
+            changed,
+        });
+        self.save();
+    }
+
+    fn save(&self) {
+        if !self.persist {
+            return;
+        }
+
+        let res = serde_json::to_vec_pretty(&self.current)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(fs::write(&self.path, json)?));
+
+        if let Err(err) = res {
+            error!("Error writing current saves: {err}");
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Storage of large save files as binary deltas against their latest full copy in an earlier backup.
+//! The full copy of each such file is kept in the data directory to make deltas against,
+//! and backups holding full copies are kept as long as later backups are stored as deltas against them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use filetime::FileTime;
+use serde_derive::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{config::game::DeltaConfig, internal::delta};
+
+use super::{
+    backups::{list_game_backups, resolve_archive},
+    manifest::{Manifest, ManifestFile},
+    EngineArgs,
+};
+
+/// Suffix of files stored as deltas in backup archives
+pub const DELTA_SUFFIX: &str = ".stool-delta";
+const BASES_DIRNAME: &str = "delta-bases";
+const BASES_FILENAME: &str = "delta-bases.json";
+
+/// Smallest file stored as a delta if not configured
+const DEFAULT_MIN_SIZE: u64 = 64 * 1024 * 1024;
+/// Number of deltas stored against a full copy before storing a full copy again, if not configured
+const DEFAULT_FULL_EVERY: u32 = 10;
+
+/// Backup that cannot be deleted, as later backups are stored as deltas against it
+#[derive(Debug, thiserror::Error)]
+#[error("Backup {name} holds full copies of files that {dependent} is stored as deltas against")]
+pub struct DeltaBaseInUse {
+    pub name: String,
+    pub dependent: String,
+}
+
+/// Line preceding the delta in a delta file
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct DeltaHeader {
+    /// Backup holding the full copy the delta was made against
+    base: String,
+    mtime: i64,
+    mtime_nanos: u32,
+}
+
+/// Full copy of a file, kept to make deltas against
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct DeltaBase {
+    /// Backup holding the same full copy
+    archive: String,
+    size: u64,
+    crc32: u32,
+    /// Number of backups stored as deltas against it so far
+    deltas: u32,
+}
+
+/// Full copies of large files kept in the data directory of a game
+pub struct DeltaBases {
+    path: PathBuf,
+    min_size: u64,
+    full_every: u32,
+    bases: BTreeMap<PathBuf, DeltaBase>,
+}
+
+/// Files moved out of the staging directory while the deltas replacing them are archived
+pub struct HeldFiles {
+    staging_path: PathBuf,
+    hold_path: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl DeltaBases {
+    pub fn load(output_path: &Path, config: &DeltaConfig) -> Self {
+        let path = output_path.join(BASES_DIRNAME);
+
+        let bases = fs::read(path.join(BASES_FILENAME))
+            .ok()
+            .and_then(|json| {
+                serde_json::from_slice(&json)
+                    .inspect_err(|err| debug!("Ignoring unreadable delta bases: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            min_size: config.min_size.unwrap_or(DEFAULT_MIN_SIZE),
+            full_every: config.full_every.unwrap_or(DEFAULT_FULL_EVERY),
+            bases,
+        }
+    }
+
+    fn is_eligible(&self, size: u64) -> bool {
+        (self.min_size..=delta::MAX_FILE_SIZE).contains(&size)
+    }
+
+    /// Replace large files in the staging directory with deltas against their full copies,
+    /// recording what they are stored against in the manifest.
+    /// Deltas that would save less than half of a file are not stored, and the file is stored in full instead.
+    pub fn encode(
+        &self,
+        args: &EngineArgs,
+        staging_path: &Path,
+        manifest: &mut Manifest,
+    ) -> Result<HeldFiles, anyhow::Error> {
+        let mut held = HeldFiles {
+            staging_path: staging_path.to_owned(),
+            hold_path: hold_path(staging_path),
+            files: Vec::new(),
+        };
+
+        let backup_paths = args.backup_paths();
+
+        // Files left out of the backup are in staging no longer
+        for file in manifest
+            .files
+            .iter_mut()
+            .filter(|f| f.stored_in.is_none() && self.is_eligible(f.size))
+        {
+            let Some(base) = self
+                .bases
+                .get(&file.path)
+                .filter(|base| base.deltas < self.full_every && resolve_archive(&backup_paths, &base.archive).is_ok())
+            else {
+                continue;
+            };
+
+            match self.encode_file(staging_path, &held.hold_path, file, base) {
+                Ok(true) => {
+                    held.files.push(file.path.clone());
+                    file.delta_base = Some(base.archive.clone());
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    // Staging must be left as it was found
+                    fs::remove_file(with_delta_suffix(&staging_path.join(&file.path))).ok();
+                    held.release()?;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(held)
+    }
+
+    /// Replace a file in the staging directory with its delta, returning whether it was
+    fn encode_file(
+        &self,
+        staging_path: &Path,
+        hold_path: &Path,
+        file: &ManifestFile,
+        base: &DeltaBase,
+    ) -> Result<bool, anyhow::Error> {
+        // The full copy must be exactly the one in the backup, or the delta cannot be reconstructed
+        let base_data = match fs::read(self.path.join(&file.path)) {
+            Ok(data) if data.len() as u64 == base.size && crc32fast::hash(&data) == base.crc32 => data,
+            _ => return Ok(false),
+        };
+
+        let file_path = staging_path.join(&file.path);
+        let delta_path = with_delta_suffix(&file_path);
+
+        let header = DeltaHeader {
+            base: base.archive.clone(),
+            mtime: file.mtime,
+            mtime_nanos: file.mtime_nanos,
+        };
+
+        let mut output = BufWriter::new(fs::File::create(&delta_path)?);
+        serde_json::to_writer(&mut output, &header)?;
+        output.write_all(b"\n")?;
+        delta::encode(&base_data, &file_path, &mut output)
+            .with_context(|| format!("Error encoding delta of {}", file.path.display()))?;
+        output.flush()?;
+        drop(output);
+
+        let delta_size = fs::metadata(&delta_path)?.len();
+
+        if delta_size > file.size / 2 {
+            info!(
+                "Storing {} in full, as it changed too much for a delta",
+                file.path.display()
+            );
+            fs::remove_file(&delta_path)?;
+            return Ok(false);
+        }
+
+        // Keep the full file out of the archive, on the same volume as staging so that it can simply be moved
+        let held_path = hold_path.join(&file.path);
+        fs::create_dir_all(held_path.parent().context("Held file has no parent")?)?;
+        fs::rename(&file_path, &held_path)?;
+
+        Ok(true)
+    }
+
+    /// Record a backup, keeping full copies of the large files stored in full in it
+    pub fn commit(
+        &mut self,
+        staging_path: &Path,
+        archive_name: &str,
+        manifest: &Manifest,
+    ) -> Result<(), anyhow::Error> {
+        let mut bases = BTreeMap::new();
+        let eligible_files: Vec<_> = manifest.files.iter().filter(|f| self.is_eligible(f.size)).collect();
+
+        for file in eligible_files {
+            // The full copy of a file left out of the backup is still the one in the backup holding it
+            if file.stored_in.is_some() {
+                if let Some(base) = self.bases.remove(&file.path) {
+                    bases.insert(file.path.clone(), base);
+                }
+
+                continue;
+            }
+
+            if file.delta_base.is_some() {
+                if let Some(mut base) = self.bases.remove(&file.path) {
+                    base.deltas += 1;
+                    bases.insert(file.path.clone(), base);
+                }
+
+                continue;
+            }
+
+            let base_path = self.path.join(&file.path);
+            fs::create_dir_all(base_path.parent().context("Delta base has no parent")?)?;
+            fs::copy(staging_path.join(&file.path), &base_path)
+                .with_context(|| format!("Error keeping full copy of {}", file.path.display()))?;
+
+            bases.insert(
+                file.path.clone(),
+                DeltaBase {
+                    archive: archive_name.to_owned(),
+                    size: file.size,
+                    crc32: file.crc32,
+                    deltas: 0,
+                },
+            );
+        }
+
+        // Full copies of files that are gone or no longer large are no longer needed
+        for path in self.bases.keys().filter(|path| !bases.contains_key(*path)) {
+            fs::remove_file(self.path.join(path)).ok();
+        }
+
+        self.bases = bases;
+
+        fs::create_dir_all(&self.path)?;
+        fs::write(self.path.join(BASES_FILENAME), serde_json::to_vec_pretty(&self.bases)?)
+            .context("Error writing delta bases")?;
+
+        Ok(())
+    }
+}
+
+impl HeldFiles {
+    /// Put the held files back in place of their deltas
+    pub fn release(self) -> Result<(), anyhow::Error> {
+        for path in self.files.iter() {
+            let file_path = self.staging_path.join(path);
+
+            fs::remove_file(with_delta_suffix(&file_path))?;
+            fs::rename(self.hold_path.join(path), &file_path)?;
+        }
+
+        if self.hold_path.exists() {
+            fs::remove_dir_all(&self.hold_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replace the deltas in an unpacked backup with the files they were made from,
+/// unpacking the backups holding the full copies they were made against next to it
+pub fn reconstruct(args: &EngineArgs, dir: &Path) -> Result<(), anyhow::Error> {
+    let mut deltas_by_base: HashMap<String, Vec<(PathBuf, DeltaHeader)>> = HashMap::new();
+
+    let delta_paths = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && entry.file_name().to_string_lossy().ends_with(DELTA_SUFFIX))
+        .map(|entry| entry.into_path());
+
+    for delta_path in delta_paths {
+        let header = read_header(&mut BufReader::new(fs::File::open(&delta_path)?))
+            .with_context(|| format!("Error reading delta {}", delta_path.display()))?;
+
+        deltas_by_base
+            .entry(header.base.clone())
+            .or_default()
+            .push((delta_path, header));
+    }
+
+    for (base, deltas) in deltas_by_base {
+        let base_archive_path = resolve_archive(&args.backup_paths(), &base)
+            .with_context(|| format!("Backup holding full copies of delta files is missing: {base}"))?;
+
+        let base_dir = hold_path(dir);
+        if base_dir.exists() {
+            fs::remove_dir_all(&base_dir)?;
+        }
+
+        fs::create_dir_all(&base_dir)?;
+        args.archiver.unpack(&base_archive_path, &base_dir)?;
+
+        for (delta_path, header) in deltas {
+            let file_path = without_delta_suffix(&delta_path);
+            let rel_path = file_path.strip_prefix(dir)?;
+            let base_data = fs::read(base_dir.join(rel_path))
+                .with_context(|| format!("Full copy of {} is missing from {base}", rel_path.display()))?;
+
+            let mut input = BufReader::new(fs::File::open(&delta_path)?);
+            read_header(&mut input)?;
+
+            let mut output = BufWriter::new(fs::File::create(&file_path)?);
+            delta::decode(&base_data, input, &mut output)
+                .with_context(|| format!("Error decoding delta of {}", rel_path.display()))?;
+            output.flush()?;
+            drop(output);
+
+            filetime::set_file_mtime(&file_path, FileTime::from_unix_time(header.mtime, header.mtime_nanos))?;
+            fs::remove_file(&delta_path)?;
+        }
+
+        fs::remove_dir_all(&base_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Refuse to delete a backup that later backups are stored as deltas against
+pub fn check_deletable(args: &EngineArgs, name: &str) -> Result<(), anyhow::Error> {
+    // Games that never stored deltas need not have every manifest read
+    if !args.output_path().join(BASES_DIRNAME).exists() {
+        return Ok(());
+    }
+
+    for backup in list_game_backups(args)? {
+        let Ok(Some(manifest)) = Manifest::load_for_archive(&backup.path) else {
+            continue;
+        };
+
+        if manifest.files.iter().any(|f| f.delta_base.as_deref() == Some(name)) {
+            return Err(DeltaBaseInUse {
+                name: name.to_owned(),
+                dependent: backup.name,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+fn read_header(reader: &mut impl BufRead) -> Result<DeltaHeader, anyhow::Error> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Directory next to another one, on the same volume, for files kept out of it for a while
+fn hold_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".delta-hold");
+
+    path.with_file_name(name)
+}
+
+pub fn with_delta_suffix(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(DELTA_SUFFIX);
+
+    path.into()
+}
+
+fn without_delta_suffix(path: &Path) -> PathBuf {
+    let path = path.to_string_lossy();
+
+    PathBuf::from(path.strip_suffix(DELTA_SUFFIX).unwrap_or(&path))
+}
@@ -0,0 +1,100 @@
+//! Descriptions of manual backups used before, offered again when creating the next one
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+pub const DESCRIPTIONS_FILENAME: &str = "descriptions.json";
+
+/// Number of descriptions kept
+const MAX_DESCRIPTIONS: usize = 50;
+
+/// Descriptions of the manual backups of a game, most recently used first
+pub struct DescriptionHistory {
+    path: PathBuf,
+    descriptions: Vec<String>,
+}
+
+impl DescriptionHistory {
+    pub fn load(output_path: &Path) -> Result<Self, anyhow::Error> {
+        let path = output_path.join(DESCRIPTIONS_FILENAME);
+
+        let descriptions = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).context("Error parsing description history")?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self { path, descriptions })
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&self.descriptions)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Previously used descriptions followed by the templates not among them
+    pub fn suggestions(&self, templates: &[String]) -> Vec<String> {
+        let mut suggestions = self.descriptions.clone();
+
+        for template in templates {
+            if !suggestions.contains(template) {
+                suggestions.push(template.clone());
+            }
+        }
+
+        suggestions
+    }
+
+    /// Move a description to the front, dropping the oldest ones past the limit
+    pub fn record(&mut self, description: &str) {
+        let description = description.trim();
+
+        if description.is_empty() {
+            return;
+        }
+
+        self.descriptions.retain(|d| d != description);
+        self.descriptions.insert(0, description.to_owned());
+        self.descriptions.truncate(MAX_DESCRIPTIONS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn description_history_keeps_most_recent_first_without_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path();
+
+        let mut history = DescriptionHistory::load(output_path).unwrap();
+        assert!(history.suggestions(&[]).is_empty());
+
+        history.record("Before boss");
+        history.record("Before mod update");
+        history.record("  ");
+        history.record("Before boss ");
+        history.save().unwrap();
+
+        let history = DescriptionHistory::load(output_path).unwrap();
+        assert_eq!(history.suggestions(&[]), ["Before boss", "Before mod update"]);
+
+        let templates = ["Before mod update".to_owned(), "Before raid".to_owned()];
+        assert_eq!(
+            history.suggestions(&templates),
+            ["Before boss", "Before mod update", "Before raid"]
+        );
+    }
+}
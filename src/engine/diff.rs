@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::internal::archive::ArchiveEntry;
+
+use super::{backups::list_backup_contents, EngineArgs};
+
+#[derive(Clone, Debug)]
+pub enum DiffItem {
+    Added(ArchiveEntry),
+    Removed(ArchiveEntry),
+    Changed { old: ArchiveEntry, new: ArchiveEntry },
+}
+
+/// Differences between the contents of two backups
+#[derive(Clone, Debug, Default)]
+pub struct BackupDiff {
+    pub items: Vec<DiffItem>,
+    pub unchanged: usize,
+}
+
+impl DiffItem {
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            Self::Added(entry) | Self::Removed(entry) => &entry.path,
+            Self::Changed { new, .. } => &new.path,
+        }
+    }
+
+    /// Describe the difference as a single line
+    pub fn describe(&self) -> String {
+        use crate::internal::format::format_bytes;
+
+        match self {
+            Self::Added(entry) => format!("+ {} ({})", entry.path.display(), format_bytes(entry.size)),
+            Self::Removed(entry) => format!("- {} ({})", entry.path.display(), format_bytes(entry.size)),
+            Self::Changed { old, new } => format!(
+                "~ {} ({} -> {})",
+                new.path.display(),
+                format_bytes(old.size),
+                format_bytes(new.size)
+            ),
+        }
+    }
+}
+
+impl BackupDiff {
+    pub fn count_added(&self) -> usize {
+        self.items.iter().filter(|i| matches!(i, DiffItem::Added(_))).count()
+    }
+
+    pub fn count_removed(&self) -> usize {
+        self.items.iter().filter(|i| matches!(i, DiffItem::Removed(_))).count()
+    }
+
+    pub fn count_changed(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|i| matches!(i, DiffItem::Changed { .. }))
+            .count()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} changed, {} unchanged",
+            self.count_added(),
+            self.count_removed(),
+            self.count_changed(),
+            self.unchanged
+        )
+    }
+}
+
+/// Compare the file entries of two backups.
+/// Directories are ignored, as only files carry save data.
+pub fn diff_entries(old: &[ArchiveEntry], new: &[ArchiveEntry]) -> BackupDiff {
+    let old: BTreeMap<_, _> = old.iter().filter(|e| !e.is_dir).map(|e| (&e.path, e)).collect();
+    let new: BTreeMap<_, _> = new.iter().filter(|e| !e.is_dir).map(|e| (&e.path, e)).collect();
+
+    let mut diff = BackupDiff::default();
+
+    for (path, old_entry) in old.iter() {
+        match new.get(path) {
+            None => diff.items.push(DiffItem::Removed((*old_entry).clone())),
+            Some(new_entry) => {
+                let changed = old_entry.size != new_entry.size || old_entry.crc32 != new_entry.crc32;
+
+                if changed {
+                    diff.items.push(DiffItem::Changed {
+                        old: (*old_entry).clone(),
+                        new: (*new_entry).clone(),
+                    });
+                } else {
+                    diff.unchanged += 1;
+                }
+            }
+        }
+    }
+
+    for (path, new_entry) in new.iter() {
+        if !old.contains_key(path) {
+            diff.items.push(DiffItem::Added((*new_entry).clone()));
+        }
+    }
+
+    diff.items.sort_by(|a, b| a.path().cmp(b.path()));
+
+    diff
+}
+
+/// Compare the contents of two backup archives
+pub fn diff_backups(args: &EngineArgs, old: &str, new: &str) -> Result<BackupDiff, anyhow::Error> {
+    let old = list_backup_contents(args, old)?;
+    let new = list_backup_contents(args, new)?;
+
+    Ok(diff_entries(&old, &new))
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use filetime::FileTime;
+
+use crate::{config::game::GameSaveFile, internal::format::format_bytes};
+
+use super::{manifest::Manifest, InternalGameSaveDir};
+
+/// A file that would be included in a backup
+#[derive(Clone, Debug)]
+pub(super) struct PlannedFile {
+    /// Path inside the backup
+    path: PathBuf,
+    size: u64,
+    mtime: FileTime,
+}
+
+/// Files a backup would contain, compared to the previous backup.
+/// Built without writing anything, for dry runs.
+pub(super) struct BackupPlan {
+    pub files: Vec<PlannedFile>,
+    pub new: usize,
+    pub changed: usize,
+    pub removed: usize,
+}
+
+impl PlannedFile {
+    /// Files recorded in the manifest of an existing backup
+    pub fn from_manifest(manifest: &Manifest) -> Vec<Self> {
+        manifest
+            .files
+            .iter()
+            .map(|f| PlannedFile {
+                path: f.path.clone(),
+                size: f.size,
+                mtime: FileTime::from_unix_time(f.mtime, f.mtime_nanos),
+            })
+            .collect()
+    }
+}
+
+impl BackupPlan {
+    /// Scan save directories and files the same way staging does, and compare against the previous backup
+    pub fn scan(
+        save_dirs: &[InternalGameSaveDir],
+        save_files: &[GameSaveFile],
+        previous: &[PlannedFile],
+    ) -> Result<Self, anyhow::Error> {
+        let mut files = Vec::new();
+
+        for gsp in save_dirs.iter() {
+            if !gsp.path.exists() {
+                continue;
+            }
+
+            let entries = walkdir::WalkDir::new(&gsp.path)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(Result::ok);
+
+            for entry in entries {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                let rel_path = entry.path().strip_prefix(&gsp.path)?;
+
+                if let Some(include_globset) = gsp.include_globset.as_ref() {
+                    if !include_globset.is_match(rel_path) {
+                        continue;
+                    }
+                }
+
+                if let Some(ignore_globset) = gsp.ignore_globset.as_ref() {
+                    if ignore_globset.is_match(rel_path) {
+                        continue;
+                    }
+                }
+
+                files.push(planned_file(entry.path(), Path::new(&gsp.name).join(rel_path))?);
+            }
+        }
+
+        for gsf in save_files.iter() {
+            if !gsf.path.is_file() {
+                continue;
+            }
+
+            let Some(file_name) = gsf.path.file_name() else {
+                continue;
+            };
+
+            let path = match gsf.staging_subdirectory.as_ref() {
+                Some(staging_subdir) => staging_subdir.join(file_name),
+                None => PathBuf::from(file_name),
+            };
+
+            files.push(planned_file(&gsf.path, path)?);
+        }
+
+        let previous: HashMap<&Path, &PlannedFile> = previous.iter().map(|f| (f.path.as_path(), f)).collect();
+
+        let mut new = 0;
+        let mut changed = 0;
+
+        for file in files.iter() {
+            match previous.get(file.path.as_path()) {
+                None => new += 1,
+                Some(prev) if prev.size != file.size || prev.mtime != file.mtime => changed += 1,
+                Some(_) => {}
+            }
+        }
+
+        let current: HashSet<&Path> = files.iter().map(|f| f.path.as_path()).collect();
+        let removed = previous.keys().filter(|p| !current.contains(*p)).count();
+
+        Ok(Self {
+            files,
+            new,
+            changed,
+            removed,
+        })
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+impl fmt::Display for BackupPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files ({}): {} new, {} changed, {} removed since the previous backup",
+            self.files.len(),
+            format_bytes(self.total_size()),
+            self.new,
+            self.changed,
+            self.removed
+        )
+    }
+}
+
+fn planned_file(src_path: &Path, path: PathBuf) -> Result<PlannedFile, anyhow::Error> {
+    let metadata = src_path.metadata()?;
+
+    Ok(PlannedFile {
+        path,
+        size: metadata.len(),
+        mtime: FileTime::from_last_modification_time(&metadata),
+    })
+}
@@ -0,0 +1,52 @@
+use std::{fs, path::Path};
+
+use crate::internal::{disk, sync::SyncEvents};
+
+use super::{
+    backups::resolve_archive,
+    delta,
+    manifest::{Manifest, ManifestMismatch},
+    partial, EngineArgs,
+};
+
+pub struct ExtractReport {
+    pub manifest: Option<Manifest>,
+    pub mismatches: Vec<ManifestMismatch>,
+}
+
+/// Unpack a backup archive into a directory, without touching the live save files,
+/// and verify the unpacked files against the archive's manifest if it has one.
+pub fn extract_backup(
+    args: &EngineArgs,
+    archive: &str,
+    dst: &Path,
+    events: &SyncEvents,
+) -> Result<ExtractReport, anyhow::Error> {
+    let archive_path = resolve_archive(&args.backup_paths(), archive)?;
+
+    if dst.exists() && fs::read_dir(dst)?.next().is_some() {
+        return Err(anyhow::anyhow!("Destination directory is not empty: {}", dst.display()));
+    }
+
+    let manifest = Manifest::load_for_archive(&archive_path)?;
+
+    // Without a manifest, the archive size is the best guess at how much space unpacking it takes
+    let needed = match manifest.as_ref() {
+        Some(manifest) => manifest.total_size(),
+        None => fs::metadata(&archive_path)?.len(),
+    };
+    disk::check_free_space(dst, needed)?;
+
+    fs::create_dir_all(dst)?;
+
+    args.archiver.unpack(&archive_path, dst)?;
+    partial::assemble(args, &archive_path, dst)?;
+    delta::reconstruct(args, dst)?;
+
+    let mismatches = match manifest.as_ref() {
+        Some(manifest) => manifest.verify(dst, events)?,
+        None => Vec::new(),
+    };
+
+    Ok(ExtractReport { manifest, mismatches })
+}
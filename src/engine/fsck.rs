@@ -0,0 +1,208 @@
+use std::{collections::HashSet, fmt, fs, path::PathBuf};
+
+use crate::internal::{parity::PARITY_SUFFIX, pid};
+
+use super::{
+    backups::{list_backups_in, BackupInfo},
+    index::BackupIndex,
+    manifest::{Manifest, MANIFEST_SUFFIX},
+    EngineArgs, PID_FILENAME,
+};
+
+/// Inconsistency found in the data directory of a game
+#[derive(Debug)]
+pub enum FsckIssue {
+    /// Archive exists on disk, but is not in the backup index
+    NotIndexed { backup: BackupInfo },
+    /// Backup index entry refers to an archive that no longer exists
+    MissingArchive { name: String },
+    /// Manifest without a corresponding archive
+    OrphanManifest { path: PathBuf },
+    /// Parity data without a corresponding archive
+    OrphanParity { path: PathBuf },
+    /// Archive without a manifest. Cannot be repaired, but does not prevent restoring.
+    NoManifest { name: String },
+    /// Staging directory left behind by an engine that did not shut down cleanly
+    LeftoverStaging { path: PathBuf },
+    /// PID-lock file of a process that is no longer running
+    StalePidFile { path: PathBuf },
+}
+
+impl FsckIssue {
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Self::NoManifest { .. })
+    }
+
+    pub fn repair(&self, args: &EngineArgs) -> Result<(), anyhow::Error> {
+        match self {
+            Self::NotIndexed { backup } => {
+                let manifest = Manifest::load_for_archive(&backup.path)?;
+                BackupIndex::open(&args.output_path())?.add_backup(backup, manifest.as_ref())?;
+            }
+            Self::MissingArchive { name } => BackupIndex::open(&args.output_path())?.remove_backup(name)?,
+            Self::OrphanManifest { path } | Self::OrphanParity { path } | Self::StalePidFile { path } => {
+                fs::remove_file(path)?
+            }
+            Self::LeftoverStaging { path } => fs::remove_dir_all(path)?,
+            Self::NoManifest { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for FsckIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotIndexed { backup } => write!(f, "Backup missing from index: {}", backup.name),
+            Self::MissingArchive { name } => write!(f, "Indexed backup archive does not exist: {name}"),
+            Self::OrphanManifest { path } => write!(f, "Manifest without archive: {}", path.display()),
+            Self::OrphanParity { path } => write!(f, "Parity data without archive: {}", path.display()),
+            Self::NoManifest { name } => write!(f, "Backup has no manifest: {name}"),
+            Self::LeftoverStaging { path } => write!(f, "Leftover staging directory: {}", path.display()),
+            Self::StalePidFile { path } => write!(f, "Stale PID file: {}", path.display()),
+        }
+    }
+}
+
+/// Whether the engine is currently running for the game
+pub fn engine_is_running(args: &EngineArgs) -> bool {
+    pid::is_held(args.output_path().join(PID_FILENAME))
+}
+
+/// Cross-check the data directory of a game for inconsistencies
+pub fn check(args: &EngineArgs) -> Result<Vec<FsckIssue>, anyhow::Error> {
+    let output_path = args.output_path();
+    let backup_paths = args.backup_paths();
+
+    let mut issues = Vec::new();
+
+    let backups = list_backups_in(&backup_paths)?;
+    let backup_names: HashSet<&str> = backups.iter().map(|b| b.name.as_str()).collect();
+
+    if args.use_index {
+        let indexed = BackupIndex::open(&output_path)?.list()?;
+        let indexed_names: HashSet<&str> = indexed.iter().map(|b| b.name.as_str()).collect();
+
+        for backup in backups.iter() {
+            if !indexed_names.contains(backup.name.as_str()) {
+                issues.push(FsckIssue::NotIndexed { backup: backup.clone() });
+            }
+        }
+
+        for backup in indexed.iter() {
+            if !backup_names.contains(backup.name.as_str()) {
+                issues.push(FsckIssue::MissingArchive {
+                    name: backup.name.clone(),
+                });
+            }
+        }
+    }
+
+    for backup_path in backup_paths.iter().filter(|p| p.exists()) {
+        for entry in fs::read_dir(backup_path)?.filter_map(Result::ok) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            if let Some(archive_name) = file_name.strip_suffix(MANIFEST_SUFFIX) {
+                if !backup_names.contains(archive_name) {
+                    issues.push(FsckIssue::OrphanManifest { path: entry.path() });
+                }
+            } else if let Some(archive_name) = file_name.strip_suffix(PARITY_SUFFIX) {
+                if !backup_names.contains(archive_name) {
+                    issues.push(FsckIssue::OrphanParity { path: entry.path() });
+                }
+            }
+        }
+    }
+
+    for backup in backups.iter() {
+        if Manifest::load_for_archive(&backup.path)?.is_none() {
+            issues.push(FsckIssue::NoManifest {
+                name: backup.name.clone(),
+            });
+        }
+    }
+
+    // Leftovers from a previous run only count as issues if the engine is not currently running
+    let pid_path = output_path.join(PID_FILENAME);
+
+    if !pid::is_held(&pid_path) {
+        if pid_path.exists() {
+            issues.push(FsckIssue::StalePidFile { path: pid_path });
+        }
+
+        let staging_path = args.staging_path();
+
+        if staging_path.exists() {
+            issues.push(FsckIssue::LeftoverStaging { path: staging_path });
+        }
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::engine::testing::{write_backup, Fixture};
+
+    #[test]
+    fn issues_are_found_and_repaired() {
+        let mut fixture = Fixture::new();
+        fixture.args.use_index = true;
+
+        let output_path = fixture.args.output_path();
+        let backup_path = fixture.args.backup_path();
+        let age = Duration::from_secs(60);
+
+        let removed = write_backup(&backup_path, "2025-01-01 00-00-01 Manual.7z", age, &["slot1.sav"]);
+        BackupIndex::open(&output_path)
+            .unwrap()
+            .sync_with_dirs(&fixture.args.backup_paths())
+            .unwrap();
+
+        fs::remove_file(&removed.path).unwrap();
+        write_backup(&backup_path, "2025-01-01 00-00-02 Manual.7z", age, &["slot1.sav"]);
+        fs::write(backup_path.join("2025-01-01 00-00-03 Manual.7z"), "").unwrap();
+        fs::write(
+            backup_path.join(format!("2025-01-01 00-00-04 Manual.7z{PARITY_SUFFIX}")),
+            "",
+        )
+        .unwrap();
+        fs::create_dir_all(fixture.args.staging_path()).unwrap();
+        fs::write(output_path.join(PID_FILENAME), "99999999").unwrap();
+
+        let issues = check(&fixture.args).unwrap();
+        let descriptions: Vec<_> = issues.iter().map(|issue| issue.to_string()).collect();
+
+        assert!(
+            matches!(
+                issues.as_slice(),
+                [
+                    FsckIssue::NotIndexed { .. },
+                    FsckIssue::NotIndexed { .. },
+                    FsckIssue::MissingArchive { .. },
+                    FsckIssue::OrphanManifest { .. },
+                    FsckIssue::OrphanParity { .. },
+                    FsckIssue::NoManifest { .. },
+                    FsckIssue::StalePidFile { .. },
+                    FsckIssue::LeftoverStaging { .. },
+                ]
+            ),
+            "{descriptions:#?}"
+        );
+
+        for issue in issues.iter().filter(|issue| issue.is_repairable()) {
+            issue.repair(&fixture.args).unwrap();
+        }
+
+        // Archives without a manifest cannot be repaired, but are still usable
+        let issues = check(&fixture.args).unwrap();
+        assert!(
+            matches!(issues.as_slice(), [FsckIssue::NoManifest { .. }]),
+            "{issues:?}"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::config::game::GameConfig;
+
+use super::InternalGameSaveDir;
+
+/// Grace time of changes to save files, which save dirs and files may override
+pub struct GraceTimes {
+    default: Duration,
+    /// Save dirs and files with their own grace time
+    overrides: Vec<(PathBuf, Duration)>,
+}
+
+impl GraceTimes {
+    pub fn new(gcfg: &GameConfig, save_dirs: &[InternalGameSaveDir]) -> Self {
+        let save_dirs = save_dirs
+            .iter()
+            .filter_map(|gsp| Some((gsp.path.clone(), gsp.grace_time?)));
+        let save_files = gcfg
+            .save_files
+            .iter()
+            .filter_map(|gsf| Some((gsf.path.clone(), Duration::from_secs(gsf.grace_time?))));
+
+        Self {
+            default: Duration::from_secs(gcfg.grace_time),
+            overrides: save_dirs.chain(save_files).collect(),
+        }
+    }
+
+    /// Grace time of a change to the given paths, the longest of those that apply to any of them
+    pub fn of_change(&self, paths: &[PathBuf]) -> Duration {
+        paths
+            .iter()
+            .map(|path| {
+                self.overrides
+                    .iter()
+                    .filter(|(override_path, _)| path.starts_with(override_path))
+                    .map(|(_, grace_time)| *grace_time)
+                    .max()
+                    .unwrap_or(self.default)
+            })
+            .max()
+            .unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde_derive::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use super::{
+    backups::{list_game_backups, BackupInfo},
+    index::BackupIndex,
+    manifest::Manifest,
+    session::SessionSummary,
+    BackupKind, EngineArgs,
+};
+
+pub const HISTORY_FILENAME: &str = "history.jsonl";
+
+/// Session durations are recorded in whole seconds, so backups at the very end of a session
+/// may be slightly past its recorded end
+const SESSION_END_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum HistoryEvent {
+    Session(SessionSummary),
+    BackupFailed(BackupFailure),
+}
+
+/// Backup that failed even after retrying
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackupFailure {
+    pub archive_name: String,
+    pub kind: BackupKind,
+    pub error: String,
+    pub attempts: u32,
+    /// Whether the changes will be backed up at the next auto-backup interval instead
+    pub will_retry_at_next_interval: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HistoryEntry {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    #[serde(flatten)]
+    pub event: HistoryEvent,
+}
+
+/// Append-only journal of engine events for a game,
+/// stored as one JSON object per line.
+#[derive(Clone, Debug)]
+pub struct History {
+    path: PathBuf,
+    /// Also record entries in the backup index
+    index_path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn new(output_path: &Path, use_index: bool) -> Self {
+        Self {
+            path: output_path.join(HISTORY_FILENAME),
+            index_path: use_index.then(|| output_path.to_owned()),
+        }
+    }
+
+    /// Read all entries of the journal, oldest first.
+    /// Lines that cannot be parsed are skipped.
+    pub fn read(&self) -> Result<Vec<HistoryEntry>, anyhow::Error> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err).context("Error reading history file"),
+        };
+
+        Ok(data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    warn!("Skipping unreadable history entry: {err}");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Summaries of all recorded sessions, oldest first
+    pub fn sessions(&self) -> Result<Vec<SessionSummary>, anyhow::Error> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter_map(|entry| match entry.event {
+                HistoryEvent::Session(session) => Some(session),
+                HistoryEvent::BackupFailed(_) => None,
+            })
+            .collect())
+    }
+
+    pub fn append(&self, event: HistoryEvent) -> Result<(), anyhow::Error> {
+        let entry = HistoryEntry {
+            timestamp: OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+            event,
+        };
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Error opening history file")?;
+
+        file.write_all(line.as_bytes())
+            .context("Error writing to history file")?;
+
+        if let Some(index_path) = self.index_path.as_ref() {
+            BackupIndex::open(index_path)?.add_history(&entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Backups created during one session, or between sessions
+#[derive(Clone, Debug)]
+pub struct SessionBackups {
+    /// Id of the session the backups were created in, if any
+    pub session_id: Option<String>,
+    /// Summary of the session, if it was recorded.
+    /// Sessions that are still running or did not shut down cleanly have no summary.
+    pub session: Option<SessionSummary>,
+    /// Backups, newest first
+    pub backups: Vec<BackupInfo>,
+}
+
+impl SessionBackups {
+    pub fn is_outside_session(&self) -> bool {
+        self.session_id.is_none() && self.session.is_none()
+    }
+}
+
+/// Backups of a game grouped by the session they were created in, newest first
+pub fn game_sessions(args: &EngineArgs) -> Result<Vec<SessionBackups>, anyhow::Error> {
+    let sessions = History::new(&args.output_path(), args.use_index).sessions()?;
+
+    let backups = list_game_backups(args)?
+        .into_iter()
+        .map(|backup| {
+            let session_id = Manifest::load_for_archive(&backup.path)
+                .ok()
+                .flatten()
+                .and_then(|m| m.session);
+
+            (backup, session_id)
+        })
+        .collect();
+
+    Ok(group_by_session(&sessions, backups))
+}
+
+/// Group backups, along with the id of the session they were created in, by session, newest first.
+/// Backups without a session id are assigned to the session during which they were created, if any.
+/// Consecutive backups created outside of any session are grouped together.
+pub fn group_by_session(
+    sessions: &[SessionSummary],
+    backups: Vec<(BackupInfo, Option<String>)>,
+) -> Vec<SessionBackups> {
+    // Groups with the time used to order them
+    let mut groups: Vec<(OffsetDateTime, SessionBackups)> = sessions
+        .iter()
+        .map(|session| {
+            let group = SessionBackups {
+                session_id: session.id.clone(),
+                session: Some(session.clone()),
+                backups: Vec::new(),
+            };
+
+            (session.started_at, group)
+        })
+        .collect();
+
+    for (backup, session_id) in backups {
+        let modified = OffsetDateTime::from(backup.modified);
+
+        let group = match session_id.as_ref() {
+            Some(session_id) => groups
+                .iter_mut()
+                .find(|(_, group)| group.session_id.as_ref() == Some(session_id)),
+            None => groups.iter_mut().find(|(_, group)| {
+                group.session.as_ref().is_some_and(|session| {
+                    let ended_at = session.started_at + session.duration + SESSION_END_TOLERANCE;
+
+                    (session.started_at..=ended_at).contains(&modified)
+                })
+            }),
+        };
+
+        match group {
+            Some((time, group)) => {
+                // Unrecorded sessions are ordered by their first backup
+                if group.session.is_none() {
+                    *time = (*time).min(modified);
+                }
+
+                group.backups.push(backup);
+            }
+            None => groups.push((
+                modified,
+                SessionBackups {
+                    session_id,
+                    session: None,
+                    backups: vec![backup],
+                },
+            )),
+        }
+    }
+
+    groups.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+
+    let mut merged: Vec<SessionBackups> = Vec::new();
+
+    for (_, mut group) in groups {
+        group.backups.sort_by_key(|b| std::cmp::Reverse(b.modified));
+
+        match merged.last_mut() {
+            Some(last) if last.is_outside_session() && group.is_outside_session() => last.backups.extend(group.backups),
+            _ => merged.push(group),
+        }
+    }
+
+    merged
+}
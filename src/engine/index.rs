@@ -0,0 +1,273 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use rusqlite::{params, Connection};
+use time::format_description::well_known::Rfc3339;
+
+use super::{
+    backups::{list_backups_in, BackupInfo},
+    history::HistoryEntry,
+    manifest::Manifest,
+};
+
+pub const INDEX_FILENAME: &str = "index.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS backups (
+    name TEXT PRIMARY KEY,
+    path TEXT NOT NULL,
+    modified INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    kind TEXT,
+    created_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS manifest_files (
+    backup TEXT NOT NULL REFERENCES backups(name) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    crc32 INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS manifest_files_backup ON manifest_files(backup);
+CREATE INDEX IF NOT EXISTS manifest_files_path ON manifest_files(path);
+
+CREATE TABLE IF NOT EXISTS history (
+    timestamp TEXT NOT NULL,
+    event TEXT NOT NULL,
+    entry TEXT NOT NULL
+);
+";
+
+/// SQLite index of the backups of a game, for fast listing and searching
+/// without scanning the backup directory and reading manifests.
+pub struct BackupIndex {
+    conn: Connection,
+}
+
+impl BackupIndex {
+    pub fn open(output_path: &Path) -> Result<Self, anyhow::Error> {
+        fs::create_dir_all(output_path)?;
+
+        let conn = Connection::open(output_path.join(INDEX_FILENAME)).context("Error opening backup index")?;
+
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)
+            .context("Error creating backup index schema")?;
+
+        Ok(Self { conn })
+    }
+
+    /// Add a backup to the index, or update it if it is already indexed
+    pub fn add_backup(&mut self, backup: &BackupInfo, manifest: Option<&Manifest>) -> Result<(), anyhow::Error> {
+        let tx = self.conn.transaction()?;
+
+        let size = backup.path.metadata().map(|m| m.len()).unwrap_or(0);
+        let kind = manifest.map(|m| serde_json::to_value(m.kind)).transpose()?;
+        let created_at = manifest.map(|m| m.created_at.format(&Rfc3339)).transpose()?;
+
+        tx.execute("DELETE FROM backups WHERE name = ?1", params![backup.name])?;
+        tx.execute(
+            "INSERT INTO backups (name, path, modified, size, kind, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                backup.name,
+                backup.path.to_string_lossy(),
+                to_unix_nanos(backup.modified),
+                size as i64,
+                kind.as_ref().and_then(|k| k.as_str()),
+                created_at,
+            ],
+        )?;
+
+        if let Some(manifest) = manifest {
+            let mut stmt =
+                tx.prepare("INSERT INTO manifest_files (backup, path, size, crc32) VALUES (?1, ?2, ?3, ?4)")?;
+
+            for file in manifest.files.iter() {
+                stmt.execute(params![
+                    backup.name,
+                    file.path.to_string_lossy(),
+                    file.size as i64,
+                    file.crc32
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    pub fn remove_backup(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        self.conn
+            .execute("DELETE FROM backups WHERE name = ?1", params![name])?;
+
+        Ok(())
+    }
+
+    /// List indexed backups, newest first
+    pub fn list(&self) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        self.query("SELECT name, path, modified FROM backups ORDER BY modified DESC", "")
+    }
+
+    /// Find backups whose name or contained file paths match a search term
+    pub fn search(&self, term: &str) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        self.query(
+            "SELECT name, path, modified FROM backups WHERE name LIKE ?1 OR name IN
+                (SELECT backup FROM manifest_files WHERE path LIKE ?1)
+            ORDER BY modified DESC",
+            &format!("%{term}%"),
+        )
+    }
+
+    fn query(&self, sql: &str, term: &str) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        let mut stmt = self.conn.prepare(sql)?;
+
+        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<BackupInfo> {
+            let path: String = row.get(1)?;
+            let modified: i64 = row.get(2)?;
+
+            Ok(BackupInfo {
+                name: row.get(0)?,
+                path: PathBuf::from(path),
+                modified: from_unix_nanos(modified),
+            })
+        };
+
+        let rows = if term.is_empty() {
+            stmt.query_map([], map_row)?.collect::<Result<Vec<_>, _>>()?
+        } else {
+            stmt.query_map(params![term], map_row)?.collect::<Result<Vec<_>, _>>()?
+        };
+
+        Ok(rows)
+    }
+
+    pub fn add_history(&mut self, entry: &HistoryEntry) -> Result<(), anyhow::Error> {
+        let json = serde_json::to_value(entry)?;
+        let event = json.get("event").and_then(|e| e.as_str()).unwrap_or_default();
+
+        self.conn.execute(
+            "INSERT INTO history (timestamp, event, entry) VALUES (?1, ?2, ?3)",
+            params![entry.timestamp.format(&Rfc3339)?, event, json.to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Bring the index up to date with the archives actually present in the backup directories
+    pub fn sync_with_dirs(&mut self, backup_paths: &[PathBuf]) -> Result<(), anyhow::Error> {
+        let on_disk = list_backups_in(backup_paths)?;
+        let indexed: HashMap<String, PathBuf> = self.list()?.into_iter().map(|b| (b.name, b.path)).collect();
+
+        let on_disk_names: HashSet<&str> = on_disk.iter().map(|b| b.name.as_str()).collect();
+
+        for name in indexed.keys() {
+            if !on_disk_names.contains(name.as_str()) {
+                self.remove_backup(name)?;
+            }
+        }
+
+        for backup in on_disk.iter() {
+            // Archives moved between directories are re-added with their new path
+            match indexed.get(&backup.name) {
+                Some(path) if *path == backup.path => continue,
+                Some(_) => self.remove_backup(&backup.name)?,
+                None => {}
+            }
+
+            let manifest = Manifest::load_for_archive(&backup.path)?;
+            self.add_backup(backup, manifest.as_ref())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn to_unix_nanos(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+fn from_unix_nanos(nanos: i64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{manifest::manifest_path, testing::write_backup};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn names(backups: Vec<BackupInfo>) -> Vec<String> {
+        backups.into_iter().map(|b| b.name).collect()
+    }
+
+    #[test]
+    fn search_matches_backup_names_and_file_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join("backups");
+        write_backup(
+            &backup_path,
+            "2025-01-01 00-00-01 Before boss.7z",
+            2 * MINUTE,
+            &["slot1.sav"],
+        );
+        write_backup(
+            &backup_path,
+            "2025-01-01 00-00-02 Quick.7z",
+            MINUTE,
+            &["slot2.sav", "photos/1.png"],
+        );
+
+        let mut index = BackupIndex::open(dir.path()).unwrap();
+        index.sync_with_dirs(&[backup_path]).unwrap();
+
+        assert_eq!(
+            names(index.list().unwrap()),
+            ["2025-01-01 00-00-02 Quick.7z", "2025-01-01 00-00-01 Before boss.7z"]
+        );
+        assert_eq!(
+            names(index.search("BOSS").unwrap()),
+            ["2025-01-01 00-00-01 Before boss.7z"]
+        );
+        assert_eq!(names(index.search("photos").unwrap()), ["2025-01-01 00-00-02 Quick.7z"]);
+        assert_eq!(index.search(".sav").unwrap().len(), 2);
+        assert!(index.search("missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn sync_forgets_removed_archives_and_follows_moved_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let hot_path = dir.path().join("backups");
+        let cold_path = dir.path().join("cold");
+        let removed = write_backup(&hot_path, "2025-01-01 00-00-01 Manual.7z", 2 * MINUTE, &["slot1.sav"]);
+        let moved = write_backup(&hot_path, "2025-01-01 00-00-02 Manual.7z", MINUTE, &["slot2.sav"]);
+
+        let mut index = BackupIndex::open(dir.path()).unwrap();
+        index.sync_with_dirs(&[hot_path.clone(), cold_path.clone()]).unwrap();
+        assert_eq!(index.list().unwrap().len(), 2);
+
+        fs::remove_file(&removed.path).unwrap();
+        fs::create_dir_all(&cold_path).unwrap();
+        fs::rename(&moved.path, cold_path.join(&moved.name)).unwrap();
+        fs::rename(manifest_path(&moved.path), manifest_path(&cold_path.join(&moved.name))).unwrap();
+
+        index.sync_with_dirs(&[hot_path, cold_path.clone()]).unwrap();
+
+        let backups = index.list().unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].path, cold_path.join(&moved.name));
+
+        // The files of moved archives are indexed again
+        assert_eq!(names(index.search("slot2").unwrap()), [moved.name]);
+        assert!(index.search("slot1").unwrap().is_empty());
+    }
+}
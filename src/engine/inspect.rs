@@ -0,0 +1,58 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use globset::GlobSet;
+use tracing::warn;
+
+use crate::{
+    config::game::SaveInspect,
+    internal::{
+        filter::build_globset,
+        inspect::{create_inspector, SaveInspector, SaveMetadata},
+    },
+};
+
+use super::manifest::Manifest;
+
+/// Inspector of a game, with the save files it inspects
+pub struct Inspection {
+    inspector: Box<dyn SaveInspector>,
+    files: GlobSet,
+}
+
+impl Inspection {
+    pub fn from_config(config: &SaveInspect) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            inspector: create_inspector(&config.inspector, &config.fields).context("Invalid save inspector")?,
+            files: build_globset(&config.files)?,
+        })
+    }
+
+    /// Extract metadata from the staged save files listed in a manifest.
+    /// When several files provide the same field, the first one in the manifest wins.
+    pub fn metadata(&self, staging_path: &Path, manifest: &Manifest) -> SaveMetadata {
+        let mut metadata = SaveMetadata::new();
+
+        let files = manifest
+            .files
+            .iter()
+            .filter(|f| self.files.is_empty() || self.files.is_match(&f.path));
+
+        for file in files {
+            let result = fs::read(staging_path.join(&file.path))
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| self.inspector.inspect(&file.path, &contents));
+
+            match result {
+                Ok(file_metadata) => {
+                    for (label, value) in file_metadata {
+                        metadata.entry(label).or_insert(value);
+                    }
+                }
+                Err(err) => warn!("Error inspecting {}: {err:#}", file.path.display()),
+            }
+        }
+
+        metadata
+    }
+}
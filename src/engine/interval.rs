@@ -0,0 +1,84 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tracing::info;
+
+/// Number of recent backups whose durations determine an adaptive interval
+const RECENT_BACKUPS: usize = 5;
+
+/// Minimum time between auto-backups.
+/// If adaptive, the interval grows when backups take long, so that no more than
+/// a given percentage of time is spent creating backups.
+pub struct AutoBackupInterval {
+    min_interval: Duration,
+    /// Maximum percentage of time to spend creating backups, if adaptive
+    max_busy_percent: Option<u32>,
+    recent_durations: VecDeque<Duration>,
+}
+
+impl AutoBackupInterval {
+    pub fn new(min_interval: Duration, max_busy_percent: Option<u32>) -> Self {
+        Self {
+            min_interval,
+            max_busy_percent: max_busy_percent.map(|p| p.clamp(1, 100)),
+            recent_durations: VecDeque::with_capacity(RECENT_BACKUPS),
+        }
+    }
+
+    /// Record how long a backup took
+    pub fn record_backup(&mut self, duration: Duration) {
+        let previous = self.current();
+
+        if self.recent_durations.len() == RECENT_BACKUPS {
+            self.recent_durations.pop_front();
+        }
+
+        self.recent_durations.push_back(duration);
+
+        let current = self.current();
+
+        if current.as_secs() != previous.as_secs() && current > self.min_interval {
+            info!(
+                "Auto-backup interval adjusted to {}s, as backups take long",
+                current.as_secs()
+            );
+        }
+    }
+
+    /// Current minimum time between auto-backups
+    pub fn current(&self) -> Duration {
+        let Some(max_busy_percent) = self.max_busy_percent else {
+            return self.min_interval;
+        };
+
+        if self.recent_durations.is_empty() {
+            return self.min_interval;
+        }
+
+        let average = self.recent_durations.iter().sum::<Duration>() / self.recent_durations.len() as u32;
+
+        // A backup taking the average time should be at most the given percentage of the interval
+        self.min_interval.max(average * 100 / max_busy_percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_interval_grows_with_backup_duration() {
+        let min_interval = Duration::from_secs(60);
+
+        let mut fixed = AutoBackupInterval::new(min_interval, None);
+        fixed.record_backup(Duration::from_secs(30));
+        assert_eq!(fixed.current(), min_interval);
+
+        let mut adaptive = AutoBackupInterval::new(min_interval, Some(10));
+        adaptive.record_backup(Duration::from_secs(3));
+        assert_eq!(adaptive.current(), min_interval);
+
+        // Backups averaging 20 seconds may take at most 10% of a 200 second interval
+        adaptive.record_backup(Duration::from_secs(37));
+        assert_eq!(adaptive.current(), Duration::from_secs(200));
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use filetime::FileTime;
+use serde_derive::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::internal::{
+    hash::hash_crc32,
+    inspect::SaveMetadata,
+    sync::{SyncEvent, SyncEvents},
+};
+
+use super::BackupKind;
+
+pub const MANIFEST_VERSION: u32 = 1;
+pub const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Record of the files contained in a backup archive
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Manifest {
+    pub version: u32,
+    pub game: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub kind: BackupKind,
+    /// Id of the engine session the backup was created in
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Display metadata extracted from the save files, such as character name or level
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SaveMetadata::is_empty")]
+    pub metadata: SaveMetadata,
+    /// Problems that left something out of the backup, such as missing save dirs
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Names of the save dirs and files left out of the backup as they were missing
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_paths: Vec<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ManifestFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub crc32: u32,
+    pub mtime: i64,
+    #[serde(default)]
+    pub mtime_nanos: u32,
+    /// Backup holding the full copy of the file that it is stored as a delta against
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_base: Option<String>,
+    /// Backup holding the file, if it is left out of this one as its save dir was unchanged
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored_in: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ManifestMismatch {
+    Missing { path: PathBuf },
+    Size { path: PathBuf, expected: u64, actual: u64 },
+    Checksum { path: PathBuf },
+}
+
+/// Path of the manifest belonging to an archive
+pub fn manifest_path(archive_path: &Path) -> PathBuf {
+    let mut path = archive_path.as_os_str().to_owned();
+    path.push(MANIFEST_SUFFIX);
+
+    path.into()
+}
+
+impl Manifest {
+    /// Build a manifest of the files in a directory.
+    /// Checksums are reused from the previous manifest for files whose size and modification time are unchanged.
+    pub fn build(
+        game: &str,
+        kind: BackupKind,
+        path: &Path,
+        previous: Option<&Manifest>,
+        events: &SyncEvents,
+    ) -> Result<Self, anyhow::Error> {
+        let previous_files: HashMap<&Path, &ManifestFile> = previous
+            .map(|m| m.files.iter().map(|f| (f.path.as_path(), f)).collect())
+            .unwrap_or_default();
+
+        let mut files = Vec::new();
+
+        let entries = walkdir::WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok);
+
+        for entry in entries {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let rel_path = entry.path().strip_prefix(path)?.to_path_buf();
+            let metadata = entry.metadata()?;
+            let size = metadata.len();
+            let modified = FileTime::from_last_modification_time(&metadata);
+
+            let previous_file = previous_files.get(rel_path.as_path()).filter(|f| {
+                f.size == size && f.mtime == modified.unix_seconds() && f.mtime_nanos == modified.nanoseconds()
+            });
+
+            let crc32 = if let Some(previous_file) = previous_file {
+                previous_file.crc32
+            } else {
+                events.send(SyncEvent::BeginFile {
+                    prefix: "Checksum".to_owned(),
+                    filename: rel_path.to_string_lossy().into_owned(),
+                    size,
+                });
+                let crc32 = hash_crc32(entry.path(), |bytes| {
+                    events.send(SyncEvent::FileProgress { bytes: bytes as u64 })
+                })?;
+                events.send(SyncEvent::EndFile);
+
+                crc32
+            };
+
+            files.push(ManifestFile {
+                path: rel_path,
+                size,
+                crc32,
+                mtime: modified.unix_seconds(),
+                mtime_nanos: modified.nanoseconds(),
+                delta_base: None,
+                stored_in: None,
+            });
+        }
+
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            game: game.to_owned(),
+            created_at: OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+            kind,
+            session: None,
+            metadata: SaveMetadata::new(),
+            warnings: Vec::new(),
+            skipped_paths: Vec::new(),
+            files,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let json = fs::read_to_string(path).context("Error reading manifest file")?;
+        let manifest = serde_json::from_str(&json).context("Error parsing manifest")?;
+
+        Ok(manifest)
+    }
+
+    /// Load the manifest belonging to an archive, if it has one
+    pub fn load_for_archive(archive_path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        let path = manifest_path(archive_path);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::from_file(&path)?))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+
+        let mut file = fs::File::create(path).context("Error creating manifest file")?;
+        file.write_all(json.as_bytes())
+            .context("Error writing to manifest file")?;
+
+        Ok(())
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    /// Verify that the files in a directory match the manifest
+    pub fn verify(&self, path: &Path, events: &SyncEvents) -> Result<Vec<ManifestMismatch>, anyhow::Error> {
+        let mut mismatches = Vec::new();
+
+        for file in self.files.iter() {
+            let file_path = path.join(&file.path);
+
+            let Ok(metadata) = file_path.metadata() else {
+                mismatches.push(ManifestMismatch::Missing {
+                    path: file.path.clone(),
+                });
+                continue;
+            };
+
+            if metadata.len() != file.size {
+                mismatches.push(ManifestMismatch::Size {
+                    path: file.path.clone(),
+                    expected: file.size,
+                    actual: metadata.len(),
+                });
+                continue;
+            }
+
+            events.send(SyncEvent::BeginFile {
+                prefix: "Verify".to_owned(),
+                filename: file.path.to_string_lossy().into_owned(),
+                size: file.size,
+            });
+            let crc32 = hash_crc32(&file_path, |bytes| {
+                events.send(SyncEvent::FileProgress { bytes: bytes as u64 })
+            })?;
+            events.send(SyncEvent::EndFile);
+
+            if crc32 != file.crc32 {
+                mismatches.push(ManifestMismatch::Checksum {
+                    path: file.path.clone(),
+                });
+            }
+        }
+
+        Ok(mismatches)
+    }
+}
+
+impl std::fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { path } => write!(f, "Missing: {}", path.display()),
+            Self::Size { path, expected, actual } => write!(
+                f,
+                "Size mismatch: {} (expected {expected} bytes, found {actual})",
+                path.display()
+            ),
+            Self::Checksum { path } => write!(f, "Checksum mismatch: {}", path.display()),
+        }
+    }
+}
@@ -1,12 +1,51 @@
+pub mod adopt;
+pub mod annotations;
+pub mod backups;
+pub mod bench;
+pub mod control;
+mod copies;
+pub mod current;
+mod delta;
+pub mod descriptions;
+pub mod diff;
+mod dryrun;
+pub mod extract;
+pub mod fsck;
+mod grace;
+pub mod history;
+pub mod index;
+mod inspect;
+mod interval;
+pub mod manifest;
+pub mod overlay;
+mod partial;
+pub mod progress;
+mod recycle;
+pub mod remote;
+pub mod restore;
+mod retention;
+mod retry;
+mod server;
+pub mod session;
+mod staging;
+pub mod stats;
+#[cfg(test)]
+pub mod testing;
+#[cfg(test)]
+mod tests;
+mod tiering;
 pub mod ui;
+mod upload;
+pub mod verify;
+pub mod watch;
 
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
-    process::Stdio,
     sync::{
         atomic::{AtomicBool, AtomicU8, Ordering},
-        mpsc::Sender,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
         Arc, Mutex, Weak,
     },
     thread::JoinHandle,
@@ -14,26 +53,94 @@ use std::{
 };
 
 use anyhow::Context;
-use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use grace::GraceTimes;
+use interval::AutoBackupInterval;
+use notify::RecursiveMode;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use progress::{ProgressModel, ProgressUiHandler};
+use serde_derive::{Deserialize, Serialize};
 use time::{format_description::BorrowedFormatItem, macros::format_description, OffsetDateTime};
 use tracing::{error, info, warn};
-use ui::StoolUiHandler;
+use ui::{spawn_ui_thread, ChannelUiHandler, MultiUiHandler, StoolUiHandler, UiEvent, UiSubscribers, TICK_INTERVAL};
+use watch::{SaveWatcher, StagedCopies, WatchEventKind, WatchState, WatchStatus};
 
-use crate::internal::{filter, pid::PidLock, sync};
+use self::{
+    backups::{
+        auto_backup_description, list_game_backups, sanitize_description, with_description, BackupInfo,
+        AUTO_BACKUP_DESCRIPTION, EXIT_BACKUP_DESCRIPTION, TRIGGER_BACKUP_DESCRIPTION,
+    },
+    copies::CopyNaming,
+    current::CurrentSavesTracker,
+    delta::DeltaBases,
+    dryrun::{BackupPlan, PlannedFile},
+    history::{BackupFailure, History, HistoryEvent},
+    index::BackupIndex,
+    inspect::Inspection,
+    manifest::{manifest_path, Manifest},
+    retry::ErrorCategory,
+    server::{Postponement, ServerHooks},
+    session::SessionSummary,
+    staging::KeptStaging,
+};
+
+use crate::config::game::{DeletedFiles, GameConfig, GameSaveFile, StagingLocation};
+use crate::internal::{
+    archive::Archiver,
+    clock::Clock,
+    filter,
+    format::{format_bytes, format_count, format_duration},
+    inspect::SaveMetadata,
+    parity,
+    pid::PidLock,
+    registry,
+    shutdown::Shutdown,
+    sync::{self, CopyOptions, Deletion, DirIndex, SyncOptions, SyncStats},
+};
 
 pub const ARCHIVE_DATE_FORMAT: &[BorrowedFormatItem<'static>] =
     format_description!("[year]-[month]-[day] [hour]-[minute]-[second]");
 
-const SLEEP_DURATION: Duration = Duration::from_secs(1);
+/// Longest backup description taken from a trigger file, in characters
+const MAX_TRIGGER_DESCRIPTION_LEN: usize = 100;
 
+/// Time a trigger file must go unmodified before it is picked up
+const TRIGGER_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Part of a per-user save path standing for the name of each user's directory
+const USER_PLACEHOLDER: &str = "{user}";
+
+pub const PID_FILENAME: &str = "stool.pid";
+pub const STAGING_DIRNAME: &str = "staging";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackupKind {
+    Manual,
+    Auto,
+    Exit,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum BackupRequest {
-    CreateBackup { archive_name: String },
-    RestoreBackup { archive_name: String },
+    CreateBackup {
+        archive_name: String,
+        kind: BackupKind,
+    },
+    RestoreBackup {
+        archive_name: String,
+        /// Only restore this file or directory, given as a path inside the archive
+        only: Option<PathBuf>,
+        /// Files to leave as they are, given as paths inside the archive, such as live save files newer than their copies
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        keep: Vec<PathBuf>,
+    },
 }
 
-#[derive(Clone, Copy, IntoPrimitive, PartialEq, TryFromPrimitive)]
+#[derive(Clone, Copy, Debug, Deserialize, IntoPrimitive, PartialEq, Serialize, TryFromPrimitive)]
 #[repr(u8)]
+#[serde(rename_all = "kebab-case")]
 pub enum EngineState {
     Starting = 0,
     Running = 1,
@@ -46,6 +153,34 @@ pub struct EngineArgs {
     pub name: String,
     pub game_config_path: PathBuf,
     pub data_path: PathBuf,
+    /// Keep an index of backups in a database
+    pub use_index: bool,
+    /// Treat unknown keys in the game config as errors
+    pub strict_config: bool,
+    /// Data root for staging, if not the data path
+    pub staging_root: Option<PathBuf>,
+    /// Data root for backup archives, if not the data path
+    pub backup_root: Option<PathBuf>,
+    /// Data root for old backup archives
+    pub cold_root: Option<PathBuf>,
+    /// Age after which backups are moved to the cold root
+    pub cold_after: Option<Duration>,
+    /// Archiver used to create and read backup archives
+    pub archiver: Arc<dyn Archiver>,
+    /// Size of parity data generated for new archives, as a percentage of the archive size
+    pub parity_percent: Option<u32>,
+    /// Clock used for grace time, auto-backup intervals and session duration
+    pub clock: Arc<dyn Clock>,
+    /// Only log what would be backed up or restored, without writing any files
+    pub dry_run: bool,
+    /// Keep status files for streaming overlays in the data directory of the game
+    pub overlay_status: bool,
+    /// Interval at which due auto-backups and the trigger file are checked for
+    pub check_interval: Duration,
+    /// Interval at which save paths are scanned for changes when watching falls back to polling
+    pub poll_interval: Duration,
+    /// Directory for temporary files, if not the system temp directory
+    pub temp_root: Option<PathBuf>,
 }
 
 /// Represents a running instance of an S-Tool engine.
@@ -53,16 +188,92 @@ pub struct Engine {
     args: EngineArgs,
     control: EngineControl,
     join_handle: JoinHandle<()>,
+    #[cfg(test)]
+    watcher: Weak<Mutex<Option<SaveWatcher>>>,
 }
 
 /// Exposes various functions to allow limited
 /// interactions with a running S-Tool engine.
 #[derive(Clone)]
 pub struct EngineControl {
-    shutdown: Arc<AtomicBool>,
+    shutdown: Shutdown,
     state: Arc<AtomicU8>,
     autobackup: Arc<AtomicBool>,
     backup_tx: Weak<Sender<BackupRequest>>,
+    /// Requests sent through the control that the backup thread has not started on yet
+    queued: Arc<Mutex<Vec<BackupRequest>>>,
+    busy: Arc<AtomicBool>,
+    session: Arc<Mutex<SessionSummary>>,
+    pending: PendingChangesTracker,
+    watch_state: Arc<Mutex<WatchState>>,
+    /// Progress of the current action, as reported to the UI
+    progress: Arc<Mutex<ProgressModel>>,
+    ui_subscribers: UiSubscribers,
+}
+
+/// Snapshot of what a running engine is doing, shared by everything that presents it
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EngineSnapshot {
+    pub state: EngineState,
+    pub autobackup: bool,
+    pub action: Option<ActionSnapshot>,
+    /// Whether a backup or restore is queued or in progress
+    pub busy: bool,
+    /// Requests waiting for the current action to finish, oldest first
+    pub queue: Vec<BackupRequest>,
+    pub watcher_fallback: Option<String>,
+    pub pending_changes: Option<PendingChanges>,
+    pub backup_timing: BackupTiming,
+    /// Statistics, warnings and errors of the current session
+    pub session: SessionSummary,
+}
+
+/// Progress of the action an engine is running
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ActionSnapshot {
+    pub description: String,
+    pub stage: Option<String>,
+    pub file: Option<String>,
+    /// Completed fraction, from 0 to 1
+    pub progress: f32,
+    pub elapsed: Duration,
+    pub bytes_processed: u64,
+}
+
+/// Changes to save files that have not been backed up yet
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PendingChanges {
+    /// Time since the latest change
+    pub since: Duration,
+    /// Time until an auto-backup is expected to be created, if auto-backup is enabled
+    pub auto_backup_in: Option<Duration>,
+}
+
+/// When backups were and can next be created, for display while the engine is idle
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackupTiming {
+    /// Time since the latest backup or restore of the session
+    pub last_backup_ago: Option<Duration>,
+    /// Time until the minimum interval since the latest backup has passed, if auto-backup is enabled
+    pub auto_backup_possible_in: Option<Duration>,
+}
+
+/// State needed to tell whether changes are pending, and when they will be backed up
+#[derive(Clone)]
+struct PendingChangesTracker {
+    clock: Arc<dyn Clock>,
+    last_change_at: Arc<Mutex<Option<Instant>>>,
+    last_backup_at: Arc<Mutex<Option<Instant>>>,
+    grace_time: Duration,
+    /// Grace time of the changes since the latest backup, if it differs from the game's
+    change_grace_time: Arc<Mutex<Option<Duration>>>,
+    interval: Arc<Mutex<AutoBackupInterval>>,
+    /// Every save is backed up, regardless of interval
+    snapshot_every_save: bool,
 }
 
 #[derive(Clone)]
@@ -71,6 +282,193 @@ struct InternalGameSaveDir {
     pub path: PathBuf,
     pub include_globset: Option<globset::GlobSet>,
     pub ignore_globset: Option<globset::GlobSet>,
+    pub streaming: bool,
+    pub grace_time: Option<Duration>,
+}
+
+impl InternalGameSaveDir {
+    /// Save directories of a game config, with their filters compiled.
+    /// Per-user save directories are expanded into one for each user directory present.
+    fn from_config(gcfg: &crate::config::game::GameConfig) -> Result<Vec<Self>, anyhow::Error> {
+        let mut save_dirs = Vec::new();
+
+        for (name, gsp) in gcfg.save_dirs.iter() {
+            let include_globset = gsp.include.as_deref().map(filter::build_globset).transpose()?;
+            let ignore_globset = gsp.ignore.as_deref().map(filter::build_globset).transpose()?;
+
+            let paths = if gsp.per_user {
+                let users = expand_user_paths(&gsp.path).with_context(|| format!("Save dir [{name}]"))?;
+
+                if users.is_empty() {
+                    warn!(
+                        "No user directories found for save dir [{name}]: {}",
+                        gsp.path.display()
+                    );
+                }
+
+                users
+                    .into_iter()
+                    .map(|(user, path)| (format!("{name}/{user}"), path))
+                    .collect()
+            } else {
+                vec![(name.clone(), gsp.path.clone())]
+            };
+
+            save_dirs.extend(paths.into_iter().map(|(name, path)| InternalGameSaveDir {
+                name,
+                path,
+                include_globset: include_globset.clone(),
+                ignore_globset: ignore_globset.clone(),
+                streaming: gsp.streaming,
+                grace_time: gsp.grace_time.map(Duration::from_secs),
+            }));
+        }
+
+        Ok(save_dirs)
+    }
+
+    fn sync_options<'a>(&'a self, exclude: &'a [PathBuf], filter_in_dst: bool, copy: CopyOptions) -> SyncOptions<'a> {
+        SyncOptions {
+            include_globset: self.include_globset.as_ref(),
+            ignore_globset: self.ignore_globset.as_ref(),
+            exclude,
+            filter_in_dst,
+            streaming: self.streaming,
+            copy,
+            deletion: Deletion::Remove,
+        }
+    }
+
+    /// Refuse save directories containing stool's own data, which would otherwise end up in its backups
+    fn check_own_paths(save_dirs: &[Self], own_paths: &[PathBuf]) -> Result<(), anyhow::Error> {
+        for gsp in save_dirs.iter() {
+            let save_dir_path = sync::resolve_path(&gsp.path);
+
+            if let Some(own_path) = own_paths
+                .iter()
+                .find(|p| sync::resolve_path(p).starts_with(&save_dir_path))
+            {
+                anyhow::bail!(
+                    "Save dir [{}] contains stool data at {}, which would be backed up along with the saves",
+                    gsp.name,
+                    own_path.display()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Existing directories matching a per-user save path, by user.
+/// The first path component containing [`USER_PLACEHOLDER`] is matched against directory names,
+/// and any later occurrences are replaced with the matched user.
+fn expand_user_paths(template: &Path) -> Result<Vec<(String, PathBuf)>, anyhow::Error> {
+    let components: Vec<_> = template.components().collect();
+    let index = components
+        .iter()
+        .position(|c| c.as_os_str().to_string_lossy().contains(USER_PLACEHOLDER))
+        .with_context(|| format!("Per-user save path does not contain {USER_PLACEHOLDER}"))?;
+
+    let base_path: PathBuf = components[..index].iter().collect();
+    let pattern = components[index].as_os_str().to_string_lossy();
+    let (prefix, suffix) = pattern.split_once(USER_PLACEHOLDER).unwrap_or_default();
+    let rest_path: PathBuf = components[index + 1..].iter().collect();
+    let rest_path = rest_path.to_string_lossy();
+
+    let Ok(entries) = fs::read_dir(&base_path) else {
+        return Ok(Vec::new());
+    };
+
+    let mut users = Vec::new();
+
+    for entry in entries {
+        let entry = entry?;
+
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let dir_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(user) = dir_name
+            .strip_prefix(prefix)
+            .and_then(|n| n.strip_suffix(suffix))
+            .filter(|user| !user.is_empty())
+        else {
+            continue;
+        };
+
+        let path = if rest_path.is_empty() {
+            entry.path()
+        } else {
+            entry.path().join(rest_path.replace(USER_PLACEHOLDER, user))
+        };
+
+        users.push((user.to_owned(), path));
+    }
+
+    users.sort();
+
+    Ok(users)
+}
+
+impl EngineArgs {
+    /// Path to the game config file
+    pub fn game_config_file_path(&self) -> PathBuf {
+        self.game_config_path.join(format!("{}.toml", self.name))
+    }
+
+    /// Path to the data directory of the game
+    pub fn output_path(&self) -> PathBuf {
+        self.data_path.join(&self.name)
+    }
+
+    /// Path to the directory containing the backup archives of the game
+    pub fn backup_path(&self) -> PathBuf {
+        match &self.backup_root {
+            Some(root) => root.join(&self.name).join("backups"),
+            None => self.output_path().join("backups"),
+        }
+    }
+
+    /// Path to the directory containing old backup archives of the game, if a cold root is configured
+    pub fn cold_backup_path(&self) -> Option<PathBuf> {
+        self.cold_root
+            .as_ref()
+            .map(|root| root.join(&self.name).join("backups"))
+    }
+
+    /// Paths to all directories that may contain backup archives of the game
+    pub fn backup_paths(&self) -> Vec<PathBuf> {
+        std::iter::once(self.backup_path())
+            .chain(self.cold_backup_path())
+            .collect()
+    }
+
+    /// Path to the staging directory of the game
+    pub fn staging_path(&self) -> PathBuf {
+        match &self.staging_root {
+            Some(root) => root.join(&self.name).join(STAGING_DIRNAME),
+            None => self.output_path().join(STAGING_DIRNAME),
+        }
+    }
+
+    /// Directory for temporary files of the game, such as backups unpacked for extraction:
+    /// the temp path of the game config, else that of the main config, else the system temp directory
+    pub fn temp_path(&self, gcfg: &GameConfig) -> PathBuf {
+        gcfg.temp_path
+            .clone()
+            .or_else(|| self.temp_root.clone())
+            .unwrap_or_else(std::env::temp_dir)
+    }
+
+    /// Directories written by stool for the game, which must never be backed up
+    pub fn own_paths(&self) -> Vec<PathBuf> {
+        [self.output_path(), self.staging_path()]
+            .into_iter()
+            .chain(self.backup_paths())
+            .collect()
+    }
 }
 
 impl Engine {
@@ -86,6 +484,13 @@ impl Engine {
         self.join_handle.is_finished()
     }
 
+    /// Report an event as if it came from the save watcher
+    #[cfg(test)]
+    pub fn send_watch_event(&self, event: notify::Event) {
+        let watcher = self.watcher.upgrade().unwrap();
+        watcher.lock().unwrap().as_ref().unwrap().send(event);
+    }
+
     /// Wait for engine thread to finish
     pub fn join(self) {
         self.join_handle.join().unwrap();
@@ -95,7 +500,7 @@ impl Engine {
 impl EngineControl {
     /// Request shutdown of engine
     pub fn shutdown(&mut self) {
-        self.shutdown.store(true, Ordering::Release);
+        self.shutdown.request();
     }
 
     pub fn state(&self) -> EngineState {
@@ -113,97 +518,502 @@ impl EngineControl {
         self.autobackup.store(val, Ordering::Relaxed);
     }
 
-    /// Request a backup operation
+    /// Summary of the current engine session.
+    /// Play duration is only filled in once the engine has shut down.
+    pub fn session_summary(&self) -> SessionSummary {
+        self.session.lock().unwrap().clone()
+    }
+
+    /// Activity of each watched save directory and file
+    pub fn watches(&self) -> Vec<WatchStatus> {
+        self.watch_state.lock().unwrap().watches.clone()
+    }
+
+    /// Why watching fell back to polling, if it did, in which case changes may be detected late
+    pub fn watcher_fallback(&self) -> Option<String> {
+        self.watch_state.lock().unwrap().fallback.clone()
+    }
+
+    /// Changes detected since the latest backup, if any
+    pub fn pending_changes(&self) -> Option<PendingChanges> {
+        let pending = &self.pending;
+
+        let now = pending.clock.now();
+        let last_change_at = (*pending.last_change_at.lock().unwrap())?;
+        let last_backup_at = *pending.last_backup_at.lock().unwrap();
+
+        // An auto-backup is requested once the minimum interval has passed,
+        // and proceeds once grace time has passed since the latest change
+        let auto_backup_in = self.get_autobackup().then(|| {
+            let grace_time = pending.change_grace_time.lock().unwrap().unwrap_or(pending.grace_time);
+            let mut due_at = last_change_at + grace_time;
+
+            if let (false, Some(last_backup_at)) = (pending.snapshot_every_save, last_backup_at) {
+                due_at = due_at.max(last_backup_at + pending.interval.lock().unwrap().current());
+            }
+
+            due_at.saturating_duration_since(now)
+        });
+
+        Some(PendingChanges {
+            since: now.saturating_duration_since(last_change_at),
+            auto_backup_in,
+        })
+    }
+
+    /// When backups were and can next be created
+    pub fn backup_timing(&self) -> BackupTiming {
+        let pending = &self.pending;
+
+        let now = pending.clock.now();
+        let last_backup_at = *pending.last_backup_at.lock().unwrap();
+
+        let auto_backup_possible_in = match (self.get_autobackup(), last_backup_at) {
+            (false, _) => None,
+            (true, None) => Some(Duration::ZERO),
+            (true, Some(last_backup_at)) => {
+                let due_at = last_backup_at + pending.interval.lock().unwrap().current();
+                Some(due_at.saturating_duration_since(now))
+            }
+        };
+
+        BackupTiming {
+            last_backup_ago: last_backup_at.map(|at| now.saturating_duration_since(at)),
+            auto_backup_possible_in,
+        }
+    }
+
+    pub fn snapshot(&self) -> EngineSnapshot {
+        let action = {
+            let progress = self.progress.lock().unwrap();
+
+            progress.action.as_ref().map(|action| ActionSnapshot {
+                description: action.describe(),
+                stage: progress.stage.as_ref().map(|s| s.describe()),
+                file: progress.file.as_ref().map(|f| f.describe()),
+                progress: progress.action_ratio(),
+                elapsed: action.started_at.elapsed(),
+                bytes_processed: action.bytes_processed,
+            })
+        };
+
+        EngineSnapshot {
+            state: self.state(),
+            autobackup: self.get_autobackup(),
+            action,
+            busy: self.is_busy(),
+            queue: self.queued.lock().unwrap().clone(),
+            watcher_fallback: self.watcher_fallback(),
+            pending_changes: self.pending_changes(),
+            backup_timing: self.backup_timing(),
+            session: self.session_summary(),
+        }
+    }
+
+    /// Follow the UI events of the engine, until it shuts down or the receiver is dropped
+    pub fn subscribe(&self) -> Receiver<UiEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.ui_subscribers.lock().unwrap().push(tx);
+
+        rx
+    }
+
+    /// Whether a backup or restore is queued or in progress
+    pub fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::Acquire) || !self.queued.lock().unwrap().is_empty()
+    }
+
+    /// Request a backup operation.
+    /// A request identical to one that is still queued is ignored.
     pub fn send(&self, req: BackupRequest) -> Result<(), anyhow::Error> {
         let Some(backup_tx) = self.backup_tx.upgrade() else {
             return Ok(());
         };
 
-        backup_tx.send(req)?;
+        queue_request(&backup_tx, &self.queued, req)?;
 
         Ok(())
     }
 }
 
-pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHandler) -> Result<Engine, anyhow::Error> {
-    let EngineArgs {
-        name,
-        game_config_path,
-        data_path,
-    } = &args;
+/// Send a request to the backup thread, recording it as queued until the backup thread starts on it.
+/// A request identical to one that is still queued is ignored.
+fn queue_request(
+    backup_tx: &Sender<BackupRequest>,
+    queued: &Mutex<Vec<BackupRequest>>,
+    req: BackupRequest,
+) -> Result<(), mpsc::SendError<BackupRequest>> {
+    {
+        let mut queued = queued.lock().unwrap();
+
+        if queued.contains(&req) {
+            info!("An identical request is already queued, ignoring");
+            return Ok(());
+        }
+
+        queued.push(req.clone());
+    }
+
+    backup_tx.send(req)
+}
+
+/// How save files are copied to and from staging
+fn copy_options(gcfg: &GameConfig) -> CopyOptions {
+    CopyOptions {
+        verify: gcfg.verify,
+        overwrite_read_only: gcfg.overwrite_read_only,
+        placeholders: gcfg.cloud_placeholders,
+    }
+}
+
+/// Create a backup archive, reporting the growth of the archive file while the archiver runs,
+/// as archivers do not report their own progress
+fn create_archive(
+    archiver: &dyn Archiver,
+    src: &Path,
+    archive_path: &Path,
+    deadline: Option<Instant>,
+    ui: &ChannelUiHandler,
+) -> Result<(), anyhow::Error> {
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+
+    std::thread::scope(|scope| {
+        let mut ui = ui.clone();
+
+        scope.spawn(move || {
+            let mut reported = 0;
+
+            while let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(TICK_INTERVAL) {
+                let size = fs::metadata(archive_path).map_or(0, |m| m.len());
+
+                if size > reported {
+                    ui.compress_progress(size - reported);
+                    reported = size;
+                }
+            }
+        });
+
+        let result = match deadline {
+            Some(deadline) => archiver.create_within(src, archive_path, deadline),
+            None => archiver.create(src, archive_path),
+        };
+        drop(done_tx);
+
+        result
+    })
+}
+
+/// Number and total size of the save files, which the first backup copies in full
+fn measure_saves(
+    save_dirs: &[InternalGameSaveDir],
+    save_files: &[GameSaveFile],
+    own_paths: &[PathBuf],
+    copy: CopyOptions,
+) -> (usize, u64) {
+    let dirs = save_dirs
+        .iter()
+        .filter(|gsp| gsp.path.exists())
+        .map(|gsp| sync::measure_dir(&gsp.path, gsp.sync_options(own_paths, false, copy)));
+    let files = save_files
+        .iter()
+        .filter_map(|gsf| fs::metadata(&gsf.path).ok())
+        .map(|m| (1, m.len()));
+
+    dirs.chain(files)
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+}
+
+/// Bytes that staging the save files is expected to copy, given what is already staged
+fn pending_stage_bytes(
+    save_dirs: &[InternalGameSaveDir],
+    save_files: &[GameSaveFile],
+    staging_path: &Path,
+    staging_index: &HashMap<String, DirIndex>,
+    own_paths: &[PathBuf],
+    copy: CopyOptions,
+) -> u64 {
+    let dirs = save_dirs.iter().filter(|gsp| gsp.path.exists()).map(|gsp| {
+        sync::pending_bytes(
+            &gsp.path,
+            &staging_path.join(&gsp.name),
+            gsp.sync_options(own_paths, false, copy),
+            staging_index.get(&gsp.name),
+        )
+    });
+    let files = save_files.iter().map(|gsf| {
+        let staging_dir_path = match &gsf.staging_subdirectory {
+            Some(staging_subdir) => staging_path.join(staging_subdir),
+            None => staging_path.to_owned(),
+        };
+
+        sync::pending_file_bytes(&gsf.path, &staging_dir_path)
+    });
+
+    dirs.chain(files).sum()
+}
+
+/// Staging directory to use, which is placed next to the save directories if configured,
+/// or if the staging root is on another volume than them
+fn resolve_staging_path(args: &EngineArgs, location: StagingLocation, save_dirs: &[InternalGameSaveDir]) -> PathBuf {
+    let staging_path = args.staging_path();
 
-    let file_name = format!("{name}.toml");
-    let file_path = game_config_path.join(&file_name);
+    let Some(save_path) = save_dirs.first().map(|gsp| &gsp.path) else {
+        return staging_path;
+    };
+
+    let Some(near_saves_path) = save_path
+        .parent()
+        .map(|parent| parent.join(format!(".stool-staging-{}", args.name)))
+    else {
+        return staging_path;
+    };
+
+    match location {
+        StagingLocation::Data => staging_path,
+        StagingLocation::NearSaves => near_saves_path,
+        StagingLocation::Auto => {
+            if sync::same_volume(&staging_path, save_path) == Some(false) {
+                info!(
+                    "Staging next to the saves, as they are on another volume than {}",
+                    staging_path.display()
+                );
+                near_saves_path
+            } else {
+                staging_path
+            }
+        }
+    }
+}
 
+pub fn run(args: EngineArgs, shutdown: Shutdown, ui: impl StoolUiHandler) -> Result<Engine, anyhow::Error> {
     // Read game config
-    let gcfg = crate::config::game::GameConfig::from_file(&file_path)?;
+    let gcfg = crate::config::game::GameConfig::from_file(&args.game_config_file_path(), args.strict_config)?;
 
-    let output_path = data_path.join(name);
+    for warning in gcfg.warnings() {
+        warn!("{}: {warning}", args.game_config_file_path().display());
+    }
+
+    let output_path = args.output_path();
+    args.archiver.set_temp_path(&args.temp_path(&gcfg));
+
+    let save_dirs = InternalGameSaveDir::from_config(&gcfg)?;
+    let staging_path = resolve_staging_path(&args, gcfg.staging, &save_dirs);
+
+    let mut own_paths = args.own_paths();
+    own_paths.push(staging_path.clone());
+    InternalGameSaveDir::check_own_paths(&save_dirs, &own_paths)?;
+
+    // The trigger file may be placed in a save directory, but is neither backed up nor restored over
+    own_paths.extend(gcfg.trigger_file.clone());
+
+    if args.dry_run {
+        info!("Dry run: no backups will be created, and no files will be written");
+    }
 
-    fs::create_dir_all(&output_path)?;
+    // A dry run does not touch the data directory, so it needs no lock and can run alongside a real run
+    let pid_lock = if args.dry_run {
+        None
+    } else {
+        fs::create_dir_all(&output_path)?;
 
-    let pid_lock = PidLock::acquire(output_path.join("stool.pid")).context("Acquiring PID-lock")?;
+        Some(
+            PidLock::acquire(output_path.join(PID_FILENAME))
+                .context("Acquiring PID-lock, is stool already running? Use `stool tui --attach` to connect to it")?,
+        )
+    };
+
+    let backup_path = args.backup_path();
 
-    let staging_path = output_path.join("staging");
-    let backup_path = output_path.join("backups");
+    // Staging is wiped at startup, unless it is kept as the previous run left it
+    let kept_staging = gcfg
+        .keep_staging
+        .then(|| KeptStaging::new(&args, &staging_path))
+        .filter(|_| !args.dry_run)
+        .transpose()?;
+    let kept_staging_index = kept_staging.as_ref().and_then(KeptStaging::load);
 
-    if staging_path.exists() {
+    if kept_staging_index.is_some() {
+        info!("Keeping staging directory of the previous run");
+    } else if !args.dry_run && staging_path.exists() {
         fs::remove_dir_all(&staging_path)?;
     }
 
+    if !args.dry_run {
+        recycle::purge(&args, gcfg.recycle_keep_days)?;
+    }
+
     let state = Arc::new(AtomicU8::new(EngineState::Starting as u8));
 
+    let history = History::new(&output_path, args.use_index);
+
+    if args.use_index && !args.dry_run {
+        BackupIndex::open(&output_path)?
+            .sync_with_dirs(&args.backup_paths())
+            .context("Updating backup index")?;
+    }
+
+    let started_at = args.clock.now();
+    let session = Arc::new(Mutex::new(SessionSummary::new(
+        OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+    )));
+
     let last_backup_at: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
     let last_change_at: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
+    let change_grace_time: Arc<Mutex<Option<Duration>>> = Arc::new(Mutex::new(None));
+    let current_saves = Arc::new(Mutex::new(CurrentSavesTracker::load(&output_path, !args.dry_run)));
     let latest_backup_path: Arc<Mutex<Option<PathBuf>>> = Arc::new(Mutex::new(None));
 
+    let interval = Arc::new(Mutex::new(AutoBackupInterval::new(
+        Duration::from_secs(gcfg.auto_backup.min_interval),
+        gcfg.auto_backup.adaptive_interval,
+    )));
+
+    let pending = PendingChangesTracker {
+        clock: args.clock.clone(),
+        last_change_at: last_change_at.clone(),
+        last_backup_at: last_backup_at.clone(),
+        grace_time: Duration::from_secs(gcfg.grace_time),
+        change_grace_time: change_grace_time.clone(),
+        interval: interval.clone(),
+        snapshot_every_save: gcfg.auto_backup.snapshot_every_save,
+    };
+
     let backup_or_restore_ongoing = Arc::new(AtomicBool::new(false));
+    // Set once save paths are watched
+    let watcher: Arc<Mutex<Option<SaveWatcher>>> = Arc::new(Mutex::new(None));
+    let queued: Arc<Mutex<Vec<BackupRequest>>> = Arc::new(Mutex::new(Vec::new()));
 
     let autobackup = Arc::new(AtomicBool::new(gcfg.auto_backup.enabled));
     let (backup_tx, backup_rx) = std::sync::mpsc::channel::<BackupRequest>();
 
-    let save_dirs: Vec<InternalGameSaveDir> = gcfg
-        .save_dirs
-        .iter()
-        .map(|(name, gsp)| {
-            let name = name.clone();
-            let path = gsp.path.clone();
-            let include_globset = gsp.include.as_ref().map(|v| filter::build_globset(v).unwrap());
-            let ignore_globset = gsp.ignore.as_ref().map(|v| filter::build_globset(v).unwrap());
+    for (name, target) in gcfg.targets.iter() {
+        upload::validate_target(target).with_context(|| format!("Invalid target [{name}]"))?;
+    }
 
-            InternalGameSaveDir {
-                name,
-                path,
-                include_globset,
-                ignore_globset,
-            }
-        })
-        .collect();
+    let inspection = gcfg.inspect.as_ref().map(Inspection::from_config).transpose()?;
+
+    if gcfg.auto_backup.description_template.is_some() && inspection.is_none() {
+        warn!("Auto-backup description template has no effect without a save inspector");
+    }
+
+    let server = ServerHooks::from_config(&gcfg);
+
+    let registry_keys = if gcfg.save_registry_keys.is_empty() || registry::SUPPORTED {
+        gcfg.save_registry_keys.clone()
+    } else {
+        warn!("Registry keys are only backed up on Windows, in builds with the `registry` feature");
+        Vec::new()
+    };
+
+    // UI thread
+    // Receives UI events from the other threads, so that slow UI updates do not hold up backups.
+    // Progress is also tracked for the engine's own status.
+    let progress = Arc::new(Mutex::new(ProgressModel::default()));
+    let ui_subscribers = UiSubscribers::default();
+    let (mut ui, ui_join_handle) = spawn_ui_thread(
+        MultiUiHandler::new(ui, ProgressUiHandler::new(progress.clone())),
+        ui_subscribers.clone(),
+    );
+
+    // Upload thread, retrying pending uploads from previous runs
+    let upload_tx = (!args.dry_run && !gcfg.targets.is_empty())
+        .then(|| upload::spawn_uploader(&output_path, gcfg.targets.clone(), ui.clone()));
 
     // Backup thread
     // Ensures that multiple backups cannot run simultaneously
     let backup_join_handle = {
+        let mut ui = ui.clone();
         let save_dirs = save_dirs.clone();
         let save_files = gcfg.save_files.clone();
+        let registry_keys = registry_keys.clone();
+        let delta_bases = gcfg.delta.as_ref().map(|config| DeltaBases::load(&output_path, config));
+        let partial_backups = gcfg.partial_backups;
+        let server = server.clone();
+        let own_paths = own_paths.clone();
+        let copy = copy_options(&gcfg);
+        let deleted_files = gcfg.deleted_files;
+        let atomic_restore = gcfg.atomic_restore;
+        let watcher = watcher.clone();
 
         let staging_path = staging_path.to_owned();
         let backup_path = backup_path.to_owned();
 
         let grace_time = Duration::from_secs(gcfg.grace_time);
+        let max_backup_duration = gcfg.max_backup_duration.map(Duration::from_secs);
+        let keep_last = gcfg.auto_backup.keep_last;
+        let collapse_sessions_after_days = gcfg.auto_backup.collapse_sessions_after_days;
+        let description_template = gcfg.auto_backup.description_template.clone();
+        let args = args.clone();
 
         let backup_or_restore_ongoing = backup_or_restore_ongoing.clone();
         let last_backup_at = last_backup_at.clone();
         let last_change_at = last_change_at.clone();
+        let change_grace_time = change_grace_time.clone();
+        let current_saves = current_saves.clone();
         let latest_backup_path = latest_backup_path.clone();
+        let session = session.clone();
+        let interval = interval.clone();
+        let queued = queued.clone();
+        let history = history.clone();
+        let autobackup = autobackup.clone();
+        let shutdown = shutdown.clone();
 
         std::thread::spawn(move || {
+            // Syncs report progress through a sender of their own, so that they can run on other threads
+            let sync_events = ui.sync_events();
+
+            // Manifest of the previous backup, used to avoid re-hashing unchanged files
+            let mut previous_manifest: Option<Manifest> = None;
+            let mut previous_archive: Option<String> = None;
+
+            let mut delta_bases = delta_bases;
+
+            // Contents of the staging directory of each save dir as left by the previous backup,
+            // used to avoid rescanning them
+            let mut staging_index: HashMap<String, DirIndex> = kept_staging_index.unwrap_or_default();
+
+            // Files of the latest backup, which dry runs compare against
+            let mut previous_plan: Vec<PlannedFile> = Vec::new();
+
+            if args.dry_run {
+                if let Ok(Some(manifest)) = list_game_backups(&args).map(|backups| {
+                    backups
+                        .first()
+                        .and_then(|b| Manifest::load_for_archive(&b.path).ok().flatten())
+                }) {
+                    previous_plan = PlannedFile::from_manifest(&manifest);
+                }
+            }
+
+            // Sessions may have aged past the collapse threshold since the last run
+            if let (Some(days), false) = (collapse_sessions_after_days, args.dry_run) {
+                if let Err(err) =
+                    retention::collapse_old_sessions(&args, Duration::from_secs(days * 24 * 60 * 60), &mut ui)
+                {
+                    error!("Error collapsing old sessions: {err}");
+                }
+            }
+
+            // Backups may have aged past the cold storage threshold since the last run
+            if let (Some(cold_after), false) = (args.cold_after, args.dry_run) {
+                if let Err(err) = tiering::move_old_backups(&args, cold_after) {
+                    error!("Error moving old backups to cold storage: {err}");
+                }
+            }
+
             for backup_request in &backup_rx {
                 // Pause autobackup while executing a request
                 backup_or_restore_ongoing.store(true, Ordering::Release);
 
-                let res: Result<(), anyhow::Error> = (|| {
-                    match backup_request {
-                        BackupRequest::CreateBackup { archive_name } => {
+                // Once started on, identical requests may be queued again
+                queued.lock().unwrap().retain(|r| *r != backup_request);
+
+                // Progress of retries is reported separately from that of the attempts
+                let mut retry_ui = ui.clone();
+
+                let mut handle_request = || -> Result<(), anyhow::Error> {
+                    match backup_request.clone() {
+                        BackupRequest::CreateBackup { archive_name, kind } => {
                             // Wait for grace time to elapse.
                             // The purpose of this is to avoid creating backup while files are still
                             // in the middle of being updated. How long grace time is needed
@@ -212,11 +1022,15 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                             // Any change detected will reset grace time.
                             // Only when grace time has elapsed with no new changes detected in the meantime
                             // should the backup proceed.
+                            // Save dirs and files may have grace times of their own, in which case the longest
+                            // of those changed since the latest backup applies.
                             loop {
                                 let grace_time_left = 'gtl: {
-                                    let now = Instant::now();
+                                    let now = args.clock.now();
 
                                     let mut last_change_at = last_change_at.lock().unwrap();
+                                    let mut change_grace_time = change_grace_time.lock().unwrap();
+                                    let grace_time = change_grace_time.unwrap_or(grace_time);
 
                                     if let Some(time_since_last_change) = last_change_at.map(|lca| now - lca) {
                                         if time_since_last_change < grace_time {
@@ -225,6 +1039,7 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                                     }
 
                                     *last_change_at = None;
+                                    *change_grace_time = None;
 
                                     Duration::ZERO
                                 };
@@ -233,12 +1048,12 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                                     break;
                                 }
 
-                                std::thread::sleep(grace_time_left);
+                                args.clock.sleep(grace_time_left);
                             }
 
                             ui.begin_backup(&archive_name);
 
-                            let now = Instant::now();
+                            let now = args.clock.now();
 
                             // Update last_backup_at
                             {
@@ -246,9 +1061,62 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                                 *last_backup_at = Some(now);
                             }
 
-                            let archive_path = backup_path.join(&archive_name);
+                            if args.dry_run {
+                                let plan = BackupPlan::scan(&save_dirs, &save_files, &previous_plan)?;
+                                info!("[Dry run] Would create {kind:?} backup {archive_name} with {plan}");
+                                previous_plan = plan.files;
+
+                                ui.end_backup(true);
+                                return Ok(());
+                            }
+
+                            let backup_started_at = args.clock.now();
+
+                            // The archived world should be the one in memory, not whatever was last written to disk
+                            if let Some(server) = server.as_ref() {
+                                if let Err(err) = server.save_world(args.clock.as_ref()) {
+                                    warn!("{err}, backing up the world as last written");
+                                }
+                            }
 
-                            ui.begin_staging(save_dirs.len() + save_files.len());
+                            if let Some(kept_staging) = kept_staging.as_ref() {
+                                kept_staging.invalidate()?;
+                            }
+
+                            // Nothing is staged before the first backup, which copies every save file
+                            let initial_snapshot = sync::is_empty_dir(&staging_path)
+                                .then(|| measure_saves(&save_dirs, &save_files, &own_paths, copy));
+
+                            let stage_bytes = match initial_snapshot {
+                                Some((_, bytes)) => bytes,
+                                None => pending_stage_bytes(
+                                    &save_dirs,
+                                    &save_files,
+                                    &staging_path,
+                                    &staging_index,
+                                    &own_paths,
+                                    copy,
+                                ),
+                            };
+
+                            ui.begin_staging(
+                                save_dirs.len() + save_files.len() + usize::from(!registry_keys.is_empty()),
+                                stage_bytes,
+                            );
+
+                            if let Some((files, bytes)) = initial_snapshot {
+                                info!(
+                                    "Initial snapshot: {} files / {}, this may take a while",
+                                    format_count(files),
+                                    format_bytes(bytes)
+                                );
+                                ui.begin_initial_snapshot(files, bytes);
+                            }
+
+                            // Problems that leave something out of the backup without failing it
+                            let mut warnings = Vec::new();
+                            // Save dirs and files left out of the backup as they are missing
+                            let mut skipped_paths = Vec::new();
 
                             for gsp in save_dirs.iter() {
                                 let name = &gsp.name;
@@ -256,26 +1124,46 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
 
                                 ui.begin_stage(name);
 
+                                let dir_index = staging_index.remove(name);
+
                                 'stage: {
                                     let staging_gsp_path = staging_path.join(name);
 
+                                    if path.exists() {
+                                        // Sync to staging directory
+                                        match sync::sync_dir_indexed(
+                                            path,
+                                            &staging_gsp_path,
+                                            gsp.sync_options(&own_paths, false, copy),
+                                            dir_index,
+                                            &sync_events,
+                                        ) {
+                                            Ok((stats, dir_index)) => {
+                                                if let Some(dir_index) = dir_index {
+                                                    staging_index.insert(name.clone(), dir_index);
+                                                }
+
+                                                if stats.placeholders_skipped > 0 {
+                                                    warnings.push(format!(
+                                                        "Cloud placeholders not kept on this device were skipped [{name}]: {} files",
+                                                        stats.placeholders_skipped
+                                                    ));
+                                                }
+                                                break 'stage;
+                                            }
+                                            // Save dirs removed while being staged are skipped like missing ones
+                                            Err(_) if !path.exists() => {}
+                                            Err(err) => return Err(err),
+                                        }
+                                    }
+
                                     // If source path is missing, remove the existing staging directory for this save path
-                                    if !path.exists() {
-                                        warn!("Save dir does not exist [{name}]: {}", path.display());
+                                    warnings.push(format!("Save dir does not exist [{name}]: {}", path.display()));
+                                    skipped_paths.push(name.clone());
 
+                                    if staging_gsp_path.exists() {
                                         fs::remove_dir_all(&staging_gsp_path)?;
-                                        break 'stage;
                                     }
-
-                                    // Sync to staging directory
-                                    sync::sync_dir(
-                                        path,
-                                        &staging_gsp_path,
-                                        gsp.include_globset.as_ref(),
-                                        gsp.ignore_globset.as_ref(),
-                                        false,
-                                        &mut ui,
-                                    )?;
                                 }
 
                                 ui.end_stage();
@@ -299,47 +1187,260 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
 
                                     let staging_file_path = staging_dir_path.join(rel_path);
 
-                                    // If source path is missing, remove the existing staging directory for this save path
-                                    if !path.exists() {
-                                        warn!("Save file does not exist [{}]: {}", rel_path.display(), path.display());
+                                    if path.exists() {
+                                        // Sync to staging directory
+                                        fs::create_dir_all(staging_dir_path)?;
+
+                                        match sync::sync_file(path, staging_dir_path, copy, &sync_events) {
+                                            Ok(stats) => {
+                                                if stats.placeholders_skipped > 0 {
+                                                    warnings.push(format!(
+                                                        "Cloud placeholder not kept on this device was skipped [{}]: {}",
+                                                        rel_path.display(),
+                                                        path.display()
+                                                    ));
+                                                }
+                                                break 'stage;
+                                            }
+                                            // Save files removed while being staged are skipped like missing ones
+                                            Err(_) if !path.exists() => {}
+                                            Err(err) => return Err(err),
+                                        }
+                                    }
+
+                                    // If source path is missing, remove the existing staging copy of this save file
+                                    warnings.push(format!(
+                                        "Save file does not exist [{}]: {}",
+                                        rel_path.display(),
+                                        path.display()
+                                    ));
+                                    skipped_paths.push(rel_path.to_string_lossy().into_owned());
 
+                                    if staging_file_path.exists() {
                                         fs::remove_file(&staging_file_path)?;
-                                        break 'stage;
                                     }
-
-                                    // Sync to staging directory
-                                    fs::create_dir_all(staging_dir_path)?;
-                                    sync::sync_file(path, staging_dir_path, &mut ui)?;
                                 }
 
                                 ui.end_stage();
                             }
 
+                            if !registry_keys.is_empty() {
+                                ui.begin_stage("Registry");
+                                registry::export(&registry_keys, &staging_path.join(registry::STAGING_DIR_NAME))?;
+                                ui.end_stage();
+                            }
+
+                            let mut manifest = Manifest::build(
+                                &args.name,
+                                kind,
+                                &staging_path,
+                                previous_manifest.as_ref(),
+                                &sync_events,
+                            )?;
+                            manifest.session = session.lock().unwrap().id.clone();
+                            manifest.warnings = warnings.clone();
+                            manifest.skipped_paths = skipped_paths.clone();
+
+                            if let Some(inspection) = inspection.as_ref() {
+                                manifest.metadata = inspection.metadata(&staging_path, &manifest);
+                            }
+
+                            // Auto-backups are named after the saves they contain, once these are known
+                            let archive_name = match (kind, description_template.as_ref()) {
+                                (BackupKind::Auto, Some(template)) => {
+                                    describe_from_metadata(template, &manifest.metadata)
+                                        .and_then(|details| {
+                                            with_description(&archive_name, &auto_backup_description(&details))
+                                        })
+                                        .inspect(|name| info!("Naming auto-backup after save metadata: {name}"))
+                                        .unwrap_or(archive_name)
+                                }
+                                _ => archive_name,
+                            };
+                            let archive_path = backup_path.join(&archive_name);
+
                             ui.end_staging();
 
                             ui.begin_compress();
 
-                            // Create backup archive
-                            create_archive(&staging_path, &archive_path)?;
+                            // Unchanged save dirs are left out, and put back in staging once archived
+                            let held_dirs =
+                                match (partial_backups, previous_archive.as_ref(), previous_manifest.as_ref()) {
+                                    (true, Some(previous_archive), Some(previous)) => {
+                                        let names: Vec<_> = save_dirs.iter().map(|gsp| gsp.name.clone()).collect();
+
+                                        Some(partial::hold_unchanged(
+                                            &args,
+                                            &staging_path,
+                                            &names,
+                                            &mut manifest,
+                                            previous_archive,
+                                            previous,
+                                        )?)
+                                    }
+                                    _ => None,
+                                };
+
+                            // Large files are archived as deltas, and put back in staging once archived
+                            let held_files = match delta_bases
+                                .as_ref()
+                                .map(|bases| bases.encode(&args, &staging_path, &mut manifest))
+                                .transpose()
+                            {
+                                Ok(held_files) => held_files,
+                                Err(err) => {
+                                    if let Some(held_dirs) = held_dirs {
+                                        held_dirs.release()?;
+                                    }
+
+                                    return Err(err);
+                                }
+                            };
+
+                            // Create backup archive.
+                            // Archivers follow real time, so they are given what is left of the maximum duration from now.
+                            let archived = create_archive(
+                                args.archiver.as_ref(),
+                                &staging_path,
+                                &archive_path,
+                                max_backup_duration.map(|max| {
+                                    Instant::now() + max.saturating_sub(args.clock.now() - backup_started_at)
+                                }),
+                                &ui,
+                            );
+
+                            let released = held_files
+                                .map(|held_files| held_files.release())
+                                .transpose()
+                                .and(held_dirs.map(|held_dirs| held_dirs.release()).transpose());
+
+                            if let Err(err) = released {
+                                // Staging no longer matches its index
+                                staging_index.clear();
+                                return Err(err);
+                            }
+
+                            archived?;
+
+                            if let Some(parity_percent) = args.parity_percent {
+                                parity::create(&archive_path, parity_percent)?;
+                            }
 
                             ui.end_compress();
 
+                            manifest.write(&manifest_path(&archive_path))?;
+
+                            if args.use_index {
+                                let backup = BackupInfo {
+                                    name: archive_name.clone(),
+                                    path: archive_path.clone(),
+                                    modified: fs::metadata(&archive_path)?.modified()?,
+                                };
+
+                                BackupIndex::open(&args.output_path())?.add_backup(&backup, Some(&manifest))?;
+                            }
+
+                            if let Some(delta_bases) = delta_bases.as_mut() {
+                                delta_bases.commit(&staging_path, &archive_name, &manifest)?;
+                            }
+
+                            previous_manifest = Some(manifest);
+                            previous_archive = Some(archive_name.clone());
+
+                            if let Some(kept_staging) = kept_staging.as_ref() {
+                                if let Err(err) = kept_staging.save(&staging_index) {
+                                    error!("Error saving staging state: {err}");
+                                }
+                            }
+
+                            // Changes since the save files were copied are not in the backup
+                            current_saves
+                                .lock()
+                                .unwrap()
+                                .record_backup(&archive_name, last_change_at.lock().unwrap().is_some());
+
+                            let backup_duration = args.clock.now() - backup_started_at;
+                            interval.lock().unwrap().record_backup(backup_duration);
+
+                            for warning in warnings.iter() {
+                                ui.backup_warning(warning);
+                            }
+
+                            if !skipped_paths.is_empty() {
+                                ui.paths_skipped(&skipped_paths);
+                            }
+
                             ui.end_backup(true);
 
+                            if let Some((files, bytes)) = initial_snapshot {
+                                info!(
+                                    "Initial snapshot complete: {} files / {} in {}",
+                                    format_count(files),
+                                    format_bytes(bytes),
+                                    format_duration(backup_duration)
+                                );
+                            }
+
+                            let archive_size = fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0);
+                            {
+                                let mut session = session.lock().unwrap();
+                                session.record_backup(kind, archive_size);
+                                session.record_backup_warnings(&archive_name, &warnings);
+                            }
+
+                            if let Some(upload_tx) = upload_tx.as_ref() {
+                                upload_tx.send((archive_path.clone(), kind))?;
+                            }
+
                             // Store path to latest backup archive
-                            let mut latest_backup_path = latest_backup_path.lock().unwrap();
-                            *latest_backup_path = Some(archive_path);
+                            {
+                                let mut latest_backup_path = latest_backup_path.lock().unwrap();
+                                *latest_backup_path = Some(archive_path);
+                            }
+
+                            // Apply retention to auto-backups
+                            if let (BackupKind::Auto, Some(keep_last)) = (kind, keep_last) {
+                                retention::prune_auto_backups(&args, keep_last, &mut ui)?;
+                            }
+
+                            // Move old backups to cold storage
+                            if let Some(cold_after) = args.cold_after {
+                                tiering::move_old_backups(&args, cold_after)?;
+                            }
                         }
-                        BackupRequest::RestoreBackup { archive_name } => {
-                            let archive_path = backup_path.join(&archive_name);
+                        BackupRequest::RestoreBackup {
+                            archive_name,
+                            only,
+                            keep,
+                        } => {
+                            if args.dry_run {
+                                match only {
+                                    Some(only) => {
+                                        info!("[Dry run] Would restore {} from {archive_name}", only.display())
+                                    }
+                                    None => info!("[Dry run] Would restore {archive_name}"),
+                                }
 
-                            if !archive_path.exists() {
-                                error!("Archive does not exist: {}", archive_path.display());
                                 return Ok(());
                             }
 
+                            let Ok(archive_path) = backups::resolve_archive(&args.backup_paths(), &archive_name) else {
+                                error!("Archive does not exist: {archive_name}");
+                                return Ok(());
+                            };
+
                             ui.begin_restore(&archive_name);
 
+                            if let Some(only) = only.as_ref() {
+                                restore::check_subpath(&partial::list_entries(&args, &archive_path)?, only)?;
+                            }
+
+                            staging_index.clear();
+
+                            if let Some(kept_staging) = kept_staging.as_ref() {
+                                kept_staging.invalidate()?;
+                            }
+
                             // Remove staging directory if it exists
                             if staging_path.exists() {
                                 fs::remove_dir_all(&staging_path)?;
@@ -351,82 +1452,159 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                             ui.begin_extract();
 
                             // Unpack archive to be restored into staging directory
-                            unpack_archive(&archive_path, &staging_path)?;
+                            args.archiver.unpack(&archive_path, &staging_path)?;
+                            partial::assemble(&args, &archive_path, &staging_path)?;
+                            delta::reconstruct(&args, &staging_path)?;
+                            restore::keep_live_files(&staging_path, &keep, &save_dirs, &save_files)?;
 
                             ui.end_extract();
 
                             // Restore save paths from staging directory
+                            let mut restore_stats = SyncStats::default();
+
+                            let recycle_batch_path = recycle::batch_path(&args);
+
+                            if let Some(only) = only.as_ref() {
+                                restore_stats += restore::restore_subpath(
+                                    &staging_path,
+                                    only,
+                                    &save_dirs,
+                                    &save_files,
+                                    copy,
+                                    &mut ui,
+                                    &sync_events,
+                                )?;
+                            } else {
+                                // Swapped save directories are new directories, which are not watched yet
+                                let mut rewatch = false;
+
+                                for gsp in save_dirs.iter() {
+                                    let name = &gsp.name;
+                                    let path = &gsp.path;
+
+                                    ui.begin_restore_sp(name);
+
+                                    'restore: {
+                                        let src_path = staging_path.join(name);
+
+                                        if !src_path.exists() {
+                                            warn!(
+                                                "Directory does not exist in backup [{name}]: {}",
+                                                src_path.display()
+                                            );
+                                            break 'restore;
+                                        }
 
-                            for gsp in save_dirs.iter() {
-                                let name = &gsp.name;
-                                let path = &gsp.path;
-
-                                ui.begin_restore_sp(name);
-
-                                'restore: {
-                                    let src_path = staging_path.join(name);
-
-                                    if !src_path.exists() {
-                                        warn!("Directory does not exist in backup [{name}]: {}", src_path.display());
-                                        break 'restore;
+                                        // Files deleted from each save directory are recycled under its name
+                                        let recycle_path = recycle_batch_path.join(name);
+                                        let deletion = match deleted_files {
+                                            DeletedFiles::Remove => Deletion::Remove,
+                                            DeletedFiles::Trash => Deletion::Trash,
+                                            DeletedFiles::Recycle => Deletion::Recycle(&recycle_path),
+                                        };
+
+                                        let options = SyncOptions {
+                                            deletion,
+                                            ..gsp.sync_options(&own_paths, true, copy)
+                                        };
+
+                                        let swapped = if atomic_restore {
+                                            restore::restore_dir_atomically(&src_path, path, options, &sync_events)?
+                                        } else {
+                                            None
+                                        };
+
+                                        // Sync to save directory, unless swapped in whole
+                                        restore_stats += match swapped {
+                                            Some(stats) => {
+                                                rewatch = true;
+                                                stats
+                                            }
+                                            None => sync::sync_dir(&src_path, path, options, &sync_events)?,
+                                        };
                                     }
 
-                                    // Sync to save directory
-                                    sync::sync_dir(
-                                        &src_path,
-                                        path,
-                                        gsp.include_globset.as_ref(),
-                                        gsp.ignore_globset.as_ref(),
-                                        true,
-                                        &mut ui,
-                                    )?;
+                                    ui.end_restore_sp();
                                 }
 
-                                ui.end_restore_sp();
-                            }
+                                for gsf in save_files.iter() {
+                                    let path = &gsf.path;
+                                    let dir_path = path
+                                        .parent()
+                                        .context("Couldn't get parent directory of game save file")?;
+                                    let rel_path = path.strip_prefix(dir_path)?;
+
+                                    ui.begin_restore_sp(&rel_path.to_string_lossy());
+
+                                    'restore: {
+                                        let staging_dir_path = if let Some(staging_subdir) = &gsf.staging_subdirectory {
+                                            &staging_path.join(staging_subdir)
+                                        } else {
+                                            &staging_path
+                                        };
+
+                                        let staging_file_path = staging_dir_path.join(rel_path);
+
+                                        if !staging_file_path.exists() {
+                                            warn!(
+                                                "File does not exist in backup [{}]: {}",
+                                                rel_path.display(),
+                                                staging_file_path.display()
+                                            );
+                                            break 'restore;
+                                        }
 
-                            for gsf in save_files.iter() {
-                                let path = &gsf.path;
-                                let dir_path = path
-                                    .parent()
-                                    .context("Couldn't get parent directory of game save file")?;
-                                let rel_path = path.strip_prefix(dir_path)?;
+                                        // Sync to save directory
+                                        fs::create_dir_all(dir_path)?;
+                                        restore_stats +=
+                                            sync::sync_file(&staging_file_path, dir_path, copy, &sync_events)?;
+                                    }
 
-                                ui.begin_restore_sp(&rel_path.to_string_lossy());
+                                    ui.end_restore_sp();
+                                }
 
-                                'restore: {
-                                    let staging_dir_path = if let Some(staging_subdir) = &gsf.staging_subdirectory {
-                                        &staging_path.join(staging_subdir)
-                                    } else {
-                                        &staging_path
-                                    };
+                                let registry_path = staging_path.join(registry::STAGING_DIR_NAME);
 
-                                    let staging_file_path = staging_dir_path.join(rel_path);
+                                if !registry_keys.is_empty() && registry_path.exists() {
+                                    ui.begin_restore_sp("Registry");
+                                    registry::import(&registry_path)?;
+                                    ui.end_restore_sp();
+                                }
 
-                                    if !staging_file_path.exists() {
-                                        warn!(
-                                            "File does not exist in backup [{}]: {}",
-                                            rel_path.display(),
-                                            staging_file_path.display()
-                                        );
-                                        break 'restore;
+                                if rewatch {
+                                    if let Some(watcher) = watcher.lock().unwrap().as_mut() {
+                                        if let Err(err) = watcher.rewatch() {
+                                            error!("Error watching save paths: {err}");
+                                        }
                                     }
-
-                                    // Sync to save directory
-                                    fs::create_dir_all(dir_path)?;
-                                    sync::sync_file(&staging_file_path, dir_path, &mut ui)?;
                                 }
-
-                                ui.end_restore_sp();
                             }
 
                             ui.end_restore(true);
 
-                            let now = Instant::now();
+                            info!(
+                                "Restore rewrote {} files ({}), deleted {}, left {} unchanged, updated timestamps of {}",
+                                restore_stats.files_copied,
+                                format_bytes(restore_stats.bytes_copied),
+                                restore_stats.files_deleted,
+                                restore_stats.files_unchanged,
+                                restore_stats.files_retimed
+                            );
+
+                            session.lock().unwrap().record_restore();
+
+                            let now = args.clock.now();
+
+                            current_saves.lock().unwrap().record_restore(
+                                &archive_name,
+                                only.is_some() || !keep.is_empty(),
+                                now,
+                            );
 
                             // Clear change tracker, to avoid restore triggering automatic backup
                             let mut last_change_at = last_change_at.lock().unwrap();
                             *last_change_at = None;
+                            *change_grace_time.lock().unwrap() = None;
 
                             // Set last backup timestamp to now, to prevent autobackup immediately after restore
                             let mut last_backup_at = last_backup_at.lock().unwrap();
@@ -435,47 +1613,124 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                     }
 
                     Ok(())
-                })();
+                };
+
+                // Transient errors, such as files briefly locked by another process, are retried with backoff
+                let mut failed_attempts = 0;
+
+                let res = loop {
+                    let Err(err) = handle_request() else {
+                        break Ok(());
+                    };
+
+                    let BackupRequest::CreateBackup { archive_name, kind } = &backup_request else {
+                        // Failed actions end like any other, so that their progress is not shown as ongoing
+                        retry_ui.end_restore(false);
+                        break Err(err);
+                    };
+
+                    let category = ErrorCategory::of(&err);
+                    failed_attempts += 1;
+
+                    // An attempt interrupted by shutdown has already ended
+                    let mut interrupted = false;
+
+                    if category.is_transient() && failed_attempts <= retry::MAX_RETRIES {
+                        let backoff = retry::backoff(failed_attempts);
+                        warn!(
+                            "Backup failed ({category:?}), retrying in {}s: {err:#}",
+                            backoff.as_secs()
+                        );
+                        retry_ui.end_backup(false);
+
+                        let retry_at = args.clock.now() + backoff;
+                        while !shutdown.is_requested() && args.clock.now() < retry_at {
+                            args.clock.wait(retry_at - args.clock.now(), &shutdown);
+                        }
+
+                        if !shutdown.is_requested() {
+                            continue;
+                        }
+
+                        warn!("Shutting down, not retrying backup");
+                        interrupted = true;
+                    }
+
+                    // Changes are left pending, so that auto-backup picks them up again
+                    let will_retry_at_next_interval =
+                        category.is_transient() && autobackup.load(Ordering::Acquire) && !shutdown.is_requested();
+
+                    if will_retry_at_next_interval {
+                        last_change_at.lock().unwrap().get_or_insert(args.clock.now());
+                        warn!("Backup failed, will retry at next interval");
+                    }
+
+                    if !args.dry_run {
+                        let failure = BackupFailure {
+                            archive_name: archive_name.clone(),
+                            kind: *kind,
+                            error: format!("{err:#}"),
+                            attempts: failed_attempts,
+                            will_retry_at_next_interval,
+                        };
+
+                        if let Err(err) = history.append(HistoryEvent::BackupFailed(failure)) {
+                            error!("Error writing backup failure to history: {err}");
+                        }
+                    }
+
+                    if !interrupted {
+                        retry_ui.end_backup(false);
+                    }
+
+                    break Err(err);
+                };
 
                 if let Err(err) = res {
                     error!("{err}");
+                    session.lock().unwrap().record_error(err.to_string());
                 }
 
                 // Resume autobackup after request is completed
                 backup_or_restore_ongoing.store(false, Ordering::Release);
             }
-
-            ui.clear().unwrap();
         })
     };
 
     // Auto-backup thread
     let autobackup_join_handle = {
         let shutdown = shutdown.clone();
+        let clock = args.clock.clone();
+        let check_interval = args.check_interval;
         let autobackup = autobackup.clone();
 
-        let min_interval = Duration::from_secs(gcfg.auto_backup.min_interval);
+        let interval = interval.clone();
+        let snapshot_every_save = gcfg.auto_backup.snapshot_every_save;
+        let server = server.clone();
 
         let backup_or_restore_ongoing = backup_or_restore_ongoing.clone();
         let last_backup_at = last_backup_at.clone();
         let last_change_at = last_change_at.clone();
 
         let backup_tx = backup_tx.clone();
+        let queued = queued.clone();
+        let archive_extension = args.archiver.extension();
 
         let mut last_autobackup_at: Option<Instant> = None;
+        let mut postponement = Postponement::default();
 
         std::thread::spawn(move || loop {
-            if shutdown.load(Ordering::Relaxed) {
+            if shutdown.is_requested() {
                 break;
             }
 
-            std::thread::sleep(Duration::from_secs(1));
+            clock.wait(check_interval, &shutdown);
 
             if !autobackup.load(Ordering::Acquire) || backup_or_restore_ongoing.load(Ordering::Acquire) {
                 continue;
             }
 
-            let now = Instant::now();
+            let now = clock.now();
 
             {
                 let last_backup_at = last_backup_at.lock().unwrap();
@@ -504,39 +1759,109 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
                     }
                 }
 
-                if let Some(last_backup_at) = *last_backup_at {
-                    if now < (last_backup_at + min_interval) {
+                // In snapshot mode, every save gets backed up regardless of interval
+                if let (false, Some(last_backup_at)) = (snapshot_every_save, *last_backup_at) {
+                    if now < (last_backup_at + interval.lock().unwrap().current()) {
                         continue;
                     }
                 }
             }
 
+            if let Some(server) = server.as_ref() {
+                if postponement.should_wait(server, now) {
+                    continue;
+                }
+            }
+
             last_autobackup_at = Some(now);
 
             info!("Creating auto-backup");
 
-            let archive_name = make_backup_filename("Auto");
-            backup_tx.send(BackupRequest::CreateBackup { archive_name }).unwrap();
+            let archive_name = make_backup_filename(AUTO_BACKUP_DESCRIPTION, archive_extension);
+            queue_request(
+                &backup_tx,
+                &queued,
+                BackupRequest::CreateBackup {
+                    archive_name,
+                    kind: BackupKind::Auto,
+                },
+            )
+            .unwrap();
         })
     };
 
+    let watch_state: Arc<Mutex<WatchState>> = Arc::new(Mutex::new(WatchState::default()));
+
     // Watch save directory for changes
-    let (watcher_join_handle, watcher) = {
+    let watcher_join_handle = {
         let last_change_at = last_change_at.clone();
+        let change_grace_time = change_grace_time.clone();
+        let grace_times = GraceTimes::new(&gcfg, &save_dirs);
+        let current_saves = current_saves.clone();
+        let watch_state = watch_state.clone();
+        let session = session.clone();
+        let clock = args.clock.clone();
         let save_files: Vec<_> = gcfg.save_files.iter().map(|gsf| gsf.path.clone()).collect();
+        let trigger_file = gcfg.trigger_file.clone();
+        let change_indicator_paths = gcfg.change_indicator_paths.clone();
+        let poll_interval = args.poll_interval;
+
+        let staged_copies = StagedCopies::new(
+            save_dirs
+                .iter()
+                .map(|gsp| (gsp.path.clone(), staging_path.join(&gsp.name)))
+                .chain(gcfg.save_files.iter().filter_map(|gsf| {
+                    let staging_dir_path = match gsf.staging_subdirectory.as_ref() {
+                        Some(staging_subdir) => staging_path.join(staging_subdir),
+                        None => staging_path.clone(),
+                    };
+
+                    Some((gsf.path.clone(), staging_dir_path.join(gsf.path.file_name()?)))
+                }))
+                .collect(),
+        );
+
+        // Save directories and change indicators are watched recursively, save files on their own
+        let watch_paths: Vec<_> = save_dirs
+            .iter()
+            .map(|gsp| (gsp.path.clone(), RecursiveMode::Recursive))
+            .chain(
+                save_files
+                    .iter()
+                    .map(|path| (path.clone(), RecursiveMode::NonRecursive)),
+            )
+            .chain(
+                change_indicator_paths
+                    .iter()
+                    .map(|path| (path.clone(), RecursiveMode::Recursive)),
+            )
+            .collect();
 
         let (tx, rx) = std::sync::mpsc::channel();
-        let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
 
-        // Watch save directories
-        for gsp in save_dirs.iter() {
-            watcher.watch(&gsp.path, RecursiveMode::Recursive)?;
-        }
+        // If the native backend cannot watch the save paths, for example because the watch limit is reached,
+        // changes can still be detected by polling
+        let save_watcher = match SaveWatcher::new(watch_paths.clone(), tx.clone(), args.poll_interval) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                let reason = format!("Watching save paths failed, falling back to polling: {err}");
+                warn!("{reason}");
+                session.lock().unwrap().record_warning(reason.clone());
+                watch_state.lock().unwrap().fallback = Some(reason);
+
+                SaveWatcher::polling(watch_paths.clone(), tx, args.poll_interval)?
+            }
+        };
 
-        // Watch save files
-        for gsf_path in save_files.iter() {
-            watcher.watch(gsf_path, RecursiveMode::NonRecursive)?;
-        }
+        // Changes to any of them are assumed when the watcher drops events
+        let all_watch_paths: Vec<_> = watch_paths.iter().map(|(path, _)| path.clone()).collect();
+
+        watch_state.lock().unwrap().watches = watch_paths
+            .into_iter()
+            .map(|(path, _)| WatchStatus::new(path, save_watcher.kind()))
+            .collect();
+
+        *watcher.lock().unwrap() = Some(save_watcher);
 
         let save_dirs: Vec<_> = save_dirs
             .into_iter()
@@ -549,67 +1874,237 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
             })
             .collect();
 
-        let join_handle = std::thread::spawn(move || {
-            'watch_event: for result in &rx {
-                match result {
-                    Ok(event) => {
-                        if event.kind.is_access() {
+        let join_handle = std::thread::spawn({
+            let watcher = watcher.clone();
+
+            move || {
+                // Switch to polling after a failure of the native backend, after which it may miss changes
+                let fall_back = |reason: String| {
+                    let mut watcher = watcher.lock().unwrap();
+
+                    let Some(watcher) = watcher.as_mut().filter(|w| !w.is_polling()) else {
+                        return;
+                    };
+
+                    let reason = format!("{reason}, falling back to polling");
+                    warn!("{reason}");
+
+                    if let Err(err) = watcher.fall_back_to_polling() {
+                        error!("Error falling back to polling: {err}");
+                        return;
+                    }
+
+                    session.lock().unwrap().record_warning(reason.clone());
+
+                    let mut watch_state = watch_state.lock().unwrap();
+                    watch_state.fallback = Some(reason);
+
+                    for watch in watch_state.watches.iter_mut() {
+                        watch.backend = watch::backend_name(watcher.kind()).to_owned();
+                    }
+                };
+
+                'watch_event: loop {
+                    let result = match rx.recv_timeout(poll_interval) {
+                        Ok(result) => result,
+                        Err(RecvTimeoutError::Timeout) => {
+                            // Paths missing when polling started are only polled once they are watched again
+                            let mut watcher = watcher.lock().unwrap();
+
+                            let Some(watcher) = watcher.as_mut() else {
+                                continue;
+                            };
+
+                            let reappeared = watcher.reappeared_paths();
+
+                            if reappeared.is_empty() {
+                                continue;
+                            }
+
+                            info!("Missing save paths reappeared, watching them again");
+
+                            if let Err(err) = watcher.rewatch() {
+                                error!("Error watching save paths: {err}");
+                            }
+
+                            // Their contents may have changed while they were missing
+                            watch::record_event(
+                                &mut watch_state.lock().unwrap().watches,
+                                &reappeared,
+                                WatchEventKind::Change,
+                            );
+                            *last_change_at.lock().unwrap() = Some(clock.now());
+                            record_grace_time(&change_grace_time, grace_times.of_change(&reappeared));
+                            current_saves.lock().unwrap().record_change(clock.now());
+
                             continue;
                         }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
 
-                        'ignore: {
-                            for path in event.paths.iter() {
-                                if save_files.contains(path) {
-                                    break 'ignore;
-                                }
+                    match result {
+                        Ok(event) => {
+                            if event.kind.is_access() {
+                                continue;
                             }
 
-                            if save_dirs.is_empty() {
-                                break 'ignore;
+                            // Such as cloud placeholders being downloaded, which leaves their contents as backed up
+                            if event.kind.is_modify()
+                                && !event.paths.is_empty()
+                                && event.paths.iter().all(|path| staged_copies.is_unchanged(path))
+                            {
+                                watch::record_event(
+                                    &mut watch_state.lock().unwrap().watches,
+                                    &event.paths,
+                                    WatchEventKind::Ignored,
+                                );
+                                continue;
                             }
 
-                            for (save_dir_path, include_globset, ignore_globset) in save_dirs.iter() {
-                                for path in event.paths.iter() {
-                                    let Ok(rel_path) = path.strip_prefix(save_dir_path) else {
-                                        continue;
-                                    };
+                            // Events dropped under heavy writes may have been changes, so save files are assumed changed
+                            if event.need_rescan() {
+                                watch::record_event(
+                                    &mut watch_state.lock().unwrap().watches,
+                                    &event.paths,
+                                    WatchEventKind::Dropped,
+                                );
+                                warn!("Watcher dropped events, assuming save files changed");
 
-                                    if let Some(include_globset) = include_globset {
-                                        if !include_globset.is_match(rel_path) {
-                                            continue;
+                                *last_change_at.lock().unwrap() = Some(clock.now());
+                                record_grace_time(&change_grace_time, grace_times.of_change(&all_watch_paths));
+                                current_saves.lock().unwrap().record_change(clock.now());
+
+                                continue;
+                            }
+
+                            // A removed save directory, such as one on an unmounted drive, is no longer watched natively
+                            if event.kind.is_remove() {
+                                let removed = event
+                                    .paths
+                                    .iter()
+                                    .find(|path| watcher.lock().unwrap().as_ref().is_some_and(|w| w.is_watched(path)));
+
+                                match removed {
+                                    // Replaced, such as by a restore swapping in a new save directory
+                                    Some(path) if path.exists() => {
+                                        info!("Watched path {} was replaced, watching it again", path.display());
+
+                                        if let Some(watcher) = watcher.lock().unwrap().as_mut() {
+                                            if let Err(err) = watcher.rewatch() {
+                                                error!("Error watching save paths: {err}");
+                                            }
                                         }
                                     }
+                                    Some(path) => fall_back(format!("Watched path {} was removed", path.display())),
+                                    None => {}
+                                }
+                            }
 
-                                    if let Some(ignore_globset) = ignore_globset {
-                                        if ignore_globset.is_match(rel_path) {
-                                            continue;
-                                        }
+                            'ignore: {
+                                // Backups requested through the trigger file are not changes to saves
+                                let paths = event.paths.iter().filter(|path| trigger_file.as_ref() != Some(*path));
+
+                                if !event.paths.is_empty() && paths.clone().next().is_none() {
+                                    watch::record_event(
+                                        &mut watch_state.lock().unwrap().watches,
+                                        &event.paths,
+                                        WatchEventKind::Ignored,
+                                    );
+                                    continue 'watch_event;
+                                }
+
+                                for path in paths.clone() {
+                                    if save_files.contains(path) {
+                                        break 'ignore;
                                     }
 
+                                    if change_indicator_paths
+                                        .iter()
+                                        .any(|indicator| path.starts_with(indicator))
+                                    {
+                                        break 'ignore;
+                                    }
+                                }
+
+                                if save_dirs.is_empty() {
                                     break 'ignore;
                                 }
+
+                                for (save_dir_path, include_globset, ignore_globset) in save_dirs.iter() {
+                                    for path in paths.clone() {
+                                        let Ok(rel_path) = path.strip_prefix(save_dir_path) else {
+                                            continue;
+                                        };
+
+                                        if let Some(include_globset) = include_globset {
+                                            if !include_globset.is_match(rel_path) {
+                                                continue;
+                                            }
+                                        }
+
+                                        if let Some(ignore_globset) = ignore_globset {
+                                            if ignore_globset.is_match(rel_path) {
+                                                continue;
+                                            }
+                                        }
+
+                                        break 'ignore;
+                                    }
+                                }
+
+                                watch::record_event(
+                                    &mut watch_state.lock().unwrap().watches,
+                                    &event.paths,
+                                    WatchEventKind::Ignored,
+                                );
+                                continue 'watch_event;
                             }
 
-                            continue 'watch_event;
-                        }
+                            watch::record_event(
+                                &mut watch_state.lock().unwrap().watches,
+                                &event.paths,
+                                WatchEventKind::Change,
+                            );
 
-                        let mut last_change_at = last_change_at.lock().unwrap();
-                        *last_change_at = Some(Instant::now())
+                            *last_change_at.lock().unwrap() = Some(clock.now());
+                            record_grace_time(&change_grace_time, grace_times.of_change(&event.paths));
+                            current_saves.lock().unwrap().record_change(clock.now());
+                        }
+                        Err(error) => {
+                            watch::record_event(
+                                &mut watch_state.lock().unwrap().watches,
+                                &error.paths,
+                                WatchEventKind::Error,
+                            );
+                            error!("Error {error:?}");
+
+                            fall_back(format!("Watcher error: {error}"));
+                        }
                     }
-                    Err(error) => error!("Error {error:?}"),
                 }
             }
         });
 
-        (join_handle, watcher)
+        join_handle
     };
 
     let backup_tx = Arc::new(backup_tx);
     let weak_backup_tx = Arc::downgrade(&backup_tx);
 
+    #[cfg(test)]
+    let weak_watcher = Arc::downgrade(&watcher);
+
     let engine_join_handle = {
         let shutdown = shutdown.clone();
+        let clock = args.clock.clone();
+        let archive_extension = args.archiver.extension();
         let state = state.clone();
+        let session = session.clone();
+        let backup_or_restore_ongoing = backup_or_restore_ongoing.clone();
+        let queued = queued.clone();
+        // Dry runs leave the trigger file in place
+        let trigger_file = gcfg.trigger_file.clone().filter(|_| !args.dry_run);
+        let copy_naming = CopyNaming::new(&gcfg, &args.name);
 
         std::thread::spawn(move || {
             let _pid_lock = pid_lock;
@@ -617,8 +2112,30 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
             // Set engine state to Running
             state.store(EngineState::Running as u8, Ordering::Release);
 
-            while !shutdown.load(Ordering::Relaxed) {
-                std::thread::sleep(SLEEP_DURATION);
+            while !shutdown.is_requested() {
+                clock.wait(args.check_interval, &shutdown);
+
+                if let Some(trigger_file) = trigger_file.as_ref() {
+                    match take_trigger(trigger_file) {
+                        Ok(Some(description)) => {
+                            info!("Backup requested through trigger file: {description}");
+
+                            let archive_name = make_backup_filename(&description, archive_extension);
+
+                            queue_request(
+                                &backup_tx,
+                                &queued,
+                                BackupRequest::CreateBackup {
+                                    archive_name,
+                                    kind: BackupKind::Manual,
+                                },
+                            )
+                            .ok();
+                        }
+                        Ok(None) => {}
+                        Err(err) => error!("Error reading trigger file {}: {err}", trigger_file.display()),
+                    }
+                }
             }
 
             info!("Shutting down...");
@@ -647,12 +2164,21 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
 
                 info!("Creating exit backup...");
 
-                let archive_name = make_backup_filename("Exit");
-
-                backup_tx.send(BackupRequest::CreateBackup { archive_name }).unwrap();
+                let archive_name = make_backup_filename(EXIT_BACKUP_DESCRIPTION, archive_extension);
+
+                queue_request(
+                    &backup_tx,
+                    &queued,
+                    BackupRequest::CreateBackup {
+                        archive_name,
+                        kind: BackupKind::Exit,
+                    },
+                )
+                .unwrap();
             }
 
-            drop(watcher);
+            // Stop watching, which ends the watcher thread
+            watcher.lock().unwrap().take();
             drop(backup_tx);
 
             // Wait for threads to complete
@@ -660,26 +2186,55 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
             autobackup_join_handle.join().unwrap();
             backup_join_handle.join().unwrap();
 
-            // If a copy_latest_to_path is set, and a backup was created this session,
-            // copy the latest backup to the specified path.
-            'copy_latest: {
-                if let Some(copy_latest_to_path) = gcfg.copy_latest_to_path {
-                    let latest_backup_path = latest_backup_path.lock().unwrap();
-                    if let Some(latest_backup_path) = latest_backup_path.as_ref() {
-                        let Some(filename) = latest_backup_path.file_name() else {
-                            break 'copy_latest;
-                        };
+            // If a backup was created this session, copy the latest backup to each copy-latest path.
+            // A failure to copy to one path does not prevent copying to the others.
+            if let Some(latest_backup_path) = latest_backup_path.lock().unwrap().as_ref() {
+                for copy_latest_to_path in gcfg.copy_latest_to_paths.iter() {
+                    match copies::copy_latest(latest_backup_path, copy_latest_to_path, &copy_naming) {
+                        Ok(copy_path) => {
+                            info!("Copied latest backup to {}", copy_path.display());
 
-                        fs::copy(latest_backup_path, copy_latest_to_path.join(filename)).unwrap();
+                            if let Some(keep) = gcfg.copy_latest_keep {
+                                let dir = copy_naming.dir(copy_latest_to_path);
+
+                                if let Err(err) = retention::prune_copies(&dir, &copy_naming, keep, &mut ui) {
+                                    error!("Error pruning copies in {}: {err}", dir.display());
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            let err = format!(
+                                "Error copying latest backup to {}: {err}",
+                                copy_latest_to_path.display()
+                            );
+                            error!("{err}");
+                            session.lock().unwrap().record_error(err);
+                        }
                     }
                 }
             }
 
-            // Try to delete staging directory
-            if staging_path.exists() {
+            // Try to delete staging directory, unless it is kept for the next run
+            if !gcfg.keep_staging && staging_path.exists() {
                 fs::remove_dir_all(&staging_path).ok();
             }
 
+            // Record session in history
+            {
+                let mut session = session.lock().unwrap();
+                session.duration = clock.now() - started_at;
+
+                if args.dry_run {
+                    // Dry runs leave no trace in history
+                } else if let Err(err) = history.append(HistoryEvent::Session(session.clone())) {
+                    error!("Error writing session to history: {err}");
+                }
+            }
+
+            // Stop the UI thread, even if the uploader is still running
+            ui.clear().ok();
+            ui_join_handle.join().unwrap();
+
             // Set engine state to ShutDown
             state.store(EngineState::ShutDown as u8, Ordering::Release);
         })
@@ -690,40 +2245,97 @@ pub fn run(args: EngineArgs, shutdown: Arc<AtomicBool>, mut ui: impl StoolUiHand
         state,
         autobackup,
         backup_tx: weak_backup_tx,
+        queued,
+        busy: backup_or_restore_ongoing,
+        session,
+        pending,
+        watch_state,
+        progress,
+        ui_subscribers,
     };
 
+    // Dry runs leave no trace in the data directory, so they cannot be controlled
+    if !args.dry_run {
+        if let Err(err) = control::spawn_control_server(&output_path, control.clone()) {
+            error!("Error starting control server: {err}");
+        }
+
+        if args.overlay_status {
+            overlay::spawn_overlay_writer(&args, control.clone());
+        }
+    }
+
     Ok(Engine {
         args,
         control,
         join_handle: engine_join_handle,
+        #[cfg(test)]
+        watcher: weak_watcher,
     })
 }
 
-pub fn make_backup_filename(description: &str) -> String {
-    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+/// Record the grace time of a change, keeping the longest of those since the latest backup
+fn record_grace_time(change_grace_time: &Mutex<Option<Duration>>, grace_time: Duration) {
+    let mut change_grace_time = change_grace_time.lock().unwrap();
+    *change_grace_time = Some(change_grace_time.map_or(grace_time, |gt| gt.max(grace_time)));
+}
+
+/// Fill in a description template with save metadata, if every field it refers to has a value
+fn describe_from_metadata(template: &str, metadata: &SaveMetadata) -> Option<String> {
+    let mut description = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        let value = metadata.get(&rest[start + 1..end])?;
+
+        description.push_str(&rest[..start]);
+        description.push_str(value);
+        rest = &rest[end + 1..];
+    }
+
+    description.push_str(rest);
 
-    format!("{} {description}.7z", now.format(ARCHIVE_DATE_FORMAT).unwrap())
+    let description = sanitize_description(&description);
+    let description = description.trim();
+
+    (!description.is_empty()).then(|| description.to_owned())
 }
 
-fn create_archive(src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
-    std::process::Command::new("7z")
-        .current_dir(src)
-        .args(["a", "-mx9"])
-        .arg(archive_path)
-        .arg(".")
-        .stdout(Stdio::null())
-        .status()?;
+/// Read and delete the trigger file, if it exists, returning the backup description it requests.
+/// The description is taken from the first line, without characters that cannot be used in filenames.
+/// Recently modified trigger files are left alone, as they may still be being written.
+fn take_trigger(path: &Path) -> Result<Option<String>, anyhow::Error> {
+    let modified = match fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => modified,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    if modified.elapsed().is_ok_and(|age| age < TRIGGER_SETTLE_TIME) {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(path)?;
 
-    Ok(())
+    fs::remove_file(path)?;
+
+    let description: String = sanitize_description(contents.lines().next().unwrap_or_default())
+        .chars()
+        .take(MAX_TRIGGER_DESCRIPTION_LEN)
+        .collect();
+
+    let description = description.trim();
+
+    if description.is_empty() {
+        return Ok(Some(TRIGGER_BACKUP_DESCRIPTION.to_owned()));
+    }
+
+    Ok(Some(description.to_owned()))
 }
 
-fn unpack_archive(archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
-    std::process::Command::new("7z")
-        .current_dir(dst)
-        .arg("x")
-        .arg(archive_path)
-        .stdout(Stdio::null())
-        .status()?;
+pub fn make_backup_filename(description: &str, extension: &str) -> String {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
 
-    Ok(())
+    format!("{} {description}.{extension}", now.format(ARCHIVE_DATE_FORMAT).unwrap())
 }
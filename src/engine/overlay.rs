@@ -0,0 +1,131 @@
+//! Status files for streaming overlays, such as OBS text or browser sources,
+//! showing what the engine is doing and when the latest backup was created
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde_derive::Serialize;
+use tracing::error;
+
+use crate::internal::format::format_duration;
+
+use super::{backups, EngineArgs, EngineControl, EngineState};
+
+pub const OVERLAY_TEXT_FILENAME: &str = "overlay.txt";
+pub const OVERLAY_JSON_FILENAME: &str = "overlay.json";
+
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct OverlayStatus {
+    game: String,
+    autobackup: bool,
+    /// Description of the ongoing backup or restore
+    action: Option<String>,
+    /// Completed fraction of the ongoing action, from 0 to 1
+    progress: Option<f32>,
+    last_backup: Option<String>,
+    /// Seconds since the latest backup was created
+    last_backup_ago: Option<u64>,
+}
+
+impl OverlayStatus {
+    fn text(&self) -> String {
+        match (self.action.as_ref(), self.progress) {
+            (Some(action), Some(progress)) => return format!("{action} ({:.0}%)", progress * 100.),
+            (Some(action), None) => return action.clone(),
+            _ => {}
+        }
+
+        match self.last_backup_ago {
+            Some(ago) => format!("Last backup {} ago", format_duration(Duration::from_secs(ago))),
+            None => "No backups yet".to_owned(),
+        }
+    }
+}
+
+/// Keep the overlay files in the data directory of the game up to date, until the engine has shut down.
+/// They are removed once it has.
+pub fn spawn_overlay_writer(args: &EngineArgs, control: EngineControl) {
+    let args = args.clone();
+    let output_path = args.output_path();
+
+    std::thread::spawn(move || {
+        let mut latest_backup = find_latest_backup(&args);
+        let mut backup_count = 0;
+        let mut written = None;
+
+        while control.state() != EngineState::ShutDown {
+            let snapshot = control.snapshot();
+
+            // The latest backup only changes when the session creates one
+            let session = &snapshot.session;
+            let session_backup_count = session.auto_backups + session.manual_backups + session.exit_backups;
+
+            if session_backup_count != backup_count {
+                backup_count = session_backup_count;
+                latest_backup = find_latest_backup(&args);
+            }
+
+            let status = OverlayStatus {
+                game: args.name.clone(),
+                autobackup: snapshot.autobackup,
+                action: snapshot.action.as_ref().map(|a| a.description.clone()),
+                progress: snapshot.action.as_ref().map(|a| a.progress),
+                last_backup: latest_backup.as_ref().map(|(name, _)| name.clone()),
+                last_backup_ago: latest_backup
+                    .as_ref()
+                    .map(|(_, modified)| modified.elapsed().unwrap_or_default().as_secs()),
+            };
+
+            if written.as_ref() != Some(&status) {
+                match write_status(&output_path, &status) {
+                    Ok(()) => written = Some(status),
+                    Err(err) => error!("Error writing overlay status: {err}"),
+                }
+            }
+
+            std::thread::sleep(UPDATE_INTERVAL);
+        }
+
+        fs::remove_file(output_path.join(OVERLAY_TEXT_FILENAME)).ok();
+        fs::remove_file(output_path.join(OVERLAY_JSON_FILENAME)).ok();
+    });
+}
+
+fn find_latest_backup(args: &EngineArgs) -> Option<(String, SystemTime)> {
+    let backups = backups::list_game_backups(args).unwrap_or_else(|err| {
+        error!("Error listing backups for overlay status: {err}");
+        Vec::new()
+    });
+
+    backups
+        .into_iter()
+        .max_by_key(|b| b.modified)
+        .map(|b| (b.name, b.modified))
+}
+
+/// Write both overlay files, replacing the previous ones at once so that overlays never read partial files
+fn write_status(output_path: &Path, status: &OverlayStatus) -> Result<(), anyhow::Error> {
+    write_atomically(&output_path.join(OVERLAY_TEXT_FILENAME), status.text().as_bytes())?;
+    write_atomically(
+        &output_path.join(OVERLAY_JSON_FILENAME),
+        &serde_json::to_vec_pretty(status)?,
+    )?;
+
+    Ok(())
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), anyhow::Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
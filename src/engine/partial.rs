@@ -0,0 +1,235 @@
+//! Partial backups, leaving out save dirs unchanged since the previous backup.
+//! The files of those are recorded in the manifest as stored in the earlier backups holding them,
+//! which are needed to restore them and kept as long as later backups refer to them.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::internal::archive::ArchiveEntry;
+
+use super::{
+    backups::{list_game_backups, resolve_archive},
+    delta,
+    manifest::{Manifest, ManifestFile},
+    EngineArgs,
+};
+
+/// Backup that cannot be deleted, as later backups leave out save dirs it holds
+#[derive(Debug, thiserror::Error)]
+#[error("Backup {name} holds save files that {dependent} leaves out")]
+pub struct BaselineInUse {
+    pub name: String,
+    pub dependent: String,
+}
+
+/// Save dirs moved out of the staging directory while the rest of it is archived
+pub struct HeldDirs {
+    staging_path: PathBuf,
+    hold_path: PathBuf,
+    dirs: Vec<String>,
+}
+
+/// Move the staging directories of save dirs whose files are unchanged since the previous backup out of staging,
+/// recording in the manifest which backups hold their files.
+/// Save dirs whose files are held by backups that no longer exist are left in staging.
+pub fn hold_unchanged(
+    args: &EngineArgs,
+    staging_path: &Path,
+    save_dir_names: &[String],
+    manifest: &mut Manifest,
+    previous_archive: &str,
+    previous: &Manifest,
+) -> Result<HeldDirs, anyhow::Error> {
+    let mut held = HeldDirs {
+        staging_path: staging_path.to_owned(),
+        hold_path: hold_path(staging_path),
+        dirs: Vec::new(),
+    };
+
+    let backup_paths = args.backup_paths();
+
+    for name in save_dir_names {
+        let files = files_in(&manifest.files, name);
+        let previous_files = files_in(&previous.files, name);
+
+        let unchanged = !files.is_empty()
+            && files.len() == previous_files.len()
+            && files.iter().zip(previous_files.iter()).all(|(f, p)| is_same(f, p));
+
+        if !unchanged {
+            continue;
+        }
+
+        // Files held by the previous backup stay with the backup it got them from
+        let holders: HashMap<&Path, String> = previous_files
+            .iter()
+            .map(|p| {
+                let holder = p.stored_in.clone().unwrap_or_else(|| previous_archive.to_owned());
+                (p.path.as_path(), holder)
+            })
+            .collect();
+
+        if holders
+            .values()
+            .any(|holder| resolve_archive(&backup_paths, holder).is_err())
+        {
+            continue;
+        }
+
+        let held_path = held.hold_path.join(name);
+
+        let res = fs::create_dir_all(held_path.parent().context("Held dir has no parent")?)
+            .and_then(|_| fs::rename(staging_path.join(name), &held_path));
+
+        if let Err(err) = res {
+            held.release()?;
+            return Err(err.into());
+        }
+
+        held.dirs.push(name.clone());
+
+        for file in manifest.files.iter_mut().filter(|f| f.path.starts_with(name)) {
+            file.stored_in = holders.get(file.path.as_path()).cloned();
+        }
+    }
+
+    Ok(held)
+}
+
+impl HeldDirs {
+    /// Put the held save dirs back in staging
+    pub fn release(self) -> Result<(), anyhow::Error> {
+        for name in self.dirs.iter() {
+            fs::rename(self.hold_path.join(name), self.staging_path.join(name))?;
+        }
+
+        if self.hold_path.exists() {
+            fs::remove_dir_all(&self.hold_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Add the files an unpacked backup leaves out, unpacking the backups holding them next to it
+pub fn assemble(args: &EngineArgs, archive_path: &Path, dir: &Path) -> Result<(), anyhow::Error> {
+    let Some(manifest) = Manifest::load_for_archive(archive_path)? else {
+        return Ok(());
+    };
+
+    let mut files_by_holder: BTreeMap<&str, Vec<&Path>> = BTreeMap::new();
+
+    for file in manifest.files.iter() {
+        if let Some(holder) = file.stored_in.as_deref() {
+            files_by_holder.entry(holder).or_default().push(&file.path);
+        }
+    }
+
+    for (holder, files) in files_by_holder {
+        let holder_path = resolve_archive(&args.backup_paths(), holder)
+            .with_context(|| format!("Backup holding save files left out of this one is missing: {holder}"))?;
+
+        let holder_dir = hold_path(dir);
+        if holder_dir.exists() {
+            fs::remove_dir_all(&holder_dir)?;
+        }
+
+        fs::create_dir_all(&holder_dir)?;
+        args.archiver.unpack(&holder_path, &holder_dir)?;
+        delta::reconstruct(args, &holder_dir)?;
+
+        for path in files {
+            let file_path = dir.join(path);
+            fs::create_dir_all(file_path.parent().context("Save file has no parent")?)?;
+            fs::rename(holder_dir.join(path), &file_path)
+                .with_context(|| format!("{} is missing from {holder}", path.display()))?;
+        }
+
+        fs::remove_dir_all(&holder_dir)?;
+    }
+
+    Ok(())
+}
+
+/// List the entries of a backup archive as restoring it leaves them,
+/// including the files it leaves out and with deltas listed as the files they reconstruct
+pub fn list_entries(args: &EngineArgs, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+    let mut entries = args.archiver.list(archive_path)?;
+
+    if let Some(manifest) = Manifest::load_for_archive(archive_path)? {
+        let deltas: HashSet<PathBuf> = manifest
+            .files
+            .iter()
+            .filter(|f| f.delta_base.is_some())
+            .map(|f| delta::with_delta_suffix(&f.path))
+            .collect();
+        entries.retain(|e| !deltas.contains(&e.path));
+
+        entries.extend(
+            manifest
+                .files
+                .into_iter()
+                .filter(|f| f.stored_in.is_some() || f.delta_base.is_some())
+                .map(|f| ArchiveEntry {
+                    path: f.path,
+                    size: f.size,
+                    is_dir: false,
+                    crc32: Some(f.crc32),
+                }),
+        );
+    }
+
+    Ok(entries)
+}
+
+/// Refuse to delete a backup that later backups leave save dirs out of
+pub fn check_deletable(args: &EngineArgs, name: &str) -> Result<(), anyhow::Error> {
+    let backups = list_game_backups(args)?;
+
+    let Some(modified) = backups.iter().find(|b| b.name == name).map(|b| b.modified) else {
+        return Ok(());
+    };
+
+    // Only backups created later can leave out files it holds
+    for backup in backups.into_iter().filter(|b| b.modified > modified) {
+        let Ok(Some(manifest)) = Manifest::load_for_archive(&backup.path) else {
+            continue;
+        };
+
+        if manifest.files.iter().any(|f| f.stored_in.as_deref() == Some(name)) {
+            return Err(BaselineInUse {
+                name: name.to_owned(),
+                dependent: backup.name,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Files of a save dir, in the order they are listed in the manifest
+fn files_in<'a>(files: &'a [ManifestFile], name: &str) -> Vec<&'a ManifestFile> {
+    files.iter().filter(|f| f.path.starts_with(name)).collect()
+}
+
+fn is_same(file: &ManifestFile, previous: &ManifestFile) -> bool {
+    file.path == previous.path
+        && file.size == previous.size
+        && file.crc32 == previous.crc32
+        && file.mtime == previous.mtime
+        && file.mtime_nanos == previous.mtime_nanos
+}
+
+/// Directory next to another one, on the same volume, for save dirs kept out of it for a while
+fn hold_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".partial-hold");
+
+    path.with_file_name(name)
+}
@@ -0,0 +1,639 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::internal::{
+    format::{format_bytes, format_count},
+    sync::SyncUiHandler,
+};
+
+use super::ui::StoolUiHandler;
+
+#[derive(Debug)]
+pub enum ActionKind {
+    CreateBackup { name: String },
+    RestoreBackup { name: String },
+    PruneBackups,
+}
+
+/// Phase of the current action
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Staging,
+    Compressing,
+    Extracting,
+    Restoring,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum Progress {
+    Exact(f32),
+    Estimate {
+        start: Instant,
+        end: Instant,
+    },
+
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug)]
+pub struct Action {
+    pub kind: ActionKind,
+    pub started_at: Instant,
+    /// Estimated progress, used when progress cannot be derived from stages
+    pub progress: Progress,
+    pub phase: Option<Phase>,
+    /// Number of save directories and files to stage, while staging
+    pub stage_count: Option<usize>,
+    pub stages_done: usize,
+    /// Bytes expected to be copied to staging, while staging
+    pub stage_bytes: Option<u64>,
+    /// Bytes copied to staging so far
+    pub bytes_staged: u64,
+    /// Bytes copied, checksummed and compressed so far
+    pub bytes_processed: u64,
+    /// Totals of a backup copying every save file, as nothing was staged yet
+    pub initial_snapshot: Option<InitialSnapshot>,
+}
+
+/// Progress of the first backup, which stages every save file
+#[derive(Clone, Copy, Debug)]
+pub struct InitialSnapshot {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Progress of staging or restoring a single save directory or file
+#[derive(Debug)]
+pub struct StageProgress {
+    pub name: String,
+    /// Number of file operations of the stage, once known
+    pub op_count: usize,
+    pub ops_done: usize,
+}
+
+/// Progress of an operation on a single file, such as copying or checksumming
+#[derive(Debug)]
+pub struct FileProgress {
+    pub operation: String,
+    pub name: String,
+    pub size: u64,
+    pub bytes: u64,
+}
+
+/// Progress of the current engine action, from the whole action down to single files
+#[derive(Debug, Default)]
+pub struct ProgressModel {
+    pub action: Option<Action>,
+    pub stage: Option<StageProgress>,
+    pub file: Option<FileProgress>,
+    /// Upload running alongside the current action
+    pub upload: Option<FileProgress>,
+}
+
+/// State holding the progress model updated by a [`ProgressUiHandler`]
+pub trait ProgressState: Send + 'static {
+    fn progress(&mut self) -> &mut ProgressModel;
+
+    /// The latest backup was created, despite these warnings and skipped save paths
+    fn backup_created(&mut self, _warnings: Vec<String>, _skipped_paths: Vec<String>) {}
+}
+
+/// UI handler updating a progress model shared with whatever shows it.
+/// Messages are left to a [`LogUiHandler`](crate::headless::LogUiHandler) running alongside it.
+pub struct ProgressUiHandler<S: ProgressState = ProgressModel> {
+    state: Arc<Mutex<S>>,
+
+    backup_estimate: Option<Duration>,
+    restore_estimate: Option<Duration>,
+
+    /// Warnings of the backup in progress
+    backup_warnings: Vec<String>,
+    skipped_paths: Vec<String>,
+}
+
+impl Action {
+    pub fn new(kind: ActionKind) -> Self {
+        Self {
+            kind,
+            started_at: Instant::now(),
+            progress: Progress::default(),
+            phase: None,
+            stage_count: None,
+            stages_done: 0,
+            stage_bytes: None,
+            bytes_staged: 0,
+            bytes_processed: 0,
+            initial_snapshot: None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let description = self.kind.describe();
+
+        match (self.phase, self.initial_snapshot) {
+            (Some(Phase::Staging), Some(snapshot)) => format!("{description} - {}", snapshot.describe()),
+            (Some(phase), _) => format!("{description} - {}", phase.describe()),
+            (None, _) => description,
+        }
+    }
+}
+
+impl ActionKind {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::CreateBackup { name } => format!("Creating backup: {name}"),
+            Self::RestoreBackup { name } => format!("Restoring backup: {name}"),
+            Self::PruneBackups => "Pruning backups".to_owned(),
+        }
+    }
+
+    pub fn describe_complete(&self) -> String {
+        match self {
+            Self::CreateBackup { name } => format!("Backup created: {name}"),
+            Self::RestoreBackup { name } => format!("Backup restored: {name}"),
+            Self::PruneBackups => "Backups pruned".to_owned(),
+        }
+    }
+
+    pub fn describe_error(&self) -> String {
+        match self {
+            Self::CreateBackup { name } => format!("Create backup failed: {name}"),
+            Self::RestoreBackup { name } => format!("Restore backup failed: {name}"),
+            Self::PruneBackups => "Pruning backups failed".to_owned(),
+        }
+    }
+}
+
+impl Phase {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Staging => "Staging",
+            Self::Compressing => "Compressing",
+            Self::Extracting => "Extracting",
+            Self::Restoring => "Restoring",
+        }
+    }
+}
+
+impl InitialSnapshot {
+    pub fn describe(&self) -> String {
+        format!(
+            "Initial snapshot: {} files / {}",
+            format_count(self.files),
+            format_bytes(self.bytes)
+        )
+    }
+}
+
+impl StageProgress {
+    pub fn describe(&self) -> String {
+        if self.op_count == 0 {
+            return self.name.clone();
+        }
+
+        format!("{} ({}/{})", self.name, self.ops_done, self.op_count)
+    }
+}
+
+impl FileProgress {
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.operation, self.name)
+    }
+
+    pub fn ratio(&self) -> f32 {
+        if self.size == 0 {
+            return 1.;
+        }
+
+        (self.bytes as f32 / self.size as f32).min(1.)
+    }
+}
+
+impl ProgressModel {
+    /// Start tracking a new action, with an estimate of its duration from previous actions of the same kind
+    pub fn begin_action(&mut self, kind: ActionKind, estimate: Option<Duration>) {
+        let mut action = Action::new(kind);
+
+        action.progress = estimate
+            .map(|est| Progress::Estimate {
+                start: action.started_at,
+                end: action.started_at + est,
+            })
+            .unwrap_or_default();
+
+        *self = Self {
+            action: Some(action),
+            upload: self.upload.take(),
+            ..Default::default()
+        };
+    }
+
+    /// Stop tracking the current action, returning it
+    pub fn end_action(&mut self) -> Option<Action> {
+        self.stage = None;
+        self.file = None;
+
+        self.action.take()
+    }
+
+    pub fn set_phase(&mut self, phase: Option<Phase>) {
+        if let Some(action) = self.action.as_mut() {
+            action.phase = phase;
+        }
+    }
+
+    pub fn begin_staging(&mut self, stage_count: usize, bytes: u64) {
+        if let Some(action) = self.action.as_mut() {
+            action.phase = Some(Phase::Staging);
+            action.stage_count = Some(stage_count);
+            action.stages_done = 0;
+            action.stage_bytes = Some(bytes);
+            action.bytes_staged = 0;
+        }
+    }
+
+    pub fn end_staging(&mut self) {
+        if let Some(action) = self.action.as_mut() {
+            action.phase = None;
+            action.stage_count = None;
+            action.stage_bytes = None;
+        }
+    }
+
+    pub fn begin_initial_snapshot(&mut self, files: usize, bytes: u64) {
+        if let Some(action) = self.action.as_mut() {
+            action.initial_snapshot = Some(InitialSnapshot { files, bytes });
+        }
+    }
+
+    pub fn begin_stage(&mut self, name: &str) {
+        self.stage = Some(StageProgress {
+            name: name.to_owned(),
+            op_count: 0,
+            ops_done: 0,
+        });
+    }
+
+    pub fn end_stage(&mut self) {
+        self.stage = None;
+
+        if let Some(action) = self.action.as_mut() {
+            action.stages_done += 1;
+        }
+    }
+
+    pub fn begin_sync(&mut self, op_count: usize) {
+        if let Some(stage) = self.stage.as_mut() {
+            stage.op_count = op_count;
+            stage.ops_done = 0;
+        }
+    }
+
+    pub fn sync_progress(&mut self) {
+        if let Some(stage) = self.stage.as_mut() {
+            stage.ops_done += 1;
+        }
+    }
+
+    pub fn begin_file(&mut self, operation: &str, name: &str, size: u64) {
+        self.file = Some(FileProgress {
+            operation: operation.to_owned(),
+            name: name.to_owned(),
+            size,
+            bytes: 0,
+        });
+    }
+
+    pub fn file_progress(&mut self, bytes: u64) {
+        if let Some(file) = self.file.as_mut() {
+            file.bytes += bytes;
+
+            // Only copies fill staging, checksums and verification read files again
+            if file.operation == "Copy" {
+                if let Some(action) = self.action.as_mut().filter(|a| a.stage_bytes.is_some()) {
+                    action.bytes_staged += bytes;
+                }
+            }
+        }
+
+        self.processed(bytes);
+    }
+
+    pub fn compress_progress(&mut self, bytes: u64) {
+        self.processed(bytes);
+    }
+
+    fn processed(&mut self, bytes: u64) {
+        if let Some(action) = self.action.as_mut() {
+            action.bytes_processed += bytes;
+        }
+    }
+
+    pub fn end_file(&mut self) {
+        self.file = None;
+    }
+
+    /// Record a backup deleted while pruning
+    pub fn pruned(&mut self, name: &str) {
+        self.stage = Some(StageProgress {
+            name: format!("Deleted {name}"),
+            op_count: 0,
+            ops_done: 0,
+        });
+
+        if let Some(action) = self.action.as_mut() {
+            action.stages_done += 1;
+        }
+    }
+
+    pub fn begin_upload(&mut self, target: &str, name: &str, size: u64) {
+        self.upload = Some(FileProgress {
+            operation: format!("Uploading to [{target}]"),
+            name: name.to_owned(),
+            size,
+            bytes: 0,
+        });
+    }
+
+    pub fn upload_progress(&mut self, bytes: u64) {
+        if let Some(upload) = self.upload.as_mut() {
+            upload.bytes += bytes;
+        }
+    }
+
+    pub fn end_upload(&mut self) {
+        self.upload = None;
+    }
+
+    /// Completed fraction of the current stage, from 0 to 1
+    pub fn stage_ratio(&self) -> f32 {
+        let Some(stage) = self.stage.as_ref().filter(|s| s.op_count > 0) else {
+            return 0.;
+        };
+
+        let file_ratio = self.file.as_ref().map_or(0., FileProgress::ratio);
+
+        ((stage.ops_done as f32 + file_ratio) / stage.op_count as f32).min(1.)
+    }
+
+    /// Completed fraction of the current action, from 0 to 1.
+    /// While staging, this is derived from the bytes copied, or from the stages when there is nothing to copy,
+    /// otherwise from the estimate.
+    pub fn action_ratio(&self) -> f32 {
+        let Some(action) = self.action.as_ref() else {
+            return 0.;
+        };
+
+        if let Some(stage_bytes) = action.stage_bytes.filter(|b| *b > 0) {
+            return (action.bytes_staged as f32 / stage_bytes as f32).clamp(0., 1.);
+        }
+
+        match action.stage_count {
+            Some(stage_count) if stage_count > 0 => {
+                ((action.stages_done as f32 + self.stage_ratio()) / stage_count as f32).clamp(0., 1.)
+            }
+            _ => action.progress.get(),
+        }
+    }
+}
+
+impl Progress {
+    pub fn set(&mut self, value: f32) {
+        *self = Self::Exact(value);
+    }
+
+    pub fn get(&self) -> f32 {
+        match self {
+            Self::Exact(v) => *v,
+            Self::Estimate { start, end } => {
+                let now = Instant::now();
+                let total = *end - *start;
+                let elapsed = now - *start;
+
+                (elapsed.as_secs_f32() / total.as_secs_f32()).clamp(0., 0.99)
+            }
+            Self::Unknown => 0.,
+        }
+    }
+}
+
+impl ProgressState for ProgressModel {
+    fn progress(&mut self) -> &mut ProgressModel {
+        self
+    }
+}
+
+impl<S: ProgressState> ProgressUiHandler<S> {
+    pub fn new(state: Arc<Mutex<S>>) -> Self {
+        Self {
+            state,
+            backup_estimate: None,
+            restore_estimate: None,
+            backup_warnings: Vec::new(),
+            skipped_paths: Vec::new(),
+        }
+    }
+
+    fn progress(&self, f: impl FnOnce(&mut ProgressModel)) {
+        f(self.state.lock().unwrap().progress());
+    }
+
+    /// End the current action, returning how long it took
+    fn end_action(&mut self) -> Option<Duration> {
+        let action = self.state.lock().unwrap().progress().end_action()?;
+
+        Some(Instant::now() - action.started_at)
+    }
+}
+
+impl<S: ProgressState> StoolUiHandler for ProgressUiHandler<S> {
+    fn clear(self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    fn begin_backup(&mut self, name: &str) {
+        let kind = ActionKind::CreateBackup { name: name.to_owned() };
+        let estimate = self.backup_estimate;
+
+        self.backup_warnings.clear();
+        self.skipped_paths.clear();
+        self.progress(|p| p.begin_action(kind, estimate));
+    }
+
+    fn backup_warning(&mut self, message: &str) {
+        self.backup_warnings.push(message.to_owned());
+    }
+
+    fn paths_skipped(&mut self, names: &[String]) {
+        self.skipped_paths.extend_from_slice(names);
+    }
+
+    fn end_backup(&mut self, success: bool) {
+        if let Some(duration) = self.end_action() {
+            self.backup_estimate = Some(duration);
+        }
+
+        if success {
+            let warnings = std::mem::take(&mut self.backup_warnings);
+            let skipped_paths = std::mem::take(&mut self.skipped_paths);
+
+            self.state.lock().unwrap().backup_created(warnings, skipped_paths);
+        }
+    }
+
+    fn begin_staging(&mut self, count: usize, bytes: u64) {
+        self.progress(|p| p.begin_staging(count, bytes));
+    }
+
+    fn begin_initial_snapshot(&mut self, files: usize, bytes: u64) {
+        self.progress(|p| p.begin_initial_snapshot(files, bytes));
+    }
+
+    fn begin_stage(&mut self, name: &str) {
+        self.progress(|p| p.begin_stage(name));
+    }
+
+    fn end_stage(&mut self) {
+        self.progress(|p| p.end_stage());
+    }
+
+    fn end_staging(&mut self) {
+        self.progress(|p| p.end_staging());
+    }
+
+    fn begin_compress(&mut self) {
+        self.progress(|p| p.set_phase(Some(Phase::Compressing)));
+    }
+
+    fn compress_progress(&mut self, bytes: u64) {
+        self.progress(|p| p.compress_progress(bytes));
+    }
+
+    fn end_compress(&mut self) {
+        self.progress(|p| p.set_phase(None));
+    }
+
+    fn begin_restore(&mut self, name: &str) {
+        let kind = ActionKind::RestoreBackup { name: name.to_owned() };
+        let estimate = self.restore_estimate;
+
+        self.progress(|p| p.begin_action(kind, estimate));
+    }
+
+    fn end_restore(&mut self, _success: bool) {
+        if let Some(duration) = self.end_action() {
+            self.restore_estimate = Some(duration);
+        }
+    }
+
+    fn begin_extract(&mut self) {
+        self.progress(|p| p.set_phase(Some(Phase::Extracting)));
+    }
+
+    fn end_extract(&mut self) {
+        self.progress(|p| p.set_phase(None));
+    }
+
+    fn begin_restore_sp(&mut self, name: &str) {
+        self.progress(|p| {
+            p.set_phase(Some(Phase::Restoring));
+            p.begin_stage(name);
+        });
+    }
+
+    fn end_restore_sp(&mut self) {
+        self.progress(|p| p.end_stage());
+    }
+
+    fn begin_prune(&mut self) {
+        self.progress(|p| p.begin_action(ActionKind::PruneBackups, None));
+    }
+
+    fn pruned(&mut self, name: &str) {
+        self.progress(|p| p.pruned(name));
+    }
+
+    fn end_prune(&mut self) {
+        self.end_action();
+    }
+
+    fn begin_upload(&mut self, target: &str, name: &str, size: u64) {
+        self.progress(|p| p.begin_upload(target, name, size));
+    }
+
+    fn upload_progress(&mut self, bytes: u64) {
+        self.progress(|p| p.upload_progress(bytes));
+    }
+
+    fn end_upload(&mut self, _success: bool) {
+        self.progress(|p| p.end_upload());
+    }
+
+    fn tick(&mut self) {}
+}
+
+impl<S: ProgressState> SyncUiHandler for ProgressUiHandler<S> {
+    fn begin_scan(&mut self) {}
+
+    fn end_scan(&mut self) {}
+
+    fn begin_prepare(&mut self) {}
+
+    fn end_prepare(&mut self) {}
+
+    fn begin_sync(&mut self, op_count: usize) {
+        self.progress(|p| p.begin_sync(op_count));
+    }
+
+    fn sync_progress(&mut self) {
+        self.progress(|p| p.sync_progress());
+    }
+
+    fn end_sync(&mut self) {}
+
+    fn begin_file(&mut self, prefix: &str, filename: &str, size: u64) {
+        self.progress(|p| p.begin_file(prefix, filename, size));
+    }
+
+    fn file_progress(&mut self, bytes: u64) {
+        self.progress(|p| p.file_progress(bytes));
+    }
+
+    fn end_file(&mut self) {
+        self.progress(|p| p.end_file());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upload_progress_outlives_the_actions_it_runs_alongside() {
+        let mut progress = ProgressModel::default();
+
+        progress.begin_upload("nas", "backup.7z", 100);
+        progress.upload_progress(40);
+
+        progress.begin_action(ActionKind::PruneBackups, None);
+        progress.pruned("old.7z");
+        progress.pruned("older.7z");
+
+        let stage = progress.stage.as_ref().unwrap();
+        assert_eq!(stage.describe(), "Deleted older.7z");
+
+        let action = progress.end_action().unwrap();
+        assert_eq!(action.stages_done, 2);
+        assert!(progress.stage.is_none());
+
+        progress.upload_progress(20);
+        let upload = progress.upload.as_ref().unwrap();
+        assert_eq!((upload.bytes, upload.ratio()), (60, 0.6));
+
+        progress.end_upload();
+        assert!(progress.upload.is_none());
+    }
+}
@@ -0,0 +1,73 @@
+//! Save files deleted when restoring, kept in the data directory of the game for a while before being removed
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use super::{EngineArgs, ARCHIVE_DATE_FORMAT};
+
+const RECYCLE_DIRNAME: &str = "recycle";
+
+/// Days to keep recycled files if not configured
+const DEFAULT_KEEP_DAYS: u64 = 30;
+
+/// Directory to move the files deleted by a restore starting now into
+pub fn batch_path(args: &EngineArgs) -> PathBuf {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+
+    args.output_path()
+        .join(RECYCLE_DIRNAME)
+        .join(now.format(ARCHIVE_DATE_FORMAT).unwrap())
+}
+
+/// Remove restores' recycled files once they are older than the days to keep them
+pub fn purge(args: &EngineArgs, keep_days: Option<u64>) -> Result<(), anyhow::Error> {
+    let keep = Duration::from_secs(keep_days.unwrap_or(DEFAULT_KEEP_DAYS) * 24 * 60 * 60);
+    let now = args.clock.system_now();
+
+    let Ok(entries) = fs::read_dir(args.output_path().join(RECYCLE_DIRNAME)) else {
+        return Ok(());
+    };
+
+    for entry in entries {
+        let entry = entry?;
+
+        let age = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok());
+
+        if age.is_some_and(|age| age > keep) {
+            info!("Removing recycled files: {}", entry.file_name().to_string_lossy());
+
+            if let Err(err) = fs::remove_dir_all(entry.path()) {
+                warn!("Error removing recycled files: {err}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::Fixture;
+
+    #[test]
+    fn recycled_files_are_purged_once_older_than_the_days_to_keep() {
+        let fixture = Fixture::new();
+        let batch = batch_path(&fixture.args);
+        fs::create_dir_all(&batch).unwrap();
+        fs::write(batch.join("slot1.sav"), "old").unwrap();
+
+        purge(&fixture.args, Some(2)).unwrap();
+        assert!(batch.exists());
+
+        fixture.clock.advance(Duration::from_secs(3 * 24 * 60 * 60));
+        purge(&fixture.args, Some(2)).unwrap();
+        assert!(!batch.exists());
+    }
+}
@@ -0,0 +1,134 @@
+//! Driving an engine running in another process, over its control socket
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::error;
+
+use super::{
+    control::{self, ControlRequest, ControlResponse},
+    ui::StoolUiHandler,
+    watch::WatchStatus,
+    BackupRequest, BackupTiming, EngineSnapshot, EngineState, PendingChanges,
+};
+
+/// Interval at which the status of the engine is refreshed
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Handle to an engine running in another process.
+/// Its status is polled in the background, and its UI events are replayed to a local UI handler.
+#[derive(Clone)]
+pub struct RemoteEngine {
+    output_path: PathBuf,
+    /// Latest known status, or `None` once the engine can no longer be reached
+    status: Arc<Mutex<Option<EngineSnapshot>>>,
+}
+
+impl RemoteEngine {
+    /// Attach to the engine running for a game, failing with [`control::NotRunning`] if there is none
+    pub fn attach(output_path: &Path, mut ui: impl StoolUiHandler) -> Result<Self, anyhow::Error> {
+        let status = Arc::new(Mutex::new(Some(request_snapshot(output_path)?)));
+        let events = control::subscribe(output_path)?;
+
+        std::thread::spawn(move || {
+            for event in events {
+                event.dispatch(&mut ui);
+            }
+
+            if let Err(err) = ui.clear() {
+                error!("Error clearing UI: {err}");
+            }
+        });
+
+        std::thread::spawn({
+            let output_path = output_path.to_owned();
+            let status = Arc::downgrade(&status);
+
+            move || {
+                // Stops once all handles are dropped, or the engine is gone
+                while let Some(status) = status.upgrade() {
+                    std::thread::sleep(STATUS_POLL_INTERVAL);
+
+                    let latest = request_snapshot(&output_path).ok();
+                    let gone = latest.is_none();
+                    *status.lock().unwrap() = latest;
+
+                    if gone {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            output_path: output_path.to_owned(),
+            status,
+        })
+    }
+
+    fn with_status<R>(&self, f: impl FnOnce(&EngineSnapshot) -> R) -> Option<R> {
+        self.status.lock().unwrap().as_ref().map(f)
+    }
+
+    pub fn state(&self) -> EngineState {
+        self.with_status(|s| s.state).unwrap_or(EngineState::ShutDown)
+    }
+
+    pub fn get_autobackup(&self) -> bool {
+        self.with_status(|s| s.autobackup).unwrap_or(false)
+    }
+
+    pub fn set_autobackup(&self, val: bool) -> Result<(), anyhow::Error> {
+        control::request(&self.output_path, ControlRequest::SetAutobackup { enabled: val })?;
+
+        if let Some(status) = self.status.lock().unwrap().as_mut() {
+            status.autobackup = val;
+        }
+
+        Ok(())
+    }
+
+    pub fn watches(&self) -> Result<Vec<WatchStatus>, anyhow::Error> {
+        match control::request(&self.output_path, ControlRequest::Watches)? {
+            ControlResponse::Watches(watches) => Ok(watches),
+            _ => Err(anyhow::anyhow!("Unexpected response from engine")),
+        }
+    }
+
+    pub fn watcher_fallback(&self) -> Option<String> {
+        self.with_status(|s| s.watcher_fallback.clone()).flatten()
+    }
+
+    pub fn pending_changes(&self) -> Option<PendingChanges> {
+        self.with_status(|s| s.pending_changes).flatten()
+    }
+
+    pub fn backup_timing(&self) -> BackupTiming {
+        self.with_status(|s| s.backup_timing).unwrap_or_default()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.with_status(|s| s.busy).unwrap_or(false)
+    }
+
+    pub fn send(&self, req: BackupRequest) -> Result<(), anyhow::Error> {
+        control::request(&self.output_path, ControlRequest::Send { request: req })?;
+
+        // Reflect the request right away, rather than at the next poll
+        if let Some(status) = self.status.lock().unwrap().as_mut() {
+            status.busy = true;
+        }
+
+        Ok(())
+    }
+}
+
+fn request_snapshot(output_path: &Path) -> Result<EngineSnapshot, anyhow::Error> {
+    match control::request(output_path, ControlRequest::Snapshot)? {
+        ControlResponse::Snapshot(status) => Ok(*status),
+        _ => Err(anyhow::anyhow!("Unexpected response from engine")),
+    }
+}
@@ -0,0 +1,410 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use filetime::FileTime;
+use tracing::{info, warn};
+
+use crate::{
+    config::game::{CloudPlaceholders, GameConfig, GameSaveFile},
+    internal::{
+        archive::ArchiveEntry,
+        format::format_duration,
+        sync::{self, CopyOptions, Deletion, SyncEvents, SyncOptions, SyncStats},
+    },
+};
+
+use super::{
+    backups, make_backup_filename, manifest::Manifest, ui::StoolUiHandler, BackupKind, BackupRequest, EngineArgs,
+    InternalGameSaveDir,
+};
+
+/// Description of the backup of live save files created before a restore that would overwrite newer ones
+const BEFORE_RESTORE_DESCRIPTION: &str = "Before restore";
+
+/// What to do about a live save file that is newer than its copy in the backup being restored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictResolution {
+    /// Replace it with the copy in the backup
+    Overwrite,
+    /// Leave it as it is
+    Keep,
+    /// Back up the live save files, then replace it
+    BackUpFirst,
+}
+
+/// Live save file that is newer than its copy in a backup
+#[derive(Clone, Debug)]
+pub struct RestoreConflict {
+    /// Path inside the backup
+    pub path: PathBuf,
+    /// How much more recently the live file was modified
+    pub newer_by: Duration,
+}
+
+impl fmt::Display for RestoreConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (live file is {} newer)",
+            self.path.display(),
+            format_duration(self.newer_by)
+        )
+    }
+}
+
+/// Live save files that are newer than their copies in a backup, limited to a path inside the backup if given.
+/// Backups without a manifest have no recorded modification times, and so no conflicts.
+pub fn find_conflicts(
+    args: &EngineArgs,
+    archive_name: &str,
+    only: Option<&Path>,
+) -> Result<Vec<RestoreConflict>, anyhow::Error> {
+    let gcfg = GameConfig::from_file(&args.game_config_file_path(), args.strict_config)?;
+    let save_dirs = InternalGameSaveDir::from_config(&gcfg)?;
+
+    let archive_path = backups::resolve_archive(&args.backup_paths(), archive_name)?;
+    let Some(manifest) = Manifest::load_for_archive(&archive_path)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut conflicts = Vec::new();
+
+    for file in manifest.files.iter() {
+        if only.is_some_and(|only| !file.path.starts_with(only)) {
+            continue;
+        }
+
+        let Some(live_path) = live_path(&file.path, &save_dirs, &gcfg.save_files) else {
+            continue;
+        };
+
+        let Ok(live_modified) = fs::metadata(&live_path).and_then(|m| m.modified()) else {
+            continue;
+        };
+
+        let backup_modified = SystemTime::from(FileTime::from_unix_time(file.mtime, file.mtime_nanos));
+
+        if let Ok(newer_by) = live_modified.duration_since(backup_modified) {
+            if !newer_by.is_zero() {
+                conflicts.push(RestoreConflict {
+                    path: file.path.clone(),
+                    newer_by,
+                });
+            }
+        }
+    }
+
+    Ok(conflicts)
+}
+
+/// Requests restoring a backup with its conflicts resolved.
+/// If any conflict is to be backed up first, a backup of the live save files is queued before the restore.
+pub fn restore_requests(
+    archive_name: String,
+    only: Option<PathBuf>,
+    resolved: &[(RestoreConflict, ConflictResolution)],
+    extension: &str,
+) -> Vec<BackupRequest> {
+    let mut requests = Vec::new();
+
+    if resolved
+        .iter()
+        .any(|(_, resolution)| *resolution == ConflictResolution::BackUpFirst)
+    {
+        requests.push(BackupRequest::CreateBackup {
+            archive_name: make_backup_filename(BEFORE_RESTORE_DESCRIPTION, extension),
+            kind: BackupKind::Manual,
+        });
+    }
+
+    let keep = resolved
+        .iter()
+        .filter(|(_, resolution)| *resolution == ConflictResolution::Keep)
+        .map(|(conflict, _)| conflict.path.clone())
+        .collect();
+
+    requests.push(BackupRequest::RestoreBackup {
+        archive_name,
+        only,
+        keep,
+    });
+
+    requests
+}
+
+/// Put live save files into a backup unpacked in the staging directory in place of their copies,
+/// so that restoring it leaves them as they are
+pub(super) fn keep_live_files(
+    staging_path: &Path,
+    keep: &[PathBuf],
+    save_dirs: &[InternalGameSaveDir],
+    save_files: &[GameSaveFile],
+) -> Result<(), anyhow::Error> {
+    for path in keep.iter() {
+        let Some(live_path) = live_path(path, save_dirs, save_files) else {
+            continue;
+        };
+
+        let Ok(metadata) = fs::metadata(&live_path) else {
+            continue;
+        };
+
+        let staging_file_path = staging_path.join(path);
+        fs::copy(&live_path, &staging_file_path)
+            .with_context(|| format!("Error keeping live save file {}", live_path.display()))?;
+        filetime::set_file_mtime(&staging_file_path, FileTime::from_last_modification_time(&metadata))?;
+    }
+
+    Ok(())
+}
+
+/// Live location of a file inside a backup
+fn live_path(path: &Path, save_dirs: &[InternalGameSaveDir], save_files: &[GameSaveFile]) -> Option<PathBuf> {
+    for gsp in save_dirs.iter() {
+        if let Ok(rel_path) = path.strip_prefix(&gsp.name) {
+            return Some(gsp.path.join(rel_path));
+        }
+    }
+
+    save_files.iter().find_map(|gsf| {
+        let file_name = gsf.path.file_name()?;
+
+        let staging_rel_path = match gsf.staging_subdirectory.as_ref() {
+            Some(staging_subdir) => staging_subdir.join(file_name),
+            None => PathBuf::from(file_name),
+        };
+
+        (staging_rel_path == path).then(|| gsf.path.clone())
+    })
+}
+
+/// Refuse to restore a single file or subdirectory that the backup does not have,
+/// before anything is unpacked
+pub(super) fn check_subpath(entries: &[ArchiveEntry], only: &Path) -> Result<(), anyhow::Error> {
+    if !entries.iter().any(|e| e.path.starts_with(only)) {
+        return Err(anyhow::anyhow!("Path does not exist in backup: {}", only.display()));
+    }
+
+    Ok(())
+}
+
+/// Restore a single file or subdirectory from an unpacked backup in the staging directory.
+/// Unlike a full restore, files not present in the backup are left alone.
+pub(super) fn restore_subpath(
+    staging_path: &Path,
+    only: &Path,
+    save_dirs: &[InternalGameSaveDir],
+    save_files: &[GameSaveFile],
+    copy: CopyOptions,
+    ui: &mut impl StoolUiHandler,
+    events: &SyncEvents,
+) -> Result<SyncStats, anyhow::Error> {
+    let src_path = staging_path.join(only);
+
+    if !src_path.exists() {
+        return Err(anyhow::anyhow!("Path does not exist in backup: {}", only.display()));
+    }
+
+    let mut stats = SyncStats::default();
+
+    // Path inside a save directory
+    for gsp in save_dirs.iter() {
+        let Ok(rel_path) = only.strip_prefix(&gsp.name) else {
+            continue;
+        };
+
+        ui.begin_restore_sp(&only.to_string_lossy());
+
+        let entries = walkdir::WalkDir::new(&src_path).into_iter().filter_map(Result::ok);
+
+        for entry in entries {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            // Path relative to the root of the save directory, which is what filters apply to
+            let file_rel_path = rel_path.join(entry.path().strip_prefix(&src_path)?);
+
+            if let Some(include_globset) = gsp.include_globset.as_ref() {
+                if !include_globset.is_match(&file_rel_path) {
+                    continue;
+                }
+            }
+
+            if let Some(ignore_globset) = gsp.ignore_globset.as_ref() {
+                if ignore_globset.is_match(&file_rel_path) {
+                    continue;
+                }
+            }
+
+            let dst_dir_path = gsp
+                .path
+                .join(&file_rel_path)
+                .parent()
+                .context("Couldn't get parent directory of save file")?
+                .to_path_buf();
+
+            stats += sync::sync_file(entry.path(), &dst_dir_path, copy, events)?;
+        }
+
+        ui.end_restore_sp();
+
+        return Ok(stats);
+    }
+
+    // Individual save file
+    for gsf in save_files.iter() {
+        let dir_path = gsf
+            .path
+            .parent()
+            .context("Couldn't get parent directory of game save file")?;
+        let file_name = gsf.path.strip_prefix(dir_path)?;
+
+        let staging_rel_path = match gsf.staging_subdirectory.as_ref() {
+            Some(staging_subdir) => staging_subdir.join(file_name),
+            None => file_name.to_path_buf(),
+        };
+
+        if staging_rel_path != only {
+            continue;
+        }
+
+        ui.begin_restore_sp(&file_name.to_string_lossy());
+        stats += sync::sync_file(&src_path, dir_path, copy, events)?;
+        ui.end_restore_sp();
+
+        return Ok(stats);
+    }
+
+    Err(anyhow::anyhow!(
+        "Path does not belong to any save directory or file: {}",
+        only.display()
+    ))
+}
+
+/// Restore a save directory by building the restored tree beside it, then swapping it into place,
+/// so that a restore failing midway leaves the live save directory as it was.
+/// Returns none, leaving the save directory untouched, if it cannot be swapped.
+/// It then has to be restored in place.
+pub(super) fn restore_dir_atomically(
+    src_path: &Path,
+    dst_path: &Path,
+    options: SyncOptions,
+    events: &SyncEvents,
+) -> Result<Option<SyncStats>, anyhow::Error> {
+    // Redirected save directories are swapped where they really are, keeping the link
+    let live_path = sync::resolve_path(dst_path);
+
+    let (Some(parent_path), Some(name)) = (live_path.parent(), live_path.file_name()) else {
+        return Ok(None);
+    };
+
+    // Renaming only moves directories within a volume
+    if sync::same_volume(&live_path, parent_path) != Some(true) {
+        info!(
+            "Restoring in place, as {} cannot be swapped within its volume",
+            live_path.display()
+        );
+        return Ok(None);
+    }
+
+    // Swapping would replace links inside the save directory with copies of what they point to
+    if contains_links(&live_path) {
+        info!(
+            "Restoring in place, as {} contains links to other folders",
+            live_path.display()
+        );
+        return Ok(None);
+    }
+
+    let name = name.to_string_lossy();
+    let new_path = parent_path.join(format!(".{name}.stool-restore"));
+    let old_path = parent_path.join(format!(".{name}.stool-old"));
+
+    // Left over by a restore interrupted while swapping, before which the save directory was complete
+    if old_path.exists() {
+        if live_path.exists() {
+            fs::remove_dir_all(&old_path)?;
+        } else {
+            warn!(
+                "Recovering save directory left aside by an interrupted restore: {}",
+                live_path.display()
+            );
+            fs::rename(&old_path, &live_path)?;
+        }
+    }
+
+    if new_path.exists() {
+        fs::remove_dir_all(&new_path)?;
+    }
+
+    let result = (|| {
+        fs::create_dir_all(&new_path)?;
+
+        // Start from the live files, so that files left alone by filters are kept.
+        // Cloud placeholders are downloaded, as the copy is all that remains after swapping.
+        if live_path.exists() {
+            sync::sync_dir(
+                &live_path,
+                &new_path,
+                SyncOptions {
+                    include_globset: None,
+                    ignore_globset: None,
+                    exclude: &[],
+                    filter_in_dst: false,
+                    copy: CopyOptions {
+                        placeholders: CloudPlaceholders::Hydrate,
+                        ..options.copy
+                    },
+                    deletion: Deletion::Remove,
+                    ..options
+                },
+                events,
+            )?;
+
+            fs::set_permissions(&new_path, fs::metadata(&live_path)?.permissions())?;
+        }
+
+        sync::sync_dir(src_path, &new_path, options, events)
+    })();
+
+    let stats = match result {
+        Ok(stats) => stats,
+        Err(err) => {
+            fs::remove_dir_all(&new_path).ok();
+            return Err(err);
+        }
+    };
+
+    if live_path.exists() {
+        if let Err(err) = fs::rename(&live_path, &old_path) {
+            fs::remove_dir_all(&new_path).ok();
+            return Err(err).with_context(|| format!("Couldn't move aside {}", live_path.display()));
+        }
+    }
+
+    if let Err(err) = fs::rename(&new_path, &live_path) {
+        fs::rename(&old_path, &live_path).ok();
+        fs::remove_dir_all(&new_path).ok();
+        return Err(err).with_context(|| format!("Couldn't move restored files into {}", live_path.display()));
+    }
+
+    if let Err(err) = fs::remove_dir_all(&old_path) {
+        warn!("Couldn't remove previous save directory {}: {err}", old_path.display());
+    }
+
+    Ok(Some(stats))
+}
+
+/// Whether a directory contains symbolic links or junctions
+fn contains_links(path: &Path) -> bool {
+    walkdir::WalkDir::new(path)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .any(|e| e.path_is_symlink())
+}
@@ -0,0 +1,186 @@
+use std::{fs, path::Path, time::Duration};
+
+use time::OffsetDateTime;
+
+use tracing::{error, info};
+
+use super::{
+    annotations::Annotations,
+    backups::{delete_game_backup, list_backups, list_game_backups, parse_backup_name},
+    copies::CopyNaming,
+    delta::DeltaBaseInUse,
+    history::game_sessions,
+    partial::BaselineInUse,
+    ui::StoolUiHandler,
+    EngineArgs,
+};
+
+/// Delete all but the `keep` most recent auto-backups.
+/// Pinned backups are never deleted, and do not count towards `keep`.
+pub fn prune_auto_backups(args: &EngineArgs, keep: usize, ui: &mut impl StoolUiHandler) -> Result<(), anyhow::Error> {
+    let backups = list_game_backups(args)?;
+    let annotations = Annotations::load(&args.output_path())?;
+
+    ui.begin_prune();
+
+    for backup in backups
+        .iter()
+        .filter(|b| b.is_auto() && !annotations.is_pinned(&b.name))
+        .skip(keep)
+    {
+        info!("Pruning old auto-backup: {}", backup.name);
+
+        match delete_game_backup(args, backup) {
+            Ok(()) => ui.pruned(&backup.name),
+            Err(err) if err.is::<DeltaBaseInUse>() || err.is::<BaselineInUse>() => info!("Keeping backup: {err}"),
+            Err(err) => error!("Error deleting backup {}: {err}", backup.name),
+        }
+    }
+
+    ui.end_prune();
+
+    Ok(())
+}
+
+/// Delete all but the final backup of each session that ended more than `age` ago.
+/// Pinned backups are never deleted.
+pub fn collapse_old_sessions(
+    args: &EngineArgs,
+    age: Duration,
+    ui: &mut impl StoolUiHandler,
+) -> Result<(), anyhow::Error> {
+    let annotations = Annotations::load(&args.output_path())?;
+    let cutoff = OffsetDateTime::now_utc() - age;
+    let sessions = game_sessions(args)?;
+
+    ui.begin_prune();
+
+    for group in sessions {
+        // Only sessions that were recorded as having ended can be collapsed
+        let Some(session) = group.session.as_ref() else {
+            continue;
+        };
+
+        if session.started_at + session.duration > cutoff {
+            continue;
+        }
+
+        // Backups are ordered newest first, so the first one is the final backup of the session
+        for backup in group.backups.iter().skip(1) {
+            if annotations.is_pinned(&backup.name) {
+                continue;
+            }
+
+            info!("Collapsing old session, deleting backup: {}", backup.name);
+
+            match delete_game_backup(args, backup) {
+                Ok(()) => ui.pruned(&backup.name),
+                Err(err) if err.is::<DeltaBaseInUse>() || err.is::<BaselineInUse>() => info!("Keeping backup: {err}"),
+                Err(err) => error!("Error deleting backup {}: {err}", backup.name),
+            }
+        }
+    }
+
+    ui.end_prune();
+
+    Ok(())
+}
+
+/// Delete all but the `keep` most recent backup copies of a game in a copy-latest path.
+/// Only files named like copies of its backup archives are considered,
+/// so other files in the directory, including copies of other games, are left alone.
+pub fn prune_copies(
+    dir: &Path,
+    naming: &CopyNaming,
+    keep: usize,
+    ui: &mut impl StoolUiHandler,
+) -> Result<(), anyhow::Error> {
+    let copies = list_backups(dir)?;
+
+    let is_copy = |name: &str| {
+        naming
+            .archive_name(name)
+            .is_some_and(|archive_name| parse_backup_name(archive_name).is_some())
+    };
+
+    ui.begin_prune();
+
+    for copy in copies.iter().filter(|c| is_copy(&c.name)).skip(keep) {
+        info!("Pruning old copy: {}", copy.path.display());
+
+        match fs::remove_file(&copy.path) {
+            Ok(()) => ui.pruned(&copy.name),
+            Err(err) => error!("Error deleting copy {}: {err}", copy.path.display()),
+        }
+    }
+
+    ui.end_prune();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        backups::{list_backups_in, BackupInfo},
+        index::BackupIndex,
+        testing::{write_backup, Fixture, NullUiHandler, RecordingUiHandler, UiEvent},
+    };
+
+    #[test]
+    fn pruning_removes_backups_from_the_index() {
+        let mut fixture = Fixture::new();
+        fixture.args.use_index = true;
+
+        let backup_path = fixture.args.backup_path();
+        for (n, age) in [(1, 30), (2, 20), (3, 10)] {
+            let name = format!("2025-01-01 00-00-0{n} Auto.7z");
+            write_backup(&backup_path, &name, Duration::from_secs(age), &["slot1.sav"]);
+        }
+        write_backup(
+            &backup_path,
+            "2025-01-01 00-00-00 Manual.7z",
+            Duration::from_secs(40),
+            &["slot1.sav"],
+        );
+
+        BackupIndex::open(&fixture.args.output_path())
+            .unwrap()
+            .sync_with_dirs(&fixture.args.backup_paths())
+            .unwrap();
+
+        prune_auto_backups(&fixture.args, 1, &mut NullUiHandler).unwrap();
+
+        let expected = ["2025-01-01 00-00-03 Auto.7z", "2025-01-01 00-00-00 Manual.7z"];
+        let names = |backups: Vec<BackupInfo>| backups.into_iter().map(|b| b.name).collect::<Vec<_>>();
+        assert_eq!(names(list_game_backups(&fixture.args).unwrap()), expected);
+        assert_eq!(names(list_backups_in(&fixture.args.backup_paths()).unwrap()), expected);
+    }
+
+    #[test]
+    fn pruning_copies_leaves_other_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        for (n, age) in [(1, 30), (2, 20), (3, 10)] {
+            let name = format!("2025-01-01 00-00-0{n} Manual.7z");
+            write_backup(dir.path(), &name, Duration::from_secs(age), &["slot1.sav"]);
+        }
+        fs::write(dir.path().join("Other game.7z"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let naming = CopyNaming::new(&Fixture::new().config(), "game");
+        let ui = RecordingUiHandler::default();
+        prune_copies(dir.path(), &naming, 1, &mut ui.clone()).unwrap();
+
+        assert_eq!(
+            ui.events(),
+            [
+                UiEvent::Pruned("2025-01-01 00-00-02 Manual.7z".to_owned()),
+                UiEvent::Pruned("2025-01-01 00-00-01 Manual.7z".to_owned()),
+            ]
+        );
+        assert!(dir.path().join("2025-01-01 00-00-03 Manual.7z").exists());
+        assert!(dir.path().join("Other game.7z").exists());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{io, time::Duration};
+
+use crate::internal::sync::SyncJobError;
+
+/// Number of times a failed backup is retried before giving up
+pub const MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled for each following one
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Windows error codes for files held open by another process
+const ERROR_SHARING_VIOLATION: i32 = 32;
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// Windows error codes for cloud placeholders that could not be downloaded for now,
+/// such as when the cloud client is not running or offline
+const ERROR_CLOUD_FILE_PROVIDER_NOT_RUNNING: i32 = 362;
+const ERROR_CLOUD_FILE_NETWORK_UNAVAILABLE: i32 = 388;
+const ERROR_CLOUD_FILE_UNSUCCESSFUL: i32 = 389;
+const ERROR_CLOUD_FILE_IN_USE: i32 = 391;
+const ERROR_CLOUD_FILE_REQUEST_TIMEOUT: i32 = 426;
+
+/// What kind of failure an error is, and whether it is worth retrying
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorCategory {
+    /// Files locked or briefly inaccessible, such as when held by a cloud client or antivirus
+    Locked,
+    /// Out of disk space or quota, which may be freed up in the meantime
+    DiskFull,
+    /// Files changed while being read
+    Changed,
+    Permanent,
+}
+
+impl ErrorCategory {
+    pub fn of(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<io::Error>() {
+                return Self::of_io(err);
+            }
+
+            if let Some(err) = cause.downcast_ref::<SyncJobError>() {
+                match err {
+                    SyncJobError::Anyhow(_) => continue,
+                    _ => return Self::Changed,
+                }
+            }
+        }
+
+        Self::Permanent
+    }
+
+    fn of_io(err: &io::Error) -> Self {
+        if matches!(
+            err.raw_os_error(),
+            Some(
+                ERROR_SHARING_VIOLATION
+                    | ERROR_LOCK_VIOLATION
+                    | ERROR_CLOUD_FILE_PROVIDER_NOT_RUNNING
+                    | ERROR_CLOUD_FILE_NETWORK_UNAVAILABLE
+                    | ERROR_CLOUD_FILE_UNSUCCESSFUL
+                    | ERROR_CLOUD_FILE_IN_USE
+                    | ERROR_CLOUD_FILE_REQUEST_TIMEOUT
+            )
+        ) {
+            return Self::Locked;
+        }
+
+        match err.kind() {
+            io::ErrorKind::PermissionDenied
+            | io::ErrorKind::ResourceBusy
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted => Self::Locked,
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => Self::DiskFull,
+            _ => Self::Permanent,
+        }
+    }
+
+    pub fn is_transient(self) -> bool {
+        self != Self::Permanent
+    }
+}
+
+/// Delay before a retry, given how many attempts have failed so far
+pub fn backoff(failed_attempts: u32) -> Duration {
+    INITIAL_BACKOFF * 2u32.pow(failed_attempts.saturating_sub(1))
+}
@@ -0,0 +1,139 @@
+//! Coordination with dedicated game servers, so that backups contain a freshly written world
+//! and are not created while the server is busy
+
+use std::{
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+use crate::{
+    config::game::{GameConfig, RconConfig},
+    internal::{clock::Clock, rcon::Rcon},
+};
+
+/// Longest time an auto-backup is postponed while the server is busy, unless configured
+const DEFAULT_MAX_POSTPONE: Duration = Duration::from_secs(60 * 60);
+
+/// Time between checks of whether a busy server has become idle
+const BUSY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time to wait for the remote console to connect or respond
+const RCON_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct ServerHooks {
+    rcon: Option<RconConfig>,
+    save_command: Vec<String>,
+    save_delay: Duration,
+    busy_command: Vec<String>,
+    max_postpone: Duration,
+}
+
+impl ServerHooks {
+    /// Hooks of a game config, if it has a server or remote console configured
+    pub fn from_config(gcfg: &GameConfig) -> Option<Self> {
+        if gcfg.server.is_none() && gcfg.rcon.is_none() {
+            return None;
+        }
+
+        let server = gcfg.server.clone().unwrap_or_default();
+
+        Some(Self {
+            rcon: gcfg.rcon.clone(),
+            save_command: server.save_command,
+            save_delay: Duration::from_secs(server.save_delay),
+            busy_command: server.busy_command,
+            max_postpone: server.max_postpone.map_or(DEFAULT_MAX_POSTPONE, Duration::from_secs),
+        })
+    }
+
+    /// Have the server write its world to disk, through its remote console and/or save command,
+    /// and wait for it to finish
+    pub fn save_world(&self, clock: &dyn Clock) -> Result<(), anyhow::Error> {
+        if let Some(rcon) = self.rcon.as_ref() {
+            let response = Rcon::connect(&rcon.address, &rcon.password, RCON_TIMEOUT)
+                .and_then(|mut console| console.command(&rcon.save_command))
+                .with_context(|| format!("Error sending save command to RCON at {}", rcon.address))?;
+
+            info!("Server world saved through RCON: {}", response.trim());
+            wait(clock, Duration::from_secs(rcon.save_delay));
+        }
+
+        if !self.save_command.is_empty() {
+            let success = run(&self.save_command).context("Error running server save command")?;
+            if !success {
+                return Err(anyhow::anyhow!("Server save command failed"));
+            }
+
+            info!("Server world saved");
+            wait(clock, self.save_delay);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the server reports being busy.
+    /// A busy command that cannot be run counts as idle, so that backups are not held up indefinitely.
+    fn is_busy(&self) -> bool {
+        if self.busy_command.is_empty() {
+            return false;
+        }
+
+        run(&self.busy_command).unwrap_or_else(|err| {
+            warn!("Error running server busy command: {err}");
+            false
+        })
+    }
+}
+
+/// Postponement of an auto-backup while the server is busy
+#[derive(Default)]
+pub struct Postponement {
+    since: Option<Instant>,
+    next_check_at: Option<Instant>,
+}
+
+impl Postponement {
+    /// Whether a due auto-backup should wait for the server to become idle.
+    /// The busy command is rerun at most every [`BUSY_CHECK_INTERVAL`], and never past the maximum postponement.
+    pub fn should_wait(&mut self, hooks: &ServerHooks, now: Instant) -> bool {
+        if self.next_check_at.is_some_and(|at| now < at) {
+            return true;
+        }
+
+        let postponed_for = self.since.map_or(Duration::ZERO, |since| now - since);
+
+        if postponed_for < hooks.max_postpone && hooks.is_busy() {
+            if self.since.is_none() {
+                info!("Server is busy, postponing auto-backup");
+                self.since = Some(now);
+            }
+
+            self.next_check_at = Some(now + BUSY_CHECK_INTERVAL);
+            return true;
+        }
+
+        *self = Self::default();
+        false
+    }
+}
+
+fn wait(clock: &dyn Clock, duration: Duration) {
+    let wait_until = clock.now() + duration;
+
+    while clock.now() < wait_until {
+        clock.sleep(wait_until - clock.now());
+    }
+}
+
+/// Run a command, returning whether it succeeded
+fn run(command: &[String]) -> Result<bool, anyhow::Error> {
+    let (program, args) = command.split_first().context("Empty command")?;
+
+    let output = Command::new(program).args(args).stdin(Stdio::null()).output()?;
+
+    Ok(output.status.success())
+}
@@ -0,0 +1,146 @@
+use std::{fmt, time::Duration};
+
+use serde_derive::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::internal::format::{format_bytes, format_duration};
+
+use super::BackupKind;
+
+/// Summary of what happened during one run of the engine
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SessionSummary {
+    /// Identifies the session in the manifests of its backups.
+    /// Missing for sessions recorded before sessions had ids.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "duration_secs")]
+    pub duration: Duration,
+
+    pub auto_backups: usize,
+    pub manual_backups: usize,
+    pub exit_backups: usize,
+    /// Backups created despite warnings, such as missing save dirs
+    #[serde(default)]
+    pub backups_with_warnings: usize,
+    pub restores: usize,
+    pub bytes_archived: u64,
+
+    pub errors: Vec<String>,
+    /// Problems that did not cause errors, such as the watcher falling back to polling
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl SessionSummary {
+    pub fn new(started_at: OffsetDateTime) -> Self {
+        Self {
+            id: Some(format!("{:x}", started_at.unix_timestamp_nanos())),
+            started_at,
+            duration: Duration::ZERO,
+            auto_backups: 0,
+            manual_backups: 0,
+            exit_backups: 0,
+            backups_with_warnings: 0,
+            restores: 0,
+            bytes_archived: 0,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn backups(&self) -> usize {
+        self.auto_backups + self.manual_backups + self.exit_backups
+    }
+
+    pub fn record_backup(&mut self, kind: BackupKind, size: u64) {
+        match kind {
+            BackupKind::Auto => self.auto_backups += 1,
+            BackupKind::Manual => self.manual_backups += 1,
+            BackupKind::Exit => self.exit_backups += 1,
+        }
+
+        self.bytes_archived += size;
+    }
+
+    /// Record the warnings of a backup, if it had any
+    pub fn record_backup_warnings(&mut self, archive_name: &str, warnings: &[String]) {
+        if warnings.is_empty() {
+            return;
+        }
+
+        self.backups_with_warnings += 1;
+        self.warnings
+            .extend(warnings.iter().map(|warning| format!("{archive_name}: {warning}")));
+    }
+
+    pub fn record_restore(&mut self) {
+        self.restores += 1;
+    }
+
+    pub fn record_error(&mut self, error: String) {
+        self.errors.push(error);
+    }
+
+    pub fn record_warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+}
+
+impl fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Session summary:")?;
+        writeln!(f, "  Play time:       {}", format_duration(self.duration))?;
+        writeln!(
+            f,
+            "  Backups created: {} ({} auto, {} manual, {} exit)",
+            self.backups(),
+            self.auto_backups,
+            self.manual_backups,
+            self.exit_backups
+        )?;
+        if self.backups_with_warnings > 0 {
+            writeln!(f, "  With warnings:   {}", self.backups_with_warnings)?;
+        }
+        writeln!(f, "  Restores:        {}", self.restores)?;
+        writeln!(f, "  Total archived:  {}", format_bytes(self.bytes_archived))?;
+
+        if self.errors.is_empty() {
+            write!(f, "  Errors:          none")?;
+        } else {
+            write!(f, "  Errors:          {}", self.errors.len())?;
+
+            for error in self.errors.iter() {
+                write!(f, "\n    - {error}")?;
+            }
+        }
+
+        if !self.warnings.is_empty() {
+            write!(f, "\n  Warnings:        {}", self.warnings.len())?;
+
+            for warning in self.warnings.iter() {
+                write!(f, "\n    - {warning}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// (De)serialize a [`Duration`] as whole seconds
+pub(crate) mod duration_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_secs(u64::deserialize(deserializer)?))
+    }
+}
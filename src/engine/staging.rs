@@ -0,0 +1,81 @@
+//! Staging directory kept between runs, so that the first backup of a session only copies changed files.
+//! A state file records that the staging directory was left as indexed, after a backup completed.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::internal::{hash::hash_crc32, sync::DirIndex};
+
+use super::EngineArgs;
+
+pub const STAGING_STATE_FILENAME: &str = "staging.json";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct StagingState {
+    staging_path: PathBuf,
+    /// Checksum of the game config, as changing it can change what belongs in staging
+    config_crc32: u32,
+    /// Contents of the staging directory of each save dir
+    index: HashMap<String, DirIndex>,
+}
+
+/// State of a staging directory kept between runs
+pub struct KeptStaging {
+    state_path: PathBuf,
+    staging_path: PathBuf,
+    config_crc32: u32,
+}
+
+impl KeptStaging {
+    pub fn new(args: &EngineArgs, staging_path: &Path) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            state_path: args.output_path().join(STAGING_STATE_FILENAME),
+            staging_path: staging_path.to_owned(),
+            config_crc32: hash_crc32(&args.game_config_file_path(), |_| {})?,
+        })
+    }
+
+    /// Index of the staging directory as the previous run left it,
+    /// if it is still in place and was staged with the same game config
+    pub fn load(&self) -> Option<HashMap<String, DirIndex>> {
+        let data = fs::read(&self.state_path).ok()?;
+        let state: StagingState = serde_json::from_slice(&data).ok()?;
+
+        let valid = state.staging_path == self.staging_path
+            && state.config_crc32 == self.config_crc32
+            && self.staging_path.exists();
+
+        valid.then_some(state.index)
+    }
+
+    /// Record that the staging directory is as indexed
+    pub fn save(&self, index: &HashMap<String, DirIndex>) -> Result<(), anyhow::Error> {
+        let state = StagingState {
+            staging_path: self.staging_path.clone(),
+            config_crc32: self.config_crc32,
+            index: index.clone(),
+        };
+
+        let tmp_path = self.state_path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&state)?)?;
+        fs::rename(&tmp_path, &self.state_path)?;
+
+        Ok(())
+    }
+
+    /// Forget the state before the staging directory is changed, so that it is wiped
+    /// at the next start if the change is interrupted
+    pub fn invalidate(&self) -> Result<(), anyhow::Error> {
+        match fs::remove_file(&self.state_path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
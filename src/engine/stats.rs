@@ -0,0 +1,98 @@
+//! Totals of the backups of a game: how much space they take, and how much save data they hold
+
+use std::fs;
+
+use time::{Month, OffsetDateTime, UtcOffset};
+
+use crate::internal::{format::format_bytes, parity::parity_path};
+
+use super::{
+    backups::list_game_backups,
+    manifest::{manifest_path, Manifest},
+    EngineArgs,
+};
+
+/// Backups created in a calendar month
+pub struct MonthStats {
+    pub year: i32,
+    pub month: Month,
+    pub backups: usize,
+    pub physical_size: u64,
+}
+
+#[derive(Default)]
+pub struct BackupStats {
+    pub backups: usize,
+    /// Size on disk of the archives, their manifests and parity data
+    pub physical_size: u64,
+    /// Size of the save files the backups restore, for backups with a manifest
+    pub logical_size: u64,
+    /// Backups without a manifest, whose logical size is unknown
+    pub without_manifest: usize,
+    /// Growth of the backups, oldest month first
+    pub months: Vec<MonthStats>,
+}
+
+impl BackupStats {
+    /// Logical size over physical size, if any backup has a manifest
+    pub fn ratio(&self) -> Option<f64> {
+        (self.logical_size > 0 && self.physical_size > 0).then(|| self.logical_size as f64 / self.physical_size as f64)
+    }
+
+    /// One line summary, for display alongside other information
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} backups, {} on disk", self.backups, format_bytes(self.physical_size));
+
+        if let Some(ratio) = self.ratio() {
+            summary.push_str(&format!(", {} of saves ({ratio:.1}x)", format_bytes(self.logical_size)));
+        }
+
+        summary
+    }
+}
+
+/// Gather the totals of the backups of a game, reading only their manifests
+pub fn backup_stats(args: &EngineArgs) -> Result<BackupStats, anyhow::Error> {
+    let mut backups = list_game_backups(args)?;
+    backups.sort_by_key(|b| b.modified);
+
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let mut stats = BackupStats::default();
+
+    for backup in backups.iter() {
+        let physical_size: u64 = [
+            backup.path.clone(),
+            manifest_path(&backup.path),
+            parity_path(&backup.path),
+        ]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|m| m.len())
+        .sum();
+
+        match Manifest::load_for_archive(&backup.path).ok().flatten() {
+            Some(manifest) => stats.logical_size += manifest.total_size(),
+            None => stats.without_manifest += 1,
+        }
+
+        stats.backups += 1;
+        stats.physical_size += physical_size;
+
+        let modified = OffsetDateTime::from(backup.modified).to_offset(offset);
+
+        match stats.months.last_mut() {
+            Some(month) if month.year == modified.year() && month.month == modified.month() => {
+                month.backups += 1;
+                month.physical_size += physical_size;
+            }
+            _ => stats.months.push(MonthStats {
+                year: modified.year(),
+                month: modified.month(),
+                backups: 1,
+                physical_size,
+            }),
+        }
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,631 @@
+//! Helpers for testing the engine against temporary directories, without external tools
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use filetime::FileTime;
+use serde_derive::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::{
+    config::game::{AutoBackup, CloudPlaceholders, DeletedFiles, GameConfig, GameSaveDir, StagingLocation, VerifyMode},
+    internal::{
+        archive::{ArchiveEntry, Archiver, PasswordRequired},
+        clock::FakeClock,
+        rcon::{self, Packet},
+        shutdown::Shutdown,
+        sync::{SyncEvents, SyncUiHandler},
+    },
+};
+
+use super::{
+    backups::BackupInfo,
+    manifest::{manifest_path, Manifest},
+    ui::StoolUiHandler,
+    BackupKind, Engine, EngineArgs, EngineState,
+};
+
+/// Name of the game in test fixtures
+pub const GAME_NAME: &str = "game";
+
+/// Name of the save directory in test fixtures
+pub const SAVE_DIR_NAME: &str = "main";
+
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+const WAIT_SLEEP_DURATION: Duration = Duration::from_millis(10);
+
+/// UI handler that ignores all events
+pub struct NullUiHandler;
+
+impl SyncUiHandler for NullUiHandler {
+    fn begin_scan(&mut self) {}
+    fn end_scan(&mut self) {}
+    fn begin_prepare(&mut self) {}
+    fn end_prepare(&mut self) {}
+    fn begin_sync(&mut self, _op_count: usize) {}
+    fn sync_progress(&mut self) {}
+    fn end_sync(&mut self) {}
+    fn begin_file(&mut self, _prefix: &str, _filename: &str, _size: u64) {}
+    fn file_progress(&mut self, _bytes: u64) {}
+    fn end_file(&mut self) {}
+}
+
+impl StoolUiHandler for NullUiHandler {
+    fn clear(self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    fn begin_backup(&mut self, _name: &str) {}
+    fn backup_warning(&mut self, _message: &str) {}
+    fn paths_skipped(&mut self, _names: &[String]) {}
+    fn end_backup(&mut self, _success: bool) {}
+    fn begin_staging(&mut self, _count: usize, _bytes: u64) {}
+    fn begin_initial_snapshot(&mut self, _files: usize, _bytes: u64) {}
+    fn begin_stage(&mut self, _name: &str) {}
+    fn end_stage(&mut self) {}
+    fn end_staging(&mut self) {}
+    fn begin_compress(&mut self) {}
+    fn compress_progress(&mut self, _bytes: u64) {}
+    fn end_compress(&mut self) {}
+    fn begin_restore(&mut self, _name: &str) {}
+    fn end_restore(&mut self, _success: bool) {}
+    fn begin_extract(&mut self) {}
+    fn end_extract(&mut self) {}
+    fn begin_restore_sp(&mut self, _name: &str) {}
+    fn end_restore_sp(&mut self) {}
+    fn begin_prune(&mut self) {}
+    fn pruned(&mut self, _name: &str) {}
+    fn end_prune(&mut self) {}
+    fn begin_upload(&mut self, _target: &str, _name: &str, _size: u64) {}
+    fn upload_progress(&mut self, _bytes: u64) {}
+    fn end_upload(&mut self, _success: bool) {}
+    fn tick(&mut self) {}
+}
+
+/// Backup, staging, initial snapshot, restore, prune and upload events recorded by [`RecordingUiHandler`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiEvent {
+    BeginBackup(String),
+    BackupWarning(String),
+    PathsSkipped(Vec<String>),
+    EndBackup(bool),
+    BeginStaging(usize, u64),
+    BeginInitialSnapshot(usize, u64),
+    BeginRestore(String),
+    EndRestore(bool),
+    Pruned(String),
+    BeginUpload(String, String),
+    EndUpload(bool),
+}
+
+/// UI handler that records backup, staging, initial snapshot, restore, prune and upload events
+#[derive(Clone, Default)]
+pub struct RecordingUiHandler {
+    pub events: Arc<Mutex<Vec<UiEvent>>>,
+}
+
+impl RecordingUiHandler {
+    pub fn events(&self) -> Vec<UiEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn record(&self, event: UiEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// Wait until `count` events matching the predicate have been recorded
+    pub fn wait_for(&self, count: usize, predicate: impl Fn(&UiEvent) -> bool) {
+        wait_until(|| self.events().iter().filter(|e| predicate(e)).count() >= count);
+    }
+}
+
+impl SyncUiHandler for RecordingUiHandler {
+    fn begin_scan(&mut self) {}
+    fn end_scan(&mut self) {}
+    fn begin_prepare(&mut self) {}
+    fn end_prepare(&mut self) {}
+    fn begin_sync(&mut self, _op_count: usize) {}
+    fn sync_progress(&mut self) {}
+    fn end_sync(&mut self) {}
+    fn begin_file(&mut self, _prefix: &str, _filename: &str, _size: u64) {}
+    fn file_progress(&mut self, _bytes: u64) {}
+    fn end_file(&mut self) {}
+}
+
+impl StoolUiHandler for RecordingUiHandler {
+    fn clear(self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    fn begin_backup(&mut self, name: &str) {
+        self.record(UiEvent::BeginBackup(name.to_owned()));
+    }
+
+    fn backup_warning(&mut self, message: &str) {
+        self.record(UiEvent::BackupWarning(message.to_owned()));
+    }
+
+    fn paths_skipped(&mut self, names: &[String]) {
+        self.record(UiEvent::PathsSkipped(names.to_vec()));
+    }
+
+    fn end_backup(&mut self, success: bool) {
+        self.record(UiEvent::EndBackup(success));
+    }
+
+    fn begin_staging(&mut self, count: usize, bytes: u64) {
+        self.record(UiEvent::BeginStaging(count, bytes));
+    }
+
+    fn begin_initial_snapshot(&mut self, files: usize, bytes: u64) {
+        self.record(UiEvent::BeginInitialSnapshot(files, bytes));
+    }
+
+    fn begin_stage(&mut self, _name: &str) {}
+    fn end_stage(&mut self) {}
+    fn end_staging(&mut self) {}
+    fn begin_compress(&mut self) {}
+    fn compress_progress(&mut self, _bytes: u64) {}
+    fn end_compress(&mut self) {}
+
+    fn begin_restore(&mut self, name: &str) {
+        self.record(UiEvent::BeginRestore(name.to_owned()));
+    }
+
+    fn end_restore(&mut self, success: bool) {
+        self.record(UiEvent::EndRestore(success));
+    }
+
+    fn begin_extract(&mut self) {}
+    fn end_extract(&mut self) {}
+    fn begin_restore_sp(&mut self, _name: &str) {}
+    fn end_restore_sp(&mut self) {}
+    fn begin_prune(&mut self) {}
+
+    fn pruned(&mut self, name: &str) {
+        self.record(UiEvent::Pruned(name.to_owned()));
+    }
+
+    fn end_prune(&mut self) {}
+
+    fn begin_upload(&mut self, target: &str, name: &str, _size: u64) {
+        self.record(UiEvent::BeginUpload(target.to_owned(), name.to_owned()));
+    }
+
+    fn upload_progress(&mut self, _bytes: u64) {}
+
+    fn end_upload(&mut self, success: bool) {
+        self.record(UiEvent::EndUpload(success));
+    }
+
+    fn tick(&mut self) {}
+}
+
+#[derive(Deserialize, Serialize)]
+struct FakeArchiveFile {
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+/// Archiver storing files in a plain JSON document, so tests do not depend on 7z
+pub struct FakeArchiver;
+
+impl FakeArchiver {
+    fn read(archive_path: &Path) -> Result<Vec<FakeArchiveFile>, anyhow::Error> {
+        Ok(serde_json::from_slice(&fs::read(archive_path)?)?)
+    }
+}
+
+impl Archiver for FakeArchiver {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        let mut files = Vec::new();
+
+        for entry in walkdir::WalkDir::new(src).sort_by_file_name() {
+            let entry = entry?;
+
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            files.push(FakeArchiveFile {
+                path: entry.path().strip_prefix(src)?.to_owned(),
+                data: fs::read(entry.path())?,
+            });
+        }
+
+        if let Some(parent) = archive_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(archive_path, serde_json::to_vec(&files)?)?;
+
+        Ok(())
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        for file in Self::read(archive_path)? {
+            let path = dst.join(&file.path);
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(path, &file.data)?;
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        Ok(Self::read(archive_path)?
+            .into_iter()
+            .map(|file| ArchiveEntry {
+                size: file.data.len() as u64,
+                is_dir: false,
+                crc32: Some(crc32fast::hash(&file.data)),
+                path: file.path,
+            })
+            .collect())
+    }
+
+    fn extension(&self) -> &'static str {
+        "7z"
+    }
+}
+
+/// Archiver that fails to create archives a number of times, as when files are locked by another process
+pub struct FlakyArchiver {
+    failures_left: AtomicU32,
+}
+
+impl FlakyArchiver {
+    pub fn new(failures: u32) -> Self {
+        Self {
+            failures_left: AtomicU32::new(failures),
+        }
+    }
+}
+
+impl Archiver for FlakyArchiver {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        let failed = self
+            .failures_left
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+            .is_ok();
+
+        if failed {
+            return Err(std::io::Error::from(std::io::ErrorKind::ResourceBusy).into());
+        }
+
+        FakeArchiver.create(src, archive_path)
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        FakeArchiver.unpack(archive_path, dst)
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        FakeArchiver.list(archive_path)
+    }
+
+    fn extension(&self) -> &'static str {
+        FakeArchiver.extension()
+    }
+}
+
+/// Archiver that reads archives only with the right password, as for password-protected 7z archives
+pub struct PasswordArchiver {
+    expected: String,
+    password: Mutex<Option<String>>,
+}
+
+impl PasswordArchiver {
+    pub fn new(expected: &str) -> Self {
+        Self {
+            expected: expected.to_owned(),
+            password: Mutex::new(None),
+        }
+    }
+
+    fn check_password(&self, archive_path: &Path) -> Result<(), anyhow::Error> {
+        if self.password.lock().unwrap().as_ref() != Some(&self.expected) {
+            return Err(PasswordRequired {
+                path: archive_path.to_owned(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+impl Archiver for PasswordArchiver {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        FakeArchiver.create(src, archive_path)
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        self.check_password(archive_path)?;
+        FakeArchiver.unpack(archive_path, dst)
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        self.check_password(archive_path)?;
+        FakeArchiver.list(archive_path)
+    }
+
+    fn extension(&self) -> &'static str {
+        FakeArchiver.extension()
+    }
+
+    fn set_password(&self, password: Option<String>) {
+        *self.password.lock().unwrap() = password;
+    }
+}
+
+/// RCON server accepting a single password, recording the commands it receives
+pub struct FakeRconServer {
+    pub address: String,
+    commands: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeRconServer {
+    pub fn start(password: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let commands = Arc::new(Mutex::new(Vec::new()));
+
+        std::thread::spawn({
+            let password = password.to_owned();
+            let commands = commands.clone();
+
+            move || {
+                for mut stream in listener.incoming().map_while(Result::ok) {
+                    Self::serve(&mut stream, &password, &commands).ok();
+                }
+            }
+        });
+
+        Self { address, commands }
+    }
+
+    fn serve(stream: &mut TcpStream, password: &str, commands: &Mutex<Vec<String>>) -> Result<(), anyhow::Error> {
+        let auth = rcon::read_packet(stream)?;
+        let id = if auth.body == password { auth.id } else { -1 };
+
+        // Like real servers, send an empty response value ahead of the authentication response
+        for kind in [rcon::TYPE_RESPONSE_VALUE, rcon::TYPE_AUTH_RESPONSE] {
+            rcon::write_packet(
+                stream,
+                &Packet {
+                    id,
+                    kind,
+                    body: String::new(),
+                },
+            )?;
+        }
+
+        loop {
+            let packet = rcon::read_packet(stream)?;
+            commands.lock().unwrap().push(packet.body);
+
+            let response = Packet {
+                id: packet.id,
+                kind: rcon::TYPE_RESPONSE_VALUE,
+                body: "Saved the game".to_owned(),
+            };
+            rcon::write_packet(stream, &response)?;
+        }
+    }
+
+    pub fn commands(&self) -> Vec<String> {
+        self.commands.lock().unwrap().clone()
+    }
+}
+
+/// A game with a single save directory, with config and data in a temporary directory
+pub struct Fixture {
+    _dir: TempDir,
+    pub save_path: PathBuf,
+    pub clock: Arc<FakeClock>,
+    pub args: EngineArgs,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Self::with_config(|_| {})
+    }
+
+    /// Create a fixture, allowing the game config to be adjusted before it is written
+    pub fn with_config(configure: impl FnOnce(&mut GameConfig)) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        let save_path = root.join("saves");
+        let game_config_path = root.join("games");
+
+        fs::create_dir_all(&save_path).unwrap();
+        fs::create_dir_all(&game_config_path).unwrap();
+
+        let mut config = GameConfig {
+            version: GameConfig::CURRENT_VERSION,
+            grace_time: 0,
+            max_backup_duration: None,
+            copy_latest_to_paths: Vec::new(),
+            copy_latest_keep: None,
+            copy_latest_name: None,
+            copy_latest_subfolder: false,
+            targets: BTreeMap::new(),
+            inspect: None,
+            staging: StagingLocation::Auto,
+            keep_staging: false,
+            temp_path: None,
+            verify: VerifyMode::Standard,
+            cloud_placeholders: CloudPlaceholders::Hydrate,
+            overwrite_read_only: false,
+            deleted_files: DeletedFiles::Remove,
+            atomic_restore: false,
+            recycle_keep_days: None,
+            description_templates: Vec::new(),
+            trigger_file: None,
+            steam_app_id: None,
+            server: None,
+            rcon: None,
+            partial_backups: false,
+            delta: None,
+            command: None,
+            working_dir: None,
+            args_file: None,
+            env: BTreeMap::new(),
+            wait_for_process: None,
+            auto_backup: AutoBackup {
+                enabled: false,
+                min_interval: 0,
+                snapshot_every_save: false,
+                keep_last: None,
+                collapse_sessions_after_days: None,
+                adaptive_interval: None,
+                description_template: None,
+            },
+            save_dirs: BTreeMap::from([(
+                SAVE_DIR_NAME.to_owned(),
+                GameSaveDir {
+                    path: save_path.clone(),
+                    include: None,
+                    ignore: None,
+                    per_user: false,
+                    streaming: false,
+                    grace_time: None,
+                },
+            )]),
+            save_files: Vec::new(),
+            save_registry_keys: Vec::new(),
+            change_indicator_paths: Vec::new(),
+        };
+
+        configure(&mut config);
+
+        let clock = Arc::new(FakeClock::new());
+
+        let args = EngineArgs {
+            name: GAME_NAME.to_owned(),
+            game_config_path,
+            data_path: root.join("data"),
+            use_index: false,
+            strict_config: true,
+            staging_root: None,
+            backup_root: None,
+            cold_root: None,
+            cold_after: None,
+            archiver: Arc::new(FakeArchiver),
+            parity_percent: None,
+            clock: clock.clone(),
+            dry_run: false,
+            overlay_status: false,
+            check_interval: Duration::from_secs(1),
+            poll_interval: Duration::from_secs(2),
+            temp_root: None,
+        };
+
+        config.write(&args.game_config_file_path()).unwrap();
+
+        Self {
+            _dir: dir,
+            save_path,
+            clock,
+            args,
+        }
+    }
+
+    pub fn config(&self) -> GameConfig {
+        GameConfig::from_file(&self.args.game_config_file_path(), true).unwrap()
+    }
+
+    pub fn write_save(&self, rel_path: &str, contents: &str) {
+        let path = self.save_path.join(rel_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    pub fn read_save(&self, rel_path: &str) -> Option<String> {
+        fs::read_to_string(self.save_path.join(rel_path)).ok()
+    }
+
+    /// Start the engine and wait for it to be running
+    pub fn start(&self) -> (Engine, RecordingUiHandler) {
+        let ui = RecordingUiHandler::default();
+
+        (self.start_with(ui.clone()), ui)
+    }
+
+    /// Start the engine with a UI handler and wait for it to be running
+    pub fn start_with(&self, ui: impl StoolUiHandler) -> Engine {
+        let shutdown = Shutdown::new();
+
+        let engine = super::run(self.args.clone(), shutdown, ui).unwrap();
+
+        let control = engine.control();
+        wait_until(|| control.state() == EngineState::Running);
+
+        engine
+    }
+}
+
+/// Shut down an engine and wait for it to finish
+pub fn stop(engine: Engine) {
+    engine.control().shutdown();
+    engine.join();
+}
+
+/// Poll a condition until it holds, panicking if it takes too long
+pub fn wait_until(condition: impl Fn() -> bool) {
+    let started_at = Instant::now();
+
+    while !condition() {
+        assert!(started_at.elapsed() < WAIT_TIMEOUT, "Timed out waiting for condition");
+        std::thread::sleep(WAIT_SLEEP_DURATION);
+    }
+}
+
+/// Write an empty archive with a manifest listing the given save files, as if it was created `age` ago
+pub fn write_backup(backup_path: &Path, name: &str, age: Duration, files: &[&str]) -> BackupInfo {
+    let contents = tempfile::tempdir().unwrap();
+
+    for file in files {
+        let path = contents.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "save").unwrap();
+    }
+
+    let manifest = Manifest::build(
+        GAME_NAME,
+        BackupKind::Manual,
+        contents.path(),
+        None,
+        &SyncEvents::discard(),
+    )
+    .unwrap();
+
+    let path = backup_path.join(name);
+    fs::create_dir_all(backup_path).unwrap();
+    fs::write(&path, "").unwrap();
+    manifest.write(&manifest_path(&path)).unwrap();
+
+    let modified = SystemTime::now() - age;
+    filetime::set_file_mtime(&path, FileTime::from_system_time(modified)).unwrap();
+
+    BackupInfo {
+        name: name.to_owned(),
+        path,
+        modified,
+    }
+}
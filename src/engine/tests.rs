@@ -0,0 +1,2468 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    config::game::{
+        BackupTarget, DeletedFiles, DeltaConfig, GameConfig, GameSaveFile, RconConfig, SaveInspect, ServerConfig,
+        StagingLocation, VerifyMode,
+    },
+    internal::{
+        archive::{ArchiveEntry, Archiver, MultiFormat, PasswordRequired},
+        clock::SystemClock,
+        disk::check_free_space,
+        encryption::Decrypting,
+        shutdown::Shutdown,
+        sync::{SyncEvent, SyncEvents},
+        tar_zstd::TarZstd,
+    },
+};
+
+use super::{
+    adopt::adopt_backups,
+    annotations::Annotations,
+    backups::{delete_game_backup, list_backup_contents, list_game_backups, EXIT_BACKUP_DESCRIPTION},
+    control::{self, ControlRequest, ControlResponse, NotRunning, CONTROL_FILENAME},
+    current::CurrentSaves,
+    delta::DeltaBaseInUse,
+    diff::diff_backups,
+    extract::extract_backup,
+    history::{game_sessions, BackupFailure, History, HistoryEvent},
+    manifest::Manifest,
+    overlay::{OVERLAY_JSON_FILENAME, OVERLAY_TEXT_FILENAME},
+    partial::BaselineInUse,
+    remote::RemoteEngine,
+    restore::{find_conflicts, restore_requests, ConflictResolution},
+    retention::collapse_old_sessions,
+    retry,
+    staging::STAGING_STATE_FILENAME,
+    stats::backup_stats,
+    testing::{
+        stop, wait_until, FakeArchiver, FakeRconServer, Fixture, FlakyArchiver, NullUiHandler, PasswordArchiver,
+        RecordingUiHandler, UiEvent, SAVE_DIR_NAME,
+    },
+    ui::MultiUiHandler,
+    verify::{verify_backups, VerifyOutcome},
+    BackupKind, BackupRequest, BackupTiming, EngineState,
+};
+
+fn backup_name(n: u32, description: &str) -> String {
+    format!("2025-01-01 00-00-{n:02} {description}.7z")
+}
+
+fn create_backup(fixture_engine: &super::Engine, archive_name: &str, kind: BackupKind) {
+    fixture_engine
+        .control()
+        .send(BackupRequest::CreateBackup {
+            archive_name: archive_name.to_owned(),
+            kind,
+        })
+        .unwrap();
+}
+
+fn archive_files(fixture: &Fixture, archive_name: &str) -> Vec<PathBuf> {
+    let mut files: Vec<_> = fixture
+        .args
+        .archiver
+        .list(&fixture.args.backup_path().join(archive_name))
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path)
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn save_path(rel_path: &str) -> PathBuf {
+    Path::new(SAVE_DIR_NAME).join(rel_path)
+}
+
+#[test]
+fn backup_archives_save_files_with_manifest() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+    fixture.write_save("sub/slot2.sav", "two");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(
+        ui.events(),
+        vec![
+            UiEvent::BeginBackup(name.clone()),
+            UiEvent::BeginStaging(1, 6),
+            UiEvent::BeginInitialSnapshot(2, 6),
+            UiEvent::EndBackup(true)
+        ]
+    );
+
+    assert_eq!(
+        archive_files(&fixture, &name),
+        vec![save_path("slot1.sav"), save_path("sub/slot2.sav")]
+    );
+
+    let backups = list_game_backups(&fixture.args).unwrap();
+    assert_eq!(backups.len(), 1);
+
+    let manifest = Manifest::load_for_archive(&backups[0].path).unwrap().unwrap();
+    assert_eq!(manifest.kind, BackupKind::Manual);
+    assert_eq!(manifest.files.len(), 2);
+    assert_eq!(manifest.total_size(), 6);
+
+    stop(engine);
+}
+
+#[test]
+#[cfg(not(all(windows, feature = "registry")))]
+fn registry_keys_are_skipped_where_unsupported() {
+    let fixture = Fixture::with_config(|c| c.save_registry_keys = vec![r"HKCU\Software\Game".to_owned()]);
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert!(ui.events().contains(&UiEvent::EndBackup(true)));
+    assert_eq!(archive_files(&fixture, &name), vec![save_path("slot1.sav")]);
+
+    stop(engine);
+}
+
+#[test]
+fn data_roots_hold_staging_and_backups() {
+    let roots = tempfile::tempdir().unwrap();
+    let mut fixture = Fixture::with_config(|config| config.keep_staging = true);
+    fixture.args.staging_root = Some(roots.path().join("ssd"));
+    fixture.args.backup_root = Some(roots.path().join("hdd"));
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    stop(engine);
+
+    let game_name = &fixture.args.name;
+    assert_eq!(
+        fixture.args.backup_path(),
+        roots.path().join("hdd").join(game_name).join("backups")
+    );
+    assert!(fixture.args.backup_path().join(&name).is_file());
+    assert!(fixture
+        .args
+        .staging_path()
+        .starts_with(roots.path().join("ssd").join(game_name)));
+    assert!(fixture.args.staging_path().is_dir());
+    assert!(!fixture.args.output_path().join("backups").exists());
+}
+
+#[test]
+fn inspector_metadata_is_stored_in_manifest() {
+    let fixture = Fixture::with_config(|config| {
+        config.inspect = Some(SaveInspect {
+            inspector: "json".to_owned(),
+            files: vec!["**/*.json".to_owned()],
+            fields: [
+                ("Character", "/player/name"),
+                ("Level", "/player/level"),
+                ("Missing", "/none"),
+            ]
+            .into_iter()
+            .map(|(label, pointer)| (label.to_owned(), pointer.to_owned()))
+            .collect(),
+        })
+    });
+    fixture.write_save("profile.json", r#"{"player": {"name": "Ada", "level": 12}}"#);
+    fixture.write_save("slot1.sav", "not json");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let manifest = Manifest::load_for_archive(&fixture.args.backup_path().join(&name))
+        .unwrap()
+        .unwrap();
+
+    let metadata: Vec<_> = manifest
+        .metadata
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(metadata, vec![("Character", "Ada"), ("Level", "12")]);
+}
+
+#[test]
+fn auto_backups_are_described_from_save_metadata() {
+    let fixture = Fixture::with_config(|config| {
+        config.inspect = Some(SaveInspect {
+            inspector: "regex".to_owned(),
+            files: Vec::new(),
+            fields: [("Character", r"name=(\w+)"), ("Level", r"level=(\d+)")]
+                .into_iter()
+                .map(|(label, pattern)| (label.to_owned(), pattern.to_owned()))
+                .collect(),
+        });
+        config.auto_backup.description_template = Some("{Character} lvl {Level}".to_owned());
+    });
+    fixture.write_save("slot1.sav", "name=Ada\nlevel=43\n");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(1, "Auto"), BackupKind::Auto);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    // Without values for every field, the plain description is kept
+    fixture.write_save("slot1.sav", "name=Ada\n");
+    create_backup(&engine, &backup_name(2, "Auto"), BackupKind::Auto);
+    ui.wait_for(2, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let mut backups = list_game_backups(&fixture.args).unwrap();
+    backups.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // The change event of the second write may only be seen once the backup has started,
+    // which is followed by an exit backup
+    backups.retain(|b| b.description() != Some(EXIT_BACKUP_DESCRIPTION));
+
+    let names: Vec<_> = backups.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, vec![backup_name(1, "Auto - Ada lvl 43"), backup_name(2, "Auto")]);
+
+    // Named auto-backups are still subject to auto-backup retention
+    assert!(backups.iter().all(|b| b.is_auto()));
+}
+
+#[test]
+fn extract_verifies_against_manifest() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let dst = tempfile::tempdir().unwrap();
+    let report = extract_backup(&fixture.args, &name, dst.path(), &SyncEvents::discard()).unwrap();
+
+    assert!(report.manifest.is_some());
+    assert!(report.mismatches.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(dst.path().join(save_path("slot1.sav"))).unwrap(),
+        "one"
+    );
+}
+
+#[test]
+fn zstd_archives_round_trip() {
+    let mut fixture = Fixture::new();
+    fixture.args.archiver = Arc::new(TarZstd {
+        level: 3,
+        threads: 2,
+        long_distance_matching: true,
+        store_only: vec!["**/*.pak".to_owned()],
+    });
+    fixture.write_save("slot1.sav", "one");
+    fixture.write_save("sub/slot2.sav", "two");
+
+    let asset = "already compressed asset ".repeat(100);
+    fixture.write_save("sub/assets.pak", &asset);
+
+    let (engine, ui) = fixture.start();
+
+    let name = "2025-01-01 00-00-00 Manual.tar.zst";
+    create_backup(&engine, name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    assert_eq!(list_game_backups(&fixture.args).unwrap()[0].name, name);
+
+    let dst = tempfile::tempdir().unwrap();
+    let report = extract_backup(&fixture.args, name, dst.path(), &SyncEvents::discard()).unwrap();
+
+    assert!(report.mismatches.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(dst.path().join(save_path("sub/slot2.sav"))).unwrap(),
+        "two"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dst.path().join(save_path("sub/assets.pak"))).unwrap(),
+        asset
+    );
+
+    // Store-only files appear uncompressed in the archive
+    let archive = std::fs::read(fixture.args.backup_path().join(name)).unwrap();
+    assert!(archive.windows(asset.len()).any(|w| w == asset.as_bytes()));
+}
+
+#[test]
+fn backups_past_max_duration_store_files_without_compression() {
+    let mut fixture = Fixture::with_config(|config| config.max_backup_duration = Some(0));
+    fixture.args.archiver = Arc::new(TarZstd {
+        level: 3,
+        threads: 0,
+        long_distance_matching: false,
+        store_only: Vec::new(),
+    });
+
+    let save = "very compressible save data ".repeat(100);
+    fixture.write_save("slot1.sav", &save);
+
+    let (engine, ui) = fixture.start();
+
+    let name = "2025-01-01 00-00-00 Manual.tar.zst";
+    create_backup(&engine, name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let archive = std::fs::read(fixture.args.backup_path().join(name)).unwrap();
+    assert!(archive.windows(save.len()).any(|w| w == save.as_bytes()));
+
+    let dst = tempfile::tempdir().unwrap();
+    let report = extract_backup(&fixture.args, name, dst.path(), &SyncEvents::discard()).unwrap();
+
+    assert!(report.mismatches.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(dst.path().join(save_path("slot1.sav"))).unwrap(),
+        save
+    );
+}
+
+#[test]
+fn multi_ui_handler_forwards_to_all_handlers() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+
+    let first = RecordingUiHandler::default();
+    let second = RecordingUiHandler::default();
+
+    let engine = fixture.start_with(MultiUiHandler::new(first.clone(), second.clone()));
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    first.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let expected = vec![
+        UiEvent::BeginBackup(name),
+        UiEvent::BeginStaging(1, 3),
+        UiEvent::BeginInitialSnapshot(1, 3),
+        UiEvent::EndBackup(true),
+    ];
+    assert_eq!(first.events(), expected);
+    assert_eq!(second.events(), expected);
+}
+
+#[test]
+fn restore_reverts_changes_and_removes_new_files() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "original");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    fixture.write_save("slot1.sav", "changed");
+    fixture.write_save("slot2.sav", "new");
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: name,
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert!(ui.events().contains(&UiEvent::EndRestore(true)));
+    assert_eq!(fixture.read_save("slot1.sav").as_deref(), Some("original"));
+    assert_eq!(fixture.read_save("slot2.sav"), None);
+
+    stop(engine);
+}
+
+#[test]
+fn restore_keeps_newer_save_files_resolved_to_be_kept() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "original one");
+    fixture.write_save("slot2.sav", "original two");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    fixture.write_save("slot1.sav", "newer one");
+    fixture.write_save("slot2.sav", "newer two");
+
+    let later = filetime::FileTime::from_unix_time(filetime::FileTime::now().unix_seconds() + 60, 0);
+    for file in ["slot1.sav", "slot2.sav"] {
+        filetime::set_file_mtime(fixture.save_path.join(file), later).unwrap();
+    }
+
+    let conflicts = find_conflicts(&fixture.args, &name, None).unwrap();
+    assert_eq!(
+        conflicts.iter().map(|c| c.path.clone()).collect::<Vec<_>>(),
+        vec![save_path("slot1.sav"), save_path("slot2.sav")]
+    );
+
+    let resolved: Vec<_> = conflicts
+        .into_iter()
+        .zip([ConflictResolution::Keep, ConflictResolution::Overwrite])
+        .collect();
+
+    for request in restore_requests(name, None, &resolved, "7z") {
+        engine.control().send(request).unwrap();
+    }
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert!(ui.events().contains(&UiEvent::EndRestore(true)));
+    assert_eq!(fixture.read_save("slot1.sav").as_deref(), Some("newer one"));
+    assert_eq!(fixture.read_save("slot2.sav").as_deref(), Some("original two"));
+
+    stop(engine);
+}
+
+#[test]
+fn restore_moves_deleted_files_to_recycle_directory() {
+    let fixture = Fixture::with_config(|config| config.deleted_files = DeletedFiles::Recycle);
+    fixture.write_save("slot1.sav", "original");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    fixture.write_save("sub/slot2.sav", "new");
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: name,
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    stop(engine);
+
+    assert_eq!(fixture.read_save("sub/slot2.sav"), None);
+
+    let batches: Vec<_> = std::fs::read_dir(fixture.args.output_path().join("recycle"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+
+    assert_eq!(batches.len(), 1);
+    assert_eq!(
+        std::fs::read_to_string(batches[0].join(SAVE_DIR_NAME).join("sub/slot2.sav")).unwrap(),
+        "new"
+    );
+}
+
+#[test]
+fn current_saves_follow_backups_restores_and_changes() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "original");
+
+    let current = || CurrentSaves::load(&fixture.args.output_path()).unwrap();
+
+    let (engine, ui) = fixture.start();
+    assert!(current().is_none());
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(current().unwrap().to_string(), format!("derived from '{name}'"));
+
+    fixture.write_save("slot1.sav", "changed");
+    wait_until(|| current().unwrap().changed);
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: name.clone(),
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    stop(engine);
+
+    let current = current().unwrap();
+    assert_eq!((current.archive, current.changed), (name, false));
+}
+
+#[test]
+fn large_files_are_stored_as_deltas_and_reconstructed_on_restore() {
+    let fixture = Fixture::with_config(|config| {
+        config.delta = Some(DeltaConfig {
+            min_size: Some(1024),
+            full_every: None,
+        })
+    });
+
+    // Data that does not compress well on its own
+    let mut seed = 1u32;
+    let mut world: Vec<u8> = (0..64 * 1024)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 24) as u8
+        })
+        .collect();
+    std::fs::write(fixture.save_path.join("world.sav"), &world).unwrap();
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    world[1000..1010].copy_from_slice(b"0123456789");
+    std::fs::write(fixture.save_path.join("world.sav"), &world).unwrap();
+
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(
+        archive_files(&fixture, &backup_name(1, "Manual")),
+        [save_path("world.sav.stool-delta")]
+    );
+
+    let manifest = Manifest::load_for_archive(&fixture.args.backup_path().join(backup_name(1, "Manual")))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        manifest.files[0].delta_base.as_deref(),
+        Some(backup_name(0, "Manual").as_str())
+    );
+
+    std::fs::write(fixture.save_path.join("world.sav"), "overwritten").unwrap();
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: backup_name(1, "Manual"),
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert!(ui.events().contains(&UiEvent::EndRestore(true)));
+    assert_eq!(std::fs::read(fixture.save_path.join("world.sav")).unwrap(), world);
+
+    stop(engine);
+
+    // The backup holding the full copy is kept while the delta against it is
+    let backups = list_game_backups(&fixture.args).unwrap();
+    let full = backups.iter().find(|b| b.name == backup_name(0, "Manual")).unwrap();
+    let err = delete_game_backup(&fixture.args, full).unwrap_err();
+    assert!(err.is::<DeltaBaseInUse>());
+}
+
+#[test]
+fn restore_of_subpath_leaves_other_files_alone() {
+    let fixture = Fixture::new();
+    fixture.write_save("a.sav", "a1");
+    fixture.write_save("b.sav", "b1");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    // Changed contents differ in size, as files written in quick succession may share a modification time
+    fixture.write_save("a.sav", "a2-changed");
+    fixture.write_save("b.sav", "b2-changed");
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: name,
+            only: Some(save_path("a.sav")),
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert_eq!(fixture.read_save("a.sav").as_deref(), Some("a1"));
+    assert_eq!(fixture.read_save("b.sav").as_deref(), Some("b2-changed"));
+
+    stop(engine);
+}
+
+#[test]
+fn filters_select_backed_up_files() {
+    let fixture = Fixture::with_config(|config| {
+        let save_dir = config.save_dirs.get_mut(SAVE_DIR_NAME).unwrap();
+        save_dir.include = Some(vec!["*.sav".to_owned(), "*.tmp".to_owned()]);
+        save_dir.ignore = Some(vec!["cache.tmp".to_owned()]);
+    });
+
+    fixture.write_save("slot1.sav", "save");
+    fixture.write_save("notes.txt", "not included");
+    fixture.write_save("cache.tmp", "ignored");
+    fixture.write_save("other.tmp", "included");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(
+        archive_files(&fixture, &name),
+        vec![save_path("other.tmp"), save_path("slot1.sav")]
+    );
+
+    stop(engine);
+}
+
+#[test]
+fn retention_prunes_only_old_auto_backups() {
+    let fixture = Fixture::with_config(|config| config.auto_backup.keep_last = Some(2));
+    fixture.write_save("slot1.sav", "save");
+
+    let (engine, ui) = fixture.start();
+
+    let names = [
+        backup_name(0, "Auto"),
+        backup_name(1, "Manual"),
+        backup_name(2, "Auto"),
+        backup_name(3, "Auto"),
+    ];
+
+    for name in names.iter() {
+        let kind = if name.ends_with("Auto.7z") {
+            BackupKind::Auto
+        } else {
+            BackupKind::Manual
+        };
+
+        create_backup(&engine, name, kind);
+    }
+
+    ui.wait_for(names.len(), |e| matches!(e, UiEvent::EndBackup(_)));
+
+    // Pruning happens after a backup is completed
+    wait_until(|| list_game_backups(&fixture.args).unwrap().len() == 3);
+
+    let mut remaining: Vec<_> = list_game_backups(&fixture.args)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    remaining.sort();
+
+    assert_eq!(remaining, names[1..].to_vec());
+
+    ui.wait_for(1, |e| matches!(e, UiEvent::Pruned(_)));
+    assert!(ui.events().contains(&UiEvent::Pruned(names[0].clone())));
+
+    stop(engine);
+}
+
+#[test]
+fn retention_keeps_pinned_backups() {
+    let fixture = Fixture::with_config(|config| config.auto_backup.keep_last = Some(1));
+    fixture.write_save("slot1.sav", "save");
+
+    let names = [backup_name(0, "Auto"), backup_name(1, "Auto"), backup_name(2, "Auto")];
+
+    let mut annotations = Annotations::load(&fixture.args.output_path()).unwrap();
+    annotations.set_pinned(&names[0], true);
+    annotations.save().unwrap();
+
+    let (engine, ui) = fixture.start();
+
+    for name in names.iter() {
+        create_backup(&engine, name, BackupKind::Auto);
+    }
+
+    ui.wait_for(names.len(), |e| matches!(e, UiEvent::EndBackup(_)));
+
+    wait_until(|| list_game_backups(&fixture.args).unwrap().len() == 2);
+
+    let mut remaining: Vec<_> = list_game_backups(&fixture.args)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    remaining.sort();
+
+    assert_eq!(remaining, vec![names[0].clone(), names[2].clone()]);
+
+    stop(engine);
+}
+
+#[test]
+fn backups_are_grouped_by_session() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "save");
+
+    for (session, count) in [(0, 2), (1, 1)] {
+        let (engine, ui) = fixture.start();
+
+        for n in 0..count {
+            create_backup(&engine, &backup_name(session * 10 + n, "Manual"), BackupKind::Manual);
+        }
+
+        ui.wait_for(count as usize, |e| matches!(e, UiEvent::EndBackup(_)));
+
+        stop(engine);
+    }
+
+    let groups = game_sessions(&fixture.args).unwrap();
+
+    let backup_counts: Vec<_> = groups.iter().map(|g| g.backups.len()).collect();
+    assert_eq!(backup_counts, vec![1, 2]);
+
+    for group in groups.iter() {
+        assert!(group.session.is_some());
+
+        for backup in group.backups.iter() {
+            let manifest = Manifest::load_for_archive(&backup.path).unwrap().unwrap();
+            assert_eq!(manifest.session, group.session_id);
+        }
+    }
+}
+
+#[test]
+fn collapsing_old_sessions_keeps_final_and_pinned_backups() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "save");
+
+    let names = [backup_name(0, "Auto"), backup_name(1, "Auto"), backup_name(2, "Exit")];
+
+    let mut annotations = Annotations::load(&fixture.args.output_path()).unwrap();
+    annotations.set_pinned(&names[0], true);
+    annotations.save().unwrap();
+
+    let (engine, ui) = fixture.start();
+
+    for name in names.iter() {
+        create_backup(&engine, name, BackupKind::Auto);
+    }
+
+    ui.wait_for(names.len(), |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    collapse_old_sessions(&fixture.args, Duration::ZERO, &mut NullUiHandler).unwrap();
+
+    let mut remaining: Vec<_> = list_game_backups(&fixture.args)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    remaining.sort();
+
+    assert_eq!(remaining, vec![names[0].clone(), names[2].clone()]);
+}
+
+#[test]
+fn auto_backup_waits_for_grace_time_and_min_interval() {
+    let fixture = Fixture::with_config(|config| {
+        config.grace_time = 30;
+        config.auto_backup.enabled = true;
+        config.auto_backup.min_interval = 600;
+    });
+
+    let (engine, ui) = fixture.start();
+    let is_end_backup = |e: &UiEvent| matches!(e, UiEvent::EndBackup(_));
+
+    // A change is backed up only once grace time has passed
+    fixture.write_save("slot1.sav", "one");
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(ui.events().is_empty());
+
+    let control = engine.control();
+    wait_until(|| control.pending_changes().is_some());
+    assert_eq!(
+        control.pending_changes().unwrap().auto_backup_in,
+        Some(Duration::from_secs(30))
+    );
+
+    fixture.clock.advance(Duration::from_secs(31));
+    ui.wait_for(1, is_end_backup);
+    wait_until(|| control.pending_changes().is_none());
+
+    // The next change is backed up only once the minimum interval has passed
+    fixture.write_save("slot1.sav", "two");
+    std::thread::sleep(Duration::from_millis(200));
+    fixture.clock.advance(Duration::from_secs(31));
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(ui.events().iter().filter(|e| is_end_backup(e)).count(), 1);
+    assert_eq!(
+        control.pending_changes().unwrap().auto_backup_in,
+        Some(Duration::from_secs(569))
+    );
+    assert_eq!(
+        control.backup_timing(),
+        BackupTiming {
+            last_backup_ago: Some(Duration::from_secs(31)),
+            auto_backup_possible_in: Some(Duration::from_secs(569)),
+        }
+    );
+
+    fixture.clock.advance(Duration::from_secs(600));
+    ui.wait_for(2, is_end_backup);
+
+    stop(engine);
+}
+
+#[test]
+fn identical_pending_requests_are_coalesced() {
+    let fixture = Fixture::with_config(|config| config.grace_time = 30);
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+    let is_end_backup = |e: &UiEvent| matches!(e, UiEvent::EndBackup(_));
+
+    // The first request is held back by grace time, while further requests queue up behind it
+    fixture.write_save("slot1.sav", "one");
+    wait_until(|| control.pending_changes().is_some());
+    std::thread::sleep(Duration::from_millis(200));
+    create_backup(&engine, &backup_name(1, "quick"), BackupKind::Manual);
+    wait_until(|| control.is_busy());
+
+    create_backup(&engine, &backup_name(2, "quick"), BackupKind::Manual);
+    create_backup(&engine, &backup_name(2, "quick"), BackupKind::Manual);
+
+    // Late change events may restart grace time
+    wait_until(|| {
+        fixture.clock.advance(Duration::from_secs(31));
+        ui.events().iter().filter(|e| is_end_backup(e)).count() >= 2
+    });
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(ui.events().iter().filter(|e| is_end_backup(e)).count(), 2);
+
+    stop(engine);
+}
+
+#[test]
+fn transient_backup_errors_are_retried_with_backoff() {
+    let mut fixture = Fixture::with_config(|config| {
+        config.auto_backup.enabled = true;
+        config.auto_backup.min_interval = 600;
+    });
+
+    // Failing more often than retried, then succeeding
+    fixture.args.archiver = Arc::new(FlakyArchiver::new(retry::MAX_RETRIES + 1));
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+    let history = History::new(&fixture.args.output_path(), false);
+
+    let failures = || -> Vec<BackupFailure> {
+        history
+            .read()
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| match entry.event {
+                HistoryEvent::BackupFailed(failure) => Some(failure),
+                _ => None,
+            })
+            .collect()
+    };
+
+    fixture.write_save("slot1.sav", "one");
+
+    // Retries wait for backoff
+    wait_until(|| {
+        fixture.clock.advance(Duration::from_secs(1));
+        !failures().is_empty()
+    });
+
+    let failure = &failures()[0];
+    assert_eq!(failure.attempts, retry::MAX_RETRIES + 1);
+    assert!(failure.will_retry_at_next_interval);
+    // Every failed attempt ends, including the last
+    ui.wait_for(retry::MAX_RETRIES as usize + 1, |e| *e == UiEvent::EndBackup(false));
+    assert_eq!(
+        ui.events().iter().filter(|e| **e == UiEvent::EndBackup(false)).count(),
+        retry::MAX_RETRIES as usize + 1
+    );
+
+    // Changes are left pending, and backed up at the next interval
+    assert!(control.pending_changes().is_some());
+
+    fixture.clock.advance(Duration::from_secs(600));
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    assert_eq!(list_game_backups(&fixture.args).unwrap().len(), 1);
+
+    stop(engine);
+}
+
+#[test]
+fn shutdown_cuts_retry_backoff_short() {
+    let mut fixture = Fixture::new();
+    fixture.args.archiver = Arc::new(FlakyArchiver::new(retry::MAX_RETRIES + 1));
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+    let history = History::new(&fixture.args.output_path(), false);
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(false));
+
+    // The clock is never advanced, so the backoff only ends early
+    engine.control().shutdown();
+    wait_until(|| engine.has_shut_down());
+
+    let attempts: Vec<_> = history
+        .read()
+        .unwrap()
+        .into_iter()
+        .filter_map(|entry| match entry.event {
+            HistoryEvent::BackupFailed(failure) if failure.archive_name == name => Some(failure.attempts),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(attempts, vec![1]);
+
+    engine.join();
+}
+
+#[test]
+fn failed_backups_are_not_reported_as_running() {
+    let mut fixture = Fixture::new();
+    fixture.args.archiver = Arc::new(FlakyArchiver::new(retry::MAX_RETRIES + 1));
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+
+    // Retries wait for backoff
+    wait_until(|| {
+        fixture.clock.advance(Duration::from_secs(1));
+        ui.events().iter().filter(|e| **e == UiEvent::EndBackup(false)).count() > retry::MAX_RETRIES as usize
+    });
+
+    wait_until(|| !control.is_busy());
+    wait_until(|| control.snapshot().action.is_none());
+    assert!(!ui.events().contains(&UiEvent::EndBackup(true)));
+
+    stop(engine);
+}
+
+#[test]
+fn control_socket_reports_watch_activity() {
+    let fixture = Fixture::new();
+    let (engine, _ui) = fixture.start();
+
+    let watches = || match control::request(&fixture.args.output_path(), ControlRequest::Watches).unwrap() {
+        ControlResponse::Watches(watches) => watches,
+        response => panic!("Unexpected response: {response:?}"),
+    };
+
+    let initial = watches();
+    assert_eq!(initial.len(), 1);
+    assert_eq!(initial[0].path, fixture.save_path);
+    assert_eq!(initial[0].events, 0);
+
+    fixture.write_save("slot1.sav", "one");
+    wait_until(|| watches()[0].events > 0);
+    assert!(watches()[0].last_event_at.is_some());
+
+    stop(engine);
+
+    // The control file is removed once the engine has shut down
+    wait_until(|| !fixture.args.output_path().join(CONTROL_FILENAME).exists());
+}
+
+#[test]
+fn control_socket_reports_engine_snapshot() {
+    let fixture = Fixture::new();
+    let (engine, ui) = fixture.start();
+
+    let status = || match control::request(&fixture.args.output_path(), ControlRequest::Snapshot).unwrap() {
+        ControlResponse::Snapshot(status) => status,
+        response => panic!("Unexpected response: {response:?}"),
+    };
+
+    let initial = status();
+    assert!(initial.action.is_none());
+    assert!(initial.queue.is_empty());
+    assert!(initial.pending_changes.is_none());
+
+    fixture.write_save("slot1.sav", "one");
+    wait_until(|| status().pending_changes.is_some());
+
+    create_backup(&engine, &backup_name(1, "manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    wait_until(|| status().action.is_none());
+    assert_eq!(status().session.manual_backups, 1);
+
+    stop(engine);
+
+    // Once the engine is gone, it is reported as not running
+    wait_until(|| !fixture.args.output_path().join(CONTROL_FILENAME).exists());
+    let err = control::request(&fixture.args.output_path(), ControlRequest::Snapshot).unwrap_err();
+    assert!(err.is::<NotRunning>());
+}
+
+#[test]
+fn engine_snapshot_round_trips_through_json() {
+    let fixture = Fixture::new();
+    let (engine, _ui) = fixture.start();
+
+    fixture.write_save("slot1.sav", "one");
+    wait_until(|| engine.control().snapshot().pending_changes.is_some());
+
+    let json = serde_json::to_value(engine.control().snapshot()).unwrap();
+    assert_eq!(json["state"], "running");
+    assert!(json["pending-changes"].is_object());
+    assert!(json["backup-timing"].is_object());
+    assert!(json["watcher-fallback"].is_null());
+    assert_eq!(json["session"]["manual-backups"], 0);
+
+    let snapshot: super::EngineSnapshot = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(snapshot.state, EngineState::Running);
+    assert_eq!(serde_json::to_value(&snapshot).unwrap(), json);
+
+    stop(engine);
+}
+
+#[test]
+fn sync_ui_events_keep_their_wire_format() {
+    let event = super::ui::UiEvent::Sync(SyncEvent::BeginSync { op_count: 2 });
+
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json, serde_json::json!({ "begin-sync": { "op_count": 2 } }));
+
+    let event: super::ui::UiEvent = serde_json::from_value(json).unwrap();
+    assert!(matches!(
+        event,
+        super::ui::UiEvent::Sync(SyncEvent::BeginSync { op_count: 2 })
+    ));
+    assert!(matches!(
+        serde_json::from_str("\"clear\"").unwrap(),
+        super::ui::UiEvent::Clear
+    ));
+}
+
+#[test]
+fn remote_engine_drives_running_engine() {
+    let fixture = Fixture::new();
+    let (engine, ui) = fixture.start();
+    fixture.write_save("slot1.sav", "one");
+
+    let remote_ui = RecordingUiHandler::default();
+    let remote = RemoteEngine::attach(&fixture.args.output_path(), remote_ui.clone()).unwrap();
+
+    remote.set_autobackup(false).unwrap();
+    assert!(!engine.control().get_autobackup());
+
+    let archive_name = backup_name(1, "remote");
+    remote
+        .send(BackupRequest::CreateBackup {
+            archive_name: archive_name.clone(),
+            kind: BackupKind::Manual,
+        })
+        .unwrap();
+
+    // Events of the backup reach both the local and the attached UI
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    remote_ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    assert_eq!(remote_ui.events()[0], UiEvent::BeginBackup(archive_name));
+
+    stop(engine);
+
+    wait_until(|| remote.state() == EngineState::ShutDown);
+}
+
+#[test]
+fn trigger_file_requests_named_backup() {
+    let fixture = Fixture::with_config(|config| {
+        let save_dir_path = config.save_dirs[SAVE_DIR_NAME].path.clone();
+        config.trigger_file = Some(save_dir_path.join("stool-trigger.txt"));
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    fixture.write_save("stool-trigger.txt", "Before boss: Ornstein?\nignored");
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    // The description comes from the first line, without characters that are invalid in filenames
+    let UiEvent::BeginBackup(name) = &ui.events()[0] else {
+        panic!("Unexpected events: {:?}", ui.events());
+    };
+    assert!(name.ends_with(" Before boss Ornstein.7z"), "{name}");
+
+    // The trigger file is consumed, and not part of the backup
+    assert!(fixture.read_save("stool-trigger.txt").is_none());
+    assert_eq!(archive_files(&fixture, name), vec![save_path("slot1.sav")]);
+
+    // Neither writing nor consuming the trigger file is a change to saves
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(engine.control().pending_changes().is_none());
+    assert_eq!(
+        ui.events()
+            .iter()
+            .filter(|e| matches!(e, UiEvent::BeginBackup(_)))
+            .count(),
+        1
+    );
+
+    stop(engine);
+}
+
+#[test]
+fn engine_requests_are_queued_like_requests_from_the_control() {
+    let fixture = Fixture::with_config(|config| {
+        let save_dir_path = config.save_dirs[SAVE_DIR_NAME].path.clone();
+        config.trigger_file = Some(save_dir_path.join("stool-trigger.txt"));
+        config.grace_time = 30;
+        config.auto_backup.enabled = true;
+    });
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+    let is_end_backup = |e: &UiEvent| matches!(e, UiEvent::EndBackup(_));
+
+    // The auto-backup is held back by grace time, while the trigger file request queues up behind it
+    fixture.write_save("slot1.sav", "one");
+    wait_until(|| control.is_busy() && control.snapshot().queue.is_empty());
+
+    fixture.write_save("stool-trigger.txt", "Before boss");
+    wait_until(|| !control.snapshot().queue.is_empty());
+    assert!(matches!(
+        control.snapshot().queue.as_slice(),
+        [BackupRequest::CreateBackup {
+            kind: BackupKind::Manual,
+            ..
+        }]
+    ));
+
+    // Late change events may restart grace time
+    wait_until(|| {
+        fixture.clock.advance(Duration::from_secs(31));
+        ui.events().iter().filter(|e| is_end_backup(e)).count() >= 2
+    });
+    wait_until(|| !control.is_busy());
+    assert!(control.snapshot().queue.is_empty());
+
+    stop(engine);
+}
+
+#[test]
+fn dropped_watch_events_are_assumed_to_be_changes() {
+    let fixture = Fixture::new();
+    let (engine, _ui) = fixture.start();
+
+    assert!(engine.control().pending_changes().is_none());
+
+    engine.send_watch_event(notify::Event::new(notify::EventKind::Other).set_flag(notify::event::Flag::Rescan));
+    wait_until(|| engine.control().pending_changes().is_some());
+
+    let watches = engine.control().watches();
+    assert_eq!(watches[0].dropped, 1);
+    assert_eq!(watches[0].events, 0);
+
+    stop(engine);
+}
+
+#[test]
+fn watcher_falls_back_to_polling_when_save_dir_is_removed() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, _ui) = fixture.start();
+    let control = engine.control();
+
+    assert!(control.watcher_fallback().is_none());
+
+    // Removing the watched directory invalidates native watches, as when a drive is unmounted
+    std::fs::remove_dir_all(&fixture.save_path).unwrap();
+    wait_until(|| control.watcher_fallback().is_some());
+
+    assert_eq!(control.watches()[0].backend, "poll");
+    assert_eq!(control.session_summary().warnings.len(), 1);
+
+    // The directory is watched again once it is back
+    std::thread::sleep(Duration::from_millis(200));
+    let events = control.watches()[0].events;
+
+    fixture.write_save("slot1.sav", "two");
+    wait_until(|| control.watches()[0].events > events);
+
+    stop(engine);
+}
+
+#[test]
+fn save_dir_containing_stool_data_is_refused() {
+    let mut fixture = Fixture::new();
+    fixture.args.data_path = fixture.save_path.join("stool");
+
+    let res = super::run(fixture.args.clone(), Shutdown::new(), NullUiHandler);
+
+    let err = res.err().expect("Engine should refuse to start");
+    assert!(err.to_string().contains("contains stool data"));
+    assert!(!fixture.args.output_path().exists());
+}
+
+#[test]
+fn staging_can_be_placed_next_to_saves() {
+    let fixture = Fixture::with_config(|config| config.staging = StagingLocation::NearSaves);
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    let near_saves_path = fixture.save_path.parent().unwrap().join(".stool-staging-game");
+    assert!(near_saves_path.join(SAVE_DIR_NAME).join("slot1.sav").exists());
+    assert!(!fixture.args.staging_path().exists());
+    assert_eq!(
+        archive_files(&fixture, &backup_name(0, "Manual")),
+        [save_path("slot1.sav")]
+    );
+
+    stop(engine);
+
+    assert!(!near_saves_path.exists());
+}
+
+#[test]
+fn later_backups_pick_up_changes_to_indexed_staging() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+    fixture.write_save("slot2.sav", "two");
+    fixture.write_save("old/slot3.sav", "three");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    fixture.write_save("slot1.sav", "one, changed");
+    fixture.write_save("new/slot4.sav", "four");
+    std::fs::remove_file(fixture.save_path.join("slot2.sav")).unwrap();
+    std::fs::remove_dir_all(fixture.save_path.join("old")).unwrap();
+
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(
+        archive_files(&fixture, &backup_name(1, "Manual")),
+        [save_path("new/slot4.sav"), save_path("slot1.sav")]
+    );
+
+    let manifest = Manifest::load_for_archive(&fixture.args.backup_path().join(backup_name(1, "Manual")))
+        .unwrap()
+        .unwrap();
+    assert_eq!(manifest.total_size(), 16);
+
+    stop(engine);
+}
+
+/// Back up a save again after changing it without changing its size or modification time,
+/// returning whether the change was picked up
+fn backup_picks_up_silent_change(verify: VerifyMode) -> bool {
+    let fixture = Fixture::with_config(|config| config.verify = verify);
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    let path = fixture.save_path.join("slot1.sav");
+    let modified = filetime::FileTime::from_last_modification_time(&std::fs::metadata(&path).unwrap());
+    fixture.write_save("slot1.sav", "two");
+    filetime::set_file_mtime(&path, modified).unwrap();
+
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let entries = fixture
+        .args
+        .archiver
+        .list(&fixture.args.backup_path().join(backup_name(1, "Manual")))
+        .unwrap();
+
+    entries[0].crc32 == Some(crc32fast::hash(b"two"))
+}
+
+#[test]
+fn paranoid_verification_picks_up_silent_changes() {
+    assert!(!backup_picks_up_silent_change(VerifyMode::Fast));
+    assert!(!backup_picks_up_silent_change(VerifyMode::Standard));
+    assert!(backup_picks_up_silent_change(VerifyMode::Paranoid));
+}
+
+#[test]
+fn restore_can_overwrite_read_only_files() {
+    let fixture = Fixture::with_config(|config| config.overwrite_read_only = true);
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    let path = fixture.save_path.join("slot1.sav");
+    fixture.write_save("slot1.sav", "two");
+    fixture.write_save("slot2.sav", "new");
+
+    for name in ["slot1.sav", "slot2.sav"] {
+        let path = fixture.save_path.join(name);
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions).unwrap();
+    }
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: backup_name(0, "Manual"),
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    stop(engine);
+
+    assert_eq!(fixture.read_save("slot1.sav").as_deref(), Some("one"));
+    assert!(!std::fs::metadata(&path).unwrap().permissions().readonly());
+    assert!(fixture.read_save("slot2.sav").is_none());
+}
+
+#[test]
+fn dry_run_writes_nothing() {
+    let mut fixture = Fixture::new();
+    fixture.args.dry_run = true;
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    assert!(!fixture.args.output_path().exists());
+    assert!(list_game_backups(&fixture.args).unwrap().is_empty());
+}
+
+#[test]
+fn backups_are_uploaded_to_targets() {
+    let target_dir = tempfile::tempdir().unwrap();
+    let target_path = target_dir.path().join("remote");
+
+    let fixture = Fixture::with_config(|config| {
+        config.targets.insert(
+            "nas".to_owned(),
+            BackupTarget {
+                path: target_path.clone(),
+                chunk_size_mib: Some(1),
+                ..Default::default()
+            },
+        );
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    // The manifest is uploaded after the archive
+    wait_until(|| Manifest::load_for_archive(&target_path.join(&name)).is_ok_and(|m| m.is_some()));
+
+    assert_eq!(
+        std::fs::read(target_path.join(&name)).unwrap(),
+        std::fs::read(fixture.args.backup_path().join(&name)).unwrap()
+    );
+
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndUpload(_)));
+    assert!(ui
+        .events()
+        .contains(&UiEvent::BeginUpload("nas".to_owned(), name.clone())));
+    assert!(ui.events().contains(&UiEvent::EndUpload(true)));
+
+    stop(engine);
+}
+
+#[test]
+fn parity_repairs_damaged_archive() {
+    let mut fixture = Fixture::new();
+    fixture.args.parity_percent = Some(10);
+    fixture.write_save("slot1.sav", &(0..50_000).map(|i| i.to_string()).collect::<String>());
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let outcome = |repair| {
+        verify_backups(&fixture.args, Some(&name), repair).unwrap()[0]
+            .outcome
+            .as_ref()
+            .copied()
+            .unwrap()
+    };
+
+    assert_eq!(outcome(false), VerifyOutcome::Intact);
+
+    let archive_path = fixture.args.backup_path().join(&name);
+    let original = std::fs::read(&archive_path).unwrap();
+
+    let mut damaged = original.clone();
+    damaged[1000..1100].fill(0);
+    std::fs::write(&archive_path, &damaged).unwrap();
+
+    assert!(matches!(outcome(false), VerifyOutcome::Damaged(_)));
+    assert!(matches!(outcome(true), VerifyOutcome::Repaired(_)));
+    assert_eq!(outcome(false), VerifyOutcome::Intact);
+    assert_eq!(std::fs::read(&archive_path).unwrap(), original);
+}
+
+#[test]
+fn encrypted_uploads_can_be_restored_from_file() {
+    use age::secrecy::ExposeSecret;
+
+    let target_dir = tempfile::tempdir().unwrap();
+    let target_path = target_dir.path().join("remote");
+
+    let identity = age::x25519::Identity::generate();
+    let identity_file = target_dir.path().join("identity.txt");
+    std::fs::write(&identity_file, identity.to_string().expose_secret()).unwrap();
+
+    let mut fixture = Fixture::with_config(|config| {
+        config.targets.insert(
+            "cloud".to_owned(),
+            BackupTarget {
+                path: target_path.clone(),
+                recipients: vec![identity.to_public().to_string()],
+                ..Default::default()
+            },
+        );
+    });
+    fixture.args.archiver = Arc::new(Decrypting {
+        inner: FakeArchiver,
+        identity_file: Some(identity_file),
+        temp_path: Default::default(),
+    });
+    fixture.write_save("slot1.sav", "secret");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    let encrypted_archive = target_path.join(format!("{name}.age"));
+    wait_until(|| target_path.join(format!("{name}.manifest.json.age")).exists());
+
+    stop(engine);
+
+    assert!(!target_path.join(&name).exists());
+    assert!(!std::fs::read(&encrypted_archive)
+        .unwrap()
+        .windows(6)
+        .any(|w| w == b"secret"));
+
+    let dst = tempfile::tempdir().unwrap();
+    extract_backup(
+        &fixture.args,
+        &encrypted_archive.to_string_lossy(),
+        dst.path(),
+        &SyncEvents::discard(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dst.path().join(save_path("slot1.sav"))).unwrap(),
+        "secret"
+    );
+}
+
+#[test]
+fn target_conditions_select_uploaded_backups() {
+    let target_dir = tempfile::tempdir().unwrap();
+    let target_path = target_dir.path().join("nas");
+
+    let fixture = Fixture::with_config(|config| {
+        config.targets.insert(
+            "nas".to_owned(),
+            BackupTarget {
+                path: target_path.clone(),
+                manual_only: true,
+                every: Some(2),
+                ..Default::default()
+            },
+        );
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let backups = [
+        (backup_name(0, "Manual"), BackupKind::Manual),
+        (backup_name(1, "Auto"), BackupKind::Auto),
+        (backup_name(2, "Manual"), BackupKind::Manual),
+        (backup_name(3, "Manual"), BackupKind::Manual),
+    ];
+
+    for (name, kind) in backups.iter() {
+        create_backup(&engine, name, *kind);
+    }
+
+    ui.wait_for(backups.len(), |e| matches!(e, UiEvent::EndBackup(_)));
+
+    // Only every second manual backup is uploaded
+    wait_until(|| target_path.join(format!("{}.manifest.json", backups[2].0)).exists());
+
+    stop(engine);
+
+    let mut uploaded: Vec<_> = std::fs::read_dir(&target_path)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".7z"))
+        .collect();
+    uploaded.sort();
+
+    assert_eq!(uploaded, vec![backups[2].0.clone()]);
+}
+
+#[test]
+fn copy_latest_rotates_copies_and_survives_failing_destinations() {
+    let dir = tempfile::tempdir().unwrap();
+    let copy_path = dir.path().join("copies");
+    let blocked_path = dir.path().join("blocked");
+
+    std::fs::create_dir(&copy_path).unwrap();
+    std::fs::write(&blocked_path, "not a directory").unwrap();
+    std::fs::write(copy_path.join("notes.txt"), "unrelated").unwrap();
+
+    for n in 1..=2 {
+        let old_copy = copy_path.join(backup_name(n, "Manual"));
+        std::fs::write(&old_copy, "old").unwrap();
+        filetime::set_file_mtime(&old_copy, filetime::FileTime::from_unix_time(1_000_000 + n as i64, 0)).unwrap();
+    }
+
+    let fixture = Fixture::with_config(|config| {
+        config.copy_latest_to_paths = vec![blocked_path.clone(), copy_path.clone()];
+        config.copy_latest_keep = Some(2);
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(10, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let mut names: Vec<_> = std::fs::read_dir(&copy_path)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+
+    assert_eq!(names, vec![backup_name(2, "Manual"), name, "notes.txt".to_owned()]);
+}
+
+#[test]
+fn save_paths_are_resolved_in_proton_prefix_of_any_steam_library() {
+    let dir = tempfile::tempdir().unwrap();
+    let steam_root = dir.path().join("Steam");
+    let library = dir.path().join("SteamLibrary");
+    let drive_c = library.join("steamapps/compatdata/1234/pfx/drive_c");
+
+    std::fs::create_dir_all(steam_root.join("steamapps/compatdata/99/pfx/drive_c")).unwrap();
+    std::fs::create_dir_all(&drive_c).unwrap();
+    std::fs::write(
+        steam_root.join("steamapps/libraryfolders.vdf"),
+        format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+            steam_root.display(),
+            library.display()
+        ),
+    )
+    .unwrap();
+
+    let toml = r#"
+        grace-time = 0
+        steam-app-id = 1234
+
+        [auto-backup]
+        enabled = false
+        min-interval = 0
+
+        [save-dirs.main]
+        path = "%proton%/users/steamuser/AppData/Roaming/Game"
+
+        [[save-file]]
+        path = "/elsewhere/settings.ini"
+    "#;
+
+    let mut config: GameConfig = toml.parse().unwrap();
+    config.resolve_proton_paths(std::slice::from_ref(&steam_root)).unwrap();
+
+    assert_eq!(
+        config.save_dirs["main"].path,
+        drive_c.join("users/steamuser/AppData/Roaming/Game")
+    );
+    assert_eq!(config.save_files[0].path, Path::new("/elsewhere/settings.ini"));
+
+    // Without the prefix, the config cannot be used
+    let mut config: GameConfig = toml.replace("1234", "5678").parse().unwrap();
+    assert!(config.resolve_proton_paths(&[steam_root]).is_err());
+}
+
+#[test]
+fn per_user_save_dirs_are_backed_up_for_each_user() {
+    let fixture = Fixture::with_config(|config| {
+        let save_dir = config.save_dirs.get_mut(SAVE_DIR_NAME).unwrap();
+        save_dir.path = save_dir.path.join("player_{user}").join("saves");
+        save_dir.per_user = true;
+    });
+    fixture.write_save("player_alice/saves/slot1.sav", "alice");
+    fixture.write_save("player_bob/saves/slot1.sav", "bob");
+    fixture.write_save("player_/saves/slot1.sav", "nobody");
+    fixture.write_save("shared/saves/slot1.sav", "shared");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(
+        archive_files(&fixture, &name),
+        vec![save_path("alice/slot1.sav"), save_path("bob/slot1.sav")]
+    );
+
+    fixture.write_save("player_bob/saves/slot1.sav", "changed");
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: name,
+            only: Some(save_path("bob/slot1.sav")),
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert_eq!(fixture.read_save("player_bob/saves/slot1.sav").as_deref(), Some("bob"));
+
+    stop(engine);
+}
+
+#[test]
+#[cfg(unix)]
+fn server_save_command_runs_before_staging() {
+    let fixture = Fixture::with_config(|config| {
+        let save_dir_path = config.save_dirs[SAVE_DIR_NAME].path.clone();
+
+        config.server = Some(ServerConfig {
+            save_command: vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                format!("echo flushed > '{}'", save_dir_path.join("world.db").display()),
+            ],
+            ..Default::default()
+        });
+    });
+    fixture.write_save("world.db", "stale");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let extract_path = fixture.args.output_path().join("extracted");
+    fixture
+        .args
+        .archiver
+        .unpack(&fixture.args.backup_path().join(&name), &extract_path)
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(extract_path.join(save_path("world.db"))).unwrap(),
+        "flushed\n"
+    );
+}
+
+#[test]
+fn rcon_save_command_is_issued_before_backup() {
+    let server = FakeRconServer::start("secret");
+
+    let fixture = Fixture::with_config(|config| {
+        config.rcon = Some(RconConfig {
+            address: server.address.clone(),
+            password: "secret".to_owned(),
+            save_command: "save-all flush".to_owned(),
+            save_delay: 0,
+        });
+    });
+    fixture.write_save("world.db", "world");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    assert_eq!(server.commands(), vec!["save-all flush".to_owned()]);
+
+    stop(engine);
+}
+
+#[test]
+fn overlay_status_files_show_latest_backup() {
+    let mut fixture = Fixture::new();
+    fixture.args.overlay_status = true;
+
+    let (engine, ui) = fixture.start();
+    let output_path = fixture.args.output_path();
+    let read = |filename| std::fs::read_to_string(output_path.join(filename)).unwrap_or_default();
+
+    wait_until(|| read(OVERLAY_TEXT_FILENAME) == "No backups yet");
+
+    fixture.write_save("slot1.sav", "one");
+    let archive_name = backup_name(1, "overlay");
+    create_backup(&engine, &archive_name, BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    wait_until(|| read(OVERLAY_JSON_FILENAME).contains(&archive_name));
+    assert!(read(OVERLAY_TEXT_FILENAME).starts_with("Last backup "));
+
+    stop(engine);
+
+    // The files are removed once the engine has shut down, so overlays do not show stale status
+    wait_until(|| !output_path.join(OVERLAY_JSON_FILENAME).exists());
+    assert!(!output_path.join(OVERLAY_TEXT_FILENAME).exists());
+}
+
+#[test]
+fn change_indicator_changes_trigger_auto_backup_without_being_backed_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let indicator_path = dir.path().join("save-complete.marker");
+    std::fs::write(&indicator_path, "0").unwrap();
+
+    let fixture = Fixture::with_config(|config| {
+        config.auto_backup.enabled = true;
+        config.change_indicator_paths = vec![indicator_path.clone()];
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(control.pending_changes().is_none());
+
+    // Only the indicator changes, as when a game writes its saves before touching it
+    std::fs::write(&indicator_path, "1").unwrap();
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    let UiEvent::BeginBackup(name) = &ui.events()[0] else {
+        panic!("Unexpected events: {:?}", ui.events());
+    };
+    assert_eq!(archive_files(&fixture, name), vec![save_path("slot1.sav")]);
+
+    stop(engine);
+}
+
+#[test]
+fn grace_time_is_the_longest_of_changed_save_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let world_path = dir.path().join("world.dat");
+    std::fs::write(&world_path, "0").unwrap();
+
+    let fixture = Fixture::with_config(|config| {
+        config.grace_time = 30;
+        config.auto_backup.enabled = true;
+        config.save_dirs.get_mut(SAVE_DIR_NAME).unwrap().grace_time = Some(5);
+        config.save_files.push(GameSaveFile {
+            path: world_path.clone(),
+            staging_subdirectory: None,
+            grace_time: Some(60),
+        });
+    });
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+    let auto_backup_in = || control.pending_changes().and_then(|p| p.auto_backup_in);
+
+    // Changes to the save dir alone wait for its own grace time
+    fixture.write_save("slot1.sav", "one");
+    std::thread::sleep(Duration::from_millis(200));
+    wait_until(|| auto_backup_in() == Some(Duration::from_secs(5)));
+
+    fixture.clock.advance(Duration::from_secs(6));
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    wait_until(|| control.pending_changes().is_none());
+
+    // Once the world file has changed as well, the longer grace time applies
+    std::fs::write(&world_path, "1").unwrap();
+    fixture.write_save("slot1.sav", "two");
+    std::thread::sleep(Duration::from_millis(200));
+    wait_until(|| auto_backup_in() == Some(Duration::from_secs(60)));
+
+    fixture.clock.advance(Duration::from_secs(61));
+    ui.wait_for(2, |e| *e == UiEvent::EndBackup(true));
+
+    stop(engine);
+}
+
+#[test]
+fn partial_backups_leave_out_unchanged_save_dirs_and_restore_them_from_earlier_backups() {
+    let dir = tempfile::tempdir().unwrap();
+    let world_path = dir.path().join("world");
+    std::fs::create_dir_all(&world_path).unwrap();
+    std::fs::write(world_path.join("world.dat"), "world").unwrap();
+
+    let fixture = Fixture::with_config(|config| {
+        config.partial_backups = true;
+
+        let mut world_dir = config.save_dirs[SAVE_DIR_NAME].clone();
+        world_dir.path = world_path.clone();
+        config.save_dirs.insert("world".to_owned(), world_dir);
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+    let is_end_backup = |e: &UiEvent| matches!(e, UiEvent::EndBackup(_));
+
+    let full_name = backup_name(0, "Manual");
+    create_backup(&engine, &full_name, BackupKind::Manual);
+    ui.wait_for(1, is_end_backup);
+
+    // Only the save dir that changed is archived
+    fixture.write_save("slot1.sav", "two");
+    let partial_name = backup_name(1, "Manual");
+    create_backup(&engine, &partial_name, BackupKind::Manual);
+    ui.wait_for(2, is_end_backup);
+
+    assert_eq!(archive_files(&fixture, &partial_name), vec![save_path("slot1.sav")]);
+
+    let manifest = Manifest::load_for_archive(&fixture.args.backup_path().join(&partial_name))
+        .unwrap()
+        .unwrap();
+    let world_file = manifest
+        .files
+        .iter()
+        .find(|f| f.path == Path::new("world").join("world.dat"))
+        .unwrap();
+    assert_eq!(world_file.stored_in.as_deref(), Some(full_name.as_str()));
+
+    // Restoring assembles the full set of save files
+    fixture.write_save("slot1.sav", "three");
+    std::fs::write(world_path.join("world.dat"), "changed").unwrap();
+
+    engine
+        .control()
+        .send(BackupRequest::RestoreBackup {
+            archive_name: partial_name,
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert!(ui.events().contains(&UiEvent::EndRestore(true)));
+    assert_eq!(fixture.read_save("slot1.sav").as_deref(), Some("two"));
+    assert_eq!(std::fs::read_to_string(world_path.join("world.dat")).unwrap(), "world");
+
+    stop(engine);
+
+    // The backup holding the unchanged save dir is kept while later backups leave it out
+    let backups = list_game_backups(&fixture.args).unwrap();
+    let full = backups.iter().find(|b| b.name == full_name).unwrap();
+    let err = delete_game_backup(&fixture.args, full).unwrap_err();
+    assert!(err.is::<BaselineInUse>());
+}
+
+#[test]
+fn missing_save_paths_are_recorded_as_backup_warnings() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing_path = dir.path().join("missing.sav");
+
+    let fixture = Fixture::with_config(|config| {
+        config.save_files.push(GameSaveFile {
+            path: missing_path.clone(),
+            staging_subdirectory: None,
+            grace_time: None,
+        });
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+    let archive_name = backup_name(1, "warnings");
+    create_backup(&engine, &archive_name, BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    // The backup is still created, with the missing save file noted
+    let warnings: Vec<_> = ui
+        .events()
+        .into_iter()
+        .filter_map(|e| match e {
+            UiEvent::BackupWarning(message) => Some(message),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].starts_with("Save file does not exist"));
+
+    let manifest = Manifest::load_for_archive(&fixture.args.backup_path().join(&archive_name))
+        .unwrap()
+        .unwrap();
+    assert_eq!(manifest.warnings, warnings);
+    assert!(manifest.files.iter().any(|f| f.path.ends_with("slot1.sav")));
+
+    let summary = engine.control().session_summary();
+    assert_eq!(summary.backups_with_warnings, 1);
+    assert!(summary.warnings.contains(&format!("{archive_name}: {}", warnings[0])));
+
+    stop(engine);
+}
+
+#[test]
+fn missing_save_dirs_are_skipped_and_reported() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let fixture = Fixture::with_config(|config| {
+        let mut missing = config.save_dirs[SAVE_DIR_NAME].clone();
+        missing.path = dir.path().join("missing");
+        config.save_dirs.insert("missing".to_owned(), missing);
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+    let archive_name = backup_name(1, "skipped");
+    create_backup(&engine, &archive_name, BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    assert!(ui.events().contains(&UiEvent::PathsSkipped(vec!["missing".to_owned()])));
+
+    let manifest = Manifest::load_for_archive(&fixture.args.backup_path().join(&archive_name))
+        .unwrap()
+        .unwrap();
+    assert_eq!(manifest.skipped_paths, vec!["missing".to_owned()]);
+    assert!(manifest.files.iter().any(|f| f.path.ends_with("slot1.sav")));
+
+    stop(engine);
+}
+
+#[test]
+fn copy_latest_names_copies_by_game_and_leaves_other_games_copies_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let copy_path = dir.path().join("copies");
+
+    let fixture = Fixture::with_config(|config| {
+        config.copy_latest_to_paths = vec![copy_path.clone()];
+        config.copy_latest_keep = Some(1);
+        config.copy_latest_name = Some("{game} - {name}".to_owned());
+        config.copy_latest_subfolder = true;
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    // Copy of another game sharing the folder, named like a backup of this one
+    let game_path = copy_path.join(&fixture.args.name);
+    std::fs::create_dir_all(&game_path).unwrap();
+    std::fs::write(game_path.join(format!("Other - {}", backup_name(1, "Manual"))), "other").unwrap();
+
+    for n in 2..=3 {
+        let (engine, ui) = fixture.start();
+        create_backup(&engine, &backup_name(n, "Manual"), BackupKind::Manual);
+        ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+        stop(engine);
+    }
+
+    let mut names: Vec<_> = std::fs::read_dir(&game_path)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+
+    assert_eq!(
+        names,
+        vec![
+            format!("Other - {}", backup_name(1, "Manual")),
+            format!("{} - {}", fixture.args.name, backup_name(3, "Manual")),
+        ]
+    );
+}
+
+#[test]
+fn engine_shuts_down_without_waiting_out_the_check_interval() {
+    let mut fixture = Fixture::new();
+    fixture.args.clock = Arc::new(SystemClock);
+    fixture.args.check_interval = Duration::from_secs(60);
+
+    let (engine, _ui) = fixture.start();
+
+    let started_at = std::time::Instant::now();
+    stop(engine);
+
+    assert!(started_at.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn staging_is_kept_between_runs_until_the_game_config_changes() {
+    let fixture = Fixture::with_config(|config| config.keep_staging = true);
+    fixture.write_save("slot1.sav", "one");
+
+    let staging_path = fixture.args.staging_path();
+    let staged_path = staging_path.join(SAVE_DIR_NAME).join("slot1.sav");
+    let state_path = fixture.args.output_path().join(STAGING_STATE_FILENAME);
+
+    let (engine, ui) = fixture.start();
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    stop(engine);
+
+    assert!(staged_path.exists());
+    assert!(state_path.exists());
+
+    // The next run picks up where the previous one left off
+    let (engine, ui) = fixture.start();
+    assert!(staged_path.exists());
+
+    fixture.write_save("slot1.sav", "two");
+    create_backup(&engine, &backup_name(2, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+    stop(engine);
+
+    assert_eq!(std::fs::read_to_string(&staged_path).unwrap(), "two");
+
+    // A changed game config may stage different files, so staging starts over
+    let mut config = GameConfig::from_file(&fixture.args.game_config_file_path(), true).unwrap();
+    config.grace_time = 1;
+    config.write(&fixture.args.game_config_file_path()).unwrap();
+
+    let (engine, _ui) = fixture.start();
+    assert!(!staging_path.exists());
+    stop(engine);
+}
+
+#[test]
+fn only_the_first_backup_is_an_initial_snapshot() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+    fixture.write_save("sub/slot2.sav", "second");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    fixture.write_save("slot1.sav", "changed");
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| *e == UiEvent::EndBackup(true));
+
+    stop(engine);
+
+    let snapshots: Vec<_> = ui
+        .events()
+        .into_iter()
+        .filter(|e| matches!(e, UiEvent::BeginInitialSnapshot(..)))
+        .collect();
+    assert_eq!(snapshots, vec![UiEvent::BeginInitialSnapshot(2, 9)]);
+}
+
+#[test]
+fn staging_reports_the_bytes_of_changed_files_only() {
+    let fixture = Fixture::new();
+    fixture.write_save("slot1.sav", "one");
+    fixture.write_save("slot2.sav", "second");
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| *e == UiEvent::EndBackup(true));
+
+    fixture.write_save("slot1.sav", "changed");
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| *e == UiEvent::EndBackup(true));
+
+    create_backup(&engine, &backup_name(2, "Manual"), BackupKind::Manual);
+    ui.wait_for(3, |e| *e == UiEvent::EndBackup(true));
+
+    stop(engine);
+
+    let staging: Vec<_> = ui
+        .events()
+        .into_iter()
+        .filter(|e| matches!(e, UiEvent::BeginStaging(..)))
+        .collect();
+    assert_eq!(
+        staging,
+        vec![
+            UiEvent::BeginStaging(1, 9),
+            UiEvent::BeginStaging(1, 7),
+            UiEvent::BeginStaging(1, 0),
+        ]
+    );
+}
+
+#[test]
+fn backup_contents_list_deltas_as_the_files_they_reconstruct() {
+    let fixture = Fixture::with_config(|config| {
+        config.delta = Some(DeltaConfig {
+            min_size: Some(1024),
+            full_every: None,
+        })
+    });
+
+    let mut world = vec![0u8; 64 * 1024];
+    std::fs::write(fixture.save_path.join("world.sav"), &world).unwrap();
+
+    let (engine, ui) = fixture.start();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    world[1000..1010].copy_from_slice(b"0123456789");
+    std::fs::write(fixture.save_path.join("world.sav"), &world).unwrap();
+
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    assert_eq!(
+        archive_files(&fixture, &backup_name(1, "Manual")),
+        [save_path("world.sav.stool-delta")]
+    );
+
+    let contents = list_backup_contents(&fixture.args, &backup_name(1, "Manual")).unwrap();
+    assert_eq!(
+        contents,
+        vec![ArchiveEntry {
+            path: save_path("world.sav"),
+            size: world.len() as u64,
+            is_dir: false,
+            crc32: Some(crc32fast::hash(&world)),
+        }]
+    );
+
+    let diff = diff_backups(&fixture.args, &backup_name(0, "Manual"), &backup_name(1, "Manual")).unwrap();
+    assert_eq!(diff.summary(), "0 added, 0 removed, 1 changed, 0 unchanged");
+}
+
+#[test]
+fn password_protected_archives_are_read_once_given_the_password() {
+    let mut fixture = Fixture::new();
+    fixture.args.archiver = Arc::new(Decrypting {
+        inner: MultiFormat::new(Box::new(PasswordArchiver::new("hunter2")), Vec::new()),
+        identity_file: None,
+        temp_path: Default::default(),
+    });
+    fixture.write_save("slot1.sav", "one");
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    let err = list_backup_contents(&fixture.args, &name).unwrap_err();
+    assert!(err.is::<PasswordRequired>(), "{err}");
+
+    fixture.args.archiver.set_password(Some("hunter2".to_owned()));
+
+    let contents = list_backup_contents(&fixture.args, &name).unwrap();
+    assert_eq!(contents.len(), 1);
+
+    let dst = tempfile::tempdir().unwrap();
+    extract_backup(&fixture.args, &name, dst.path(), &SyncEvents::discard()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dst.path().join(save_path("slot1.sav"))).unwrap(),
+        "one"
+    );
+}
+
+#[test]
+fn backups_made_by_hand_are_adopted_once() {
+    let fixture = Fixture::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    // A copy of the save folder, and an archive holding the save folder itself
+    let folder = dir.path().join("before patch 2023-05-01_21-30");
+    std::fs::create_dir_all(&folder).unwrap();
+    std::fs::write(folder.join("slot1.sav"), "one").unwrap();
+
+    let archived = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(archived.path().join("Saves")).unwrap();
+    std::fs::write(archived.path().join("Saves/slot1.sav"), "two").unwrap();
+    FakeArchiver
+        .create(archived.path(), &dir.path().join("20240102.7z"))
+        .unwrap();
+
+    std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+    let report = adopt_backups(&fixture.args, dir.path(), &SyncEvents::discard()).unwrap();
+
+    let adopted = vec![
+        "2024-01-02 00-00-00 Adopted 20240102.7z".to_owned(),
+        "2023-05-01 21-30-00 Adopted before patch 2023-05-01_21-30.7z".to_owned(),
+    ];
+    assert_eq!(report.adopted, adopted);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].path, dir.path().join("notes.txt"));
+
+    for name in adopted.iter() {
+        assert_eq!(archive_files(&fixture, name), [save_path("slot1.sav")]);
+        assert!(Manifest::load_for_archive(&fixture.args.backup_path().join(name))
+            .unwrap()
+            .is_some());
+    }
+
+    // Newest first, by the timestamps in their names
+    let backups: Vec<_> = list_game_backups(&fixture.args)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    assert_eq!(backups, adopted);
+
+    let report = adopt_backups(&fixture.args, dir.path(), &SyncEvents::discard()).unwrap();
+    assert!(report.adopted.is_empty());
+    assert_eq!(report.skipped.len(), 3);
+}
+
+#[test]
+fn stats_total_backups_by_month() {
+    let fixture = Fixture::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    for (name, contents) in [("2023-05-01", "one"), ("2023-05-20", "two"), ("2024-01-02", "three")] {
+        std::fs::create_dir_all(dir.path().join(name)).unwrap();
+        std::fs::write(dir.path().join(name).join("slot1.sav"), contents).unwrap();
+    }
+
+    adopt_backups(&fixture.args, dir.path(), &SyncEvents::discard()).unwrap();
+
+    // Made by hand in the backup directory, without a manifest
+    let saves = tempfile::tempdir().unwrap();
+    let archive_path = fixture.args.backup_path().join("2024-01-15 00-00-00 Manual.7z");
+    FakeArchiver.create(saves.path(), &archive_path).unwrap();
+    filetime::set_file_mtime(&archive_path, filetime::FileTime::from_unix_time(1_705_320_000, 0)).unwrap();
+
+    let stats = backup_stats(&fixture.args).unwrap();
+
+    assert_eq!(stats.backups, 4);
+    assert_eq!(stats.logical_size, 11);
+    assert_eq!(stats.without_manifest, 1);
+
+    let disk_size: u64 = std::fs::read_dir(fixture.args.backup_path())
+        .unwrap()
+        .map(|e| e.unwrap().metadata().unwrap().len())
+        .sum();
+    assert_eq!(stats.physical_size, disk_size);
+
+    let months: Vec<_> = stats
+        .months
+        .iter()
+        .map(|m| (m.year, m.month as u8, m.backups))
+        .collect();
+    assert_eq!(months, [(2023, 5, 2), (2024, 1, 2)]);
+}
+
+#[test]
+fn temp_path_of_the_game_overrides_the_main_config() {
+    let mut fixture = Fixture::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut config = fixture.config();
+    assert_eq!(fixture.args.temp_path(&config), std::env::temp_dir());
+
+    fixture.args.temp_root = Some(dir.path().join("main"));
+    assert_eq!(fixture.args.temp_path(&config), dir.path().join("main"));
+
+    config.temp_path = Some(dir.path().join("game"));
+    assert_eq!(fixture.args.temp_path(&config), dir.path().join("game"));
+
+    // Checked against the nearest existing ancestor, as temp directories are created on demand
+    let temp_path = fixture.args.temp_path(&config);
+    check_free_space(&temp_path, 0).unwrap();
+    let err = check_free_space(&temp_path, u64::MAX).unwrap_err();
+    assert!(err.to_string().starts_with("Not enough free space"));
+}
+
+#[test]
+#[cfg(unix)]
+fn redirected_save_dirs_are_followed_but_links_inside_them_are_not() {
+    // A save folder redirected elsewhere, as into OneDrive
+    let redirected = tempfile::tempdir().unwrap();
+    std::fs::write(redirected.path().join("slot1.sav"), "one").unwrap();
+
+    // Links inside save folders may lead anywhere, including back into them
+    let elsewhere = tempfile::tempdir().unwrap();
+    std::fs::write(elsewhere.path().join("other.sav"), "other").unwrap();
+    std::os::unix::fs::symlink(elsewhere.path(), redirected.path().join("elsewhere")).unwrap();
+    std::os::unix::fs::symlink(redirected.path(), redirected.path().join("loop")).unwrap();
+
+    let fixture = Fixture::with_config(|config| {
+        let save_dir = config.save_dirs.get_mut(SAVE_DIR_NAME).unwrap();
+        let link_path = save_dir.path.with_file_name("redirected");
+        std::os::unix::fs::symlink(redirected.path(), &link_path).unwrap();
+        save_dir.path = link_path;
+    });
+
+    let (engine, ui) = fixture.start();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    stop(engine);
+
+    assert_eq!(archive_files(&fixture, &name), [save_path("slot1.sav")]);
+}
+
+#[test]
+fn downloaded_cloud_placeholders_are_not_changes() {
+    let fixture = Fixture::with_config(|config| {
+        config.grace_time = 30;
+        config.auto_backup.enabled = true;
+    });
+    fixture.write_save("slot1.sav", "one");
+    fixture.write_save("slot2.sav", "two");
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+
+    create_backup(&engine, &backup_name(0, "Manual"), BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    // Downloading a cloud placeholder changes its attributes, but neither its contents nor its modification time
+    let slot1_path = fixture.save_path.join("slot1.sav");
+    let mut permissions = std::fs::metadata(&slot1_path).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&slot1_path, permissions).unwrap();
+
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(control.pending_changes().is_none());
+
+    fixture.write_save("slot2.sav", "changed");
+    wait_until(|| control.pending_changes().is_some());
+
+    // Late change events may restart grace time
+    wait_until(|| {
+        fixture.clock.advance(Duration::from_secs(31));
+        ui.events()
+            .iter()
+            .filter(|e| matches!(e, UiEvent::EndBackup(_)))
+            .count()
+            >= 2
+    });
+
+    stop(engine);
+}
+
+#[test]
+fn atomic_restore_swaps_in_restored_save_dir_and_keeps_watching_it() {
+    let fixture = Fixture::with_config(|config| {
+        config.atomic_restore = true;
+
+        let save_dir = config.save_dirs.get_mut(SAVE_DIR_NAME).unwrap();
+        save_dir.ignore = Some(vec!["settings.ini".to_owned()]);
+    });
+
+    fixture.write_save("slot1.sav", "original");
+    fixture.write_save("settings.ini", "original");
+
+    let (engine, ui) = fixture.start();
+    let control = engine.control();
+
+    let name = backup_name(0, "Manual");
+    create_backup(&engine, &name, BackupKind::Manual);
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndBackup(_)));
+
+    fixture.write_save("slot1.sav", "changed");
+    fixture.write_save("sub/slot2.sav", "new");
+    fixture.write_save("settings.ini", "changed");
+
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(&std::fs::metadata(&fixture.save_path).unwrap());
+
+    control
+        .send(BackupRequest::RestoreBackup {
+            archive_name: name,
+            only: None,
+            keep: Vec::new(),
+        })
+        .unwrap();
+    ui.wait_for(1, |e| matches!(e, UiEvent::EndRestore(_)));
+
+    assert!(ui.events().contains(&UiEvent::EndRestore(true)));
+    assert_eq!(fixture.read_save("slot1.sav").as_deref(), Some("original"));
+    assert_eq!(fixture.read_save("sub/slot2.sav"), None);
+    // Files left alone by filters survive the swap
+    assert_eq!(fixture.read_save("settings.ini").as_deref(), Some("changed"));
+
+    #[cfg(unix)]
+    assert_ne!(
+        std::os::unix::fs::MetadataExt::ino(&std::fs::metadata(&fixture.save_path).unwrap()),
+        inode
+    );
+
+    let siblings: Vec<_> = std::fs::read_dir(fixture.save_path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .filter(|name| name.to_string_lossy().contains(".stool-"))
+        .collect();
+    assert!(siblings.is_empty(), "{siblings:?}");
+
+    // The swapped in save directory is watched like the one it replaced
+    create_backup(&engine, &backup_name(1, "Manual"), BackupKind::Manual);
+    ui.wait_for(2, |e| matches!(e, UiEvent::EndBackup(_)));
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(control.pending_changes().is_none());
+
+    fixture.write_save("slot1.sav", "played");
+    wait_until(|| control.pending_changes().is_some());
+
+    stop(engine);
+}
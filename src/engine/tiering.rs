@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use tracing::{error, info};
+
+use crate::internal::parity::parity_path;
+
+use super::{
+    backups::{list_backups, BackupInfo},
+    index::BackupIndex,
+    manifest::{manifest_path, Manifest},
+    EngineArgs,
+};
+
+/// Move backups older than `cold_after` from the backup directory to the cold backup directory.
+/// Moved backups remain listed and restorable.
+pub fn move_old_backups(args: &EngineArgs, cold_after: Duration) -> Result<(), anyhow::Error> {
+    let Some(cold_backup_path) = args.cold_backup_path() else {
+        return Ok(());
+    };
+
+    let now = SystemTime::now();
+
+    for backup in list_backups(&args.backup_path())? {
+        let age = now.duration_since(backup.modified).unwrap_or_default();
+
+        if age < cold_after {
+            continue;
+        }
+
+        info!("Moving old backup to cold storage: {}", backup.name);
+
+        if let Err(err) = move_backup(args, &backup, &cold_backup_path) {
+            error!("Error moving backup {} to cold storage: {err}", backup.name);
+        }
+    }
+
+    Ok(())
+}
+
+fn move_backup(args: &EngineArgs, backup: &BackupInfo, dst_dir: &Path) -> Result<(), anyhow::Error> {
+    fs::create_dir_all(dst_dir)?;
+
+    let dst_path = dst_dir.join(&backup.name);
+
+    // Move the manifest and parity data first, so that an interrupted move never leaves an archive without them
+    let src_manifest_path = manifest_path(&backup.path);
+    if src_manifest_path.exists() {
+        move_file(&src_manifest_path, &manifest_path(&dst_path))?;
+    }
+
+    let src_parity_path = parity_path(&backup.path);
+    if src_parity_path.exists() {
+        move_file(&src_parity_path, &parity_path(&dst_path))?;
+    }
+
+    move_file(&backup.path, &dst_path)?;
+
+    if args.use_index {
+        let moved = BackupInfo {
+            path: dst_path.clone(),
+            ..backup.clone()
+        };
+
+        let mut index = BackupIndex::open(&args.output_path())?;
+        index.remove_backup(&backup.name)?;
+        index.add_backup(&moved, Manifest::load_for_archive(&dst_path)?.as_ref())?;
+    }
+
+    Ok(())
+}
+
+/// Move a file, falling back to copying if it is on a different file system
+fn move_file(src: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(src, dst).with_context(|| format!("Error copying {}", src.display()))?;
+
+    // Preserve modification time, as backup age is based on it
+    let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(src)?);
+    filetime::set_file_mtime(dst, mtime)?;
+
+    fs::remove_file(src)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        backups::list_game_backups,
+        testing::{write_backup, Fixture},
+    };
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn old_backups_move_to_cold_storage_with_their_manifest_and_parity() {
+        let roots = tempfile::tempdir().unwrap();
+        let mut fixture = Fixture::new();
+        fixture.args.cold_root = Some(roots.path().join("cold"));
+        fixture.args.use_index = true;
+
+        let backup_path = fixture.args.backup_path();
+        let old = write_backup(&backup_path, "2025-01-01 00-00-01 Manual.7z", 3 * DAY, &["slot1.sav"]);
+        let recent = write_backup(&backup_path, "2025-01-01 00-00-02 Manual.7z", DAY / 2, &["slot1.sav"]);
+        fs::write(parity_path(&old.path), "parity").unwrap();
+
+        BackupIndex::open(&fixture.args.output_path())
+            .unwrap()
+            .sync_with_dirs(&fixture.args.backup_paths())
+            .unwrap();
+
+        move_old_backups(&fixture.args, DAY).unwrap();
+
+        let cold_path = fixture.args.cold_backup_path().unwrap();
+        let moved_path = cold_path.join(&old.name);
+        assert!(moved_path.is_file());
+        assert!(manifest_path(&moved_path).is_file());
+        assert!(parity_path(&moved_path).is_file());
+        assert!(!old.path.exists() && !manifest_path(&old.path).exists() && !parity_path(&old.path).exists());
+        assert!(recent.path.is_file());
+
+        // Moved backups stay listed, at their new location
+        let paths: Vec<_> = list_game_backups(&fixture.args)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.path)
+            .collect();
+        assert_eq!(paths, [recent.path, moved_path]);
+    }
+}
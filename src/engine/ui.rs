@@ -1,17 +1,38 @@
-use crate::internal::sync::SyncUiHandler;
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use serde_derive::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::internal::sync::{SyncEvent, SyncEvents, SyncUiHandler};
 
 pub trait StoolUiHandler: SyncUiHandler + 'static + Send {
     fn clear(self) -> Result<(), anyhow::Error>;
 
     fn begin_backup(&mut self, name: &str);
+    /// Something was left out of the backup in progress, which still completes
+    fn backup_warning(&mut self, message: &str);
+    /// Save dirs and files left out of the backup in progress as they are missing
+    fn paths_skipped(&mut self, names: &[String]);
     fn end_backup(&mut self, success: bool);
 
-    fn begin_staging(&mut self, count: usize);
+    /// Staging of `count` save dirs and files, expected to copy `bytes` bytes
+    fn begin_staging(&mut self, count: usize, bytes: u64);
+    /// The backup in progress copies every save file, as nothing is staged yet
+    fn begin_initial_snapshot(&mut self, files: usize, bytes: u64);
     fn begin_stage(&mut self, name: &str);
     fn end_stage(&mut self);
     fn end_staging(&mut self);
 
     fn begin_compress(&mut self);
+    /// Bytes written to the archive since the last call
+    fn compress_progress(&mut self, bytes: u64);
     fn end_compress(&mut self);
 
     fn begin_restore(&mut self, name: &str);
@@ -22,4 +43,431 @@ pub trait StoolUiHandler: SyncUiHandler + 'static + Send {
 
     fn begin_restore_sp(&mut self, name: &str);
     fn end_restore_sp(&mut self);
+
+    fn begin_prune(&mut self);
+    fn pruned(&mut self, name: &str);
+    fn end_prune(&mut self);
+
+    fn begin_upload(&mut self, target: &str, name: &str, size: u64);
+    fn upload_progress(&mut self, bytes: u64);
+    fn end_upload(&mut self, success: bool);
+
+    /// Called periodically by the UI thread, whether or not events are received
+    fn tick(&mut self);
+}
+
+/// UI handler forwarding all calls to two handlers, in order.
+/// Nest to forward to more handlers.
+pub struct MultiUiHandler<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: StoolUiHandler, B: StoolUiHandler> MultiUiHandler<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+/// Forward a call to both handlers
+macro_rules! forward {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $name(&mut self, $($arg: $ty),*) {
+                self.first.$name($($arg),*);
+                self.second.$name($($arg),*);
+            }
+        )*
+    };
+}
+
+impl<A: StoolUiHandler, B: StoolUiHandler> StoolUiHandler for MultiUiHandler<A, B> {
+    fn clear(self) -> Result<(), anyhow::Error> {
+        let first = self.first.clear();
+        let second = self.second.clear();
+
+        first.and(second)
+    }
+
+    forward! {
+        begin_backup(name: &str);
+        backup_warning(message: &str);
+        paths_skipped(names: &[String]);
+        end_backup(success: bool);
+        begin_staging(count: usize, bytes: u64);
+        begin_initial_snapshot(files: usize, bytes: u64);
+        begin_stage(name: &str);
+        end_stage();
+        end_staging();
+        begin_compress();
+        compress_progress(bytes: u64);
+        end_compress();
+        begin_restore(name: &str);
+        end_restore(success: bool);
+        begin_extract();
+        end_extract();
+        begin_restore_sp(name: &str);
+        end_restore_sp();
+        begin_prune();
+        pruned(name: &str);
+        end_prune();
+        begin_upload(target: &str, name: &str, size: u64);
+        upload_progress(bytes: u64);
+        end_upload(success: bool);
+        tick();
+    }
+}
+
+impl<A: SyncUiHandler, B: SyncUiHandler> SyncUiHandler for MultiUiHandler<A, B> {
+    forward! {
+        begin_scan();
+        end_scan();
+        begin_prepare();
+        end_prepare();
+        begin_sync(op_count: usize);
+        sync_progress();
+        end_sync();
+        begin_file(prefix: &str, filename: &str, size: u64);
+        file_progress(bytes: u64);
+        end_file();
+    }
+}
+
+/// How often the UI thread ticks its handler
+pub const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A call to a UI handler, sent from the engine to the UI thread, and on to attached clients
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UiEvent {
+    BeginBackup {
+        name: String,
+    },
+    BackupWarning {
+        message: String,
+    },
+    PathsSkipped {
+        names: Vec<String>,
+    },
+    EndBackup {
+        success: bool,
+    },
+    BeginStaging {
+        count: usize,
+        bytes: u64,
+    },
+    BeginInitialSnapshot {
+        files: usize,
+        bytes: u64,
+    },
+    BeginStage {
+        name: String,
+    },
+    EndStage,
+    EndStaging,
+    BeginCompress,
+    CompressProgress {
+        bytes: u64,
+    },
+    EndCompress,
+    BeginRestore {
+        name: String,
+    },
+    EndRestore {
+        success: bool,
+    },
+    BeginExtract,
+    EndExtract,
+    BeginRestoreSp {
+        name: String,
+    },
+    EndRestoreSp,
+    BeginPrune,
+    Pruned {
+        name: String,
+    },
+    EndPrune,
+    BeginUpload {
+        target: String,
+        name: String,
+        size: u64,
+    },
+    UploadProgress {
+        bytes: u64,
+    },
+    EndUpload {
+        success: bool,
+    },
+
+    /// Stops the UI thread, which clears its handler
+    Clear,
+
+    /// Serialized as the sync event itself, as subscribers expect
+    #[serde(untagged)]
+    Sync(SyncEvent),
+}
+
+impl UiEvent {
+    /// Call the handler method corresponding to the event
+    pub fn dispatch(self, ui: &mut impl StoolUiHandler) {
+        match self {
+            Self::BeginBackup { name } => ui.begin_backup(&name),
+            Self::BackupWarning { message } => ui.backup_warning(&message),
+            Self::PathsSkipped { names } => ui.paths_skipped(&names),
+            Self::EndBackup { success } => ui.end_backup(success),
+            Self::BeginStaging { count, bytes } => ui.begin_staging(count, bytes),
+            Self::BeginInitialSnapshot { files, bytes } => ui.begin_initial_snapshot(files, bytes),
+            Self::BeginStage { name } => ui.begin_stage(&name),
+            Self::EndStage => ui.end_stage(),
+            Self::EndStaging => ui.end_staging(),
+            Self::BeginCompress => ui.begin_compress(),
+            Self::CompressProgress { bytes } => ui.compress_progress(bytes),
+            Self::EndCompress => ui.end_compress(),
+            Self::BeginRestore { name } => ui.begin_restore(&name),
+            Self::EndRestore { success } => ui.end_restore(success),
+            Self::BeginExtract => ui.begin_extract(),
+            Self::EndExtract => ui.end_extract(),
+            Self::BeginRestoreSp { name } => ui.begin_restore_sp(&name),
+            Self::EndRestoreSp => ui.end_restore_sp(),
+            Self::BeginPrune => ui.begin_prune(),
+            Self::Pruned { name } => ui.pruned(&name),
+            Self::EndPrune => ui.end_prune(),
+            Self::BeginUpload { target, name, size } => ui.begin_upload(&target, &name, size),
+            Self::UploadProgress { bytes } => ui.upload_progress(bytes),
+            Self::EndUpload { success } => ui.end_upload(success),
+
+            Self::Clear => {}
+            Self::Sync(event) => event.dispatch(ui),
+        }
+    }
+}
+
+/// UI handler sending its calls as events to the UI thread.
+/// Cheap to clone, so that work on several threads can report to the same UI.
+/// Clearing any clone stops the UI thread.
+#[derive(Clone)]
+pub struct ChannelUiHandler {
+    tx: Sender<UiEvent>,
+}
+
+impl ChannelUiHandler {
+    fn send(&self, event: UiEvent) {
+        // Events sent after the UI thread has stopped are dropped
+        self.tx.send(event).ok();
+    }
+
+    fn send_sync(&self, event: SyncEvent) {
+        self.send(UiEvent::Sync(event));
+    }
+
+    /// Sender of sync events to the UI thread, for syncs to report their progress through
+    pub fn sync_events(&self) -> SyncEvents {
+        let tx = self.tx.clone();
+
+        SyncEvents::new(move |event| {
+            tx.send(UiEvent::Sync(event)).ok();
+        })
+    }
+}
+
+/// Senders of clients following the UI events of an engine
+pub type UiSubscribers = Arc<Mutex<Vec<Sender<UiEvent>>>>;
+
+/// Run a UI handler on its own thread, consuming events until a sender is cleared or all senders are dropped.
+/// Events are also passed on to subscribers, which are dropped once they disconnect.
+/// The handler is ticked every [`TICK_INTERVAL`] in between.
+/// The handler is cleared when the thread finishes.
+pub fn spawn_ui_thread(mut ui: impl StoolUiHandler, subscribers: UiSubscribers) -> (ChannelUiHandler, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel::<UiEvent>();
+
+    let join_handle = std::thread::spawn(move || {
+        let mut last_tick = Instant::now();
+
+        loop {
+            match rx.recv_timeout(TICK_INTERVAL.saturating_sub(last_tick.elapsed())) {
+                Ok(UiEvent::Clear) | Err(RecvTimeoutError::Disconnected) => break,
+                Ok(event) => {
+                    subscribers
+                        .lock()
+                        .unwrap()
+                        .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+
+                    event.dispatch(&mut ui);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            if last_tick.elapsed() >= TICK_INTERVAL {
+                ui.tick();
+                last_tick = Instant::now();
+            }
+        }
+
+        // Disconnect subscribers, so that they can tell the engine is gone
+        subscribers.lock().unwrap().clear();
+
+        if let Err(err) = ui.clear() {
+            error!("Error clearing UI: {err}");
+        }
+    });
+
+    (ChannelUiHandler { tx }, join_handle)
+}
+
+impl StoolUiHandler for ChannelUiHandler {
+    fn clear(self) -> Result<(), anyhow::Error> {
+        self.send(UiEvent::Clear);
+
+        Ok(())
+    }
+
+    fn begin_backup(&mut self, name: &str) {
+        self.send(UiEvent::BeginBackup { name: name.to_owned() });
+    }
+
+    fn backup_warning(&mut self, message: &str) {
+        self.send(UiEvent::BackupWarning {
+            message: message.to_owned(),
+        });
+    }
+
+    fn paths_skipped(&mut self, names: &[String]) {
+        self.send(UiEvent::PathsSkipped { names: names.to_vec() });
+    }
+
+    fn end_backup(&mut self, success: bool) {
+        self.send(UiEvent::EndBackup { success });
+    }
+
+    fn begin_staging(&mut self, count: usize, bytes: u64) {
+        self.send(UiEvent::BeginStaging { count, bytes });
+    }
+
+    fn begin_initial_snapshot(&mut self, files: usize, bytes: u64) {
+        self.send(UiEvent::BeginInitialSnapshot { files, bytes });
+    }
+
+    fn begin_stage(&mut self, name: &str) {
+        self.send(UiEvent::BeginStage { name: name.to_owned() });
+    }
+
+    fn end_stage(&mut self) {
+        self.send(UiEvent::EndStage);
+    }
+
+    fn end_staging(&mut self) {
+        self.send(UiEvent::EndStaging);
+    }
+
+    fn begin_compress(&mut self) {
+        self.send(UiEvent::BeginCompress);
+    }
+
+    fn compress_progress(&mut self, bytes: u64) {
+        self.send(UiEvent::CompressProgress { bytes });
+    }
+
+    fn end_compress(&mut self) {
+        self.send(UiEvent::EndCompress);
+    }
+
+    fn begin_restore(&mut self, name: &str) {
+        self.send(UiEvent::BeginRestore { name: name.to_owned() });
+    }
+
+    fn end_restore(&mut self, success: bool) {
+        self.send(UiEvent::EndRestore { success });
+    }
+
+    fn begin_extract(&mut self) {
+        self.send(UiEvent::BeginExtract);
+    }
+
+    fn end_extract(&mut self) {
+        self.send(UiEvent::EndExtract);
+    }
+
+    fn begin_restore_sp(&mut self, name: &str) {
+        self.send(UiEvent::BeginRestoreSp { name: name.to_owned() });
+    }
+
+    fn end_restore_sp(&mut self) {
+        self.send(UiEvent::EndRestoreSp);
+    }
+
+    fn begin_prune(&mut self) {
+        self.send(UiEvent::BeginPrune);
+    }
+
+    fn pruned(&mut self, name: &str) {
+        self.send(UiEvent::Pruned { name: name.to_owned() });
+    }
+
+    fn end_prune(&mut self) {
+        self.send(UiEvent::EndPrune);
+    }
+
+    fn begin_upload(&mut self, target: &str, name: &str, size: u64) {
+        self.send(UiEvent::BeginUpload {
+            target: target.to_owned(),
+            name: name.to_owned(),
+            size,
+        });
+    }
+
+    fn upload_progress(&mut self, bytes: u64) {
+        self.send(UiEvent::UploadProgress { bytes });
+    }
+
+    fn end_upload(&mut self, success: bool) {
+        self.send(UiEvent::EndUpload { success });
+    }
+
+    fn tick(&mut self) {}
+}
+
+impl SyncUiHandler for ChannelUiHandler {
+    fn begin_scan(&mut self) {
+        self.send_sync(SyncEvent::BeginScan);
+    }
+
+    fn end_scan(&mut self) {
+        self.send_sync(SyncEvent::EndScan);
+    }
+
+    fn begin_prepare(&mut self) {
+        self.send_sync(SyncEvent::BeginPrepare);
+    }
+
+    fn end_prepare(&mut self) {
+        self.send_sync(SyncEvent::EndPrepare);
+    }
+
+    fn begin_sync(&mut self, op_count: usize) {
+        self.send_sync(SyncEvent::BeginSync { op_count });
+    }
+
+    fn sync_progress(&mut self) {
+        self.send_sync(SyncEvent::SyncProgress);
+    }
+
+    fn end_sync(&mut self) {
+        self.send_sync(SyncEvent::EndSync);
+    }
+
+    fn begin_file(&mut self, prefix: &str, filename: &str, size: u64) {
+        self.send_sync(SyncEvent::BeginFile {
+            prefix: prefix.to_owned(),
+            filename: filename.to_owned(),
+            size,
+        });
+    }
+
+    fn file_progress(&mut self, bytes: u64) {
+        self.send_sync(SyncEvent::FileProgress { bytes });
+    }
+
+    fn end_file(&mut self) {
+        self.send_sync(SyncEvent::EndFile);
+    }
 }
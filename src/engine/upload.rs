@@ -0,0 +1,443 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender},
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde_derive::{Deserialize, Serialize};
+use time::{macros::format_description, OffsetDateTime, Time};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::game::BackupTarget,
+    internal::{
+        encryption::{self, encrypted_path},
+        format::format_bytes,
+        network,
+        parity::parity_path,
+    },
+};
+
+use super::{manifest::manifest_path, ui::StoolUiHandler, BackupKind};
+
+pub const UPLOAD_JOURNAL_FILENAME: &str = "uploads.json";
+/// Directory where encrypted copies are prepared before uploading
+pub const UPLOAD_STAGING_DIRNAME: &str = "upload-staging";
+
+const DEFAULT_CHUNK_SIZE_MIB: u64 = 8;
+const PART_SUFFIX: &str = ".part";
+/// Interval at which failed and postponed uploads are retried while the engine runs
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// An upload of a backup archive to a target that has not completed yet
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PendingUpload {
+    target: String,
+    archive_path: PathBuf,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct JournalData {
+    #[serde(default)]
+    pending: Vec<PendingUpload>,
+    /// Backups not uploaded since the last upload, by target, for targets only receiving every Nth backup
+    #[serde(default)]
+    skipped: BTreeMap<String, u32>,
+}
+
+/// Pending uploads, kept on disk so that uploads interrupted by network problems or shutdown are retried
+struct UploadJournal {
+    path: PathBuf,
+    data: JournalData,
+}
+
+impl UploadJournal {
+    fn load(path: PathBuf) -> Result<Self, anyhow::Error> {
+        let data = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).context("Error parsing upload journal")?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => JournalData::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self { path, data })
+    }
+
+    fn save(&self) -> Result<(), anyhow::Error> {
+        if self.data.pending.is_empty() && self.data.skipped.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path)?;
+            }
+
+            return Ok(());
+        }
+
+        // Write to a temporary file first, so that a crash never leaves a truncated journal
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&self.data)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Queue uploads of a new backup to the targets it should be uploaded to
+    fn queue(&mut self, archive_path: &Path, kind: BackupKind, targets: &BTreeMap<String, BackupTarget>) {
+        for (name, target) in targets.iter() {
+            if target.manual_only && kind != BackupKind::Manual {
+                continue;
+            }
+
+            if let Some(every) = target.every.filter(|every| *every > 1) {
+                let skipped = self.data.skipped.entry(name.clone()).or_default();
+
+                if *skipped + 1 < every {
+                    *skipped += 1;
+                    continue;
+                }
+
+                self.data.skipped.remove(name);
+            }
+
+            self.data.pending.push(PendingUpload {
+                target: name.clone(),
+                archive_path: archive_path.to_owned(),
+            });
+        }
+    }
+}
+
+/// Local time range in which uploads are allowed
+#[derive(Clone, Copy, Debug)]
+struct TimeWindow {
+    start: Time,
+    end: Time,
+}
+
+impl TimeWindow {
+    /// Parse a range of the form "HH:MM-HH:MM"
+    fn parse(s: &str) -> Result<Self, anyhow::Error> {
+        let format = format_description!("[hour]:[minute]");
+
+        let (start, end) = s
+            .split_once('-')
+            .with_context(|| format!("Invalid time range '{s}', expected HH:MM-HH:MM"))?;
+
+        Ok(Self {
+            start: Time::parse(start.trim(), format).with_context(|| format!("Invalid time '{start}'"))?,
+            end: Time::parse(end.trim(), format).with_context(|| format!("Invalid time '{end}'"))?,
+        })
+    }
+
+    fn contains(&self, time: Time) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            // Range wraps around midnight
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Check the settings of a target
+pub fn validate_target(target: &BackupTarget) -> Result<(), anyhow::Error> {
+    encryption::parse_recipients(&target.recipients)?;
+
+    if let Some(hours) = target.hours.as_deref() {
+        TimeWindow::parse(hours)?;
+    }
+
+    if target.every == Some(0) {
+        return Err(anyhow::anyhow!("'every' must be at least 1"));
+    }
+
+    Ok(())
+}
+
+/// Reason to postpone uploads to a target at the moment, if any
+fn postpone_reason(target: &BackupTarget, metered: &mut Option<Option<bool>>) -> Option<&'static str> {
+    if let Some(window) = target.hours.as_deref().and_then(|h| TimeWindow::parse(h).ok()) {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+
+        if !window.contains(now.time()) {
+            return Some("outside of upload hours");
+        }
+    }
+
+    // An unknown connection type is treated as not metered
+    if target.skip_metered && *metered.get_or_insert_with(network::is_metered) == Some(true) {
+        return Some("network connection is metered");
+    }
+
+    None
+}
+
+/// Start a thread uploading backup archives sent to it to the targets whose conditions they meet.
+/// Pending uploads from previous runs are retried first, and periodically afterwards.
+pub fn spawn_uploader(
+    output_path: &Path,
+    targets: BTreeMap<String, BackupTarget>,
+    mut ui: impl StoolUiHandler,
+) -> Sender<(PathBuf, BackupKind)> {
+    let (tx, rx) = std::sync::mpsc::channel::<(PathBuf, BackupKind)>();
+    let journal_path = output_path.join(UPLOAD_JOURNAL_FILENAME);
+    let upload_staging_path = output_path.join(UPLOAD_STAGING_DIRNAME);
+
+    std::thread::spawn(move || {
+        if let Err(err) = run_uploader(journal_path, &upload_staging_path, &targets, rx, &mut ui) {
+            error!("Uploader stopped: {err}");
+        }
+    });
+
+    tx
+}
+
+fn run_uploader(
+    journal_path: PathBuf,
+    upload_staging_path: &Path,
+    targets: &BTreeMap<String, BackupTarget>,
+    rx: Receiver<(PathBuf, BackupKind)>,
+    ui: &mut impl StoolUiHandler,
+) -> Result<(), anyhow::Error> {
+    let mut journal = UploadJournal::load(journal_path)?;
+
+    if !journal.data.pending.is_empty() {
+        info!("Retrying {} pending uploads", journal.data.pending.len());
+    }
+
+    // Runs until the backup thread drops its sender
+    loop {
+        process_pending(&mut journal, upload_staging_path, targets, ui)?;
+
+        match rx.recv_timeout(RETRY_INTERVAL) {
+            Ok((archive_path, kind)) => {
+                journal.queue(&archive_path, kind, targets);
+                journal.save()?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Attempt all pending uploads, keeping failed and postponed ones in the journal for the next attempt
+fn process_pending(
+    journal: &mut UploadJournal,
+    upload_staging_path: &Path,
+    targets: &BTreeMap<String, BackupTarget>,
+    ui: &mut impl StoolUiHandler,
+) -> Result<(), anyhow::Error> {
+    let mut metered = None;
+    let mut i = 0;
+
+    while i < journal.data.pending.len() {
+        let upload = &journal.data.pending[i];
+
+        let Some(target) = targets.get(&upload.target) else {
+            warn!("Dropping upload to removed target [{}]", upload.target);
+            journal.data.pending.remove(i);
+            journal.save()?;
+            continue;
+        };
+
+        // The backup may have been pruned or moved since the upload was queued
+        if !upload.archive_path.is_file() {
+            warn!(
+                "Dropping upload of missing backup to [{}]: {}",
+                upload.target,
+                upload.archive_path.display()
+            );
+            journal.data.pending.remove(i);
+            journal.save()?;
+            continue;
+        }
+
+        if let Some(reason) = postpone_reason(target, &mut metered) {
+            debug!("Postponing upload to [{}]: {reason}", upload.target);
+            i += 1;
+            continue;
+        }
+
+        let archive_name = upload.archive_path.file_name().unwrap_or_default().to_string_lossy();
+        let files = upload_files(&upload.archive_path);
+        let size = files.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+
+        ui.begin_upload(&upload.target, &archive_name, size);
+
+        let result = upload_backup(&files, target, &upload_staging_path.join(&upload.target), ui);
+
+        ui.end_upload(result.is_ok());
+
+        match result {
+            Ok(()) => {
+                info!("Uploaded to [{}]: {}", upload.target, upload.archive_path.display());
+                journal.data.pending.remove(i);
+                journal.save()?;
+            }
+            Err(err) => {
+                warn!("Upload to [{}] failed, will retry later: {err:#}", upload.target);
+                i += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Files uploaded for a backup archive: the archive, its parity data and its manifest, in upload order.
+/// The manifest is uploaded last, so a backup with a manifest at the target is complete.
+fn upload_files(archive_path: &Path) -> Vec<PathBuf> {
+    [
+        archive_path.to_owned(),
+        parity_path(archive_path),
+        manifest_path(archive_path),
+    ]
+    .into_iter()
+    .filter(|p| p.exists())
+    .collect()
+}
+
+/// Upload the files of a backup archive to a target
+fn upload_backup(
+    files: &[PathBuf],
+    target: &BackupTarget,
+    upload_staging_path: &Path,
+    ui: &mut impl StoolUiHandler,
+) -> Result<(), anyhow::Error> {
+    let chunk_size = target.chunk_size_mib.unwrap_or(DEFAULT_CHUNK_SIZE_MIB).max(1) * 1024 * 1024;
+
+    fs::create_dir_all(&target.path).with_context(|| format!("Creating {}", target.path.display()))?;
+
+    for src in files.iter() {
+        let file_name = src.file_name().context("Path has no file name")?;
+        let dst = target.path.join(file_name);
+
+        if target.recipients.is_empty() {
+            upload_file(src, &dst, chunk_size, ui)?;
+            continue;
+        }
+
+        // Encrypted copies are kept until uploaded, as encrypting again would not allow resuming
+        let encrypted = encrypted_path(&upload_staging_path.join(file_name));
+
+        if !encrypted.exists() {
+            fs::create_dir_all(upload_staging_path)?;
+
+            let tmp_path = encrypted.with_extension("tmp");
+            encryption::encrypt_file(src, &tmp_path, &target.recipients)?;
+
+            let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(src)?);
+            filetime::set_file_mtime(&tmp_path, mtime)?;
+
+            fs::rename(&tmp_path, &encrypted)?;
+        }
+
+        upload_file(&encrypted, &encrypted_path(&dst), chunk_size, ui)?;
+        fs::remove_file(&encrypted)?;
+    }
+
+    Ok(())
+}
+
+/// Copy a file in chunks, syncing each chunk to disk.
+/// Data is written to a partial file next to the destination, which is renamed into place when complete.
+/// If a partial file exists from an interrupted upload, copying resumes from its last complete chunk.
+pub(super) fn upload_file(
+    src: &Path,
+    dst: &Path,
+    chunk_size: u64,
+    ui: &mut impl StoolUiHandler,
+) -> Result<(), anyhow::Error> {
+    let src_len = fs::metadata(src)?.len();
+
+    if fs::metadata(dst).is_ok_and(|m| m.len() == src_len) {
+        ui.upload_progress(src_len);
+        return Ok(());
+    }
+
+    let mut part_path = dst.as_os_str().to_owned();
+    part_path.push(PART_SUFFIX);
+    let part_path = PathBuf::from(part_path);
+
+    let mut input = File::open(src)?;
+    let mut output = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&part_path)
+        .with_context(|| format!("Opening {}", part_path.display()))?;
+
+    // The last chunk of an interrupted upload may be incomplete, so resume at a chunk boundary
+    let part_len = output.metadata()?.len();
+    let offset = if part_len > src_len {
+        0
+    } else {
+        part_len / chunk_size * chunk_size
+    };
+
+    if offset > 0 {
+        info!("Resuming upload of {} at {}", src.display(), format_bytes(offset));
+    }
+
+    ui.upload_progress(offset);
+
+    output.set_len(offset)?;
+    output.seek(SeekFrom::Start(offset))?;
+    input.seek(SeekFrom::Start(offset))?;
+
+    let mut buf = Vec::with_capacity(chunk_size as usize);
+
+    loop {
+        buf.clear();
+        (&mut input).take(chunk_size).read_to_end(&mut buf)?;
+
+        if buf.is_empty() {
+            break;
+        }
+
+        output.write_all(&buf)?;
+        output.sync_data()?;
+
+        ui.upload_progress(buf.len() as u64);
+    }
+
+    drop(output);
+
+    // Preserve modification time, as backup age is based on it
+    let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(src)?);
+    filetime::set_file_mtime(&part_path, mtime)?;
+
+    fs::rename(&part_path, dst)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::testing::NullUiHandler;
+
+    use super::*;
+
+    #[test]
+    fn interrupted_upload_resumes_from_last_complete_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+
+        std::fs::write(&src, "0123456789").unwrap();
+
+        // One complete chunk, followed by a partially written one with bad data
+        std::fs::write(dir.path().join("dst.part"), "0123xyz").unwrap();
+
+        upload_file(&src, &dst, 4, &mut NullUiHandler).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&dst).unwrap(), "0123456789");
+        assert!(!dir.path().join("dst.part").exists());
+    }
+}
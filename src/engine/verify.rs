@@ -0,0 +1,102 @@
+use std::{fmt, fs, path::Path};
+
+use crate::internal::parity::{self, ParityStatus};
+
+use super::{backups, EngineArgs};
+
+/// Result of verifying a backup archive against its parity data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VerifyOutcome {
+    /// Archive has no parity data, so it cannot be verified
+    NoParity,
+    Intact,
+    /// Archive is damaged, but can be repaired
+    Damaged(ParityStatus),
+    Repaired(ParityStatus),
+    /// Archive is damaged in more blocks than the parity data can repair
+    Unrepairable(ParityStatus),
+}
+
+impl VerifyOutcome {
+    /// Whether the archive is damaged after verification
+    pub fn is_damaged(&self) -> bool {
+        matches!(self, Self::Damaged(_) | Self::Unrepairable(_))
+    }
+}
+
+impl fmt::Display for VerifyOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let damage = |status: &ParityStatus| {
+            format!(
+                "{} of {} data blocks and {} of {} parity blocks damaged",
+                status.damaged_data_blocks, status.data_blocks, status.damaged_parity_blocks, status.parity_blocks
+            )
+        };
+
+        match self {
+            Self::NoParity => write!(f, "no parity data"),
+            Self::Intact => write!(f, "OK"),
+            Self::Damaged(status) => write!(f, "damaged, repairable ({})", damage(status)),
+            Self::Repaired(status) => write!(f, "repaired ({})", damage(status)),
+            Self::Unrepairable(status) => write!(f, "damaged, NOT repairable ({})", damage(status)),
+        }
+    }
+}
+
+/// Result of verifying one backup
+pub struct VerifyResult {
+    pub name: String,
+    pub outcome: Result<VerifyOutcome, anyhow::Error>,
+}
+
+/// Verify a backup, or all backups of a game, against their parity data, optionally repairing damage
+pub fn verify_backups(
+    args: &EngineArgs,
+    archive: Option<&str>,
+    repair: bool,
+) -> Result<Vec<VerifyResult>, anyhow::Error> {
+    let backup_paths = args.backup_paths();
+
+    let archives = match archive {
+        Some(archive) => vec![(archive.to_owned(), backups::resolve_archive(&backup_paths, archive)?)],
+        None => backups::list_backups_in(&backup_paths)?
+            .into_iter()
+            .map(|b| (b.name, b.path))
+            .collect(),
+    };
+
+    Ok(archives
+        .into_iter()
+        .map(|(name, path)| VerifyResult {
+            name,
+            outcome: verify_archive(&path, repair),
+        })
+        .collect())
+}
+
+fn verify_archive(archive_path: &Path, repair: bool) -> Result<VerifyOutcome, anyhow::Error> {
+    let status = if repair {
+        // Keep the modification time, as backups are ordered and aged by it
+        let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(archive_path)?);
+        let status = parity::repair(archive_path)?;
+        filetime::set_file_mtime(archive_path, mtime)?;
+
+        status
+    } else {
+        parity::check(archive_path)?
+    };
+
+    let Some(status) = status else {
+        return Ok(VerifyOutcome::NoParity);
+    };
+
+    Ok(if status.is_intact() {
+        VerifyOutcome::Intact
+    } else if !status.is_repairable() {
+        VerifyOutcome::Unrepairable(status)
+    } else if repair {
+        VerifyOutcome::Repaired(status)
+    } else {
+        VerifyOutcome::Damaged(status)
+    })
+}
@@ -0,0 +1,289 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+use notify::{Config, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher, WatcherKind};
+use serde_derive::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::internal::sync;
+
+/// Activity of a watched save directory or file, for diagnosing missed changes
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WatchStatus {
+    pub path: PathBuf,
+    /// Watcher backend, such as inotify or poll
+    pub backend: String,
+    /// Events counted as changes to save files
+    pub events: u64,
+    /// Events for files excluded by filters
+    pub ignored: u64,
+    pub errors: u64,
+    /// Times the backend dropped events, such as when its queue overflowed under heavy writes
+    #[serde(default)]
+    pub dropped: u64,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_event_at: Option<OffsetDateTime>,
+}
+
+/// Watch activity shared with the engine control
+#[derive(Default)]
+pub struct WatchState {
+    pub watches: Vec<WatchStatus>,
+    /// Why watching fell back to polling, if it did
+    pub fallback: Option<String>,
+}
+
+type EventSender = Sender<notify::Result<notify::Event>>;
+
+/// Watcher of save paths, which can fall back to polling if the native backend fails
+pub struct SaveWatcher {
+    _watcher: Box<dyn Watcher + Send>,
+    kind: WatcherKind,
+    paths: Vec<(PathBuf, RecursiveMode)>,
+    /// Paths that did not exist when watching started, which polling cannot pick up
+    missing: Vec<PathBuf>,
+    tx: EventSender,
+    /// Interval at which save paths are scanned for changes when polling
+    poll_interval: Duration,
+}
+
+/// What a watcher event was counted as
+#[derive(Clone, Copy)]
+pub enum WatchEventKind {
+    Change,
+    Ignored,
+    Error,
+    Dropped,
+}
+
+impl WatchStatus {
+    pub fn new(path: PathBuf, backend: WatcherKind) -> Self {
+        Self {
+            path,
+            backend: backend_name(backend).to_owned(),
+            events: 0,
+            ignored: 0,
+            errors: 0,
+            dropped: 0,
+            last_event_at: None,
+        }
+    }
+}
+
+impl SaveWatcher {
+    /// Watch paths with the recommended backend of the platform
+    pub fn new(
+        paths: Vec<(PathBuf, RecursiveMode)>,
+        tx: EventSender,
+        poll_interval: Duration,
+    ) -> Result<Self, notify::Error> {
+        let watcher = RecommendedWatcher::new(tx.clone(), Config::default())?;
+
+        Self::watch(Box::new(watcher), RecommendedWatcher::kind(), paths, tx, poll_interval)
+    }
+
+    /// Watch paths by scanning them periodically
+    pub fn polling(
+        paths: Vec<(PathBuf, RecursiveMode)>,
+        tx: EventSender,
+        poll_interval: Duration,
+    ) -> Result<Self, notify::Error> {
+        let watcher = PollWatcher::new(tx.clone(), Config::default().with_poll_interval(poll_interval))?;
+
+        Self::watch(Box::new(watcher), PollWatcher::kind(), paths, tx, poll_interval)
+    }
+
+    fn watch(
+        mut watcher: Box<dyn Watcher + Send>,
+        kind: WatcherKind,
+        paths: Vec<(PathBuf, RecursiveMode)>,
+        tx: EventSender,
+        poll_interval: Duration,
+    ) -> Result<Self, notify::Error> {
+        for (path, mode) in paths.iter() {
+            watcher.watch(path, *mode)?;
+        }
+
+        let missing = paths
+            .iter()
+            .filter(|(path, _)| !path.exists())
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        Ok(Self {
+            _watcher: watcher,
+            kind,
+            paths,
+            missing,
+            tx,
+            poll_interval,
+        })
+    }
+
+    pub fn kind(&self) -> WatcherKind {
+        self.kind
+    }
+
+    pub fn is_polling(&self) -> bool {
+        self.kind == WatcherKind::PollWatcher
+    }
+
+    /// Replace the watcher with one polling the same paths
+    pub fn fall_back_to_polling(&mut self) -> Result<(), notify::Error> {
+        *self = Self::polling(self.paths.clone(), self.tx.clone(), self.poll_interval)?;
+
+        Ok(())
+    }
+
+    pub fn reappeared_paths(&self) -> Vec<PathBuf> {
+        self.missing.iter().filter(|path| path.exists()).cloned().collect()
+    }
+
+    /// Watch the same paths again with the same backend
+    pub fn rewatch(&mut self) -> Result<(), notify::Error> {
+        *self = if self.is_polling() {
+            Self::polling(self.paths.clone(), self.tx.clone(), self.poll_interval)?
+        } else {
+            Self::new(self.paths.clone(), self.tx.clone(), self.poll_interval)?
+        };
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn send(&self, event: notify::Event) {
+        self.tx.send(Ok(event)).unwrap();
+    }
+
+    /// Whether a path is one of the watched save directories or files
+    pub fn is_watched(&self, path: &Path) -> bool {
+        self.paths.iter().any(|(p, _)| p == path)
+    }
+}
+
+/// Staged copies of save directories and files, as of the last backup.
+/// Cloud clients report placeholders modified when downloading or freeing them up, without changing their contents,
+/// which the staged copies tell apart from changes.
+pub struct StagedCopies {
+    /// Save paths with the paths of their staged copies
+    paths: Vec<(PathBuf, PathBuf)>,
+}
+
+impl StagedCopies {
+    pub fn new(paths: Vec<(PathBuf, PathBuf)>) -> Self {
+        Self { paths }
+    }
+
+    /// Whether a save file is the same as its staged copy
+    pub fn is_unchanged(&self, path: &Path) -> bool {
+        let staged_path = self.paths.iter().find_map(|(save_path, staged_path)| {
+            let rel_path = path.strip_prefix(save_path).ok()?;
+
+            Some(match rel_path.as_os_str().is_empty() {
+                true => staged_path.clone(),
+                false => staged_path.join(rel_path),
+            })
+        });
+
+        staged_path.is_some_and(|staged_path| path.is_file() && sync::same_as_copy(path, &staged_path))
+    }
+}
+
+pub fn backend_name(kind: WatcherKind) -> &'static str {
+    match kind {
+        WatcherKind::Inotify => "inotify",
+        WatcherKind::Fsevent => "fsevent",
+        WatcherKind::Kqueue => "kqueue",
+        WatcherKind::PollWatcher => "poll",
+        WatcherKind::ReadDirectoryChangesWatcher => "windows",
+        WatcherKind::NullWatcher => "null",
+        _ => "other",
+    }
+}
+
+/// Count an event for each watch containing one of its paths.
+/// Events without paths, such as some errors, are counted for all watches.
+pub fn record_event(watches: &mut [WatchStatus], paths: &[PathBuf], kind: WatchEventKind) {
+    let now = OffsetDateTime::now_utc();
+
+    let matches = |watch: &WatchStatus| paths.is_empty() || paths.iter().any(|p| p.starts_with(&watch.path));
+
+    for watch in watches.iter_mut().filter(|w| matches(w)) {
+        match kind {
+            WatchEventKind::Change => watch.events += 1,
+            WatchEventKind::Ignored => watch.ignored += 1,
+            WatchEventKind::Error => watch.errors += 1,
+            WatchEventKind::Dropped => watch.dropped += 1,
+        }
+
+        watch.last_event_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use filetime::FileTime;
+
+    use super::*;
+
+    #[test]
+    fn staged_copies_tell_edits_apart_despite_same_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_path = dir.path().join("saves");
+        let staged_path = dir.path().join("staging");
+
+        for path in [&save_path, &staged_path] {
+            fs::create_dir_all(path).unwrap();
+        }
+
+        fs::write(save_path.join("slot1.sav"), "one").unwrap();
+        fs::write(staged_path.join("slot1.sav"), "one").unwrap();
+        fs::write(save_path.join("slot2.sav"), "new").unwrap();
+        fs::write(staged_path.join("slot2.sav"), "old").unwrap();
+
+        // As on filesystems with coarse modification times
+        let modified = FileTime::from_unix_time(1_000_000_000, 0);
+        for path in [&save_path, &staged_path] {
+            for file in ["slot1.sav", "slot2.sav"] {
+                filetime::set_file_mtime(path.join(file), modified).unwrap();
+            }
+        }
+
+        let staged_copies = StagedCopies::new(vec![(save_path.clone(), staged_path)]);
+
+        assert!(staged_copies.is_unchanged(&save_path.join("slot1.sav")));
+        assert!(!staged_copies.is_unchanged(&save_path.join("slot2.sav")));
+        assert!(!staged_copies.is_unchanged(&save_path.join("slot3.sav")));
+    }
+
+    #[test]
+    fn events_are_counted_for_the_watches_containing_them() {
+        let mut watches = vec![
+            WatchStatus::new(PathBuf::from("/saves/a"), WatcherKind::Inotify),
+            WatchStatus::new(PathBuf::from("/saves/b"), WatcherKind::Inotify),
+        ];
+
+        record_event(
+            &mut watches,
+            &[PathBuf::from("/saves/a/slot1.sav")],
+            WatchEventKind::Change,
+        );
+        record_event(
+            &mut watches,
+            &[PathBuf::from("/saves/b/slot1.sav")],
+            WatchEventKind::Dropped,
+        );
+        // Dropped events often come without paths
+        record_event(&mut watches, &[], WatchEventKind::Dropped);
+
+        assert_eq!((watches[0].events, watches[0].dropped), (1, 1));
+        assert_eq!((watches[1].events, watches[1].dropped), (0, 2));
+        assert!(watches.iter().all(|w| w.last_event_at.is_some()));
+    }
+}
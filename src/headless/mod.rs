@@ -0,0 +1,60 @@
+mod uihandler;
+
+use std::{fs, path::Path, sync::Mutex};
+
+use anyhow::Context;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+pub use uihandler::LogUiHandler;
+
+use crate::engine::Engine;
+
+/// Initialize logging to standard error, or to the specified log file
+pub fn init_logging(log_file_path: Option<&Path>) -> Result<(), anyhow::Error> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let registry = tracing_subscriber::registry().with(filter);
+
+    if let Some(log_file_path) = log_file_path {
+        if let Some(parent) = log_file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let log_file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file_path)
+            .with_context(|| format!("Opening log file: {}", log_file_path.display()))?;
+
+        registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(log_file)),
+            )
+            .init();
+    } else {
+        registry
+            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+            .init();
+    }
+
+    Ok(())
+}
+
+/// Run a function logging to standard error, for what happens before a command sets up logging
+pub fn with_startup_logging<T>(f: impl FnOnce() -> T) -> T {
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .without_time()
+        .finish();
+
+    tracing::subscriber::with_default(subscriber, f)
+}
+
+/// Run engine without any user interface until it shuts down
+pub fn run(engine: Engine) -> Result<(), anyhow::Error> {
+    // Wait for engine thread to finish
+    engine.join();
+
+    Ok(())
+}
@@ -0,0 +1,309 @@
+use std::time::{Duration, Instant};
+
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    engine::{
+        progress::{ActionKind, Phase, ProgressModel},
+        ui::StoolUiHandler,
+    },
+    internal::{
+        format::{format_bytes, format_duration},
+        sync::SyncUiHandler,
+    },
+};
+
+/// How often a long-running action is reported as still in progress
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// UI handler that reports progress through log messages only
+#[derive(Default)]
+pub struct LogUiHandler {
+    progress: ProgressModel,
+
+    /// Whether to periodically log that an action is still in progress
+    heartbeat: bool,
+    last_heartbeat_at: Option<Instant>,
+    /// Warnings of the backup in progress
+    backup_warnings: usize,
+    /// Save dirs and files left out of the backup in progress
+    skipped_paths: usize,
+}
+
+impl LogUiHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Periodically log the elapsed time and bytes processed of long-running actions,
+    /// so that a hung action can be told apart from a slow one
+    pub fn with_heartbeat(mut self) -> Self {
+        self.heartbeat = true;
+        self
+    }
+
+    /// Message reporting the current action as still in progress, if one is due
+    fn heartbeat(&mut self, now: Instant) -> Option<String> {
+        if !self.heartbeat {
+            return None;
+        }
+
+        let action = self.progress.action.as_ref()?;
+        let last_heartbeat_at = self.last_heartbeat_at.unwrap_or(action.started_at);
+
+        if now.saturating_duration_since(last_heartbeat_at) < HEARTBEAT_INTERVAL {
+            return None;
+        }
+
+        let elapsed = format_duration(now.saturating_duration_since(action.started_at));
+
+        let message = match action.stage_bytes.filter(|b| *b > 0) {
+            Some(stage_bytes) => format!(
+                "Still busy: {}... {elapsed} elapsed, {} of {} staged ({:.0}%)",
+                action.describe(),
+                format_bytes(action.bytes_staged),
+                format_bytes(stage_bytes),
+                self.progress.action_ratio() * 100.
+            ),
+            None => format!(
+                "Still busy: {}... {elapsed} elapsed, {} processed",
+                action.describe(),
+                format_bytes(action.bytes_processed)
+            ),
+        };
+
+        self.last_heartbeat_at = Some(now);
+
+        Some(message)
+    }
+
+    fn begin_action(&mut self, kind: ActionKind) {
+        info!("{}", kind.describe());
+        self.progress.begin_action(kind, None);
+        self.last_heartbeat_at = None;
+    }
+
+    fn end_action(&mut self, success: bool) {
+        let Some(action) = self.progress.end_action() else {
+            return;
+        };
+
+        let warnings = std::mem::take(&mut self.backup_warnings);
+        let skipped_paths = std::mem::take(&mut self.skipped_paths);
+
+        if success && skipped_paths > 0 {
+            warn!(
+                "{} ({skipped_paths} paths skipped, completed with {warnings} warnings)",
+                action.kind.describe_complete()
+            );
+        } else if success && warnings > 0 {
+            warn!(
+                "{} (completed with {warnings} warnings)",
+                action.kind.describe_complete()
+            );
+        } else if success {
+            info!("{}", action.kind.describe_complete());
+        } else {
+            error!("{}", action.kind.describe_error());
+        }
+    }
+}
+
+impl StoolUiHandler for LogUiHandler {
+    fn clear(self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    fn begin_backup(&mut self, name: &str) {
+        self.begin_action(ActionKind::CreateBackup { name: name.to_owned() });
+    }
+
+    fn backup_warning(&mut self, message: &str) {
+        warn!("{message}");
+        self.backup_warnings += 1;
+    }
+
+    fn paths_skipped(&mut self, names: &[String]) {
+        self.skipped_paths += names.len();
+    }
+
+    fn end_backup(&mut self, success: bool) {
+        self.end_action(success);
+    }
+
+    fn begin_staging(&mut self, count: usize, bytes: u64) {
+        self.progress.begin_staging(count, bytes);
+    }
+
+    fn begin_initial_snapshot(&mut self, files: usize, bytes: u64) {
+        self.progress.begin_initial_snapshot(files, bytes);
+    }
+
+    fn begin_stage(&mut self, name: &str) {
+        let stage_count = self.progress.action.as_ref().and_then(|a| a.stage_count);
+        let stages_done = self.progress.action.as_ref().map_or(0, |a| a.stages_done);
+
+        match stage_count {
+            Some(stage_count) => debug!("Staging: {name} ({}/{stage_count})", stages_done + 1),
+            None => debug!("Staging: {name}"),
+        }
+
+        self.progress.begin_stage(name);
+    }
+
+    fn end_stage(&mut self) {
+        self.progress.end_stage();
+    }
+
+    fn end_staging(&mut self) {
+        self.progress.end_staging();
+    }
+
+    fn begin_compress(&mut self) {
+        debug!("Compressing...");
+        self.progress.set_phase(Some(Phase::Compressing));
+    }
+
+    fn compress_progress(&mut self, bytes: u64) {
+        self.progress.compress_progress(bytes);
+    }
+
+    fn end_compress(&mut self) {
+        self.progress.set_phase(None);
+    }
+
+    fn begin_restore(&mut self, name: &str) {
+        self.begin_action(ActionKind::RestoreBackup { name: name.to_owned() });
+    }
+
+    fn end_restore(&mut self, success: bool) {
+        self.end_action(success);
+    }
+
+    fn begin_extract(&mut self) {
+        debug!("Extracting...");
+        self.progress.set_phase(Some(Phase::Extracting));
+    }
+
+    fn end_extract(&mut self) {
+        self.progress.set_phase(None);
+    }
+
+    fn begin_restore_sp(&mut self, name: &str) {
+        debug!("Restoring: {name}");
+        self.progress.set_phase(Some(Phase::Restoring));
+        self.progress.begin_stage(name);
+    }
+
+    fn end_restore_sp(&mut self) {
+        self.progress.end_stage();
+    }
+
+    fn begin_prune(&mut self) {
+        self.progress.begin_action(ActionKind::PruneBackups, None);
+    }
+
+    fn pruned(&mut self, name: &str) {
+        self.progress.pruned(name);
+    }
+
+    fn end_prune(&mut self) {
+        // Deleted backups are logged as they are pruned, so only report when something was deleted
+        if let Some(action) = self.progress.end_action().filter(|a| a.stages_done > 0) {
+            debug!("Pruned {} backups", action.stages_done);
+        }
+    }
+
+    fn begin_upload(&mut self, target: &str, name: &str, _size: u64) {
+        debug!("Uploading to [{target}]: {name}");
+    }
+
+    fn upload_progress(&mut self, _bytes: u64) {}
+
+    fn end_upload(&mut self, _success: bool) {}
+
+    fn tick(&mut self) {
+        if let Some(message) = self.heartbeat(Instant::now()) {
+            info!("{message}");
+        }
+    }
+}
+
+impl SyncUiHandler for LogUiHandler {
+    fn begin_scan(&mut self) {}
+
+    fn end_scan(&mut self) {}
+
+    fn begin_prepare(&mut self) {}
+
+    fn end_prepare(&mut self) {}
+
+    fn begin_sync(&mut self, op_count: usize) {
+        self.progress.begin_sync(op_count);
+    }
+
+    fn sync_progress(&mut self) {
+        self.progress.sync_progress();
+    }
+
+    fn end_sync(&mut self) {}
+
+    fn begin_file(&mut self, prefix: &str, filename: &str, size: u64) {
+        self.progress.begin_file(prefix, filename, size);
+    }
+
+    fn file_progress(&mut self, bytes: u64) {
+        self.progress.file_progress(bytes);
+    }
+
+    fn end_file(&mut self) {
+        self.progress.end_file();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heartbeat_reports_long_running_actions_periodically() {
+        let mut ui = LogUiHandler::new().with_heartbeat();
+        ui.begin_backup("backup.7z");
+        ui.begin_compress();
+        ui.compress_progress(2048);
+
+        let started_at = ui.progress.action.as_ref().unwrap().started_at;
+        let at = |secs| started_at + Duration::from_secs(secs);
+
+        assert_eq!(ui.heartbeat(at(10)), None);
+        assert_eq!(
+            ui.heartbeat(at(31)).as_deref(),
+            Some("Still busy: Creating backup: backup.7z - Compressing... 31s elapsed, 2.0 KiB processed")
+        );
+        assert_eq!(ui.heartbeat(at(45)), None);
+        assert!(ui.heartbeat(at(61)).is_some());
+
+        ui.end_backup(true);
+        assert_eq!(ui.heartbeat(at(100)), None);
+    }
+
+    #[test]
+    fn failed_actions_stop_the_heartbeat() {
+        let mut ui = LogUiHandler::new().with_heartbeat();
+        ui.begin_backup("backup.7z");
+
+        let started_at = ui.progress.action.as_ref().unwrap().started_at;
+        ui.end_backup(false);
+
+        assert_eq!(ui.heartbeat(started_at + HEARTBEAT_INTERVAL * 2), None);
+    }
+
+    #[test]
+    fn heartbeat_is_off_unless_enabled() {
+        let mut ui = LogUiHandler::new();
+        ui.begin_backup("backup.7z");
+
+        let started_at = ui.progress.action.as_ref().unwrap().started_at;
+        assert_eq!(ui.heartbeat(started_at + HEARTBEAT_INTERVAL * 2), None);
+    }
+}
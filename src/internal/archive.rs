@@ -0,0 +1,332 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use tracing::warn;
+
+/// A file or directory contained in an archive
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+    pub crc32: Option<u32>,
+}
+
+/// Archive that cannot be read without a password, or with the password given
+#[derive(Debug, thiserror::Error)]
+#[error("Archive is password-protected, or the password is wrong: {}", path.display())]
+pub struct PasswordRequired {
+    pub path: PathBuf,
+}
+
+/// Creates, unpacks and lists backup archives
+pub trait Archiver: Send + Sync {
+    /// Create an archive containing the contents of a directory
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error>;
+
+    /// Create an archive, trading compression for speed once past a deadline so as to finish soon after it.
+    /// Archivers that cannot do so ignore the deadline.
+    fn create_within(&self, src: &Path, archive_path: &Path, _deadline: Instant) -> Result<(), anyhow::Error> {
+        self.create(src, archive_path)
+    }
+
+    /// Unpack an archive into a directory
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error>;
+
+    /// List the contents of an archive without extracting it
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error>;
+
+    /// File name extension of created archives, without the leading dot
+    fn extension(&self) -> &'static str;
+
+    /// Password to read password-protected archives with, such as 7z archives made by hand.
+    /// Archivers that cannot read such archives ignore it.
+    fn set_password(&self, _password: Option<String>) {}
+
+    /// Directory for temporary files made while reading archives, such as decrypted copies.
+    /// Archivers that read archives in place ignore it.
+    fn set_temp_path(&self, _path: &Path) {}
+}
+
+/// How often a running 7z is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Creates archives with one archiver, and reads archives with whichever archiver matches their extension.
+/// Keeps backups readable after switching archive formats.
+pub struct MultiFormat {
+    archivers: Vec<Box<dyn Archiver>>,
+}
+
+impl MultiFormat {
+    /// The first archiver is used to create archives
+    pub fn new(primary: Box<dyn Archiver>, others: Vec<Box<dyn Archiver>>) -> Self {
+        let mut archivers = vec![primary];
+        archivers.extend(others);
+
+        Self { archivers }
+    }
+
+    fn for_archive(&self, archive_path: &Path) -> Result<&dyn Archiver, anyhow::Error> {
+        let file_name = archive_path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+
+        self.archivers
+            .iter()
+            .find(|a| file_name.ends_with(&format!(".{}", a.extension())))
+            .map(|a| a.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("Unknown archive format: {}", archive_path.display()))
+    }
+}
+
+impl Archiver for MultiFormat {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        self.archivers[0].create(src, archive_path)
+    }
+
+    fn create_within(&self, src: &Path, archive_path: &Path, deadline: Instant) -> Result<(), anyhow::Error> {
+        self.archivers[0].create_within(src, archive_path, deadline)
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        self.for_archive(archive_path)?.unpack(archive_path, dst)
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        self.for_archive(archive_path)?.list(archive_path)
+    }
+
+    fn extension(&self) -> &'static str {
+        self.archivers[0].extension()
+    }
+
+    fn set_password(&self, password: Option<String>) {
+        for archiver in self.archivers.iter() {
+            archiver.set_password(password.clone());
+        }
+    }
+}
+
+/// Archiver using the external 7z command
+pub struct SevenZip {
+    /// Compression level, from 0 to 9
+    pub level: u32,
+    /// Password of password-protected archives being read, which created archives never have
+    password: Mutex<Option<String>>,
+}
+
+impl SevenZip {
+    pub fn new(level: u32) -> Self {
+        Self {
+            level,
+            password: Mutex::new(None),
+        }
+    }
+
+    fn password(&self) -> Option<String> {
+        self.password.lock().unwrap().clone()
+    }
+}
+
+impl Archiver for SevenZip {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        create(src, archive_path, self.level, None)?;
+
+        Ok(())
+    }
+
+    /// 7z cannot change compression partway, so once past the deadline, it is started over storing files as they are
+    fn create_within(&self, src: &Path, archive_path: &Path, deadline: Instant) -> Result<(), anyhow::Error> {
+        if create(src, archive_path, self.level, Some(deadline))? {
+            return Ok(());
+        }
+
+        warn!("Backup is taking too long, storing files without compression");
+
+        if archive_path.exists() {
+            std::fs::remove_file(archive_path)?;
+        }
+
+        create(src, archive_path, 0, None)?;
+
+        Ok(())
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        unpack(archive_path, dst, self.password().as_deref())
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        list(archive_path, self.password().as_deref())
+    }
+
+    fn extension(&self) -> &'static str {
+        "7z"
+    }
+
+    fn set_password(&self, password: Option<String>) {
+        *self.password.lock().unwrap() = password;
+    }
+}
+
+/// Create an archive with 7z, returning whether it finished before the deadline, if any.
+/// 7z is stopped at the deadline.
+fn create(src: &Path, archive_path: &Path, level: u32, deadline: Option<Instant>) -> Result<bool, anyhow::Error> {
+    let mut child = std::process::Command::new("7z")
+        .current_dir(src)
+        .arg("a")
+        .arg(format!("-mx{}", level.min(9)))
+        .arg(archive_path)
+        .arg(".")
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Error running 7z")?;
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            child.kill()?;
+            child.wait()?;
+
+            return Ok(false);
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("7z exited with {status} while creating archive"));
+    }
+
+    Ok(true)
+}
+
+/// Run 7z to read an archive, with the password if any.
+/// Without standard input, 7z fails instead of prompting for a password the archive needs.
+fn read_archive(
+    mut command: std::process::Command,
+    archive_path: &Path,
+    password: Option<&str>,
+) -> Result<Output, anyhow::Error> {
+    if let Some(password) = password {
+        command.arg(format!("-p{password}"));
+    }
+
+    let output = command
+        .arg(archive_path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Error running 7z")?;
+
+    // 7z reports a missing or wrong password on either output, depending on its version
+    let mentions_password = |out: &[u8]| String::from_utf8_lossy(out).to_lowercase().contains("password");
+
+    if !output.status.success() && (mentions_password(&output.stderr) || mentions_password(&output.stdout)) {
+        return Err(PasswordRequired {
+            path: archive_path.to_owned(),
+        }
+        .into());
+    }
+
+    Ok(output)
+}
+
+fn unpack(archive_path: &Path, dst: &Path, password: Option<&str>) -> Result<(), anyhow::Error> {
+    let mut command = std::process::Command::new("7z");
+    command.current_dir(dst).arg("x");
+
+    let output = read_archive(command, archive_path, password)?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "7z exited with {} while unpacking archive: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+fn list(archive_path: &Path, password: Option<&str>) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+    let mut command = std::process::Command::new("7z");
+    command.args(["l", "-slt"]);
+
+    let output = read_archive(command, archive_path, password)?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "7z exited with {} while listing archive: {}",
+            output.status,
+            archive_path.display()
+        ));
+    }
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+
+    // Archives with only their contents encrypted can be listed, but not unpacked without a password
+    if password.is_none() && listing.lines().any(|line| line.trim() == "Encrypted = +") {
+        return Err(PasswordRequired {
+            path: archive_path.to_owned(),
+        }
+        .into());
+    }
+
+    Ok(parse_7z_listing(&listing))
+}
+
+/// Parse the technical listing (`7z l -slt`) output of 7z
+fn parse_7z_listing(listing: &str) -> Vec<ArchiveEntry> {
+    let listing = listing.replace("\r\n", "\n");
+
+    // Entries follow after a separator line, as blocks of "Key = Value" lines separated by blank lines
+    let Some((_, entries)) = listing.split_once("\n----------") else {
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+
+    for block in entries.split("\n\n") {
+        let mut path = None;
+        let mut size = 0;
+        let mut is_dir = false;
+        let mut crc32 = None;
+
+        for line in block.lines() {
+            let Some((key, value)) = line.split_once(" = ").or_else(|| line.split_once(" =")) else {
+                continue;
+            };
+
+            let value = value.trim();
+
+            match key.trim() {
+                "Path" => path = Some(PathBuf::from(value)),
+                "Size" => size = value.parse().unwrap_or(0),
+                "Folder" => is_dir |= value == "+",
+                "Attributes" => is_dir |= value.starts_with('D'),
+                "CRC" => crc32 = u32::from_str_radix(value, 16).ok(),
+                _ => {}
+            }
+        }
+
+        if let Some(path) = path {
+            result.push(ArchiveEntry {
+                path,
+                size,
+                is_dir,
+                crc32,
+            });
+        }
+    }
+
+    result
+}
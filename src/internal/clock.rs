@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use super::shutdown::Shutdown;
+
+/// Source of time for the engine, so that timing can be simulated
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Current wall clock time, for comparing against file times
+    fn system_now(&self) -> SystemTime;
+
+    fn sleep(&self, duration: Duration);
+
+    /// Sleep, waking up early if shutdown is requested
+    fn wait(&self, duration: Duration, shutdown: &Shutdown);
+}
+
+/// Clock following real time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn wait(&self, duration: Duration, shutdown: &Shutdown) {
+        shutdown.wait_timeout(duration);
+    }
+}
+
+/// Clock that only moves forward when advanced manually.
+/// Sleeping only yields briefly, so waits can be fast-forwarded with [`FakeClock::advance`].
+#[cfg(test)]
+pub struct FakeClock {
+    start: Instant,
+    system_start: SystemTime,
+    offset: std::sync::Mutex<Duration>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    const YIELD_DURATION: Duration = Duration::from_millis(1);
+
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            system_start: SystemTime::now(),
+            offset: std::sync::Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.start + *self.offset.lock().unwrap()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        self.system_start + *self.offset.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration.min(Self::YIELD_DURATION));
+    }
+
+    fn wait(&self, duration: Duration, shutdown: &Shutdown) {
+        shutdown.wait_timeout(duration.min(Self::YIELD_DURATION));
+    }
+}
@@ -0,0 +1,45 @@
+//! Binary deltas between versions of a file, stored as zstd frames compressed with the older version as reference,
+//! like `zstd --patch-from`
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+/// Largest file a delta can be made of or against, as zstd cannot reference data further back than 2 GiB
+pub const MAX_FILE_SIZE: u64 = 1 << 31;
+
+const MAX_WINDOW_LOG: u32 = 31;
+const MIN_WINDOW_LOG: u32 = 10;
+
+/// Write the delta from a base to a file
+pub fn encode(base: &[u8], path: &Path, output: &mut impl Write) -> Result<(), anyhow::Error> {
+    let size = fs::metadata(path)?.len();
+
+    let mut encoder = zstd::stream::Encoder::with_ref_prefix(output, zstd::DEFAULT_COMPRESSION_LEVEL, base)?;
+    encoder.window_log(window_log(base.len() as u64, size))?;
+    encoder.long_distance_matching(true)?;
+
+    io::copy(&mut BufReader::new(fs::File::open(path)?), &mut encoder)?;
+    encoder.finish()?;
+
+    Ok(())
+}
+
+/// Write the file reconstructed from a base and the delta to it
+pub fn decode(base: &[u8], delta: impl BufRead, output: &mut impl Write) -> Result<(), anyhow::Error> {
+    let mut decoder = zstd::stream::Decoder::with_ref_prefix(delta, base)?;
+    decoder.window_log_max(MAX_WINDOW_LOG)?;
+
+    io::copy(&mut decoder, output)?;
+
+    Ok(())
+}
+
+/// Window large enough for matches anywhere in the base
+fn window_log(base_size: u64, size: u64) -> u32 {
+    let max_size = base_size.max(size).max(1);
+
+    (u64::BITS - max_size.leading_zeros()).clamp(MIN_WINDOW_LOG, MAX_WINDOW_LOG)
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use sysinfo::Disks;
+use thiserror::Error;
+
+use super::format::format_bytes;
+
+#[derive(Debug, Error)]
+#[error("Not enough free space in {path}: {needed} needed, {available} available")]
+pub struct InsufficientSpace {
+    pub path: String,
+    pub needed: String,
+    pub available: String,
+}
+
+/// Space available on the volume holding a path, if the volume can be told
+pub fn available_space(path: &Path) -> Option<u64> {
+    // The path may not exist yet, so look up its nearest existing ancestor
+    let path = path.ancestors().find_map(|p| p.canonicalize().ok())?;
+    let disks = Disks::new_with_refreshed_list();
+
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Fail if the volume holding a path has less space available than needed.
+/// Passes if the volume cannot be told, as the operation will fail on its own if space runs out.
+pub fn check_free_space(path: &Path, needed: u64) -> Result<(), InsufficientSpace> {
+    match available_space(path) {
+        Some(available) if available < needed => Err(InsufficientSpace {
+            path: path.display().to_string(),
+            needed: format_bytes(needed),
+            available: format_bytes(available),
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,166 @@
+//! Encryption of backup copies to age recipients
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use anyhow::Context;
+
+use super::{
+    archive::{ArchiveEntry, Archiver},
+    disk,
+};
+
+pub const ENCRYPTED_SUFFIX: &str = ".age";
+
+/// Distinguishes temporary directories of concurrent decryptions
+static TMP_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Path of the encrypted copy of a file
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut encrypted_path = path.as_os_str().to_owned();
+    encrypted_path.push(ENCRYPTED_SUFFIX);
+
+    encrypted_path.into()
+}
+
+/// Check that recipients are valid age public keys
+pub fn parse_recipients(recipients: &[String]) -> Result<Vec<age::x25519::Recipient>, anyhow::Error> {
+    recipients
+        .iter()
+        .map(|r| {
+            r.parse()
+                .map_err(|err| anyhow::anyhow!("Invalid age recipient '{r}': {err}"))
+        })
+        .collect()
+}
+
+/// Encrypt a file to a list of age recipients
+pub fn encrypt_file(src: &Path, dst: &Path, recipients: &[String]) -> Result<(), anyhow::Error> {
+    let recipients = parse_recipients(recipients)?;
+
+    let encryptor = age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))?;
+
+    let mut input = File::open(src)?;
+    let output = BufWriter::new(File::create(dst)?);
+
+    let mut writer = encryptor.wrap_output(output)?;
+    io::copy(&mut input, &mut writer)?;
+    writer
+        .finish()?
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()?;
+
+    Ok(())
+}
+
+/// Decrypt a file with the identities in an age identity file
+pub fn decrypt_file(src: &Path, dst: &Path, identity_file: &Path) -> Result<(), anyhow::Error> {
+    let identities = age::IdentityFile::from_file(identity_file.to_string_lossy().into_owned())
+        .with_context(|| format!("Reading identity file {}", identity_file.display()))?
+        .into_identities()?;
+
+    let decryptor = age::Decryptor::new_buffered(BufReader::new(File::open(src)?))?;
+    let mut reader = decryptor.decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity))?;
+
+    let mut output = File::create(dst)?;
+    io::copy(&mut reader, &mut output)?;
+
+    Ok(())
+}
+
+/// Archiver that reads encrypted archives by decrypting them to a temporary file first.
+/// Other archives are passed through to the inner archiver.
+pub struct Decrypting<A: Archiver> {
+    pub inner: A,
+    /// Age identity file used for decryption
+    pub identity_file: Option<PathBuf>,
+    /// Directory archives are decrypted in, the system temp directory if none
+    pub temp_path: Mutex<Option<PathBuf>>,
+}
+
+impl<A: Archiver> Decrypting<A> {
+    fn with_decrypted<T>(
+        &self,
+        archive_path: &Path,
+        f: impl FnOnce(&Path) -> Result<T, anyhow::Error>,
+    ) -> Result<T, anyhow::Error> {
+        let file_name = archive_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let Some(decrypted_name) = file_name.strip_suffix(ENCRYPTED_SUFFIX) else {
+            return f(archive_path);
+        };
+
+        let identity_file = self
+            .identity_file
+            .as_deref()
+            .context("Archive is encrypted, but no age identity file is configured")?;
+
+        let temp_path = self
+            .temp_path
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(std::env::temp_dir);
+        disk::check_free_space(&temp_path, fs::metadata(archive_path)?.len())?;
+
+        let tmp_dir = temp_path.join(format!(
+            "stool-decrypt-{}-{}",
+            std::process::id(),
+            TMP_DIR_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&tmp_dir)?;
+
+        // The decrypted name keeps the inner extension, which selects the archive format
+        let decrypted_path = tmp_dir.join(decrypted_name);
+
+        let result = decrypt_file(archive_path, &decrypted_path, identity_file)
+            .with_context(|| format!("Decrypting {}", archive_path.display()))
+            .and_then(|_| f(&decrypted_path));
+
+        fs::remove_dir_all(&tmp_dir).ok();
+
+        result
+    }
+}
+
+impl<A: Archiver> Archiver for Decrypting<A> {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        self.inner.create(src, archive_path)
+    }
+
+    fn create_within(&self, src: &Path, archive_path: &Path, deadline: Instant) -> Result<(), anyhow::Error> {
+        self.inner.create_within(src, archive_path, deadline)
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        self.with_decrypted(archive_path, |path| self.inner.unpack(path, dst))
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        self.with_decrypted(archive_path, |path| self.inner.list(path))
+    }
+
+    fn extension(&self) -> &'static str {
+        self.inner.extension()
+    }
+
+    fn set_password(&self, password: Option<String>) {
+        self.inner.set_password(password);
+    }
+
+    fn set_temp_path(&self, path: &Path) {
+        *self.temp_path.lock().unwrap() = Some(path.to_owned());
+    }
+}
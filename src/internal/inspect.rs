@@ -0,0 +1,120 @@
+//! Inspectors extracting display metadata, such as character name, level or playtime, from save files.
+//!
+//! Inspectors are registered by name in [`INSPECTORS`], and created from the options given in a game config.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use regex::bytes::Regex;
+
+/// Metadata extracted from save files, as labels with display values
+pub type SaveMetadata = BTreeMap<String, String>;
+
+/// Parses save files of a game to extract display metadata
+pub trait SaveInspector: Send {
+    /// Extract metadata from a save file.
+    /// Returns empty metadata if the file contains none of the values the inspector looks for.
+    fn inspect(&self, path: &Path, contents: &[u8]) -> Result<SaveMetadata, anyhow::Error>;
+}
+
+/// Creates an inspector from the fields it should extract, as labels with inspector-specific locations
+type InspectorFactory = fn(&BTreeMap<String, String>) -> Result<Box<dyn SaveInspector>, anyhow::Error>;
+
+/// Available inspectors, by name
+pub const INSPECTORS: &[(&str, InspectorFactory)] =
+    &[("json", JsonInspector::create), ("regex", RegexInspector::create)];
+
+/// Create a registered inspector by name
+pub fn create_inspector(
+    name: &str,
+    fields: &BTreeMap<String, String>,
+) -> Result<Box<dyn SaveInspector>, anyhow::Error> {
+    let (_, factory) = INSPECTORS.iter().find(|(n, _)| *n == name).with_context(|| {
+        let names: Vec<_> = INSPECTORS.iter().map(|(n, _)| *n).collect();
+
+        format!("Unknown save inspector '{name}', expected one of: {}", names.join(", "))
+    })?;
+
+    factory(fields)
+}
+
+/// Inspector for JSON save files.
+/// Fields are located with JSON pointers, such as `/player/name`.
+pub struct JsonInspector {
+    fields: BTreeMap<String, String>,
+}
+
+impl JsonInspector {
+    fn create(fields: &BTreeMap<String, String>) -> Result<Box<dyn SaveInspector>, anyhow::Error> {
+        for (label, pointer) in fields.iter() {
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                return Err(anyhow::anyhow!(
+                    "Field '{label}': JSON pointer '{pointer}' must be empty or start with '/'"
+                ));
+            }
+        }
+
+        Ok(Box::new(Self { fields: fields.clone() }))
+    }
+}
+
+impl SaveInspector for JsonInspector {
+    fn inspect(&self, path: &Path, contents: &[u8]) -> Result<SaveMetadata, anyhow::Error> {
+        let json: serde_json::Value =
+            serde_json::from_slice(contents).with_context(|| format!("Parsing {} as JSON", path.display()))?;
+
+        Ok(self
+            .fields
+            .iter()
+            .filter_map(|(label, pointer)| {
+                let value = match json.pointer(pointer)? {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Null => return None,
+                    value => value.to_string(),
+                };
+
+                Some((label.clone(), value))
+            })
+            .collect())
+    }
+}
+
+/// Inspector for save files of any format, including binary ones.
+/// Fields are located with regular expressions, whose first capture group is the value,
+/// or the whole match if there is none, such as `"level":\s*(\d+)`.
+pub struct RegexInspector {
+    fields: Vec<(String, Regex)>,
+}
+
+impl RegexInspector {
+    fn create(fields: &BTreeMap<String, String>) -> Result<Box<dyn SaveInspector>, anyhow::Error> {
+        let fields = fields
+            .iter()
+            .map(|(label, pattern)| {
+                let regex = Regex::new(pattern).with_context(|| format!("Field '{label}': invalid regex"))?;
+
+                Ok((label.clone(), regex))
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+
+        Ok(Box::new(Self { fields }))
+    }
+}
+
+impl SaveInspector for RegexInspector {
+    fn inspect(&self, _path: &Path, contents: &[u8]) -> Result<SaveMetadata, anyhow::Error> {
+        Ok(self
+            .fields
+            .iter()
+            .filter_map(|(label, regex)| {
+                let captures = regex.captures(contents)?;
+                let value = captures.get(1).or_else(|| captures.get(0))?;
+
+                Some((
+                    label.clone(),
+                    String::from_utf8_lossy(value.as_bytes()).trim().to_owned(),
+                ))
+            })
+            .collect())
+    }
+}
@@ -1,4 +1,22 @@
+pub mod archive;
+pub mod clock;
+pub mod delta;
+pub mod disk;
+pub mod encryption;
 pub mod filter;
+pub mod format;
 pub mod hash;
+pub mod inspect;
+pub mod network;
+pub mod parity;
 pub mod pid;
+pub mod process;
+pub mod proton;
+pub mod rcon;
+pub mod registry;
+pub mod shutdown;
 pub mod sync;
+pub mod tar_zstd;
+#[cfg(feature = "self-update")]
+pub mod update;
+pub mod zip;
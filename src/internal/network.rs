@@ -0,0 +1,64 @@
+#[cfg(any(target_os = "linux", windows))]
+use std::process::{Command, Stdio};
+
+/// Whether the current network connection is metered, if it can be determined
+#[cfg(target_os = "linux")]
+pub fn is_metered() -> Option<bool> {
+    // NetworkManager reports an NMMetered value: 1 yes, 2 no, 3 guessed yes, 4 guessed no
+    let output = Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // Output is of the form "u 4"
+    match String::from_utf8_lossy(&output.stdout).split_whitespace().nth(1)? {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the current network connection is metered, if it can be determined
+#[cfg(windows)]
+pub fn is_metered() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let script = "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,\
+                  ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType";
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "Fixed" | "Variable" => Some(true),
+        "Unrestricted" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the current network connection is metered, if it can be determined
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn is_metered() -> Option<bool> {
+    None
+}
@@ -0,0 +1,319 @@
+//! Reed-Solomon parity data stored next to a file, for detecting and repairing corruption.
+//!
+//! The file is split into at most [`MAX_DATA_BLOCKS`] equally sized blocks, with the last one zero-padded.
+//! Parity blocks are computed over all data blocks, and a checksum of every block is kept to locate damage.
+//! As many damaged blocks as there are parity blocks can be repaired.
+//!
+//! The parity file contains the parity blocks, followed by a header with the checksums of all blocks.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use serde_derive::{Deserialize, Serialize};
+
+pub const PARITY_SUFFIX: &str = ".parity";
+
+const MAGIC: &[u8] = b"STOOLPAR1\n";
+const MAX_DATA_BLOCKS: u64 = 128;
+const BLOCK_ALIGN: u64 = 4096;
+/// Blocks are processed in segments of this size, to bound memory use for large files
+const SEGMENT_SIZE: u64 = 64 * 1024;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ParityHeader {
+    file_size: u64,
+    block_size: u64,
+    data_crcs: Vec<u32>,
+    parity_crcs: Vec<u32>,
+}
+
+/// Result of checking a file against its parity data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParityStatus {
+    pub data_blocks: usize,
+    pub parity_blocks: usize,
+    pub damaged_data_blocks: usize,
+    pub damaged_parity_blocks: usize,
+}
+
+impl ParityStatus {
+    pub fn is_intact(&self) -> bool {
+        self.damaged_data_blocks == 0 && self.damaged_parity_blocks == 0
+    }
+
+    pub fn is_repairable(&self) -> bool {
+        self.damaged_data_blocks + self.damaged_parity_blocks <= self.parity_blocks
+    }
+}
+
+/// Path of the parity file of a file
+pub fn parity_path(path: &Path) -> PathBuf {
+    let mut parity_path = path.as_os_str().to_owned();
+    parity_path.push(PARITY_SUFFIX);
+
+    parity_path.into()
+}
+
+/// Create a parity file for a file, with parity blocks amounting to `percent` of its size
+pub fn create(path: &Path, percent: u32) -> Result<(), anyhow::Error> {
+    let file_size = fs::metadata(path)?.len();
+
+    let block_size = file_size.div_ceil(MAX_DATA_BLOCKS).max(1).next_multiple_of(BLOCK_ALIGN);
+    let data_blocks = file_size.div_ceil(block_size).max(1) as usize;
+    let parity_blocks = (data_blocks * percent.clamp(1, 100) as usize).div_ceil(100);
+
+    let rs = ReedSolomon::new(data_blocks, parity_blocks)?;
+
+    let mut input = File::open(path)?;
+    let mut output = File::create(parity_path(path))?;
+    output.write_all(MAGIC)?;
+
+    let parity_offset = MAGIC.len() as u64;
+
+    let mut data_crcs = vec![crc32fast::Hasher::new(); data_blocks];
+    let mut parity_crcs = vec![crc32fast::Hasher::new(); parity_blocks];
+
+    for_each_segment(block_size, |offset, len| {
+        let mut shards = Vec::with_capacity(data_blocks + parity_blocks);
+
+        for (i, crc) in data_crcs.iter_mut().enumerate() {
+            let segment = read_padded(&mut input, i as u64 * block_size + offset, len)?;
+            crc.update(&segment);
+            shards.push(segment);
+        }
+
+        shards.resize(data_blocks + parity_blocks, vec![0; len]);
+        rs.encode(&mut shards)?;
+
+        for (i, segment) in shards[data_blocks..].iter().enumerate() {
+            parity_crcs[i].update(segment);
+
+            output.seek(SeekFrom::Start(parity_offset + i as u64 * block_size + offset))?;
+            output.write_all(segment)?;
+        }
+
+        Ok(())
+    })?;
+
+    let header = ParityHeader {
+        file_size,
+        block_size,
+        data_crcs: data_crcs.into_iter().map(|h| h.finalize()).collect(),
+        parity_crcs: parity_crcs.into_iter().map(|h| h.finalize()).collect(),
+    };
+
+    // The header follows the parity blocks, as checksums are only known once they are written
+    let header_json = serde_json::to_vec(&header)?;
+
+    output.seek(SeekFrom::Start(parity_offset + parity_blocks as u64 * block_size))?;
+    output.write_all(&header_json)?;
+    output.write_all(&(header_json.len() as u32).to_le_bytes())?;
+    output.sync_all()?;
+
+    Ok(())
+}
+
+/// Check a file against its parity file, if one exists
+pub fn check(path: &Path) -> Result<Option<ParityStatus>, anyhow::Error> {
+    let Some((header, parity_offset)) = read_header(path)? else {
+        return Ok(None);
+    };
+
+    let (damaged_data, damaged_parity) = find_damaged_blocks(path, &header, parity_offset)?;
+
+    Ok(Some(status(&header, &damaged_data, &damaged_parity)))
+}
+
+/// Repair damaged blocks of a file and its parity file.
+/// Returns the status from before the repair, or nothing if the file has no parity file.
+pub fn repair(path: &Path) -> Result<Option<ParityStatus>, anyhow::Error> {
+    let Some((header, parity_offset)) = read_header(path)? else {
+        return Ok(None);
+    };
+
+    let (damaged_data, damaged_parity) = find_damaged_blocks(path, &header, parity_offset)?;
+    let status = status(&header, &damaged_data, &damaged_parity);
+
+    if status.is_intact() || !status.is_repairable() {
+        return Ok(Some(status));
+    }
+
+    let data_blocks = header.data_crcs.len();
+    let parity_blocks = header.parity_crcs.len();
+    let block_size = header.block_size;
+
+    let rs = ReedSolomon::new(data_blocks, parity_blocks)?;
+
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut parity_file = OpenOptions::new().read(true).write(true).open(parity_path(path))?;
+
+    for_each_segment(block_size, |offset, len| {
+        let mut shards = Vec::with_capacity(data_blocks + parity_blocks);
+
+        for (i, damaged) in damaged_data.iter().enumerate() {
+            shards.push(
+                (!damaged)
+                    .then(|| read_padded(&mut file, i as u64 * block_size + offset, len))
+                    .transpose()?,
+            );
+        }
+
+        for (i, damaged) in damaged_parity.iter().enumerate() {
+            let position = parity_offset + i as u64 * block_size + offset;
+            shards.push(
+                (!damaged)
+                    .then(|| read_padded(&mut parity_file, position, len))
+                    .transpose()?,
+            );
+        }
+
+        rs.reconstruct(&mut shards)?;
+
+        for (i, shard) in shards.iter().enumerate() {
+            let (output, position) = match i.checked_sub(data_blocks) {
+                None if damaged_data[i] => (&mut file, i as u64 * block_size + offset),
+                Some(p) if damaged_parity[p] => (&mut parity_file, parity_offset + p as u64 * block_size + offset),
+                _ => continue,
+            };
+
+            output.seek(SeekFrom::Start(position))?;
+            output.write_all(shard.as_ref().context("Shard was not reconstructed")?)?;
+        }
+
+        Ok(())
+    })?;
+
+    // Drop the padding written into the last block
+    file.set_len(header.file_size)?;
+    file.sync_all()?;
+    parity_file.sync_all()?;
+
+    Ok(Some(status))
+}
+
+fn status(header: &ParityHeader, damaged_data: &[bool], damaged_parity: &[bool]) -> ParityStatus {
+    ParityStatus {
+        data_blocks: header.data_crcs.len(),
+        parity_blocks: header.parity_crcs.len(),
+        damaged_data_blocks: damaged_data.iter().filter(|d| **d).count(),
+        damaged_parity_blocks: damaged_parity.iter().filter(|d| **d).count(),
+    }
+}
+
+/// Read the header of the parity file of a file, along with the offset of the parity blocks
+fn read_header(path: &Path) -> Result<Option<(ParityHeader, u64)>, anyhow::Error> {
+    let parity_path = parity_path(path);
+
+    if !parity_path.exists() {
+        return Ok(None);
+    }
+
+    let mut input = File::open(&parity_path)?;
+    let parity_file_size = input.metadata()?.len();
+
+    let mut magic = [0; MAGIC.len()];
+    input.read_exact(&mut magic)?;
+    if magic != MAGIC || parity_file_size < (MAGIC.len() + 4) as u64 {
+        return Err(anyhow::anyhow!("Not a parity file: {}", parity_path.display()));
+    }
+
+    let mut header_len = [0; 4];
+    input.seek(SeekFrom::End(-4))?;
+    input.read_exact(&mut header_len)?;
+    let header_len = u32::from_le_bytes(header_len) as u64;
+
+    let header_offset = parity_file_size
+        .checked_sub(4 + header_len)
+        .with_context(|| format!("Damaged parity file: {}", parity_path.display()))?;
+
+    let mut header_json = vec![0; header_len as usize];
+    input.seek(SeekFrom::Start(header_offset))?;
+    input.read_exact(&mut header_json)?;
+
+    let header: ParityHeader = serde_json::from_slice(&header_json)
+        .with_context(|| format!("Error parsing parity file {}", parity_path.display()))?;
+
+    Ok(Some((header, MAGIC.len() as u64)))
+}
+
+/// Compare checksums of all data and parity blocks against the header
+fn find_damaged_blocks(
+    path: &Path,
+    header: &ParityHeader,
+    parity_offset: u64,
+) -> Result<(Vec<bool>, Vec<bool>), anyhow::Error> {
+    let mut file = File::open(path)?;
+    let mut parity_file = File::open(parity_path(path))?;
+
+    let mut data_crcs = vec![crc32fast::Hasher::new(); header.data_crcs.len()];
+    let mut parity_crcs = vec![crc32fast::Hasher::new(); header.parity_crcs.len()];
+
+    for_each_segment(header.block_size, |offset, len| {
+        for (i, crc) in data_crcs.iter_mut().enumerate() {
+            crc.update(&read_padded(&mut file, i as u64 * header.block_size + offset, len)?);
+        }
+
+        for (i, crc) in parity_crcs.iter_mut().enumerate() {
+            let position = parity_offset + i as u64 * header.block_size + offset;
+            crc.update(&read_padded(&mut parity_file, position, len)?);
+        }
+
+        Ok(())
+    })?;
+
+    // A file with the wrong size has damage in its last block, even if the padding happens to match
+    let size_matches = fs::metadata(path)?.len() == header.file_size;
+
+    let mut damaged_data: Vec<bool> = data_crcs
+        .into_iter()
+        .zip(header.data_crcs.iter())
+        .map(|(h, crc)| h.finalize() != *crc)
+        .collect();
+
+    if !size_matches {
+        if let Some(last) = damaged_data.last_mut() {
+            *last = true;
+        }
+    }
+
+    let damaged_parity = parity_crcs
+        .into_iter()
+        .zip(header.parity_crcs.iter())
+        .map(|(h, crc)| h.finalize() != *crc)
+        .collect();
+
+    Ok((damaged_data, damaged_parity))
+}
+
+/// Call a function with the offset and length of each segment of a block
+fn for_each_segment(
+    block_size: u64,
+    mut f: impl FnMut(u64, usize) -> Result<(), anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    let mut offset = 0;
+
+    while offset < block_size {
+        let len = SEGMENT_SIZE.min(block_size - offset);
+        f(offset, len as usize)?;
+        offset += len;
+    }
+
+    Ok(())
+}
+
+/// Read `len` bytes at a position, padding with zeros past the end of the file
+fn read_padded(file: &mut File, position: u64, len: usize) -> Result<Vec<u8>, anyhow::Error> {
+    let mut buf = Vec::with_capacity(len);
+
+    file.seek(SeekFrom::Start(position))?;
+    file.take(len as u64).read_to_end(&mut buf)?;
+    buf.resize(len, 0);
+
+    Ok(buf)
+}
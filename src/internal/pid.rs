@@ -60,6 +60,19 @@ impl Drop for PidLock {
     }
 }
 
+/// Check whether a PID-lock file is held by a running process
+pub fn is_held(path: impl AsRef<Path>) -> bool {
+    let Ok(pid) = fs::read_to_string(path) else {
+        return false;
+    };
+
+    let Ok(pid) = pid.trim().parse::<Pid>() else {
+        return false;
+    };
+
+    process_exists(pid)
+}
+
 fn process_exists(pid: Pid) -> bool {
     use sysinfo::{RefreshKind, System};
 
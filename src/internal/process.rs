@@ -0,0 +1,49 @@
+//! Finding running processes by name
+
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+
+/// Running processes whose names match a regular expression
+pub struct ProcessMatcher {
+    regex: regex::Regex,
+    sys: System,
+}
+
+impl ProcessMatcher {
+    pub fn new(pattern: &str) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            regex: regex::Regex::new(pattern)?,
+            sys: System::new_with_specifics(RefreshKind::nothing()),
+        })
+    }
+
+    /// Whether any running process has a matching name
+    pub fn is_running(&mut self) -> bool {
+        self.sys
+            .refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+
+        self.sys
+            .processes()
+            .values()
+            .any(|process| self.regex.is_match(&process.name().to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_matcher_finds_running_processes_by_name() {
+        let name = std::env::current_exe()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let pattern = format!("^{}", regex::escape(&name[..name.len().min(15)]));
+
+        assert!(ProcessMatcher::new(&pattern).unwrap().is_running());
+        assert!(!ProcessMatcher::new("^no-such-game-process$").unwrap().is_running());
+        assert!(ProcessMatcher::new("(").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Locating Proton prefixes of Windows games run through Steam on Linux
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Leading path component standing for the `drive_c` directory of the Proton prefix of a game
+pub const PREFIX_PLACEHOLDER: &str = "%proton%";
+
+/// Steam installation directories to look for libraries in: native, Debian/Ubuntu, Flatpak and Snap installs
+pub fn steam_roots() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+
+    [
+        ".steam/steam",
+        ".local/share/Steam",
+        ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+        "snap/steam/common/.local/share/Steam",
+    ]
+    .into_iter()
+    .map(|p| home.join(p))
+    .filter(|p| p.is_dir())
+    .collect()
+}
+
+/// Steam library directories of a Steam installation, including the installation itself
+fn library_paths(steam_root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![steam_root.to_owned()];
+
+    // Additional libraries are listed as `"path"		"/mnt/games/SteamLibrary"` lines
+    if let Ok(vdf) = fs::read_to_string(steam_root.join("steamapps/libraryfolders.vdf")) {
+        for line in vdf.lines() {
+            let mut tokens = line.split('"').filter(|t| !t.trim().is_empty());
+
+            if tokens.next() == Some("path") {
+                if let Some(path) = tokens.next() {
+                    libraries.push(PathBuf::from(path.replace("\\\\", "\\")));
+                }
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Find the `drive_c` directory of the Proton prefix of a Steam app in any library of the given Steam installations
+pub fn find_drive_c(app_id: u32, steam_roots: &[PathBuf]) -> Option<PathBuf> {
+    steam_roots
+        .iter()
+        .flat_map(|root| library_paths(root))
+        .map(|library| {
+            library
+                .join("steamapps/compatdata")
+                .join(app_id.to_string())
+                .join("pfx/drive_c")
+        })
+        .find(|drive_c| drive_c.is_dir())
+}
+
+/// Replace a leading [`PREFIX_PLACEHOLDER`] component of a path with the given `drive_c` directory
+pub fn expand_path(path: &Path, drive_c: &Path) -> Option<PathBuf> {
+    path.strip_prefix(PREFIX_PLACEHOLDER)
+        .ok()
+        .map(|rest| drive_c.join(rest))
+}
+
+/// Whether a path starts with [`PREFIX_PLACEHOLDER`]
+pub fn is_in_prefix(path: &Path) -> bool {
+    path.starts_with(PREFIX_PLACEHOLDER)
+}
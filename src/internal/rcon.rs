@@ -0,0 +1,129 @@
+//! Minimal client for the Source RCON protocol, spoken by the remote consoles of many game servers
+
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use anyhow::Context;
+
+/// Packet type of command requests
+pub const TYPE_EXEC_COMMAND: i32 = 2;
+/// Packet type of authentication responses, which shares its value with command requests
+pub const TYPE_AUTH_RESPONSE: i32 = 2;
+/// Packet type of authentication requests
+pub const TYPE_AUTH: i32 = 3;
+/// Packet type of command responses
+pub const TYPE_RESPONSE_VALUE: i32 = 0;
+
+/// Largest packet accepted, well above the 4096 byte bodies servers send
+const MAX_PACKET_SIZE: i32 = 64 * 1024;
+
+pub struct Packet {
+    pub id: i32,
+    pub kind: i32,
+    pub body: String,
+}
+
+/// Authenticated connection to a remote console
+pub struct Rcon {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl Rcon {
+    pub fn connect(address: &str, password: &str, timeout: Duration) -> Result<Self, anyhow::Error> {
+        let socket_address = address
+            .to_socket_addrs()?
+            .next()
+            .with_context(|| format!("Could not resolve {address}"))?;
+
+        let stream = TcpStream::connect_timeout(&socket_address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut rcon = Self { stream, next_id: 1 };
+        let id = rcon.send(TYPE_AUTH, password)?;
+
+        // Some servers send an empty response value ahead of the authentication response
+        loop {
+            let packet = read_packet(&mut rcon.stream)?;
+
+            if packet.kind != TYPE_AUTH_RESPONSE {
+                continue;
+            }
+
+            if packet.id != id {
+                return Err(anyhow::anyhow!("RCON authentication failed"));
+            }
+
+            return Ok(rcon);
+        }
+    }
+
+    /// Run a command, returning its response
+    pub fn command(&mut self, command: &str) -> Result<String, anyhow::Error> {
+        let id = self.send(TYPE_EXEC_COMMAND, command)?;
+
+        loop {
+            let packet = read_packet(&mut self.stream)?;
+
+            if packet.id == id && packet.kind == TYPE_RESPONSE_VALUE {
+                return Ok(packet.body);
+            }
+        }
+    }
+
+    fn send(&mut self, kind: i32, body: &str) -> Result<i32, anyhow::Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        write_packet(
+            &mut self.stream,
+            &Packet {
+                id,
+                kind,
+                body: body.to_owned(),
+            },
+        )?;
+
+        Ok(id)
+    }
+}
+
+/// Read a packet: its size, ID and type as little-endian 32-bit integers, then its null-terminated body and an empty string
+pub fn read_packet(reader: &mut impl Read) -> Result<Packet, anyhow::Error> {
+    let mut int = [0; 4];
+
+    reader.read_exact(&mut int)?;
+    let size = i32::from_le_bytes(int);
+
+    if !(10..=MAX_PACKET_SIZE).contains(&size) {
+        return Err(anyhow::anyhow!("Invalid RCON packet size: {size}"));
+    }
+
+    let mut data = vec![0; size as usize];
+    reader.read_exact(&mut data)?;
+
+    let id = i32::from_le_bytes(data[0..4].try_into()?);
+    let kind = i32::from_le_bytes(data[4..8].try_into()?);
+    let body = String::from_utf8_lossy(&data[8..data.len() - 2]).into_owned();
+
+    Ok(Packet { id, kind, body })
+}
+
+pub fn write_packet(writer: &mut impl Write, packet: &Packet) -> Result<(), anyhow::Error> {
+    let size = 4 + 4 + packet.body.len() as i32 + 2;
+
+    let mut data = Vec::with_capacity(4 + size as usize);
+    data.extend(size.to_le_bytes());
+    data.extend(packet.id.to_le_bytes());
+    data.extend(packet.kind.to_le_bytes());
+    data.extend(packet.body.as_bytes());
+    data.extend([0, 0]);
+
+    writer.write_all(&data)?;
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Export and import of Windows registry keys, using `reg.exe`
+
+use std::path::Path;
+
+/// Directory in the staging directory that registry keys are exported to
+pub const STAGING_DIR_NAME: &str = "%registry%";
+
+/// Whether registry keys can be backed up on this platform and build
+pub const SUPPORTED: bool = cfg!(all(windows, feature = "registry"));
+
+/// Export registry keys to .reg files in a directory, replacing any previous exports
+#[cfg(all(windows, feature = "registry"))]
+pub fn export(keys: &[String], dir: &Path) -> Result<(), anyhow::Error> {
+    use std::fs;
+
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+
+    fs::create_dir_all(dir)?;
+
+    for key in keys {
+        reg(&["export", key, &dir.join(export_file_name(key)).to_string_lossy(), "/y"])?;
+    }
+
+    Ok(())
+}
+
+/// Name of the .reg file a registry key is exported to
+#[cfg(any(test, all(windows, feature = "registry")))]
+fn export_file_name(key: &str) -> String {
+    format!("{}.reg", key.replace(['\\', '/', ':'], "_"))
+}
+
+/// Import all .reg files in a directory into the registry
+#[cfg(all(windows, feature = "registry"))]
+pub fn import(dir: &Path) -> Result<(), anyhow::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("reg")) {
+            reg(&["import", &path.to_string_lossy()])?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(windows, feature = "registry"))]
+fn reg(args: &[&str]) -> Result<(), anyhow::Error> {
+    use std::os::windows::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let output = Command::new("reg")
+        .args(args)
+        .stdin(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "reg {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(all(windows, feature = "registry")))]
+pub fn export(_keys: &[String], _dir: &Path) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!(
+        "Registry backup requires Windows and the `registry` feature"
+    ))
+}
+
+#[cfg(not(all(windows, feature = "registry")))]
+pub fn import(_dir: &Path) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!(
+        "Registry restore requires Windows and the `registry` feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_exported_to_valid_file_names() {
+        assert_eq!(
+            export_file_name(r"HKEY_CURRENT_USER\Software\Studio\Game"),
+            "HKEY_CURRENT_USER_Software_Studio_Game.reg"
+        );
+        assert_eq!(export_file_name(r"HKCU\Software/Game:1"), "HKCU_Software_Game_1.reg");
+    }
+
+    #[test]
+    #[cfg(not(all(windows, feature = "registry")))]
+    fn unsupported_builds_refuse_registry_keys() {
+        assert!(export(&[r"HKCU\Software\Game".to_owned()], Path::new("registry")).is_err());
+        assert!(import(Path::new("registry")).is_err());
+    }
+}
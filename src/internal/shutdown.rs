@@ -0,0 +1,39 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+/// Shutdown request, which threads waiting on it wake up from right away.
+/// Cheap to clone, and all clones share the same request.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        let (requested, condvar) = &*self.inner;
+
+        *requested.lock().unwrap() = true;
+        condvar.notify_all();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    /// Wait until shutdown is requested or the timeout passes, returning whether it was requested
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (requested, condvar) = &*self.inner;
+
+        let (requested, _) = condvar
+            .wait_timeout_while(requested.lock().unwrap(), timeout, |requested| !*requested)
+            .unwrap();
+
+        *requested
+    }
+}
@@ -1,15 +1,22 @@
 use std::{
-    collections::HashSet,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     fs,
-    io::ErrorKind,
+    io::{self, ErrorKind},
+    ops::AddAssign,
     path::{Path, PathBuf},
+    sync::{mpsc, Arc},
 };
 
 use anyhow::Context;
 use filetime::FileTime;
-use tracing::error;
+use serde_derive::{Deserialize, Serialize};
+use tracing::{error, info, warn};
 
-use crate::internal::hash::hash_crc32;
+use crate::{
+    config::game::{CloudPlaceholders, VerifyMode},
+    internal::hash::hash_crc32,
+};
 
 #[derive(Debug)]
 pub struct SyncDir {
@@ -17,6 +24,25 @@ pub struct SyncDir {
 
     dirs: HashSet<PathBuf>,
     files: HashSet<PathBuf>,
+    /// Known states of files, for directories read from an index
+    states: HashMap<PathBuf, FileState>,
+}
+
+/// Contents of a directory as left by a sync, which the next sync to it can use instead of scanning it
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DirIndex {
+    dirs: HashSet<PathBuf>,
+    files: HashMap<PathBuf, FileState>,
+}
+
+/// Size and modification time of a file
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct FileState {
+    size: u64,
+    mtime: i64,
+    mtime_nanos: u32,
 }
 
 #[derive(Debug)]
@@ -26,6 +52,27 @@ enum SyncOp {
     Delete { path: PathBuf },
     RemoveDir { path: PathBuf },
     VerifyCheckSum { path: PathBuf, size: u64, crc32: u32 },
+    SetModified { path: PathBuf, modified: FileTime },
+}
+
+/// File or directory found by scanning a directory
+struct ScanEntry {
+    rel_path: PathBuf,
+    is_file: bool,
+}
+
+/// How a destination file differs from its source
+enum FileDiff {
+    Unchanged,
+    /// Same content, with a different modification time
+    Retimed {
+        modified: FileTime,
+    },
+    /// Different content, with the source checksum if it was computed to tell
+    Changed {
+        size: u64,
+        crc32: Option<u32>,
+    },
 }
 
 #[derive(Debug)]
@@ -34,6 +81,60 @@ pub struct SyncJob {
     dst_path: PathBuf,
 
     ops: Vec<SyncOp>,
+    unchanged: usize,
+    placeholders_skipped: usize,
+    /// Contents of the destination once the job has run
+    index: DirIndex,
+    overwrite_read_only: bool,
+}
+
+/// How files are copied
+#[derive(Clone, Copy, Default)]
+pub struct CopyOptions {
+    pub verify: VerifyMode,
+    /// Clear read-only and hidden attributes of destination files that are overwritten or deleted
+    pub overwrite_read_only: bool,
+    pub placeholders: CloudPlaceholders,
+}
+
+/// What happens to files deleted from the destination
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Deletion<'a> {
+    #[default]
+    Remove,
+    /// Move to the trash of the operating system
+    Trash,
+    /// Move into a directory, at the same path relative to it as to the destination
+    Recycle(&'a Path),
+}
+
+/// How a directory is synced
+#[derive(Clone, Copy)]
+pub struct SyncOptions<'a> {
+    pub include_globset: Option<&'a globset::GlobSet>,
+    pub ignore_globset: Option<&'a globset::GlobSet>,
+    /// Paths never to be synced, such as stool's own data
+    pub exclude: &'a [PathBuf],
+    /// Apply filters to the destination as well, leaving files excluded by them alone
+    pub filter_in_dst: bool,
+    /// Compare and copy files while walking both directories in sorted order, instead of scanning them first.
+    /// Memory use no longer grows with the number of files, but the number of operations is not known up front.
+    pub streaming: bool,
+    pub copy: CopyOptions,
+    pub deletion: Deletion<'a>,
+}
+
+/// Summary of the changes made by a sync
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SyncStats {
+    pub files_copied: usize,
+    pub bytes_copied: u64,
+    pub files_deleted: usize,
+    pub files_unchanged: usize,
+    /// Files with the same content, of which only the modification time was updated
+    pub files_retimed: usize,
+    /// Cloud placeholders left as they were in the destination, rather than downloaded
+    pub placeholders_skipped: usize,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -64,131 +165,234 @@ pub trait SyncUiHandler {
     fn end_file(&mut self);
 }
 
+/// Progress of a sync, sent as it happens to a UI handler consuming it on its own thread
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncEvent {
+    BeginScan,
+    EndScan,
+    BeginPrepare,
+    EndPrepare,
+    BeginSync {
+        op_count: usize,
+    },
+    SyncProgress,
+    EndSync,
+    BeginFile {
+        prefix: String,
+        filename: String,
+        size: u64,
+    },
+    FileProgress {
+        bytes: u64,
+    },
+    EndFile,
+}
+
+impl SyncEvent {
+    /// Call the handler method corresponding to the event
+    pub fn dispatch(self, ui: &mut (impl SyncUiHandler + ?Sized)) {
+        match self {
+            Self::BeginScan => ui.begin_scan(),
+            Self::EndScan => ui.end_scan(),
+            Self::BeginPrepare => ui.begin_prepare(),
+            Self::EndPrepare => ui.end_prepare(),
+            Self::BeginSync { op_count } => ui.begin_sync(op_count),
+            Self::SyncProgress => ui.sync_progress(),
+            Self::EndSync => ui.end_sync(),
+            Self::BeginFile { prefix, filename, size } => ui.begin_file(&prefix, &filename, size),
+            Self::FileProgress { bytes } => ui.file_progress(bytes),
+            Self::EndFile => ui.end_file(),
+        }
+    }
+}
+
+/// Sender of sync events.
+/// Cheap to clone, so that syncs running on several threads can report to the same UI.
+#[derive(Clone)]
+pub struct SyncEvents {
+    send: Arc<dyn Fn(SyncEvent) + Send + Sync>,
+}
+
+impl SyncEvents {
+    pub fn new(send: impl Fn(SyncEvent) + Send + Sync + 'static) -> Self {
+        Self { send: Arc::new(send) }
+    }
+
+    /// Sender dropping its events, for syncs with no UI to report to
+    #[cfg(test)]
+    pub fn discard() -> Self {
+        Self::new(|_| {})
+    }
+
+    pub fn send(&self, event: SyncEvent) {
+        (self.send)(event);
+    }
+}
+
+/// Run a function reporting sync progress to a UI handler, which consumes its events on its own thread.
+/// Returns once the function has finished and the handler has consumed all of its events.
+pub fn with_sync_ui<T>(mut ui: impl SyncUiHandler + Send + 'static, f: impl FnOnce(&SyncEvents) -> T) -> T {
+    let (tx, rx) = mpsc::channel::<SyncEvent>();
+
+    let join_handle = std::thread::spawn(move || {
+        for event in rx {
+            event.dispatch(&mut ui);
+        }
+    });
+
+    let events = SyncEvents::new(move |event| {
+        tx.send(event).ok();
+    });
+
+    let result = f(&events);
+
+    // The UI thread stops once all senders are dropped
+    drop(events);
+    join_handle.join().unwrap();
+
+    result
+}
+
 impl SyncDir {
     pub fn new(
         path: &Path,
         include_globset: Option<&globset::GlobSet>,
         ignore_globset: Option<&globset::GlobSet>,
-        ui: &mut dyn SyncUiHandler,
+        exclude: &[PathBuf],
+        events: &SyncEvents,
     ) -> Result<Self, anyhow::Error> {
         let path = path.canonicalize()?;
         let mut dirs: HashSet<PathBuf> = HashSet::new();
         let mut files: HashSet<PathBuf> = HashSet::new();
 
-        ui.begin_scan();
-
-        let entries = walkdir::WalkDir::new(&path).into_iter().filter_map(Result::ok);
-
-        for entry in entries {
-            let is_file = entry.file_type().is_file();
-            let rel_path = entry.into_path().strip_prefix(&path)?.to_path_buf();
+        events.send(SyncEvent::BeginScan);
 
-            if !is_file {
-                dirs.insert(rel_path);
-                continue;
-            }
+        for entry in scan(&path, include_globset, ignore_globset, exclude, false) {
+            // Unreadable entries would otherwise pass for deleted
+            let entry = entry?;
 
-            if let Some(include_globset) = include_globset {
-                if !include_globset.is_match(&rel_path) {
-                    continue;
-                }
+            if entry.is_file {
+                files.insert(entry.rel_path);
+            } else {
+                dirs.insert(entry.rel_path);
             }
-
-            if let Some(ignore_globset) = ignore_globset {
-                if ignore_globset.is_match(&rel_path) {
-                    continue;
-                }
-            }
-
-            files.insert(rel_path);
         }
 
-        ui.end_scan();
+        events.send(SyncEvent::EndScan);
+
+        Ok(Self {
+            path,
+            dirs,
+            files,
+            states: HashMap::new(),
+        })
+    }
 
-        Ok(Self { path, dirs, files })
+    /// Directory as recorded by the index of a previous sync to it
+    pub fn from_index(path: &Path, index: DirIndex) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            path: path.canonicalize()?,
+            dirs: index.dirs,
+            files: index.files.keys().cloned().collect(),
+            states: index.files,
+        })
     }
 
-    pub fn sync_from(&self, other: &Self, ui: &mut dyn SyncUiHandler) -> Result<SyncJob, anyhow::Error> {
+    pub fn sync_from(&self, other: &Self, copy: CopyOptions, events: &SyncEvents) -> Result<SyncJob, anyhow::Error> {
         let src = other;
         let dst = self;
+        let verify = copy.verify;
 
         let src_path = src.path.clone();
         let dst_path = dst.path.clone();
 
-        ui.begin_prepare();
+        events.send(SyncEvent::BeginPrepare);
 
         let item_count = src.dirs.len() + src.files.len();
         let mut ops: Vec<SyncOp> = Vec::with_capacity(item_count);
         let mut post_ops: Vec<SyncOp> = Vec::with_capacity(item_count);
+        let mut index = DirIndex {
+            dirs: src.dirs.clone(),
+            files: HashMap::with_capacity(src.files.len()),
+        };
 
         // Create dirs not in destination
         let dirs_not_in_dst = src.dirs.difference(&self.dirs);
         ops.extend(dirs_not_in_dst.map(|p| SyncOp::CreateDir { path: p.clone() }));
 
         // Copy files not in destination
+        let mut placeholders_skipped = 0;
         let files_not_in_dst = src.files.difference(&self.files);
         for p in files_not_in_dst {
             let src_file_path = src_path.join(p);
 
-            let src_metadata = src_file_path.metadata()?;
-            let size = src_metadata.len();
+            if skips_placeholder(copy, &src_file_path, p) {
+                placeholders_skipped += 1;
+                continue;
+            }
 
-            ui.begin_file("Checksum", &p.to_string_lossy(), size);
+            let src_state = FileState::read(&src_file_path)?;
+            let size = src_state.size;
+            index.files.insert(p.clone(), src_state);
 
-            let src_hash = hash_crc32(&src_file_path, |bytes| ui.file_progress(bytes as u64))?;
+            ops.push(SyncOp::Copy { path: p.clone() });
 
-            ui.end_file();
+            if verify != VerifyMode::Fast {
+                let src_hash = checksum(&src_file_path, p, size, events)?;
 
-            ops.push(SyncOp::Copy { path: p.clone() });
-            post_ops.push(SyncOp::VerifyCheckSum {
-                path: p.clone(),
-                size,
-                crc32: src_hash,
-            });
+                post_ops.push(SyncOp::VerifyCheckSum {
+                    path: p.clone(),
+                    size,
+                    crc32: src_hash,
+                });
+            }
         }
 
         // Copy files that differ
+        let mut unchanged = 0;
         let files_in_both = src.files.intersection(&dst.files);
-        'copy_different: for p in files_in_both.into_iter() {
+        for p in files_in_both.into_iter() {
             let src_file_path = src_path.join(p);
             let dst_file_path = dst_path.join(p);
 
-            let src_size;
-
-            'diff: {
-                let dst_metadata = dst_file_path.metadata()?;
-                let src_metadata = src_file_path.metadata()?;
-
-                src_size = src_metadata.len();
-                let dst_size = dst_metadata.len();
-
-                if src_size != dst_size {
-                    break 'diff;
-                }
+            let dst_state = match dst.states.get(p) {
+                Some(state) => *state,
+                None => FileState::read(&dst_file_path)?,
+            };
 
-                let src_modified = FileTime::from_last_modification_time(&src_metadata);
-                let dst_modified = FileTime::from_last_modification_time(&dst_metadata);
+            if skips_placeholder(copy, &src_file_path, p) {
+                placeholders_skipped += 1;
+                index.files.insert(p.clone(), dst_state);
+                continue;
+            }
 
-                if src_modified != dst_modified {
-                    break 'diff;
+            let src_state = FileState::read(&src_file_path)?;
+            index.files.insert(p.clone(), src_state);
+
+            match compare_files(&src_file_path, src_state, &dst_file_path, dst_state, p, verify, events)? {
+                FileDiff::Unchanged => unchanged += 1,
+                FileDiff::Retimed { modified } => ops.push(SyncOp::SetModified {
+                    path: p.clone(),
+                    modified,
+                }),
+                FileDiff::Changed { size, crc32 } => {
+                    ops.push(SyncOp::Copy { path: p.clone() });
+
+                    if verify != VerifyMode::Fast {
+                        let crc32 = match crc32 {
+                            Some(hash) => hash,
+                            None => checksum(&src_file_path, p, size, events)?,
+                        };
+
+                        post_ops.push(SyncOp::VerifyCheckSum {
+                            path: p.clone(),
+                            size,
+                            crc32,
+                        });
+                    }
                 }
-
-                // No differences found, skip to next file
-                continue 'copy_different;
             }
-
-            ui.begin_file("Checksum", &p.to_string_lossy(), src_size);
-
-            let src_hash = hash_crc32(&src_file_path, |bytes| ui.file_progress(bytes as u64))?;
-
-            ui.end_file();
-
-            ops.push(SyncOp::Copy { path: p.clone() });
-            post_ops.push(SyncOp::VerifyCheckSum {
-                path: p.clone(),
-                size: src_size,
-                crc32: src_hash,
-            });
         }
 
         // Delete files not in source
@@ -208,106 +412,777 @@ impl SyncDir {
         // Add post-ops to the end
         ops.extend(post_ops);
 
-        ui.end_prepare();
+        events.send(SyncEvent::EndPrepare);
 
         Ok(SyncJob {
             src_path,
             dst_path,
             ops,
+            unchanged,
+            placeholders_skipped,
+            index,
+            overwrite_read_only: copy.overwrite_read_only,
         })
     }
 }
 
 impl SyncJob {
-    pub fn execute(self, ui: &mut dyn SyncUiHandler) -> Result<(), SyncJobError> {
+    pub fn execute(self, deletion: Deletion, events: &SyncEvents) -> Result<SyncStats, SyncJobError> {
         let src_path = self.src_path;
         let dst_path = self.dst_path;
 
-        ui.begin_sync(self.ops.len());
+        let mut stats = SyncStats {
+            files_unchanged: self.unchanged,
+            placeholders_skipped: self.placeholders_skipped,
+            ..Default::default()
+        };
+
+        events.send(SyncEvent::BeginSync {
+            op_count: self.ops.len(),
+        });
 
         for op in self.ops {
-            match op {
-                SyncOp::Copy { path } => {
-                    let src_file_path = src_path.join(&path);
-                    let dst_file_path = dst_path.join(&path);
-
-                    let Ok(src_metadata) = src_file_path.metadata() else {
-                        error!("Could not get metadata for source file: {}", src_file_path.display());
-                        return Err(SyncJobError::ReadError { path });
-                    };
-
-                    let src_modified = FileTime::from_last_modification_time(&src_metadata);
-
-                    let size = src_metadata.len();
-                    ui.begin_file("Copy", &path.to_string_lossy(), size);
-
-                    let res = fs::copy(&src_file_path, &dst_file_path);
-                    match res {
-                        Ok(_) => {}
-                        Err(err) => match err.kind() {
-                            ErrorKind::NotFound => return Err(SyncJobError::FileNotFound { path }),
-                            _ => return Err(SyncJobError::Anyhow(err.into())),
-                        },
-                    }
+            execute_op(
+                op,
+                &src_path,
+                &dst_path,
+                self.overwrite_read_only,
+                deletion,
+                &mut stats,
+                events,
+            )?;
+            events.send(SyncEvent::SyncProgress);
+        }
+
+        events.send(SyncEvent::EndSync);
 
-                    ui.file_progress(size);
+        Ok(stats)
+    }
+}
 
-                    filetime::set_file_mtime(&dst_file_path, src_modified)
-                        .map_err(|e| SyncJobError::Anyhow(e.into()))?;
+/// Run a single operation of a sync job
+fn execute_op(
+    op: SyncOp,
+    src_path: &Path,
+    dst_path: &Path,
+    overwrite_read_only: bool,
+    deletion: Deletion,
+    stats: &mut SyncStats,
+    events: &SyncEvents,
+) -> Result<(), SyncJobError> {
+    match op {
+        SyncOp::Copy { path } => {
+            let src_file_path = src_path.join(&path);
+            let dst_file_path = dst_path.join(&path);
+
+            let Ok(src_metadata) = src_file_path.metadata() else {
+                error!("Could not get metadata for source file: {}", src_file_path.display());
+                return Err(SyncJobError::ReadError { path });
+            };
+
+            let src_modified = FileTime::from_last_modification_time(&src_metadata);
 
-                    ui.end_file();
-                }
-                SyncOp::CreateDir { path } => {
-                    fs::create_dir_all(dst_path.join(path)).map_err(|e| SyncJobError::Anyhow(e.into()))?;
-                }
-                SyncOp::Delete { path } => {
-                    fs::remove_file(dst_path.join(path)).map_err(|e| SyncJobError::Anyhow(e.into()))?;
+            let size = src_metadata.len();
+            events.send(SyncEvent::BeginFile {
+                prefix: "Copy".to_owned(),
+                filename: path.to_string_lossy().into_owned(),
+                size,
+            });
+
+            prepare_overwrite(&dst_file_path, overwrite_read_only)?;
+
+            // Reading placeholders through has the cloud client download them, where copying them may fail
+            let res = if is_placeholder(&src_file_path) {
+                copy_by_reading(&src_file_path, &dst_file_path)
+            } else {
+                fs::copy(&src_file_path, &dst_file_path)
+            };
+            match res {
+                Ok(_) => {}
+                Err(err) => match err.kind() {
+                    ErrorKind::NotFound => return Err(SyncJobError::FileNotFound { path }),
+                    _ => return Err(SyncJobError::Anyhow(dst_error("overwriting", &dst_file_path, err))),
+                },
+            }
+
+            events.send(SyncEvent::FileProgress { bytes: size });
+
+            stats.files_copied += 1;
+            stats.bytes_copied += size;
+
+            filetime::set_file_mtime(&dst_file_path, src_modified).map_err(|e| SyncJobError::Anyhow(e.into()))?;
+
+            events.send(SyncEvent::EndFile);
+        }
+        SyncOp::CreateDir { path } => {
+            fs::create_dir_all(dst_path.join(path)).map_err(|e| SyncJobError::Anyhow(e.into()))?;
+        }
+        SyncOp::Delete { path } => {
+            let dst_file_path = dst_path.join(&path);
+
+            prepare_overwrite(&dst_file_path, overwrite_read_only)?;
+
+            delete_file(&dst_file_path, &path, deletion)?;
+
+            stats.files_deleted += 1;
+        }
+        SyncOp::RemoveDir { path } => {
+            let res = fs::remove_dir(dst_path.join(path));
+            match res {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::DirectoryNotEmpty => {}
+                Err(err) => Err(SyncJobError::Anyhow(err.into()))?,
+            }
+        }
+        SyncOp::SetModified { path, modified } => {
+            filetime::set_file_mtime(dst_path.join(path), modified).map_err(|e| SyncJobError::Anyhow(e.into()))?;
+
+            stats.files_retimed += 1;
+        }
+        SyncOp::VerifyCheckSum { path, size, crc32 } => {
+            let dst_file_path = dst_path.join(&path);
+
+            events.send(SyncEvent::BeginFile {
+                prefix: "Verify".to_owned(),
+                filename: path.to_string_lossy().into_owned(),
+                size,
+            });
+
+            let dst_hash = hash_crc32(&dst_file_path, |bytes| {
+                events.send(SyncEvent::FileProgress { bytes: bytes as u64 })
+            })?;
+
+            events.send(SyncEvent::EndFile);
+
+            if dst_hash != crc32 {
+                return Err(SyncJobError::ChecksumMismatch);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl FileState {
+    fn read(path: &Path) -> io::Result<Self> {
+        let metadata = path.metadata()?;
+        let modified = FileTime::from_last_modification_time(&metadata);
+
+        Ok(Self {
+            size: metadata.len(),
+            mtime: modified.unix_seconds(),
+            mtime_nanos: modified.nanoseconds(),
+        })
+    }
+
+    fn modified(&self) -> FileTime {
+        FileTime::from_unix_time(self.mtime, self.mtime_nanos)
+    }
+
+    /// Whether another state has the same size and modification time
+    fn matches(&self, other: &Self) -> bool {
+        self.size == other.size && self.modified() == other.modified()
+    }
+}
+
+impl AddAssign for SyncStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.files_copied += rhs.files_copied;
+        self.bytes_copied += rhs.bytes_copied;
+        self.files_deleted += rhs.files_deleted;
+        self.files_unchanged += rhs.files_unchanged;
+        self.files_retimed += rhs.files_retimed;
+        self.placeholders_skipped += rhs.placeholders_skipped;
+    }
+}
+
+/// Delete a destination file, or move it out of the way
+fn delete_file(path: &Path, rel_path: &Path, deletion: Deletion) -> Result<(), SyncJobError> {
+    let res = match deletion {
+        Deletion::Remove => fs::remove_file(path).map_err(|e| dst_error("deleting", path, e)),
+        Deletion::Trash => trash::delete(path).with_context(|| format!("Error moving {} to trash", path.display())),
+        Deletion::Recycle(recycle_path) => recycle_file(path, &recycle_path.join(rel_path))
+            .with_context(|| format!("Error moving {} to {}", path.display(), recycle_path.display())),
+    };
+
+    res.map_err(SyncJobError::Anyhow)
+}
+
+fn recycle_file(path: &Path, recycled_path: &Path) -> Result<(), anyhow::Error> {
+    fs::create_dir_all(recycled_path.parent().context("Recycled file has no parent")?)?;
+
+    // The recycle directory may be on another volume, which files cannot simply be moved to
+    if fs::rename(path, recycled_path).is_err() {
+        fs::copy(path, recycled_path)?;
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Clear attributes preventing a destination file from being overwritten or deleted, if allowed
+fn prepare_overwrite(path: &Path, overwrite_read_only: bool) -> Result<(), SyncJobError> {
+    if !overwrite_read_only {
+        return Ok(());
+    }
+
+    let cleared =
+        clear_read_only(path).with_context(|| format!("Error clearing read-only attribute of {}", path.display()))?;
+
+    if cleared {
+        info!("Cleared read-only attribute of {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Error writing to or deleting a destination file, naming the file and why it may have failed
+fn dst_error(action: &str, path: &Path, err: io::Error) -> anyhow::Error {
+    let read_only = path.metadata().is_ok_and(|m| m.permissions().readonly());
+
+    let hint = if read_only && err.kind() == ErrorKind::PermissionDenied {
+        " (the file is read-only, set overwrite-read-only in the game config to replace it)"
+    } else {
+        ""
+    };
+
+    let message = format!("Error {action} {}: {err}{hint}", path.display());
+    anyhow::Error::new(err).context(message)
+}
+
+/// Clear read-only attribute of a file, returning whether it was set
+#[cfg(unix)]
+fn clear_read_only(path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = match fs::metadata(path) {
+        Ok(metadata) => metadata.permissions(),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    if !permissions.readonly() {
+        return Ok(false);
+    }
+
+    permissions.set_mode(permissions.mode() | 0o200);
+    fs::set_permissions(path, permissions)?;
+
+    Ok(true)
+}
+
+/// Clear read-only and hidden attributes of a file, returning whether either was set.
+/// Windows refuses to copy over hidden files as well as read-only ones.
+#[cfg(windows)]
+fn clear_read_only(path: &Path) -> io::Result<bool> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileAttributesW, SetFileAttributesW, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY, INVALID_FILE_ATTRIBUTES,
+    };
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+
+    // SAFETY: The path is null-terminated, and outlives the call.
+    let attributes = unsafe { GetFileAttributesW(wide_path.as_ptr()) };
+
+    if attributes == INVALID_FILE_ATTRIBUTES {
+        let err = io::Error::last_os_error();
+
+        return match err.kind() {
+            ErrorKind::NotFound => Ok(false),
+            _ => Err(err),
+        };
+    }
+
+    let blocking = FILE_ATTRIBUTE_READONLY | FILE_ATTRIBUTE_HIDDEN;
+
+    if attributes & blocking == 0 {
+        return Ok(false);
+    }
+
+    // SAFETY: The path is null-terminated, and outlives the call.
+    if unsafe { SetFileAttributesW(wide_path.as_ptr(), attributes & !blocking) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(true)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn clear_read_only(_path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Whether a file is a cloud placeholder, such as a OneDrive Files On-Demand file
+/// whose contents are downloaded when it is read
+#[cfg(windows)]
+fn is_placeholder(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS, FILE_ATTRIBUTE_RECALL_ON_OPEN,
+    };
+
+    let recall = FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_OFFLINE;
+
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_attributes() & recall != 0)
+}
+
+#[cfg(not(windows))]
+fn is_placeholder(_path: &Path) -> bool {
+    false
+}
+
+/// Whether a source file is a cloud placeholder that is not to be downloaded, warning that it is skipped
+fn skips_placeholder(copy: CopyOptions, path: &Path, rel_path: &Path) -> bool {
+    let skips = copy.placeholders == CloudPlaceholders::Skip && is_placeholder(path);
+
+    if skips {
+        warn!(
+            "Skipping cloud placeholder not kept on this device: {}",
+            rel_path.display()
+        );
+    }
+
+    skips
+}
+
+/// Copy a file by reading it through, returning the number of bytes copied
+fn copy_by_reading(src: &Path, dst: &Path) -> io::Result<u64> {
+    let mut input = fs::File::open(src)?;
+    let mut output = fs::File::create(dst)?;
+
+    io::copy(&mut input, &mut output)
+}
+
+/// Checksum of a file, reporting progress
+fn checksum(path: &Path, rel_path: &Path, size: u64, events: &SyncEvents) -> Result<u32, anyhow::Error> {
+    events.send(SyncEvent::BeginFile {
+        prefix: "Checksum".to_owned(),
+        filename: rel_path.to_string_lossy().into_owned(),
+        size,
+    });
+
+    let hash = hash_crc32(path, |bytes| {
+        events.send(SyncEvent::FileProgress { bytes: bytes as u64 })
+    })?;
+
+    events.send(SyncEvent::EndFile);
+
+    Ok(hash)
+}
+
+/// Files and directories in a directory, with files filtered by the globsets.
+/// When sorted, entries are in the order of their relative paths.
+/// A redirected directory is followed, but links inside it are skipped.
+fn scan<'a>(
+    path: &'a Path,
+    include_globset: Option<&'a globset::GlobSet>,
+    ignore_globset: Option<&'a globset::GlobSet>,
+    exclude: &[PathBuf],
+    sorted: bool,
+) -> impl Iterator<Item = Result<ScanEntry, walkdir::Error>> + 'a {
+    let exclude: Vec<PathBuf> = exclude.iter().map(|p| resolve_path(p)).collect();
+
+    let mut walker = walkdir::WalkDir::new(path);
+    if sorted {
+        walker = walker.sort_by_file_name();
+    }
+
+    walker
+        .into_iter()
+        .filter_entry(move |entry| {
+            let excluded = entry.depth() > 0 && exclude.iter().any(|p| p == entry.path());
+
+            if excluded {
+                warn!("Skipping stool data: {}", entry.path().display());
+            }
+
+            !excluded
+        })
+        .filter_map(move |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err)),
+            };
+
+            // Links inside a save directory may lead anywhere, including back into it
+            if entry.depth() > 0 && entry.path_is_symlink() {
+                warn!("Skipping link: {}", entry.path().display());
+                return None;
+            }
+
+            let is_file = entry.file_type().is_file();
+            let rel_path = entry.into_path().strip_prefix(path).ok()?.to_path_buf();
+
+            if is_file {
+                if include_globset.is_some_and(|globset| !globset.is_match(&rel_path)) {
+                    return None;
                 }
-                SyncOp::RemoveDir { path } => {
-                    let res = fs::remove_dir(dst_path.join(path));
-                    match res {
-                        Ok(_) => {}
-                        Err(err) if err.kind() == ErrorKind::DirectoryNotEmpty => {}
-                        Err(err) => Err(SyncJobError::Anyhow(err.into()))?,
-                    }
+
+                if ignore_globset.is_some_and(|globset| globset.is_match(&rel_path)) {
+                    return None;
                 }
-                SyncOp::VerifyCheckSum { path, size, crc32 } => {
-                    let dst_file_path = dst_path.join(&path);
+            }
+
+            Some(Ok(ScanEntry { rel_path, is_file }))
+        })
+}
 
-                    ui.begin_file("Verify", &path.to_string_lossy(), size);
+fn next_entry(
+    entries: &mut impl Iterator<Item = Result<ScanEntry, walkdir::Error>>,
+) -> Result<Option<ScanEntry>, anyhow::Error> {
+    Ok(entries.next().transpose()?)
+}
+
+/// Compare a file present in both source and destination
+fn compare_files(
+    src_file_path: &Path,
+    src: FileState,
+    dst_file_path: &Path,
+    dst: FileState,
+    rel_path: &Path,
+    verify: VerifyMode,
+    events: &SyncEvents,
+) -> Result<FileDiff, anyhow::Error> {
+    let src_size = src.size;
+    let dst_size = dst.size;
+
+    if src_size != dst_size {
+        return Ok(FileDiff::Changed {
+            size: src_size,
+            crc32: None,
+        });
+    }
+
+    let src_modified = src.modified();
+    let same_modified = src_modified == dst.modified();
+
+    // Contents are compared when only modification times differ, as after some cloud syncs,
+    // and always when paranoid, as files may differ despite matching size and modification time
+    if verify == VerifyMode::Paranoid || (!same_modified && verify != VerifyMode::Fast) {
+        let hash = checksum(src_file_path, rel_path, src_size, events)?;
+
+        if hash != checksum(dst_file_path, rel_path, dst_size, events)? {
+            if same_modified {
+                warn!(
+                    "Content differs despite same size and modification time: {}",
+                    rel_path.display()
+                );
+            }
 
-                    let dst_hash = hash_crc32(&dst_file_path, |bytes| ui.file_progress(bytes as u64))?;
+            return Ok(FileDiff::Changed {
+                size: src_size,
+                crc32: Some(hash),
+            });
+        }
+    } else if !same_modified {
+        return Ok(FileDiff::Changed {
+            size: src_size,
+            crc32: None,
+        });
+    }
 
-                    ui.end_file();
+    Ok(match same_modified {
+        true => FileDiff::Unchanged,
+        false => FileDiff::Retimed { modified: src_modified },
+    })
+}
 
-                    if dst_hash != crc32 {
-                        return Err(SyncJobError::ChecksumMismatch);
+/// Sync a directory by walking source and destination side by side, running operations as they are found.
+/// Copies are verified right away rather than at the end, and only directories to remove are held on to.
+fn sync_streaming(
+    src: &Path,
+    dst: &Path,
+    options: SyncOptions,
+    events: &SyncEvents,
+) -> Result<SyncStats, SyncJobError> {
+    let src_path = src.canonicalize().map_err(anyhow::Error::from)?;
+    let dst_path = dst.canonicalize().map_err(anyhow::Error::from)?;
+    let copy = options.copy;
+    let verify = copy.verify;
+    let overwrite_read_only = copy.overwrite_read_only;
+    let deletion = options.deletion;
+
+    let (dst_include_globset, dst_ignore_globset) = if options.filter_in_dst {
+        (options.include_globset, options.ignore_globset)
+    } else {
+        (None, None)
+    };
+
+    let mut src_entries = scan(
+        &src_path,
+        options.include_globset,
+        options.ignore_globset,
+        options.exclude,
+        true,
+    );
+    let mut dst_entries = scan(
+        &dst_path,
+        dst_include_globset,
+        dst_ignore_globset,
+        options.exclude,
+        true,
+    );
+
+    // Unreadable entries fail the sync, as they would otherwise pass for deleted
+    let mut src_next = next_entry(&mut src_entries)?;
+    let mut dst_next = next_entry(&mut dst_entries)?;
+
+    let mut stats = SyncStats::default();
+    let mut dirs_not_in_src = Vec::new();
+
+    // The number of operations is not known until the walk is done
+    events.send(SyncEvent::BeginSync { op_count: 0 });
+
+    loop {
+        let order = match (&src_next, &dst_next) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(src), Some(dst)) => match src.rel_path.cmp(&dst.rel_path) {
+                // A file replaced by a directory, or the other way around, is removed and created again
+                Ordering::Equal if src.is_file != dst.is_file => Ordering::Greater,
+                order => order,
+            },
+        };
+
+        let mut ops = Vec::with_capacity(2);
+
+        match order {
+            Ordering::Less => {
+                let src = src_next.take().unwrap();
+                src_next = next_entry(&mut src_entries)?;
+
+                if !src.is_file {
+                    ops.push(SyncOp::CreateDir { path: src.rel_path });
+                } else if skips_placeholder(copy, &src_path.join(&src.rel_path), &src.rel_path) {
+                    stats.placeholders_skipped += 1;
+                } else {
+                    let size = src_path
+                        .join(&src.rel_path)
+                        .metadata()
+                        .map_err(anyhow::Error::from)?
+                        .len();
+                    push_copy(&mut ops, &src_path, src.rel_path, size, None, verify, events)?;
+                }
+            }
+            Ordering::Greater => {
+                let dst = dst_next.take().unwrap();
+                dst_next = next_entry(&mut dst_entries)?;
+
+                if dst.is_file {
+                    ops.push(SyncOp::Delete { path: dst.rel_path });
+                } else if src_next.as_ref().is_some_and(|src| src.rel_path == dst.rel_path) {
+                    // A directory replaced by a file is removed with its contents before the file is copied
+                    let mut dirs = vec![dst.rel_path];
+
+                    while let Some(entry) = dst_next.take_if(|entry| entry.rel_path.starts_with(&dirs[0])) {
+                        dst_next = next_entry(&mut dst_entries)?;
+
+                        if entry.is_file {
+                            ops.push(SyncOp::Delete { path: entry.rel_path });
+                        } else {
+                            dirs.push(entry.rel_path);
+                        }
+                    }
+
+                    ops.extend(dirs.into_iter().rev().map(|path| SyncOp::RemoveDir { path }));
+                } else {
+                    dirs_not_in_src.push(dst.rel_path);
+                }
+            }
+            Ordering::Equal => {
+                let src = src_next.take().unwrap();
+                src_next = next_entry(&mut src_entries)?;
+                dst_next = next_entry(&mut dst_entries)?;
+
+                if src.is_file && skips_placeholder(copy, &src_path.join(&src.rel_path), &src.rel_path) {
+                    stats.placeholders_skipped += 1;
+                } else if src.is_file {
+                    let p = src.rel_path;
+
+                    let src_file_path = src_path.join(&p);
+                    let dst_file_path = dst_path.join(&p);
+                    let src_state = FileState::read(&src_file_path).map_err(anyhow::Error::from)?;
+                    let dst_state = FileState::read(&dst_file_path).map_err(anyhow::Error::from)?;
+
+                    match compare_files(&src_file_path, src_state, &dst_file_path, dst_state, &p, verify, events)? {
+                        FileDiff::Unchanged => stats.files_unchanged += 1,
+                        FileDiff::Retimed { modified } => ops.push(SyncOp::SetModified { path: p, modified }),
+                        FileDiff::Changed { size, crc32 } => {
+                            push_copy(&mut ops, &src_path, p, size, crc32, verify, events)?
+                        }
                     }
                 }
             }
+        }
 
-            ui.sync_progress();
+        for op in ops {
+            execute_op(
+                op,
+                &src_path,
+                &dst_path,
+                overwrite_read_only,
+                deletion,
+                &mut stats,
+                events,
+            )?;
+            events.send(SyncEvent::SyncProgress);
         }
+    }
+
+    // Directories come before their contents in the walk, so removing them in reverse removes the deepest first
+    for path in dirs_not_in_src.into_iter().rev() {
+        execute_op(
+            SyncOp::RemoveDir { path },
+            &src_path,
+            &dst_path,
+            overwrite_read_only,
+            deletion,
+            &mut stats,
+            events,
+        )?;
+        events.send(SyncEvent::SyncProgress);
+    }
+
+    events.send(SyncEvent::EndSync);
+
+    Ok(stats)
+}
+
+/// Add a copy of a source file to the operations, followed by its verification unless verification is fast
+fn push_copy(
+    ops: &mut Vec<SyncOp>,
+    src_path: &Path,
+    path: PathBuf,
+    size: u64,
+    crc32: Option<u32>,
+    verify: VerifyMode,
+    events: &SyncEvents,
+) -> Result<(), anyhow::Error> {
+    ops.push(SyncOp::Copy { path: path.clone() });
+
+    if verify != VerifyMode::Fast {
+        let crc32 = match crc32 {
+            Some(hash) => hash,
+            None => checksum(&src_path.join(&path), &path, size, events)?,
+        };
+
+        ops.push(SyncOp::VerifyCheckSum { path, size, crc32 });
+    }
+
+    Ok(())
+}
+
+pub fn sync_dir(src: &Path, dst: &Path, options: SyncOptions, events: &SyncEvents) -> Result<SyncStats, anyhow::Error> {
+    sync_dir_indexed(src, dst, options, None, events).map(|(stats, _)| stats)
+}
+
+/// Whether a directory is missing or has nothing in it
+pub fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).map_or(true, |mut entries| entries.next().is_none())
+}
+
+/// Number and total size of the files in a directory that syncing it would copy to an empty destination
+pub fn measure_dir(path: &Path, options: SyncOptions) -> (usize, u64) {
+    scan(
+        path,
+        options.include_globset,
+        options.ignore_globset,
+        options.exclude,
+        false,
+    )
+    .filter_map(Result::ok)
+    .filter(|entry| entry.is_file)
+    .fold((0, 0), |(files, bytes), entry| {
+        let size = fs::metadata(path.join(&entry.rel_path)).map_or(0, |m| m.len());
+        (files + 1, bytes + size)
+    })
+}
 
-        ui.end_sync();
+/// Bytes that syncing a directory is expected to copy, going by file sizes and modification times.
+/// The destination is taken to be as recorded by its index, if there is one.
+pub fn pending_bytes(src: &Path, dst: &Path, options: SyncOptions, index: Option<&DirIndex>) -> u64 {
+    scan(
+        src,
+        options.include_globset,
+        options.ignore_globset,
+        options.exclude,
+        false,
+    )
+    .filter_map(Result::ok)
+    .filter(|entry| entry.is_file)
+    .filter_map(|entry| {
+        let src_state = FileState::read(&src.join(&entry.rel_path)).ok()?;
+        let dst_state = match index {
+            Some(index) => index.files.get(&entry.rel_path).copied(),
+            None => FileState::read(&dst.join(&entry.rel_path)).ok(),
+        };
+
+        (!dst_state.is_some_and(|dst_state| src_state.matches(&dst_state))).then_some(src_state.size)
+    })
+    .sum()
+}
 
-        Ok(())
+/// Whether a file is the same as its copy, going by size and modification time, and by contents,
+/// as modification times may be too coarse to tell edits apart.
+/// Contents of cloud placeholders are not compared, as reading them would download them.
+pub fn same_as_copy(path: &Path, copy_path: &Path) -> bool {
+    match (FileState::read(path), FileState::read(copy_path)) {
+        (Ok(a), Ok(b)) if a.matches(&b) => {
+            is_placeholder(path)
+                || matches!(
+                    (hash_crc32(path, |_| {}), hash_crc32(copy_path, |_| {})),
+                    (Ok(a), Ok(b)) if a == b
+                )
+        }
+        _ => false,
     }
 }
 
-pub fn sync_dir(
+/// Bytes that syncing a file into a directory is expected to copy, going by its size and modification time
+pub fn pending_file_bytes(src_file_path: &Path, dst: &Path) -> u64 {
+    let Ok(src_state) = FileState::read(src_file_path) else {
+        return 0;
+    };
+
+    let dst_state = src_file_path
+        .file_name()
+        .and_then(|name| FileState::read(&dst.join(name)).ok());
+
+    if dst_state.is_some_and(|dst_state| src_state.matches(&dst_state)) {
+        0
+    } else {
+        src_state.size
+    }
+}
+
+/// Sync a directory, using the index left by the previous sync to the destination instead of scanning it.
+/// Returns the index of the destination once synced, unless streaming.
+pub fn sync_dir_indexed(
     src: &Path,
     dst: &Path,
-    include_globset: Option<&globset::GlobSet>,
-    ignore_globset: Option<&globset::GlobSet>,
-    filter_in_dst: bool,
-    ui: &mut dyn SyncUiHandler,
-) -> Result<(), anyhow::Error> {
+    options: SyncOptions,
+    mut index: Option<DirIndex>,
+    events: &SyncEvents,
+) -> Result<(SyncStats, Option<DirIndex>), anyhow::Error> {
     // Create destination directory if it does not exist
     if !dst.exists() {
         fs::create_dir_all(dst)?;
     }
 
+    let SyncOptions {
+        include_globset,
+        ignore_globset,
+        exclude,
+        filter_in_dst,
+        streaming,
+        copy,
+        deletion,
+    } = options;
+
     let (dst_include_globset, dst_ignore_globset) = if filter_in_dst {
         (include_globset, ignore_globset)
     } else {
@@ -317,13 +1192,24 @@ pub fn sync_dir(
     let mut attempt = 0;
 
     loop {
-        let src = SyncDir::new(src, include_globset, ignore_globset, ui)?;
-        let dst = SyncDir::new(dst, dst_include_globset, dst_ignore_globset, ui)?;
-        let job = dst.sync_from(&src, ui)?;
+        let res = if streaming {
+            sync_streaming(src, dst, options, events).map(|stats| (stats, None))
+        } else {
+            let src = SyncDir::new(src, include_globset, ignore_globset, exclude, events)?;
+
+            // Retries scan the destination, as a failed job leaves it in an unknown state
+            let dst = match index.take() {
+                Some(index) => SyncDir::from_index(dst, index)?,
+                None => SyncDir::new(dst, dst_include_globset, dst_ignore_globset, exclude, events)?,
+            };
+
+            let mut job = dst.sync_from(&src, copy, events)?;
+            let index = std::mem::take(&mut job.index);
 
-        let res = job.execute(ui);
+            job.execute(deletion, events).map(|stats| (stats, Some(index)))
+        };
         match res {
-            Ok(_) => {}
+            Ok(res) => return Ok(res),
             Err(err) => {
                 attempt += 1;
 
@@ -337,18 +1223,73 @@ pub fn sync_dir(
                     SyncJobError::ReadError { path } => error!("Error reading source file: {}", path.display()),
                     _ => Err(err)?,
                 }
+            }
+        };
+    }
+}
 
-                continue;
+/// Absolute form of a path, with symlinks resolved in the part of it that exists
+pub fn resolve_path(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return rest.iter().rev().fold(resolved, |path, name| path.join(name));
+        }
+
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
             }
+            _ => return path.to_owned(),
+        }
+    }
+}
+
+/// Whether two paths are on the same volume, if it can be told.
+/// Paths that do not exist yet are located by their nearest existing ancestor.
+pub fn same_volume(a: &Path, b: &Path) -> Option<bool> {
+    let a = resolve_path(a);
+    let b = resolve_path(b);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let device = |path: &Path| path.ancestors().find_map(|p| p.metadata().ok()).map(|m| m.dev());
+
+        Some(device(&a)? == device(&b)?)
+    }
+
+    #[cfg(windows)]
+    {
+        use std::path::Component;
+
+        let prefix = |path: &Path| match path.components().next() {
+            Some(Component::Prefix(prefix)) => Some(prefix.as_os_str().to_ascii_lowercase()),
+            _ => None,
         };
 
-        break;
+        Some(prefix(&a)? == prefix(&b)?)
     }
 
-    Ok(())
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (a, b);
+        None
+    }
 }
 
-pub fn sync_file(src_file_path: &Path, dst: &Path, ui: &mut dyn SyncUiHandler) -> Result<(), anyhow::Error> {
+pub fn sync_file(
+    src_file_path: &Path,
+    dst: &Path,
+    copy: CopyOptions,
+    events: &SyncEvents,
+) -> Result<SyncStats, anyhow::Error> {
+    let verify = copy.verify;
+
     let src_dir_path = src_file_path
         .parent()
         .context("Error getting parent directory of source file")?;
@@ -362,16 +1303,17 @@ pub fn sync_file(src_file_path: &Path, dst: &Path, ui: &mut dyn SyncUiHandler) -
 
     let mut attempt = 0;
 
+    if skips_placeholder(copy, src_file_path, rel_file_path) {
+        return Ok(SyncStats {
+            placeholders_skipped: 1,
+            ..Default::default()
+        });
+    }
+
     loop {
         let src_metadata = src_file_path.metadata()?;
         let src_size = src_metadata.len();
 
-        ui.begin_file("Checksum", &rel_file_path.to_string_lossy(), src_size);
-
-        let src_hash = hash_crc32(src_file_path, |bytes| ui.file_progress(bytes as u64))?;
-
-        ui.end_file();
-
         if dst_file_path.exists() {
             'diff: {
                 let dst_metadata = dst_file_path.metadata()?;
@@ -383,34 +1325,68 @@ pub fn sync_file(src_file_path: &Path, dst: &Path, ui: &mut dyn SyncUiHandler) -
 
                 let src_modified = FileTime::from_last_modification_time(&src_metadata);
                 let dst_modified = FileTime::from_last_modification_time(&dst_metadata);
-
-                if src_modified != dst_modified {
+                let same_modified = src_modified == dst_modified;
+
+                // Contents are compared when only modification times differ, as after some cloud syncs,
+                // and always when paranoid, as files may differ despite matching size and modification time
+                if verify == VerifyMode::Paranoid || (!same_modified && verify != VerifyMode::Fast) {
+                    if checksum(src_file_path, rel_file_path, src_size, events)?
+                        != checksum(&dst_file_path, rel_file_path, dst_size, events)?
+                    {
+                        if same_modified {
+                            warn!(
+                                "Content differs despite same size and modification time: {}",
+                                rel_file_path.display()
+                            );
+                        }
+                        break 'diff;
+                    }
+                } else if !same_modified {
                     break 'diff;
                 }
 
-                // No differences found
-                return Ok(());
+                // Same content, so at most the modification time needs fixing
+                if same_modified {
+                    return Ok(SyncStats {
+                        files_unchanged: 1,
+                        ..Default::default()
+                    });
+                }
+
+                filetime::set_file_mtime(&dst_file_path, src_modified)?;
+
+                return Ok(SyncStats {
+                    files_retimed: 1,
+                    ..Default::default()
+                });
             }
         }
 
+        let mut ops = vec![SyncOp::Copy {
+            path: rel_file_path.to_path_buf(),
+        }];
+
+        if verify != VerifyMode::Fast {
+            ops.push(SyncOp::VerifyCheckSum {
+                path: rel_file_path.to_path_buf(),
+                size: src_size,
+                crc32: checksum(src_file_path, rel_file_path, src_size, events)?,
+            });
+        }
+
         let job = SyncJob {
-            ops: vec![
-                SyncOp::Copy {
-                    path: rel_file_path.to_path_buf(),
-                },
-                SyncOp::VerifyCheckSum {
-                    path: rel_file_path.to_path_buf(),
-                    size: src_size,
-                    crc32: src_hash,
-                },
-            ],
+            ops,
             src_path: src_dir_path.to_path_buf(),
             dst_path: dst.to_path_buf(),
+            unchanged: 0,
+            placeholders_skipped: 0,
+            index: DirIndex::default(),
+            overwrite_read_only: copy.overwrite_read_only,
         };
 
-        let res = job.execute(ui);
+        let res = job.execute(Deletion::Remove, events);
         match res {
-            Ok(_) => {}
+            Ok(stats) => return Ok(stats),
             Err(err) => {
                 attempt += 1;
 
@@ -424,13 +1400,202 @@ pub fn sync_file(src_file_path: &Path, dst: &Path, ui: &mut dyn SyncUiHandler) -
                     SyncJobError::ReadError { path } => error!("Error reading source file: {}", path.display()),
                     _ => Err(err)?,
                 }
-
-                continue;
             }
         };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_only_updates_timestamps_of_identical_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+
+        for path in [&src, &dst] {
+            std::fs::create_dir_all(path).unwrap();
+            std::fs::write(path.join("slot1.sav"), "same").unwrap();
+        }
+
+        let modified = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(src.join("slot1.sav"), modified).unwrap();
+
+        let sync = |verify| {
+            let options = SyncOptions {
+                include_globset: None,
+                ignore_globset: None,
+                exclude: &[],
+                filter_in_dst: false,
+                streaming: false,
+                copy: CopyOptions {
+                    verify,
+                    overwrite_read_only: false,
+                    placeholders: Default::default(),
+                },
+                deletion: Deletion::Remove,
+            };
+
+            sync_dir(&src, &dst, options, &SyncEvents::discard()).unwrap()
+        };
+
+        let stats = sync(VerifyMode::Standard);
+        assert_eq!((stats.files_copied, stats.files_retimed), (0, 1));
+
+        let dst_metadata = std::fs::metadata(dst.join("slot1.sav")).unwrap();
+        assert_eq!(filetime::FileTime::from_last_modification_time(&dst_metadata), modified);
 
-        break;
+        // Without checksums, files with other modification times are copied
+        filetime::set_file_mtime(src.join("slot1.sav"), filetime::FileTime::from_unix_time(0, 0)).unwrap();
+
+        let stats = sync(VerifyMode::Fast);
+        assert_eq!((stats.files_copied, stats.files_retimed), (1, 0));
     }
 
-    Ok(())
+    #[test]
+    fn streaming_sync_mirrors_source_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+
+        let write = |path: &std::path::Path, contents: &str| {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+
+        write(&src.join("a/1.sav"), "new");
+        write(&src.join("a/b/2.sav"), "two");
+        write(&src.join("a.b/3.sav"), "three");
+        write(&src.join("c.sav"), "same");
+
+        write(&dst.join("a/1.sav"), "old");
+        write(&dst.join("c.sav"), "same");
+        write(&dst.join("old/x/y.sav"), "gone");
+        write(&dst.join("stale.sav"), "gone");
+
+        let modified = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(src.join("c.sav"), modified).unwrap();
+        filetime::set_file_mtime(dst.join("c.sav"), modified).unwrap();
+
+        let options = SyncOptions {
+            include_globset: None,
+            ignore_globset: None,
+            exclude: &[],
+            filter_in_dst: false,
+            streaming: true,
+            copy: CopyOptions::default(),
+            deletion: Deletion::Remove,
+        };
+
+        let stats = sync_dir(&src, &dst, options, &SyncEvents::discard()).unwrap();
+        assert_eq!(
+            (stats.files_copied, stats.files_deleted, stats.files_unchanged),
+            (3, 2, 1)
+        );
+
+        for (path, contents) in [
+            ("a/1.sav", "new"),
+            ("a/b/2.sav", "two"),
+            ("a.b/3.sav", "three"),
+            ("c.sav", "same"),
+        ] {
+            assert_eq!(std::fs::read_to_string(dst.join(path)).unwrap(), contents);
+        }
+
+        assert!(!dst.join("old").exists());
+        assert!(!dst.join("stale.sav").exists());
+    }
+
+    #[test]
+    fn scan_errors_are_not_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+
+        let mut entries = scan(&missing, None, None, &[], true);
+        assert!(entries.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn streaming_sync_swaps_files_and_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+
+        let write = |path: &Path, contents: &str| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+
+        // A directory in the destination that is a file in the source, and the other way around
+        write(&src.join("a"), "file");
+        write(&src.join("b/1.sav"), "one");
+        write(&src.join("c.sav"), "three");
+
+        write(&dst.join("a/1.sav"), "one");
+        write(&dst.join("a/sub/2.sav"), "two");
+        write(&dst.join("b"), "file");
+        write(&dst.join("c.sav"), "old");
+
+        let options = SyncOptions {
+            include_globset: None,
+            ignore_globset: None,
+            exclude: &[],
+            filter_in_dst: false,
+            streaming: true,
+            copy: CopyOptions::default(),
+            deletion: Deletion::Remove,
+        };
+
+        let stats = sync_dir(&src, &dst, options, &SyncEvents::discard()).unwrap();
+        assert_eq!((stats.files_copied, stats.files_deleted), (3, 3));
+
+        assert_eq!(fs::read_to_string(dst.join("a")).unwrap(), "file");
+        assert_eq!(fs::read_to_string(dst.join("b/1.sav")).unwrap(), "one");
+        assert_eq!(fs::read_to_string(dst.join("c.sav")).unwrap(), "three");
+    }
+
+    #[test]
+    fn sync_ui_receives_events_sent_from_other_threads() {
+        struct FileNames(Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl SyncUiHandler for FileNames {
+            fn begin_scan(&mut self) {}
+            fn end_scan(&mut self) {}
+            fn begin_prepare(&mut self) {}
+            fn end_prepare(&mut self) {}
+            fn begin_sync(&mut self, _op_count: usize) {}
+            fn sync_progress(&mut self) {}
+            fn end_sync(&mut self) {}
+            fn begin_file(&mut self, _prefix: &str, filename: &str, _size: u64) {
+                self.0.lock().unwrap().push(filename.to_owned());
+            }
+            fn file_progress(&mut self, _bytes: u64) {}
+            fn end_file(&mut self) {}
+        }
+
+        let names = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        with_sync_ui(FileNames(names.clone()), |events| {
+            std::thread::scope(|scope| {
+                for i in 0..4 {
+                    let events = events.clone();
+
+                    scope.spawn(move || {
+                        events.send(SyncEvent::BeginFile {
+                            prefix: "Copy".to_owned(),
+                            filename: format!("slot{i}.sav"),
+                            size: 0,
+                        });
+                    });
+                }
+            });
+        });
+
+        // All events are consumed by the time it returns
+        let mut names = names.lock().unwrap().clone();
+        names.sort();
+        assert_eq!(names, ["slot0.sav", "slot1.sav", "slot2.sav", "slot3.sav"]);
+    }
 }
@@ -0,0 +1,295 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::Context;
+use tracing::warn;
+
+use super::{
+    archive::{ArchiveEntry, Archiver},
+    filter::build_globset,
+};
+
+/// Window size used with long-distance matching, 128 MiB.
+/// This is the largest window decoders accept by default.
+const LONG_WINDOW_LOG: u32 = 27;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// Largest size of a zstd block
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+/// Native archiver writing zstd-compressed tar archives, without external tools
+pub struct TarZstd {
+    /// Compression level, from 1 to 22
+    pub level: u32,
+    /// Number of compression worker threads, 0 for one per CPU core
+    pub threads: u32,
+    /// Find matches across a large window, for better compression of big, repetitive saves
+    pub long_distance_matching: bool,
+    /// Glob patterns of files stored without compression, such as already-compressed assets
+    pub store_only: Vec<String>,
+}
+
+impl TarZstd {
+    fn worker_count(&self) -> u32 {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+            n => n,
+        }
+    }
+
+    fn encoder<W: Write>(&self, output: W) -> io::Result<zstd::Encoder<'static, W>> {
+        let mut encoder = zstd::Encoder::new(output, self.level.clamp(1, 22) as i32)?;
+        encoder.multithread(self.worker_count())?;
+
+        if self.long_distance_matching {
+            encoder.long_distance_matching(true)?;
+            encoder.window_log(LONG_WINDOW_LOG)?;
+        }
+
+        Ok(encoder)
+    }
+
+    fn open(archive_path: &Path) -> Result<tar::Archive<zstd::Decoder<'static, BufReader<File>>>, anyhow::Error> {
+        let file = File::open(archive_path).with_context(|| format!("Opening archive {}", archive_path.display()))?;
+
+        let mut decoder = zstd::Decoder::new(file)?;
+        decoder.window_log_max(LONG_WINDOW_LOG)?;
+
+        Ok(tar::Archive::new(decoder))
+    }
+
+    /// Create an archive, storing files without compression once past the deadline, if any
+    fn create_until(&self, src: &Path, archive_path: &Path, deadline: Option<Instant>) -> Result<(), anyhow::Error> {
+        if let Some(parent) = archive_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file =
+            File::create(archive_path).with_context(|| format!("Creating archive {}", archive_path.display()))?;
+
+        let store_only = build_globset(&self.store_only)?;
+
+        let writer = FrameWriter {
+            archiver: self,
+            frame: Some(Frame::Compressed(self.encoder(BufWriter::new(file))?)),
+        };
+
+        let mut builder = tar::Builder::new(writer);
+        builder.follow_symlinks(false);
+
+        let mut past_deadline = false;
+
+        for entry in walkdir::WalkDir::new(src).min_depth(1).sort_by_file_name() {
+            let entry = entry?;
+            let rel_path = entry.path().strip_prefix(src)?;
+
+            if entry.file_type().is_dir() {
+                builder.get_mut().set_stored(past_deadline)?;
+                builder.append_dir(rel_path, entry.path())?;
+            } else {
+                if !past_deadline && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    warn!("Backup is taking too long, storing remaining files without compression");
+                    past_deadline = true;
+                }
+
+                builder
+                    .get_mut()
+                    .set_stored(past_deadline || store_only.is_match(rel_path))?;
+                builder.append_path_with_name(entry.path(), rel_path)?;
+            }
+        }
+
+        builder.into_inner()?.finish()?.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Archiver for TarZstd {
+    fn create(&self, src: &Path, archive_path: &Path) -> Result<(), anyhow::Error> {
+        self.create_until(src, archive_path, None)
+    }
+
+    /// Files started on once past the deadline are stored without compression
+    fn create_within(&self, src: &Path, archive_path: &Path, deadline: Instant) -> Result<(), anyhow::Error> {
+        self.create_until(src, archive_path, Some(deadline))
+    }
+
+    fn unpack(&self, archive_path: &Path, dst: &Path) -> Result<(), anyhow::Error> {
+        fs::create_dir_all(dst)?;
+        Self::open(archive_path)?.unpack(dst)?;
+
+        Ok(())
+    }
+
+    fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+        let mut archive = Self::open(archive_path)?;
+        let mut result = Vec::new();
+
+        // Tar does not store checksums of contents, so they are computed while reading
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let is_dir = entry.header().entry_type().is_dir();
+
+            let crc32 = if is_dir {
+                None
+            } else {
+                let mut hasher = crc32fast::Hasher::new();
+                let mut buf = [0; 64 * 1024];
+
+                loop {
+                    match entry.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => hasher.update(&buf[..n]),
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Some(hasher.finalize())
+            };
+
+            result.push(ArchiveEntry {
+                path,
+                size: entry.size(),
+                is_dir,
+                crc32,
+            });
+        }
+
+        Ok(result)
+    }
+
+    fn extension(&self) -> &'static str {
+        "tar.zst"
+    }
+}
+
+/// Zstd stream written as a sequence of frames, each either compressed or stored.
+/// Decoders read concatenated frames as one stream.
+struct FrameWriter<'a> {
+    archiver: &'a TarZstd,
+    /// Always present, except while switching frames
+    frame: Option<Frame>,
+}
+
+enum Frame {
+    Compressed(zstd::Encoder<'static, BufWriter<File>>),
+    Stored(StoredFrame<BufWriter<File>>),
+}
+
+impl FrameWriter<'_> {
+    /// Continue in a stored or compressed frame, ending the current frame if it is of the other kind
+    fn set_stored(&mut self, stored: bool) -> io::Result<()> {
+        let frame = self.frame.take().expect("frame is present");
+
+        self.frame = Some(match (frame, stored) {
+            (frame @ Frame::Compressed(_), false) | (frame @ Frame::Stored(_), true) => frame,
+            (frame, _) => {
+                let output = frame.finish()?;
+
+                if stored {
+                    Frame::Stored(StoredFrame::new(output)?)
+                } else {
+                    Frame::Compressed(self.archiver.encoder(output)?)
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<BufWriter<File>> {
+        self.frame.take().expect("frame is present").finish()
+    }
+
+    fn frame(&mut self) -> &mut dyn Write {
+        match self.frame.as_mut().expect("frame is present") {
+            Frame::Compressed(encoder) => encoder,
+            Frame::Stored(frame) => frame,
+        }
+    }
+}
+
+impl Write for FrameWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.frame().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.frame().flush()
+    }
+}
+
+impl Frame {
+    fn finish(self) -> io::Result<BufWriter<File>> {
+        match self {
+            Self::Compressed(encoder) => encoder.finish(),
+            Self::Stored(frame) => frame.finish(),
+        }
+    }
+}
+
+/// Zstd frame consisting of raw, uncompressed blocks
+struct StoredFrame<W: Write> {
+    output: W,
+    block: Vec<u8>,
+}
+
+impl<W: Write> StoredFrame<W> {
+    fn new(mut output: W) -> io::Result<Self> {
+        output.write_all(&ZSTD_MAGIC)?;
+        // Frame header descriptor without content size, checksum or dictionary
+        output.write_all(&[0])?;
+        // Window descriptor for a window of the largest block size, 2^(10 + 7)
+        output.write_all(&[7 << 3])?;
+
+        Ok(Self {
+            output,
+            block: Vec::with_capacity(MAX_BLOCK_SIZE),
+        })
+    }
+
+    fn write_block(&mut self, last: bool) -> io::Result<()> {
+        // Block header: last block flag, block type 0 (raw), and block size
+        let header = (last as u32) | ((self.block.len() as u32) << 3);
+
+        self.output.write_all(&header.to_le_bytes()[..3])?;
+        self.output.write_all(&self.block)?;
+        self.block.clear();
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.write_block(true)?;
+
+        Ok(self.output)
+    }
+}
+
+impl<W: Write> Write for StoredFrame<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_BLOCK_SIZE - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+
+        if self.block.len() == MAX_BLOCK_SIZE {
+            self.write_block(false)?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.block.is_empty() {
+            self.write_block(false)?;
+        }
+
+        self.output.flush()
+    }
+}
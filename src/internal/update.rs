@@ -0,0 +1,227 @@
+//! Checking for and installing new releases of stool, published as GitHub releases.
+//! Each release carries a binary per platform, named `stool-<arch>-<os>[.exe]`,
+//! and a `SHA256SUMS` file of their checksums.
+
+use std::{env, fs, process::Command};
+
+use anyhow::Context;
+use semver::Version;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/forbsoft/stool/releases/latest";
+const CHECKSUMS_ASSET_NAME: &str = "SHA256SUMS";
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+pub struct Release {
+    pub version: Version,
+    /// Release page
+    pub url: String,
+    assets: Vec<GithubAsset>,
+}
+
+pub fn current_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is valid semver")
+}
+
+/// Look up the latest release
+pub fn latest_release() -> Result<Release, anyhow::Error> {
+    parse_release(&download(LATEST_RELEASE_URL)?)
+}
+
+fn parse_release(json: &[u8]) -> Result<Release, anyhow::Error> {
+    let release: GithubRelease = serde_json::from_slice(json).context("Error parsing release information")?;
+
+    let version = Version::parse(release.tag_name.trim_start_matches('v'))
+        .with_context(|| format!("Invalid release version: {}", release.tag_name))?;
+
+    Ok(Release {
+        version,
+        url: release.html_url,
+        assets: release.assets,
+    })
+}
+
+impl Release {
+    pub fn is_newer(&self) -> bool {
+        self.version > current_version()
+    }
+
+    /// Download the binary of this release for the current platform, verify its checksum,
+    /// and replace the running executable with it
+    pub fn install(&self) -> Result<(), anyhow::Error> {
+        let binary_name = binary_asset_name();
+
+        let checksums = String::from_utf8(download(self.asset_url(CHECKSUMS_ASSET_NAME)?)?)?;
+        let expected_checksum = find_checksum(&checksums, &binary_name)
+            .with_context(|| format!("No checksum for {binary_name} in release {}", self.version))?;
+
+        let binary = download(self.asset_url(&binary_name)?)?;
+
+        if sha256_hex(&binary) != expected_checksum {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for {binary_name}, refusing to install it"
+            ));
+        }
+
+        replace_executable(&binary)
+    }
+
+    fn asset_url(&self, name: &str) -> Result<&str, anyhow::Error> {
+        self.assets
+            .iter()
+            .find(|a| a.name == name)
+            .map(|a| a.browser_download_url.as_str())
+            .with_context(|| format!("Release {} has no {name}", self.version))
+    }
+}
+
+/// Find the checksum of a file in a `SHA256SUMS` file
+fn find_checksum(checksums: &str, file_name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        // Lines are of the form "<checksum>  <file>", with a `*` before binary-mode files
+        let (checksum, file) = line.split_once(char::is_whitespace)?;
+        (file.trim().trim_start_matches('*') == file_name).then(|| checksum.to_ascii_lowercase())
+    })
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn binary_asset_name() -> String {
+    format!(
+        "stool-{}-{}{}",
+        env::consts::ARCH,
+        env::consts::OS,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+/// Download a URL with curl, which ships with all supported platforms
+fn download(url: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--user-agent", concat!("stool/", env!("CARGO_PKG_VERSION"))])
+        .arg(url)
+        .output()
+        .context("Error running curl")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Error downloading {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Replace the running executable.
+/// Windows does not allow overwriting it, but does allow renaming it out of the way.
+fn replace_executable(binary: &[u8]) -> Result<(), anyhow::Error> {
+    let exe_path = env::current_exe()?;
+    let new_path = exe_path.with_extension("new");
+    let old_path = exe_path.with_extension("old");
+
+    fs::write(&new_path, binary).context("Error writing new executable")?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&new_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Left over from a previous update on Windows, where it could not be removed while running
+    if old_path.exists() {
+        fs::remove_file(&old_path)?;
+    }
+
+    fs::rename(&exe_path, &old_path).context("Error moving current executable")?;
+
+    if let Err(err) = fs::rename(&new_path, &exe_path) {
+        fs::rename(&old_path, &exe_path)?;
+        return Err(err).context("Error moving new executable into place");
+    }
+
+    fs::remove_file(&old_path).ok();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases_are_parsed_from_github() {
+        let json = br#"{
+            "tag_name": "v1.2.3",
+            "html_url": "https://github.com/forbsoft/stool/releases/tag/v1.2.3",
+            "assets": [
+                {"name": "SHA256SUMS", "browser_download_url": "https://example.com/SHA256SUMS"}
+            ]
+        }"#;
+
+        let release = parse_release(json).unwrap();
+        assert_eq!(release.version, Version::new(1, 2, 3));
+        assert_eq!(release.url, "https://github.com/forbsoft/stool/releases/tag/v1.2.3");
+        assert_eq!(
+            release.asset_url(CHECKSUMS_ASSET_NAME).unwrap(),
+            "https://example.com/SHA256SUMS"
+        );
+        assert!(release.asset_url("stool-unknown").is_err());
+
+        assert!(parse_release(br#"{"tag_name": "latest", "html_url": "", "assets": []}"#).is_err());
+    }
+
+    #[test]
+    fn only_later_versions_are_newer() {
+        let release = |version| Release {
+            version,
+            url: String::new(),
+            assets: Vec::new(),
+        };
+
+        let current = current_version();
+        let mut next = current.clone();
+        next.patch += 1;
+
+        assert!(release(next).is_newer());
+        assert!(!release(current.clone()).is_newer());
+        assert!(!release(Version::new(0, 0, 0)).is_newer());
+    }
+
+    #[test]
+    fn checksums_are_found_by_file_name() {
+        let checksums = "AAAA  stool-x86_64-linux\nbbbb *stool-x86_64-windows.exe\n";
+
+        assert_eq!(find_checksum(checksums, "stool-x86_64-linux").as_deref(), Some("aaaa"));
+        assert_eq!(
+            find_checksum(checksums, "stool-x86_64-windows.exe").as_deref(),
+            Some("bbbb")
+        );
+        assert_eq!(find_checksum(checksums, "stool-x86_64"), None);
+    }
+
+    #[test]
+    fn sha256_is_hex_encoded() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
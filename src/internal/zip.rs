@@ -0,0 +1,115 @@
+//! Minimal writer of uncompressed zip archives, for bundles that users attach to bug reports
+
+use std::io::{self, Write};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+/// Version 2.0, the minimum for files in directories
+const VERSION: u16 = 20;
+/// Flag marking file names as UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+/// Files are dated 1980-01-01, the earliest date zip archives can hold
+const MODIFICATION_DATE: u16 = (1 << 5) | 1;
+
+struct CentralDirectoryEntry {
+    name: String,
+    crc32: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Zip archive of stored (uncompressed) files, written as files are added.
+/// Sizes are limited to 4 GiB, as the zip64 extensions are not supported.
+pub struct ZipWriter<W: Write> {
+    output: W,
+    offset: u32,
+    entries: Vec<CentralDirectoryEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let size = u32::try_from(data.len()).map_err(|_| io::Error::other("File too large for zip"))?;
+        let crc32 = crc32fast::hash(data);
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend(LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        header.extend(VERSION.to_le_bytes());
+        header.extend(FLAG_UTF8.to_le_bytes());
+        header.extend(0u16.to_le_bytes()); // Compression method: stored
+        header.extend(0u16.to_le_bytes()); // Modification time
+        header.extend(MODIFICATION_DATE.to_le_bytes());
+        header.extend(crc32.to_le_bytes());
+        header.extend(size.to_le_bytes()); // Compressed size
+        header.extend(size.to_le_bytes()); // Uncompressed size
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes()); // Extra field length
+        header.extend(name.as_bytes());
+
+        self.output.write_all(&header)?;
+        self.output.write_all(data)?;
+
+        self.entries.push(CentralDirectoryEntry {
+            name: name.to_owned(),
+            crc32,
+            size,
+            offset: self.offset,
+        });
+        self.offset += header.len() as u32 + size;
+
+        Ok(())
+    }
+
+    /// Write the central directory, completing the archive
+    pub fn finish(mut self) -> io::Result<W> {
+        let central_directory_offset = self.offset;
+        let mut central_directory = Vec::new();
+
+        for entry in self.entries.iter() {
+            central_directory.extend(CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+            central_directory.extend(VERSION.to_le_bytes()); // Version made by
+            central_directory.extend(VERSION.to_le_bytes()); // Version needed to extract
+            central_directory.extend(FLAG_UTF8.to_le_bytes());
+            central_directory.extend(0u16.to_le_bytes()); // Compression method: stored
+            central_directory.extend(0u16.to_le_bytes()); // Modification time
+            central_directory.extend(MODIFICATION_DATE.to_le_bytes());
+            central_directory.extend(entry.crc32.to_le_bytes());
+            central_directory.extend(entry.size.to_le_bytes());
+            central_directory.extend(entry.size.to_le_bytes());
+            central_directory.extend((entry.name.len() as u16).to_le_bytes());
+            central_directory.extend(0u16.to_le_bytes()); // Extra field length
+            central_directory.extend(0u16.to_le_bytes()); // Comment length
+            central_directory.extend(0u16.to_le_bytes()); // Disk number
+            central_directory.extend(0u16.to_le_bytes()); // Internal attributes
+            central_directory.extend(0u32.to_le_bytes()); // External attributes
+            central_directory.extend(entry.offset.to_le_bytes());
+            central_directory.extend(entry.name.as_bytes());
+        }
+
+        let entry_count = self.entries.len() as u16;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        end.extend(0u16.to_le_bytes()); // Number of this disk
+        end.extend(0u16.to_le_bytes()); // Disk where central directory starts
+        end.extend(entry_count.to_le_bytes()); // Entries on this disk
+        end.extend(entry_count.to_le_bytes()); // Total entries
+        end.extend((central_directory.len() as u32).to_le_bytes());
+        end.extend(central_directory_offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes()); // Comment length
+
+        self.output.write_all(&central_directory)?;
+        self.output.write_all(&end)?;
+        self.output.flush()?;
+
+        Ok(self.output)
+    }
+}
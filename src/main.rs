@@ -1,16 +1,54 @@
 mod command;
 mod config;
 mod engine;
+mod headless;
 mod internal;
 mod tui;
 
+use std::{path::PathBuf, process::ExitCode, sync::Arc, time::Duration};
+
 use anyhow::Context;
 use clap::Parser;
+use config::main::{ArchiveFormat, ArchiveSettings};
 use engine::EngineArgs;
+use internal::{
+    archive::{Archiver, MultiFormat, SevenZip},
+    clock::SystemClock,
+    encryption::Decrypting,
+    tar_zstd::TarZstd,
+};
+
+const DRY_RUN_HELP: &str = "Only log what would be backed up or restored, without writing any files";
+const PASSWORD_FILE_HELP: &str =
+    "File containing the password of a password-protected 7z archive, such as one made by hand";
 
 #[derive(Debug, Parser)]
 #[clap(name = "stool", version = env!("CARGO_PKG_VERSION"), author = env!("CARGO_PKG_AUTHORS"))]
 struct Opt {
+    #[clap(
+        long,
+        global = true,
+        env = "STOOL_CONFIG_PATH",
+        help = "Config directory, containing config.toml and game configs"
+    )]
+    config_path: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "STOOL_DATA_PATH",
+        help = "Data directory (overrides the data path in the main config)"
+    )]
+    data_path: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Keep config and data in a stool-data directory beside the executable. \
+                Enabled automatically if that directory exists."
+    )]
+    portable: bool,
+
     #[clap(subcommand)]
     command: Command,
 }
@@ -24,46 +62,576 @@ enum Command {
         #[clap(help = "Game name")]
         name: String,
 
-        #[clap(help = "Game command")]
+        #[clap(
+            help = "Game command (uses the command from the game config if omitted)",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
         game_command: Vec<String>,
+
+        #[clap(
+            long,
+            help = "Run without TUI, passing the game's standard input/output through and exiting with its exit code \
+                    (3 if it exited successfully, but backups were created with warnings)"
+        )]
+        no_tui: bool,
+
+        #[clap(
+            long,
+            conflicts_with = "no_tui",
+            help = "Run in the background without TUI, logging to a file. \
+                    On Windows, run it through stoolw to not show a console window."
+        )]
+        background: bool,
+
+        #[clap(
+            long,
+            requires = "no_tui",
+            value_name = "PATH",
+            help = "Read commands (backup <description>, toggle-auto, status) line by line from a file or named pipe, \
+                    or from standard input if `-`, which is then not passed to the game"
+        )]
+        commands: Option<PathBuf>,
+
+        #[clap(long, help = DRY_RUN_HELP)]
+        dry_run: bool,
+    },
+    #[clap(
+        about = "Wrap a launcher command, such as from a Lutris command prefix or Heroic wrapper field",
+        long_about = "Wrap a launcher command, such as from a Lutris command prefix or Heroic wrapper field. \
+                      Runs like run-game --no-tui, with the game named by --game or the STOOL_GAME environment variable."
+    )]
+    Wrap {
+        #[clap(long, env = "STOOL_GAME", help = "Game name")]
+        game: String,
+
+        #[clap(
+            help = "Launcher command to run",
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+
+        #[clap(long, help = DRY_RUN_HELP)]
+        dry_run: bool,
+    },
+    #[clap(
+        about = "Import a folder of backups made before stool, as save folders, zip, 7z or tar.zst archives",
+        long_about = "Import a folder of backups made before stool, as save folders, zip, 7z or tar.zst archives. \
+                      Each is archived as a backup with a manifest, timestamped by the date in its name \
+                      or when it was last modified. Backups already adopted are skipped."
+    )]
+    Adopt {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(help = "Folder containing the backups")]
+        dir: PathBuf,
+
+        #[clap(long, value_name = "PATH", help = PASSWORD_FILE_HELP)]
+        password_file: Option<PathBuf>,
     },
     #[clap(about = "Run stool in TUI mode")]
     Tui {
         #[clap(help = "Game name")]
         name: String,
+
+        #[clap(long, help = DRY_RUN_HELP)]
+        dry_run: bool,
+
+        #[clap(
+            long,
+            conflicts_with = "dry_run",
+            help = "Attach to the engine already running for the game, leaving it running on exit"
+        )]
+        attach: bool,
+    },
+    #[clap(about = "Run stool with a simple line-based menu, for terminals the TUI renders poorly in")]
+    Prompt {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(long, help = DRY_RUN_HELP)]
+        dry_run: bool,
+    },
+    #[clap(about = "Measure scan, copy, hash and compression performance on a game's save data")]
+    Bench {
+        #[clap(help = "Game name")]
+        name: String,
+    },
+    #[clap(about = "Check a game config for settings that are likely mistakes")]
+    Check {
+        #[clap(help = "Game name")]
+        name: String,
+    },
+    #[clap(about = "Show how much space a game's backups take and how they grew over time")]
+    Stats {
+        #[clap(help = "Game name")]
+        name: String,
+    },
+    #[clap(about = "Show what the engine running for a game is doing")]
+    Status {
+        #[clap(help = "Game name")]
+        name: String,
+    },
+    #[clap(about = "Query the engine running for a game")]
+    Ctl {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(subcommand)]
+        command: command::CtlCommand,
+    },
+    #[clap(about = "Compare the contents of two backups")]
+    Diff {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(help = "Old backup archive")]
+        old_archive: String,
+
+        #[clap(help = "New backup archive")]
+        new_archive: String,
+    },
+    #[clap(about = "Extract a backup to a directory for inspection, without touching save files")]
+    Extract {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(help = "Backup archive")]
+        archive: String,
+
+        #[clap(long, help = "Directory to extract to (a new temporary directory if omitted)")]
+        to: Option<PathBuf>,
+
+        #[clap(long, value_name = "PATH", help = PASSWORD_FILE_HELP)]
+        password_file: Option<PathBuf>,
+    },
+    #[clap(about = "Check the data directory of a game for inconsistencies")]
+    Fsck {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(long, help = "Repair issues that were found")]
+        repair: bool,
+    },
+    #[clap(about = "List backups")]
+    List {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(
+            long,
+            help = "Only list backups whose name or contained files match this search term"
+        )]
+        search: Option<String>,
+    },
+    #[clap(
+        about = "Bundle logs, recent history and config of a game into a zip to attach to a bug report",
+        long_about = "Bundle logs, recent history and config of a game into a zip to attach to a bug report. \
+                      Paths are stripped of the user's name and home directory, and passwords and environment \
+                      variables are redacted. Nothing is sent anywhere."
+    )]
+    Report {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(long, help = "Path of the zip file (stool-report-<game>-<time>.zip if omitted)")]
+        output: Option<PathBuf>,
+    },
+    #[clap(about = "Restore a backup")]
+    Restore {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(help = "Backup archive")]
+        archive: String,
+
+        #[clap(long, help = "Only restore this file or directory (path inside the backup)")]
+        only: Option<PathBuf>,
+
+        #[clap(
+            long,
+            value_enum,
+            help = "What to do about save files that are newer than in the backup (refuse to restore if omitted)"
+        )]
+        on_conflict: Option<command::OnConflict>,
+
+        #[clap(long, value_name = "PATH", help = PASSWORD_FILE_HELP)]
+        password_file: Option<PathBuf>,
+    },
+    #[clap(about = "Print a JSON Schema of a config file, for editor validation and autocompletion")]
+    Schema {
+        #[clap(value_enum, default_value = "game", help = "Config file")]
+        kind: command::SchemaKind,
+    },
+    #[cfg(feature = "self-update")]
+    #[clap(about = "Update stool to the latest release, verifying its checksum")]
+    SelfUpdate {
+        #[clap(long, help = "Only check whether a newer release is available")]
+        check: bool,
+    },
+    #[clap(about = "Check backups for damage using their parity data")]
+    Verify {
+        #[clap(help = "Game name")]
+        name: String,
+
+        #[clap(help = "Backup archive (all backups if omitted)")]
+        archive: Option<String>,
+
+        #[clap(long, help = "Repair damaged backups")]
+        repair: bool,
     },
 }
 
-fn main() -> Result<(), anyhow::Error> {
-    let opt = Opt::parse();
+/// Archiver creating archives in the configured format, and reading archives in any supported format
+fn archiver(settings: &ArchiveSettings) -> MultiFormat {
+    let seven_zip = Box::new(SevenZip::new(settings.level));
+    let tar_zstd = Box::new(TarZstd {
+        level: settings.level,
+        threads: settings.threads,
+        long_distance_matching: settings.long_distance_matching,
+        store_only: settings.store_only.clone(),
+    });
+
+    match settings.format {
+        ArchiveFormat::SevenZip => MultiFormat::new(seven_zip, vec![tar_zstd]),
+        ArchiveFormat::Zstd => MultiFormat::new(tar_zstd, vec![seven_zip]),
+    }
+}
+
+/// Config directory to use, and whether portable mode is in effect.
+/// A config path given explicitly takes precedence over portable mode.
+fn resolve_config_path(opt: &Opt, portable_config_path: PathBuf) -> Result<(PathBuf, bool), anyhow::Error> {
+    if let Some(config_path) = opt.config_path.as_ref() {
+        return Ok((config_path.clone(), false));
+    }
+
+    if opt.portable || portable_config_path.is_dir() {
+        return Ok((portable_config_path, true));
+    }
 
     let config_path = self::config::main::get_default_config_path().context("Getting default config path")?;
+
+    Ok((config_path, false))
+}
+
+fn main() -> Result<ExitCode, anyhow::Error> {
+    let opt = Opt::parse();
+
+    let (config_path, portable) = resolve_config_path(&opt, self::config::main::get_portable_config_path()?)?;
     let game_config_path = config_path.join("games");
 
-    let config = self::config::main::MainConfig::load_or_write_default_from_location(&config_path)?;
+    let config = headless::with_startup_logging(|| {
+        self::config::main::MainConfig::load_or_write_default_from_location(&config_path, portable)
+    })?;
+
+    let data_path = opt.data_path.unwrap_or_else(|| config.resolved_data_path(&config_path));
+    let use_index = config.use_index;
+    let strict_config = config.strict_config;
+    let overlay_status = config.overlay_status;
+    let check_interval = Duration::from_millis(config.engine.check_interval_ms);
+    let poll_interval = Duration::from_millis(config.engine.poll_interval_ms);
+    let archiver: Arc<dyn Archiver> = Arc::new(Decrypting {
+        inner: archiver(&config.archive),
+        identity_file: config.age_identity_file.as_ref().map(|path| config_path.join(path)),
+        temp_path: Default::default(),
+    });
+    let archive_parity_percent = config.archive.parity_percent;
+    let staging_root = config
+        .storage
+        .staging
+        .as_deref()
+        .map(|name| config.resolved_data_root(name, &config_path))
+        .transpose()?;
+    let backup_root = config
+        .storage
+        .backups
+        .as_deref()
+        .map(|name| config.resolved_data_root(name, &config_path))
+        .transpose()?;
+    let cold_root = config
+        .storage
+        .cold
+        .as_deref()
+        .map(|name| config.resolved_data_root(name, &config_path))
+        .transpose()?;
+    let temp_root = config.temp_path.as_ref().map(|path| config_path.join(path));
+    let cold_after = config
+        .storage
+        .cold_after_days
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+
+    let engine_args = |name: String| EngineArgs {
+        name,
+        game_config_path: game_config_path.clone(),
+        data_path: data_path.clone(),
+        use_index,
+        strict_config,
+        staging_root: staging_root.clone(),
+        backup_root: backup_root.clone(),
+        cold_root: cold_root.clone(),
+        cold_after,
+        archiver: archiver.clone(),
+        parity_percent: archive_parity_percent,
+        clock: Arc::new(SystemClock),
+        dry_run: false,
+        overlay_status,
+        check_interval,
+        poll_interval,
+        temp_root: temp_root.clone(),
+    };
+
+    #[cfg(feature = "self-update")]
+    if config.check_for_updates
+        && matches!(
+            opt.command,
+            Command::RunGame { .. } | Command::Wrap { .. } | Command::Tui { .. } | Command::Prompt { .. }
+        )
+    {
+        command::spawn_update_check();
+    }
 
-    let data_path = config.data_path;
+    let exit_code = match opt.command {
+        Command::New => {
+            command::new(&game_config_path)?;
+            ExitCode::SUCCESS
+        }
+        Command::RunGame {
+            name,
+            game_command,
+            no_tui,
+            background,
+            commands,
+            dry_run,
+        } => {
+            let mode = if background {
+                command::RunGameMode::Background
+            } else if no_tui {
+                command::RunGameMode::NoTui
+            } else {
+                command::RunGameMode::Tui
+            };
+
+            let engine_args = EngineArgs {
+                dry_run,
+                ..engine_args(name)
+            };
+
+            command::rungame(engine_args, game_command, mode, commands)?
+        }
+        Command::Wrap { game, command, dry_run } => {
+            let engine_args = EngineArgs {
+                dry_run,
+                ..engine_args(game)
+            };
 
-    match opt.command {
-        Command::New => command::new(&game_config_path),
-        Command::RunGame { name, game_command } => {
+            command::rungame(engine_args, command, command::RunGameMode::NoTui, None)?
+        }
+        Command::Tui { name, dry_run, attach } => {
             let engine_args = EngineArgs {
-                name,
-                game_config_path,
-                data_path,
+                dry_run,
+                ..engine_args(name)
             };
-            command::rungame(engine_args, game_command)
+
+            command::tui(engine_args, attach)?;
+            ExitCode::SUCCESS
         }
-        Command::Tui { name } => {
+        Command::Prompt { name, dry_run } => {
             let engine_args = EngineArgs {
-                name,
-                game_config_path,
-                data_path,
+                dry_run,
+                ..engine_args(name)
             };
 
-            command::tui(engine_args)
+            command::prompt(engine_args)?;
+            ExitCode::SUCCESS
+        }
+        Command::Adopt {
+            name,
+            dir,
+            password_file,
+        } => {
+            command::adopt(engine_args(name), &dir, password_file.as_deref())?;
+            ExitCode::SUCCESS
+        }
+        Command::Bench { name } => {
+            command::bench(engine_args(name))?;
+            ExitCode::SUCCESS
+        }
+        Command::Check { name } => command::check(engine_args(name))?,
+        Command::Stats { name } => {
+            command::stats(engine_args(name))?;
+            ExitCode::SUCCESS
+        }
+        Command::Status { name } => {
+            command::status(engine_args(name))?;
+            ExitCode::SUCCESS
+        }
+        Command::Ctl { name, command } => {
+            command::ctl(engine_args(name), command)?;
+            ExitCode::SUCCESS
+        }
+        Command::Diff {
+            name,
+            old_archive,
+            new_archive,
+        } => {
+            command::diff(engine_args(name), &old_archive, &new_archive)?;
+            ExitCode::SUCCESS
+        }
+        Command::Extract {
+            name,
+            archive,
+            to,
+            password_file,
+        } => {
+            command::extract(engine_args(name), &archive, to, password_file.as_deref())?;
+            ExitCode::SUCCESS
+        }
+        Command::Fsck { name, repair } => {
+            command::fsck(engine_args(name), repair)?;
+            ExitCode::SUCCESS
+        }
+        Command::List { name, search } => {
+            command::list(engine_args(name), search.as_deref())?;
+            ExitCode::SUCCESS
+        }
+        Command::Report { name, output } => {
+            let report_path = command::report(engine_args(name), &config_path, output)?;
+            println!(
+                "Report written to {}. Review its contents before attaching it to an issue.",
+                report_path.display()
+            );
+            ExitCode::SUCCESS
+        }
+        Command::Restore {
+            name,
+            archive,
+            only,
+            on_conflict,
+            password_file,
+        } => {
+            command::restore(engine_args(name), archive, only, on_conflict, password_file.as_deref())?;
+            ExitCode::SUCCESS
+        }
+        Command::Schema { kind } => {
+            command::schema(kind)?;
+            ExitCode::SUCCESS
+        }
+        #[cfg(feature = "self-update")]
+        Command::SelfUpdate { check } => {
+            command::self_update(check)?;
+            ExitCode::SUCCESS
         }
-    }?;
+        Command::Verify { name, archive, repair } => {
+            command::verify(engine_args(name), archive.as_deref(), repair)?;
+            ExitCode::SUCCESS
+        }
+    };
+
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_taken_from_flags_or_environment() {
+        let opt =
+            Opt::try_parse_from(["stool", "list", "game", "--config-path", "cfg", "--data-path", "data"]).unwrap();
+        assert_eq!(opt.config_path, Some(PathBuf::from("cfg")));
+        assert_eq!(opt.data_path, Some(PathBuf::from("data")));
+
+        // No other test reads these variables
+        std::env::set_var("STOOL_CONFIG_PATH", "env-cfg");
+        std::env::set_var("STOOL_DATA_PATH", "env-data");
+
+        let opt = Opt::try_parse_from(["stool", "list", "game"]).unwrap();
+        assert_eq!(opt.config_path, Some(PathBuf::from("env-cfg")));
+        assert_eq!(opt.data_path, Some(PathBuf::from("env-data")));
+
+        // Flags take precedence over the environment
+        let opt = Opt::try_parse_from(["stool", "--config-path", "cfg", "list", "game"]).unwrap();
+        assert_eq!(opt.config_path, Some(PathBuf::from("cfg")));
+
+        std::env::remove_var("STOOL_CONFIG_PATH");
+        std::env::remove_var("STOOL_DATA_PATH");
+    }
+
+    #[test]
+    fn explicit_config_path_is_used_as_is() {
+        let dir = tempfile::tempdir().unwrap();
+        let opt = Opt::try_parse_from(["stool", "list", "game"]).unwrap();
+        let opt = Opt {
+            config_path: Some(PathBuf::from("cfg")),
+            ..opt
+        };
+
+        assert_eq!(
+            resolve_config_path(&opt, dir.path().to_owned()).unwrap(),
+            (PathBuf::from("cfg"), false)
+        );
+    }
+
+    #[test]
+    fn portable_mode_is_used_when_requested_or_its_directory_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let portable_config_path = dir.path().join(config::main::PORTABLE_DIR_NAME);
+
+        // The config path may be set in the environment by another test
+        let opt = Opt::try_parse_from(["stool", "--portable", "list", "game"]).unwrap();
+        let opt = Opt {
+            config_path: None,
+            ..opt
+        };
+        assert_eq!(
+            resolve_config_path(&opt, portable_config_path.clone()).unwrap(),
+            (portable_config_path.clone(), true)
+        );
+
+        std::fs::create_dir(&portable_config_path).unwrap();
+        let opt = Opt::try_parse_from(["stool", "list", "game"]).unwrap();
+        let opt = Opt {
+            config_path: None,
+            ..opt
+        };
+        assert_eq!(
+            resolve_config_path(&opt, portable_config_path.clone()).unwrap(),
+            (portable_config_path, true)
+        );
+    }
+
+    #[test]
+    fn wrap_passes_the_launcher_command_through() {
+        let opt = Opt::try_parse_from([
+            "stool",
+            "wrap",
+            "--game",
+            "elden",
+            "--dry-run",
+            "wine",
+            "game.exe",
+            "--dry-run",
+        ])
+        .unwrap();
+        let Command::Wrap { game, command, dry_run } = opt.command else {
+            panic!("Unexpected command: {:?}", opt.command);
+        };
+        assert_eq!(game, "elden");
+        assert_eq!(command, ["wine", "game.exe", "--dry-run"]);
+        assert!(dry_run);
+
+        // No other test reads this variable
+        std::env::set_var("STOOL_GAME", "sekiro");
+        let opt = Opt::try_parse_from(["stool", "wrap", "-x", "game.exe"]);
+        std::env::remove_var("STOOL_GAME");
 
-    Ok(())
+        let Command::Wrap { game, command, .. } = opt.unwrap().command else {
+            panic!("Unexpected command");
+        };
+        assert_eq!(game, "sekiro");
+        assert_eq!(command, ["-x", "game.exe"]);
+    }
 }
@@ -1,10 +1,6 @@
 use std::{
-    path::PathBuf,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
-    },
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
@@ -17,59 +13,86 @@ use ratatui::{
     DefaultTerminal,
 };
 
-use crate::engine::{Engine, EngineControl};
+use crate::{
+    engine::{self, backups::QUICK_BACKUP_DESCRIPTION, BackupKind, BackupRequest, Engine, EngineArgs, EngineState},
+    internal::{format::format_duration, shutdown::Shutdown},
+};
 
 use super::{
+    compare_backups_view::CompareBackupsView,
     create_backup_view::CreateBackupView,
+    history_view::HistoryView,
+    link::EngineLink,
     log_widget::Log,
     menu_view::{MenuItem, MenuView},
     restore_backup_view::RestoreBackupView,
     state::AppState,
     style::{
-        FOOTER_AUTOBACKUP_OFF_STYLE, FOOTER_AUTOBACKUP_ON_STYLE, HEADER_STYLE, PROGRESS_BAR_BG_COLOR,
+        FOOTER_AUTOBACKUP_OFF_STYLE, FOOTER_AUTOBACKUP_ON_STYLE, FOOTER_BACKUP_WARNINGS_STYLE, FOOTER_CONFIRM_STYLE,
+        FOOTER_PENDING_STYLE, FOOTER_WARNING_STYLE, HEADER_STYLE, PROGRESS_BAR_BG_COLOR, PROGRESS_BAR_DETAIL_STYLE,
         PROGRESS_BAR_STYLE,
     },
+    watches_view::WatchesView,
 };
 
 const EVENT_POLL_DURATION: Duration = Duration::from_millis(100);
 
+/// How long the footer shows that a quick backup was requested
+const QUICK_BACKUP_FLASH_DURATION: Duration = Duration::from_secs(3);
+
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum View {
     #[default]
     Menu,
     CreateBackup,
     RestoreBackup,
+    CompareBackups,
+    History,
+    Watches,
     Shutdown,
 }
 
 pub struct App<'a> {
     state: Arc<Mutex<AppState>>,
-    backup_path: PathBuf,
-    engine: Engine,
-    engine_control: EngineControl,
-    shutdown: Arc<AtomicBool>,
+    /// Engine running in this process, if not attached to one running elsewhere
+    engine: Option<Engine>,
+    engine_control: EngineLink,
+    engine_args: EngineArgs,
+    shutdown: Shutdown,
 
     view: View,
 
+    /// When a quick backup was last requested, to confirm it in the footer
+    quick_backup_at: Option<Instant>,
+
     log_widget: Log,
     menu_view: MenuView,
     create_backup_view: Option<CreateBackupView<'a>>,
     restore_backup_view: Option<RestoreBackupView>,
+    compare_backups_view: Option<CompareBackupsView>,
+    history_view: Option<HistoryView<'a>>,
+    watches_view: Option<WatchesView>,
 }
 
 impl App<'_> {
-    pub fn new(state: Arc<Mutex<AppState>>, engine: Engine, backup_path: PathBuf, shutdown: Arc<AtomicBool>) -> Self {
-        let engine_control = engine.control();
-
+    pub fn new(
+        state: Arc<Mutex<AppState>>,
+        engine: Option<Engine>,
+        engine_control: EngineLink,
+        engine_args: EngineArgs,
+        shutdown: Shutdown,
+    ) -> Self {
         Self {
             state,
             engine,
             engine_control,
-            backup_path,
+            engine_args,
             shutdown,
 
             view: View::Menu,
 
+            quick_backup_at: None,
+
             log_widget: Log::default(),
 
             menu_view: MenuView::new(vec![
@@ -81,6 +104,18 @@ impl App<'_> {
                     description: "Restore backup".to_owned(),
                     view: View::RestoreBackup,
                 },
+                MenuItem {
+                    description: "Compare backups".to_owned(),
+                    view: View::CompareBackups,
+                },
+                MenuItem {
+                    description: "History".to_owned(),
+                    view: View::History,
+                },
+                MenuItem {
+                    description: "Watches".to_owned(),
+                    view: View::Watches,
+                },
                 MenuItem {
                     description: "Exit".to_owned(),
                     view: View::Shutdown,
@@ -89,6 +124,9 @@ impl App<'_> {
 
             create_backup_view: None,
             restore_backup_view: None,
+            compare_backups_view: None,
+            history_view: None,
+            watches_view: None,
         }
     }
 
@@ -98,7 +136,8 @@ impl App<'_> {
 
         loop {
             if !shutting_down {
-                if self.shutdown.load(Ordering::Relaxed) {
+                // An attached engine may shut down on its own
+                if self.shutdown.is_requested() || self.engine_control.state() == EngineState::ShutDown {
                     self.view = View::Shutdown;
                 }
 
@@ -108,7 +147,7 @@ impl App<'_> {
 
                     shutting_down = true;
                 }
-            } else if shutting_down && self.engine.has_shut_down() {
+            } else if shutting_down && self.engine.as_ref().is_none_or(Engine::has_shut_down) {
                 break;
             }
 
@@ -122,7 +161,9 @@ impl App<'_> {
         }
 
         // Wait for engine thread to finish
-        self.engine.join();
+        if let Some(engine) = self.engine {
+            engine.join();
+        }
 
         Ok(())
     }
@@ -146,6 +187,21 @@ impl App<'_> {
 
     /// Handles the key events and updates the state of [`App`].
     fn on_key_event(&mut self, key: KeyEvent) -> Result<(), anyhow::Error> {
+        // F5 to create a quick backup, from any view
+        if key.code == KeyCode::F(5) && self.view != View::Shutdown {
+            self.engine_control.send(BackupRequest::CreateBackup {
+                archive_name: engine::make_backup_filename(
+                    QUICK_BACKUP_DESCRIPTION,
+                    self.engine_args.archiver.extension(),
+                ),
+                kind: BackupKind::Manual,
+            })?;
+
+            self.quick_backup_at = Some(Instant::now());
+
+            return Ok(());
+        }
+
         'view: {
             match self.view {
                 View::CreateBackup => {
@@ -176,6 +232,48 @@ impl App<'_> {
 
                     return Ok(());
                 }
+                View::CompareBackups => {
+                    let Some(view) = self.compare_backups_view.as_mut() else {
+                        break 'view;
+                    };
+
+                    view.on_key_event(key)?;
+
+                    if view.is_done() {
+                        self.view = View::Menu;
+                        self.compare_backups_view = None;
+                    }
+
+                    return Ok(());
+                }
+                View::History => {
+                    let Some(view) = self.history_view.as_mut() else {
+                        break 'view;
+                    };
+
+                    view.on_key_event(key)?;
+
+                    if view.is_done() {
+                        self.view = View::Menu;
+                        self.history_view = None;
+                    }
+
+                    return Ok(());
+                }
+                View::Watches => {
+                    let Some(view) = self.watches_view.as_mut() else {
+                        break 'view;
+                    };
+
+                    view.on_key_event(key);
+
+                    if view.is_done() {
+                        self.view = View::Menu;
+                        self.watches_view = None;
+                    }
+
+                    return Ok(());
+                }
                 View::Shutdown => return Ok(()),
                 _ => {}
             }
@@ -184,11 +282,9 @@ impl App<'_> {
         match (key.modifiers, key.code) {
             (_, KeyCode::Char('q')) => self.quit(),
             // F12 to toggle Autobackup
-            (_, KeyCode::F(12)) => {
-                let control = self.engine.control();
-
-                control.set_autobackup(!control.get_autobackup())
-            }
+            (_, KeyCode::F(12)) => self
+                .engine_control
+                .set_autobackup(!self.engine_control.get_autobackup()),
             _ => {
                 self.menu_view.on_key_event(key);
 
@@ -210,11 +306,23 @@ impl App<'_> {
     /// Create views if needed
     fn create_views(&mut self) -> Result<(), anyhow::Error> {
         if self.view == View::CreateBackup && self.create_backup_view.is_none() {
-            self.create_backup_view = Some(CreateBackupView::new(self.engine_control.clone()));
+            self.create_backup_view = Some(CreateBackupView::new(self.engine_control.clone(), &self.engine_args));
         }
 
         if self.view == View::RestoreBackup && self.restore_backup_view.is_none() {
-            self.restore_backup_view = Some(RestoreBackupView::new(self.engine_control.clone(), &self.backup_path)?);
+            self.restore_backup_view = Some(RestoreBackupView::new(self.engine_control.clone(), &self.engine_args)?);
+        }
+
+        if self.view == View::CompareBackups && self.compare_backups_view.is_none() {
+            self.compare_backups_view = Some(CompareBackupsView::new(&self.engine_args)?);
+        }
+
+        if self.view == View::History && self.history_view.is_none() {
+            self.history_view = Some(HistoryView::new(self.engine_control.clone(), &self.engine_args)?);
+        }
+
+        if self.view == View::Watches && self.watches_view.is_none() {
+            self.watches_view = Some(WatchesView::new(self.engine_control.clone()));
         }
 
         Ok(())
@@ -231,8 +339,40 @@ impl Widget for &mut App<'_> {
     where
         Self: Sized,
     {
-        let [header_area, main_area, footer_area] =
-            Layout::vertical([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        // Gauges of the current action, of its current stage and file, and of any upload, taken at once for a consistent view
+        let (action_gauge, detail_gauges) = {
+            let state = self.state.lock().unwrap();
+            let progress = &state.progress;
+
+            let action_gauge = progress
+                .action
+                .as_ref()
+                .map(|action| (action.describe(), progress.action_ratio()));
+
+            let mut detail_gauges = Vec::new();
+
+            if let Some(stage) = progress.stage.as_ref() {
+                detail_gauges.push((stage.describe(), progress.stage_ratio()));
+            }
+
+            if let Some(file) = progress.file.as_ref() {
+                detail_gauges.push((file.describe(), file.ratio()));
+            }
+
+            if let Some(upload) = progress.upload.as_ref() {
+                detail_gauges.push((upload.describe(), upload.ratio()));
+            }
+
+            (action_gauge, detail_gauges)
+        };
+
+        let [header_area, main_area, details_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(detail_gauges.len() as u16),
+            Constraint::Length(1),
+        ])
+        .areas(area);
 
         let [main_area, log_area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(10)]).areas(main_area);
 
@@ -255,10 +395,31 @@ impl Widget for &mut App<'_> {
                     view.render(main_area, buf);
                 }
             }
+            View::CompareBackups => {
+                if let Some(view) = self.compare_backups_view.as_mut() {
+                    view.render(main_area, buf);
+                }
+            }
+            View::History => {
+                if let Some(view) = self.history_view.as_mut() {
+                    view.render(main_area, buf);
+                }
+            }
+            View::Watches => {
+                if let Some(view) = self.watches_view.as_mut() {
+                    view.render(main_area, buf);
+                }
+            }
             View::Shutdown => {
                 let block = Block::new().padding(Padding::top(1));
 
-                Paragraph::new("Shutting down...")
+                let text = if self.engine.is_some() {
+                    "Shutting down..."
+                } else {
+                    "Detaching..."
+                };
+
+                Paragraph::new(text)
                     .block(block)
                     .bold()
                     .centered()
@@ -269,8 +430,66 @@ impl Widget for &mut App<'_> {
 
         self.log_widget.render(log_area, buf);
 
-        let [autobackup_area, _, action_area] =
-            Layout::horizontal([Constraint::Length(16), Constraint::Length(1), Constraint::Fill(1)]).areas(footer_area);
+        // Badges showing that watching degraded to polling, that the latest backup had warnings, and that changes have been seen but not backed up yet
+        let mut badges = Vec::new();
+
+        if self
+            .quick_backup_at
+            .is_some_and(|at| at.elapsed() < QUICK_BACKUP_FLASH_DURATION)
+        {
+            badges.push(("Quick backup requested".to_owned(), FOOTER_CONFIRM_STYLE));
+        }
+
+        if self.engine_control.watcher_fallback().is_some() {
+            badges.push(("Watcher: polling".to_owned(), FOOTER_WARNING_STYLE));
+        }
+
+        let (backup_warnings, skipped_paths) = {
+            let state = self.state.lock().unwrap();
+            (state.last_backup_warnings.len(), state.last_backup_skipped_paths.len())
+        };
+
+        if skipped_paths > 0 {
+            badges.push((
+                format!("Backup: {skipped_paths} paths skipped"),
+                FOOTER_BACKUP_WARNINGS_STYLE,
+            ));
+        } else if backup_warnings > 0 {
+            badges.push((
+                format!("Backup completed with {backup_warnings} warnings"),
+                FOOTER_BACKUP_WARNINGS_STYLE,
+            ));
+        }
+
+        if let Some(pending) = self.engine_control.pending_changes() {
+            let text = match pending.auto_backup_in {
+                Some(auto_backup_in) => {
+                    format!("Unsaved changes: {} until auto-backup", format_duration(auto_backup_in))
+                }
+                None => format!("Unsaved changes: {} ago", format_duration(pending.since)),
+            };
+
+            badges.push((text, FOOTER_PENDING_STYLE));
+        }
+
+        let mut constraints = vec![Constraint::Length(16), Constraint::Length(1)];
+
+        for (text, _) in badges.iter() {
+            constraints.extend([Constraint::Length(text.len() as u16 + 2), Constraint::Length(1)]);
+        }
+
+        constraints.push(Constraint::Fill(1));
+
+        let footer_areas = Layout::horizontal(constraints).split(footer_area);
+        let autobackup_area = footer_areas[0];
+        let action_area = footer_areas[footer_areas.len() - 1];
+
+        for (i, (text, style)) in badges.into_iter().enumerate() {
+            Paragraph::new(text)
+                .style(style)
+                .centered()
+                .render(footer_areas[2 + i * 2], buf);
+        }
 
         let (autobackup_text, autobackup_style) = if self.engine_control.get_autobackup() {
             ("ON ", FOOTER_AUTOBACKUP_ON_STYLE)
@@ -284,15 +503,51 @@ impl Widget for &mut App<'_> {
             .centered()
             .render(autobackup_area, buf);
 
-        if let Some(action) = self.state.lock().unwrap().current_action.as_ref() {
+        let detail_areas = Layout::vertical(vec![Constraint::Length(1); detail_gauges.len()]).split(details_area);
+
+        for ((label, ratio), detail_area) in detail_gauges.into_iter().zip(detail_areas.iter()) {
+            let [_, gauge_area] = Layout::horizontal([Constraint::Length(17), Constraint::Fill(1)]).areas(*detail_area);
+
+            Gauge::default()
+                .gauge_style(PROGRESS_BAR_DETAIL_STYLE)
+                .bg(PROGRESS_BAR_BG_COLOR)
+                .label(label)
+                .ratio(ratio as f64)
+                .render(gauge_area, buf);
+        }
+
+        if let Some((label, ratio)) = action_gauge {
             Gauge::default()
                 .gauge_style(PROGRESS_BAR_STYLE)
                 .bg(PROGRESS_BAR_BG_COLOR)
-                .label(action.describe())
-                .ratio(action.progress.get() as f64)
+                .label(label)
+                .ratio(ratio as f64)
                 .render(action_area, buf);
         } else {
-            Line::raw("Idle").centered().render(action_area, buf);
+            Line::raw(idle_summary(&self.engine_control))
+                .centered()
+                .render(action_area, buf);
         };
     }
 }
+
+/// Glanceable timing of backups and changes, shown in place of progress while no action is running
+fn idle_summary(engine_control: &EngineLink) -> String {
+    let timing = engine_control.backup_timing();
+
+    let mut parts = vec!["Idle".to_owned()];
+
+    if let Some(ago) = timing.last_backup_ago {
+        parts.push(format!("last backup {} ago", format_duration(ago)));
+    }
+
+    if let Some(pending) = engine_control.pending_changes() {
+        parts.push(format!("last change {} ago", format_duration(pending.since)));
+    }
+
+    if let Some(possible_in) = timing.auto_backup_possible_in.filter(|d| !d.is_zero()) {
+        parts.push(format!("next auto-backup possible in {}", format_duration(possible_in)));
+    }
+
+    parts.join(" | ")
+}
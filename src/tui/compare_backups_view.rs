@@ -0,0 +1,153 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::Stylize,
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+use tracing::error;
+
+use crate::engine::{backups, diff, EngineArgs};
+
+use super::style::{list_item_color, LIST_BORDER_COLOR, LIST_HIGHLIGHT_STYLE};
+
+pub struct CompareBackupsView {
+    engine_args: EngineArgs,
+
+    items: Vec<String>,
+    list_state: ListState,
+
+    /// Backup selected as the old side of the comparison
+    old_backup: Option<String>,
+    /// Result of the comparison, one line per difference
+    result: Option<(Vec<String>, String)>,
+    result_list_state: ListState,
+
+    is_done: bool,
+}
+
+impl CompareBackupsView {
+    pub fn new(engine_args: &EngineArgs) -> Result<Self, anyhow::Error> {
+        let items: Vec<_> = backups::list_game_backups(engine_args)?
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+
+        Ok(Self {
+            engine_args: engine_args.clone(),
+            items,
+            list_state: ListState::default(),
+            old_backup: None,
+            result: None,
+            result_list_state: ListState::default(),
+            is_done: false,
+        })
+    }
+
+    pub fn on_key_event(&mut self, event: KeyEvent) -> Result<(), anyhow::Error> {
+        let list_state = if self.result.is_some() {
+            &mut self.result_list_state
+        } else {
+            &mut self.list_state
+        };
+
+        match event.code {
+            KeyCode::Esc => self.is_done = true,
+            KeyCode::Down => list_state.select_next(),
+            KeyCode::Up => list_state.select_previous(),
+            KeyCode::PageDown => list_state.scroll_down_by(10),
+            KeyCode::PageUp => list_state.scroll_up_by(10),
+            KeyCode::Enter if self.result.is_none() => {
+                let Some(ix) = self.list_state.selected() else {
+                    return Ok(());
+                };
+
+                let Some(item) = self.items.get(ix).cloned() else {
+                    return Ok(());
+                };
+
+                match self.old_backup.take() {
+                    None => self.old_backup = Some(item),
+                    Some(old_backup) => {
+                        if let Err(err) = self.compare(&old_backup, &item) {
+                            error!("Error comparing backups: {err}");
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.is_done
+    }
+
+    fn compare(&mut self, old_backup: &str, new_backup: &str) -> Result<(), anyhow::Error> {
+        let diff = diff::diff_backups(&self.engine_args, old_backup, new_backup)?;
+
+        let lines = diff.items.iter().map(|item| item.describe()).collect();
+        let summary = format!("{old_backup} -> {new_backup}: {}", diff.summary());
+
+        self.result = Some((lines, summary));
+
+        Ok(())
+    }
+}
+
+impl Widget for &mut CompareBackupsView {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let [list_area, status_area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+
+        let (title, items, list_state, status) = if let Some((lines, summary)) = self.result.as_ref() {
+            (
+                "Backup differences",
+                lines.as_slice(),
+                &mut self.result_list_state,
+                summary.clone(),
+            )
+        } else {
+            let status = match self.old_backup.as_ref() {
+                None => "Select the old backup".to_owned(),
+                Some(old_backup) => format!("Comparing {old_backup} with... (select the new backup)"),
+            };
+
+            ("Compare backups", self.items.as_slice(), &mut self.list_state, status)
+        };
+
+        let block = Block::new()
+            .title(Line::raw(title))
+            .borders(Borders::all())
+            .border_set(symbols::border::ROUNDED)
+            .border_style(LIST_BORDER_COLOR);
+
+        let items: Vec<ListItem> = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let color = list_item_color(i);
+
+                ListItem::from(item.as_str()).bg(color)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(LIST_HIGHLIGHT_STYLE)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        // We need to disambiguate this trait method as both `Widget` and `StatefulWidget` share the
+        // same method name `render`.
+        StatefulWidget::render(list, list_area, buf, list_state);
+
+        Paragraph::new(status).render(status_area, buf);
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    style::Stylize,
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, StatefulWidget, Widget},
+};
+use tracing::error;
+
+use crate::engine::{
+    restore::{self, ConflictResolution, RestoreConflict},
+    EngineArgs,
+};
+
+use super::{
+    link::EngineLink,
+    style::{list_item_color, LIST_BORDER_COLOR, LIST_HIGHLIGHT_STYLE},
+};
+
+/// Choice of what to do about each live save file that is newer than its copy in a backup about to be restored
+pub struct ConflictPicker {
+    archive_name: String,
+    only: Option<PathBuf>,
+    extension: &'static str,
+    conflicts: Vec<(RestoreConflict, ConflictResolution)>,
+    list_state: ListState,
+}
+
+pub enum PickerAction {
+    None,
+    Cancel,
+    Restore,
+}
+
+/// Restore a backup right away if no live save files are newer than their copies in it,
+/// or return a picker to resolve the conflicts with first
+pub fn restore_or_pick(
+    engine_control: &EngineLink,
+    engine_args: &EngineArgs,
+    archive_name: String,
+    only: Option<PathBuf>,
+) -> Result<Option<ConflictPicker>, anyhow::Error> {
+    let conflicts = restore::find_conflicts(engine_args, &archive_name, only.as_deref()).unwrap_or_else(|err| {
+        error!("Error looking for newer save files: {err}");
+        Vec::new()
+    });
+
+    let mut picker = ConflictPicker {
+        archive_name,
+        only,
+        extension: engine_args.archiver.extension(),
+        conflicts: conflicts
+            .into_iter()
+            .map(|c| (c, ConflictResolution::Overwrite))
+            .collect(),
+        list_state: ListState::default(),
+    };
+
+    if picker.conflicts.is_empty() {
+        picker.restore(engine_control)?;
+        return Ok(None);
+    }
+
+    picker.list_state.select_first();
+
+    Ok(Some(picker))
+}
+
+impl ConflictPicker {
+    pub fn on_key_event(&mut self, event: KeyEvent) -> PickerAction {
+        let resolution = match event.code {
+            KeyCode::Esc => return PickerAction::Cancel,
+            KeyCode::Enter => return PickerAction::Restore,
+            KeyCode::Down => {
+                self.list_state.select_next();
+                return PickerAction::None;
+            }
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                return PickerAction::None;
+            }
+            KeyCode::Char('o') => ConflictResolution::Overwrite,
+            KeyCode::Char('k') => ConflictResolution::Keep,
+            KeyCode::Char('b') => ConflictResolution::BackUpFirst,
+            _ => return PickerAction::None,
+        };
+
+        if let Some((_, selected)) = self.list_state.selected().and_then(|ix| self.conflicts.get_mut(ix)) {
+            *selected = resolution;
+        }
+
+        PickerAction::None
+    }
+
+    /// Send the requests restoring the backup with the conflicts resolved as chosen
+    pub fn restore(self, engine_control: &EngineLink) -> Result<(), anyhow::Error> {
+        for request in restore::restore_requests(self.archive_name, self.only, &self.conflicts, self.extension) {
+            engine_control.send(request)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Widget for &mut ConflictPicker {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::new()
+            .title(Line::raw(format!("Save files newer than in {}", self.archive_name)))
+            .title_bottom(
+                Line::raw(" o: overwrite | k: keep | b: back up first | Enter: restore | Esc: cancel ").centered(),
+            )
+            .borders(Borders::all())
+            .border_set(symbols::border::ROUNDED)
+            .border_style(LIST_BORDER_COLOR);
+
+        let items: Vec<ListItem> = self
+            .conflicts
+            .iter()
+            .enumerate()
+            .map(|(i, (conflict, resolution))| {
+                let resolution = match resolution {
+                    ConflictResolution::Overwrite => "overwrite",
+                    ConflictResolution::Keep => "keep",
+                    ConflictResolution::BackUpFirst => "back up first",
+                };
+
+                ListItem::from(format!("[{resolution:^13}] {conflict}")).bg(list_item_color(i))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(LIST_HIGHLIGHT_STYLE)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(list, area, buf, &mut self.list_state);
+    }
+}
@@ -6,34 +6,61 @@ use ratatui::{
     text::Line,
     widgets::{Block, Borders, Widget},
 };
+use tracing::error;
 use tui_textarea::TextArea;
 
-use crate::engine::{self, BackupRequest, EngineControl};
+use crate::{
+    config::game::GameConfig,
+    engine::{self, descriptions::DescriptionHistory, BackupKind, BackupRequest, EngineArgs},
+};
+
+use super::link::EngineLink;
 
 pub struct CreateBackupView<'a> {
-    engine_control: EngineControl,
+    engine_control: EngineLink,
+    archive_extension: &'static str,
     backup_name: TextArea<'a>,
+    history: Option<DescriptionHistory>,
+    /// Previously used descriptions followed by the configured templates, cycled through with Up and Down
+    suggestions: Vec<String>,
+    /// Suggestion shown, or none while showing what was typed
+    suggestion_ix: Option<usize>,
+    /// What was typed before cycling through suggestions
+    draft: String,
     is_done: bool,
 }
 
 impl CreateBackupView<'_> {
-    pub fn new(engine_control: EngineControl) -> Self {
-        let title = Line::raw("Create backup");
-
-        let block = Block::default()
-            .title(title)
-            .border_set(symbols::border::ROUNDED)
-            .border_style(Style::default())
-            .borders(Borders::all());
-
+    pub fn new(engine_control: EngineLink, engine_args: &EngineArgs) -> Self {
         let mut backup_description = TextArea::default();
-        backup_description.set_block(block);
+        backup_description.set_block(make_block(false));
         backup_description.set_cursor_line_style(Style::default());
         backup_description.set_placeholder_text("Enter backup name");
 
+        let history = DescriptionHistory::load(&engine_args.output_path())
+            .inspect_err(|err| error!("Error loading description history: {err}"))
+            .ok();
+
+        let templates = GameConfig::from_file(&engine_args.game_config_file_path(), engine_args.strict_config)
+            .map(|gcfg| gcfg.description_templates)
+            .unwrap_or_else(|err| {
+                error!("Error loading description templates: {err}");
+                Vec::new()
+            });
+
+        let suggestions = match history.as_ref() {
+            Some(history) => history.suggestions(&templates),
+            None => templates,
+        };
+
         Self {
             engine_control,
+            archive_extension: engine_args.archiver.extension(),
             backup_name: backup_description,
+            history,
+            suggestions,
+            suggestion_ix: None,
+            draft: String::new(),
             is_done: false,
         }
     }
@@ -42,10 +69,20 @@ impl CreateBackupView<'_> {
         match event.code {
             KeyCode::Esc => self.is_done = true,
             KeyCode::Enter => {
-                self.create_backup()?;
+                // Submission is held until the ongoing backup or restore finishes
+                if !self.engine_control.is_busy() {
+                    self.create_backup()?;
+                }
+                return Ok(());
+            }
+            KeyCode::Up => {
+                self.cycle_suggestions(true);
+                return Ok(());
+            }
+            KeyCode::Down => {
+                self.cycle_suggestions(false);
                 return Ok(());
             }
-            KeyCode::Down | KeyCode::Up => {}
             _ => {}
         }
 
@@ -54,6 +91,30 @@ impl CreateBackupView<'_> {
         Ok(())
     }
 
+    /// Show the next older suggestion, or the next newer one and finally what was typed
+    fn cycle_suggestions(&mut self, older: bool) {
+        let ix = match (self.suggestion_ix, older) {
+            (None, true) if !self.suggestions.is_empty() => {
+                self.draft = self.backup_name.lines().first().cloned().unwrap_or_default();
+                Some(0)
+            }
+            (None, _) => return,
+            (Some(ix), true) => Some((ix + 1).min(self.suggestions.len() - 1)),
+            (Some(ix), false) => ix.checked_sub(1),
+        };
+
+        self.suggestion_ix = ix;
+
+        let text = match ix {
+            Some(ix) => self.suggestions[ix].clone(),
+            None => self.draft.clone(),
+        };
+
+        self.backup_name.select_all();
+        self.backup_name.cut();
+        self.backup_name.insert_str(text);
+    }
+
     pub fn is_done(&self) -> bool {
         self.is_done
     }
@@ -73,9 +134,20 @@ impl CreateBackupView<'_> {
             return Ok(());
         }
 
-        let archive_name = engine::make_backup_filename(&description);
+        let archive_name = engine::make_backup_filename(&description, self.archive_extension);
 
-        self.engine_control.send(BackupRequest::CreateBackup { archive_name })?;
+        self.engine_control.send(BackupRequest::CreateBackup {
+            archive_name,
+            kind: BackupKind::Manual,
+        })?;
+
+        if let Some(history) = self.history.as_mut() {
+            history.record(&description);
+
+            if let Err(err) = history.save() {
+                error!("Error saving description history: {err}");
+            }
+        }
 
         Ok(())
     }
@@ -88,6 +160,24 @@ impl Widget for &mut CreateBackupView<'_> {
     {
         let [backup_name_area, _] = Layout::vertical([Constraint::Length(3), Constraint::Length(10)]).areas(area);
 
+        self.backup_name.set_block(make_block(self.engine_control.is_busy()));
         self.backup_name.render(backup_name_area, buf);
     }
 }
+
+fn make_block(busy: bool) -> Block<'static> {
+    let title = if busy {
+        Line::raw("Create backup (waiting for ongoing operation)")
+    } else {
+        Line::raw("Create backup")
+    };
+
+    let hints = Line::raw(" Up/Down: previous descriptions and templates | Enter: create | Esc: cancel ").centered();
+
+    Block::default()
+        .title(title)
+        .title_bottom(hints)
+        .border_set(symbols::border::ROUNDED)
+        .border_style(Style::default())
+        .borders(Borders::all())
+}
@@ -0,0 +1,274 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, StatefulWidget, Widget},
+};
+use std::time::SystemTime;
+
+use time::{format_description::BorrowedFormatItem, macros::format_description, OffsetDateTime, UtcOffset};
+use tracing::{error, info};
+use tui_textarea::TextArea;
+
+use crate::{
+    engine::{annotations::Annotations, history, stats, EngineArgs},
+    internal::format::format_duration,
+};
+
+use super::{
+    conflict_picker::{self, ConflictPicker, PickerAction},
+    link::EngineLink,
+    style::{list_item_color, LIST_BORDER_COLOR, LIST_HIGHLIGHT_STYLE},
+};
+
+const SESSION_DATE_FORMAT: &[BorrowedFormatItem<'static>] = format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+enum Row {
+    Header(String),
+    Backup(String),
+}
+
+/// Past sessions and their backups, newest first.
+/// Backups can be restored, pinned and annotated.
+pub struct HistoryView<'a> {
+    engine_control: EngineLink,
+    engine_args: EngineArgs,
+
+    annotations: Annotations,
+    /// Totals of the backups, shown in the title
+    stats_summary: String,
+
+    rows: Vec<Row>,
+    list_state: ListState,
+
+    /// Note being edited, with the archive it belongs to
+    note_editor: Option<(String, TextArea<'a>)>,
+    /// Save files newer than in the backup to be restored, to resolve before restoring
+    conflict_picker: Option<ConflictPicker>,
+
+    is_done: bool,
+}
+
+impl HistoryView<'_> {
+    pub fn new(engine_control: EngineLink, engine_args: &EngineArgs) -> Result<Self, anyhow::Error> {
+        let output_path = engine_args.output_path();
+
+        let mut rows = Vec::new();
+
+        for group in history::game_sessions(engine_args)? {
+            let header = match (group.session, group.backups.last()) {
+                (Some(session), _) => format!(
+                    "Session {} ({}, {} backups)",
+                    session.started_at.format(SESSION_DATE_FORMAT)?,
+                    format_duration(session.duration),
+                    group.backups.len()
+                ),
+                (None, Some(first_backup)) if group.session_id.is_some() => format!(
+                    "Session {} (unfinished, {} backups)",
+                    local_time(first_backup.modified).format(SESSION_DATE_FORMAT)?,
+                    group.backups.len()
+                ),
+                _ => format!("Outside sessions ({} backups)", group.backups.len()),
+            };
+
+            rows.push(Row::Header(header));
+            rows.extend(group.backups.into_iter().map(|b| Row::Backup(b.name)));
+        }
+
+        Ok(Self {
+            engine_control,
+            engine_args: engine_args.clone(),
+            annotations: Annotations::load(&output_path)?,
+            stats_summary: stats::backup_stats(engine_args)?.summary(),
+            rows,
+            list_state: ListState::default(),
+            note_editor: None,
+            conflict_picker: None,
+            is_done: false,
+        })
+    }
+
+    pub fn on_key_event(&mut self, event: KeyEvent) -> Result<(), anyhow::Error> {
+        if let Some(picker) = self.conflict_picker.as_mut() {
+            match picker.on_key_event(event) {
+                PickerAction::None => {}
+                PickerAction::Cancel => self.conflict_picker = None,
+                PickerAction::Restore => {
+                    if let Some(picker) = self.conflict_picker.take() {
+                        picker.restore(&self.engine_control)?;
+                    }
+
+                    self.is_done = true;
+                }
+            }
+
+            return Ok(());
+        }
+
+        if let Some((archive_name, editor)) = self.note_editor.as_mut() {
+            match event.code {
+                KeyCode::Esc => self.note_editor = None,
+                KeyCode::Enter => {
+                    let note = editor.lines().first().cloned().unwrap_or_default();
+
+                    self.annotations.set_note(archive_name, &note);
+                    self.save_annotations();
+
+                    self.note_editor = None;
+                }
+                _ => {
+                    editor.input(event);
+                }
+            }
+
+            return Ok(());
+        }
+
+        match event.code {
+            KeyCode::Esc => self.is_done = true,
+            KeyCode::Down => self.list_state.select_next(),
+            KeyCode::Up => self.list_state.select_previous(),
+            KeyCode::PageDown => self.list_state.scroll_down_by(10),
+            KeyCode::PageUp => self.list_state.scroll_up_by(10),
+            KeyCode::Enter => {
+                let Some(archive_name) = self.selected_backup() else {
+                    return Ok(());
+                };
+
+                self.conflict_picker =
+                    conflict_picker::restore_or_pick(&self.engine_control, &self.engine_args, archive_name, None)?;
+                self.is_done = self.conflict_picker.is_none();
+            }
+            KeyCode::Char('p') => {
+                let Some(archive_name) = self.selected_backup() else {
+                    return Ok(());
+                };
+
+                let pinned = !self.annotations.is_pinned(&archive_name);
+                self.annotations.set_pinned(&archive_name, pinned);
+                self.save_annotations();
+
+                if pinned {
+                    info!("Pinned backup: {archive_name}");
+                } else {
+                    info!("Unpinned backup: {archive_name}");
+                }
+            }
+            KeyCode::Char('n') => {
+                let Some(archive_name) = self.selected_backup() else {
+                    return Ok(());
+                };
+
+                let note = self
+                    .annotations
+                    .get(&archive_name)
+                    .and_then(|a| a.note.clone())
+                    .unwrap_or_default();
+
+                let block = Block::default()
+                    .title(Line::raw(format!("Note for {archive_name}")))
+                    .border_set(symbols::border::ROUNDED)
+                    .border_style(Style::default())
+                    .borders(Borders::all());
+
+                let mut editor = TextArea::new(vec![note]);
+                editor.set_block(block);
+                editor.set_cursor_line_style(Style::default());
+                editor.set_placeholder_text("Enter note (blank to remove)");
+                editor.move_cursor(tui_textarea::CursorMove::End);
+
+                self.note_editor = Some((archive_name, editor));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.is_done
+    }
+
+    fn selected_backup(&self) -> Option<String> {
+        match self.rows.get(self.list_state.selected()?)? {
+            Row::Backup(name) => Some(name.clone()),
+            Row::Header(_) => None,
+        }
+    }
+
+    fn save_annotations(&self) {
+        if let Err(err) = self.annotations.save() {
+            error!("Error saving annotations: {err}");
+        }
+    }
+}
+
+fn local_time(time: SystemTime) -> OffsetDateTime {
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+
+    OffsetDateTime::from(time).to_offset(offset)
+}
+
+impl Widget for &mut HistoryView<'_> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        if let Some(picker) = self.conflict_picker.as_mut() {
+            picker.render(area, buf);
+            return;
+        }
+
+        let list_area = match self.note_editor.as_ref() {
+            Some((_, editor)) => {
+                let [editor_area, list_area] =
+                    Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(area);
+
+                editor.render(editor_area, buf);
+
+                list_area
+            }
+            None => area,
+        };
+
+        let block = Block::new()
+            .title(Line::raw("History"))
+            .title(Line::raw(format!(" {} ", self.stats_summary)).right_aligned())
+            .title_bottom(Line::raw(" Enter: restore | p: pin | n: note | Esc: back ").centered())
+            .borders(Borders::all())
+            .border_set(symbols::border::ROUNDED)
+            .border_style(LIST_BORDER_COLOR);
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| match row {
+                Row::Header(header) => ListItem::from(header.as_str()).bold(),
+                Row::Backup(name) => {
+                    let annotation = self.annotations.get(name);
+
+                    let pin = if annotation.is_some_and(|a| a.pinned) { "*" } else { " " };
+                    let note = annotation
+                        .and_then(|a| a.note.as_deref())
+                        .map(|note| format!(" - {note}"))
+                        .unwrap_or_default();
+
+                    ListItem::from(format!("  {pin} {name}{note}")).bg(list_item_color(i))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(LIST_HIGHLIGHT_STYLE)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        // We need to disambiguate this trait method as both `Widget` and `StatefulWidget` share the
+        // same method name `render`.
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+    }
+}
@@ -0,0 +1,90 @@
+use tracing::error;
+
+use crate::engine::{
+    remote::RemoteEngine, watch::WatchStatus, BackupRequest, BackupTiming, EngineControl, EngineState, PendingChanges,
+};
+
+/// Engine driven by the TUI, either running in this process or attached to over its control socket
+#[derive(Clone)]
+pub enum EngineLink {
+    Local(EngineControl),
+    Remote(RemoteEngine),
+}
+
+impl EngineLink {
+    /// Request shutdown of a local engine.
+    /// An attached engine is left running.
+    pub fn shutdown(&mut self) {
+        if let Self::Local(control) = self {
+            control.shutdown();
+        }
+    }
+
+    pub fn state(&self) -> EngineState {
+        match self {
+            Self::Local(control) => control.state(),
+            Self::Remote(remote) => remote.state(),
+        }
+    }
+
+    pub fn get_autobackup(&self) -> bool {
+        match self {
+            Self::Local(control) => control.get_autobackup(),
+            Self::Remote(remote) => remote.get_autobackup(),
+        }
+    }
+
+    pub fn set_autobackup(&self, val: bool) {
+        match self {
+            Self::Local(control) => control.set_autobackup(val),
+            Self::Remote(remote) => {
+                if let Err(err) = remote.set_autobackup(val) {
+                    error!("Error setting autobackup: {err}");
+                }
+            }
+        }
+    }
+
+    pub fn watches(&self) -> Vec<WatchStatus> {
+        match self {
+            Self::Local(control) => control.watches(),
+            // Read on every render, so errors are left to the status poll to detect
+            Self::Remote(remote) => remote.watches().unwrap_or_default(),
+        }
+    }
+
+    pub fn watcher_fallback(&self) -> Option<String> {
+        match self {
+            Self::Local(control) => control.watcher_fallback(),
+            Self::Remote(remote) => remote.watcher_fallback(),
+        }
+    }
+
+    pub fn pending_changes(&self) -> Option<PendingChanges> {
+        match self {
+            Self::Local(control) => control.pending_changes(),
+            Self::Remote(remote) => remote.pending_changes(),
+        }
+    }
+
+    pub fn backup_timing(&self) -> BackupTiming {
+        match self {
+            Self::Local(control) => control.backup_timing(),
+            Self::Remote(remote) => remote.backup_timing(),
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        match self {
+            Self::Local(control) => control.is_busy(),
+            Self::Remote(remote) => remote.is_busy(),
+        }
+    }
+
+    pub fn send(&self, req: BackupRequest) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Local(control) => control.send(req),
+            Self::Remote(remote) => remote.send(req),
+        }
+    }
+}
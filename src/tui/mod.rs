@@ -1,30 +1,34 @@
 mod app;
+mod compare_backups_view;
+mod conflict_picker;
 mod create_backup_view;
+mod history_view;
+mod link;
 mod log_widget;
 mod menu_view;
 mod restore_backup_view;
 mod state;
 mod style;
-mod uihandler;
+mod watches_view;
 
-use std::sync::{atomic::AtomicBool, Arc, Mutex};
+use std::sync::{Arc, Mutex};
 
 pub use state::AppState;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-pub use uihandler::TuiUiHandler;
 
-use crate::engine::{Engine, EngineArgs};
+use crate::{
+    engine::{progress::ProgressUiHandler, remote::RemoteEngine, Engine, EngineArgs},
+    internal::shutdown::Shutdown,
+};
 
-use self::app::App;
+use self::{app::App, link::EngineLink};
 
-pub fn run(engine: Engine, app_state: Arc<Mutex<AppState>>, shutdown: Arc<AtomicBool>) -> Result<(), anyhow::Error> {
-    let backup_path = {
-        let EngineArgs { name, data_path, .. } = engine.args();
-
-        let output_path = data_path.join(name);
-        output_path.join("backups")
-    };
+/// UI handler updating the progress shown in the TUI
+pub type TuiUiHandler = ProgressUiHandler<AppState>;
 
+/// Set up logging to the TUI log widget.
+/// Should be called before starting the engine, so that messages logged during startup are shown.
+pub fn init_logging() -> Result<(), anyhow::Error> {
     tui_logger::init_logger(tui_logger::LevelFilter::Debug)?;
     tui_logger::set_default_level(tui_logger::LevelFilter::Info);
 
@@ -32,8 +36,36 @@ pub fn run(engine: Engine, app_state: Arc<Mutex<AppState>>, shutdown: Arc<Atomic
         .with(tui_logger::tracing_subscriber_layer())
         .init();
 
+    Ok(())
+}
+
+pub fn run(engine: Engine, app_state: Arc<Mutex<AppState>>, shutdown: Shutdown) -> Result<(), anyhow::Error> {
+    let link = EngineLink::Local(engine.control());
+    let engine_args = engine.args().clone();
+
+    run_app(App::new(app_state, Some(engine), link, engine_args, shutdown))
+}
+
+/// Run the TUI against an engine running in another process.
+/// Exiting detaches from the engine, leaving it running.
+pub fn run_attached(
+    remote: RemoteEngine,
+    engine_args: EngineArgs,
+    app_state: Arc<Mutex<AppState>>,
+    shutdown: Shutdown,
+) -> Result<(), anyhow::Error> {
+    run_app(App::new(
+        app_state,
+        None,
+        EngineLink::Remote(remote),
+        engine_args,
+        shutdown,
+    ))
+}
+
+fn run_app(app: App) -> Result<(), anyhow::Error> {
     let terminal = ratatui::init();
-    let result = App::new(app_state, engine, backup_path, shutdown).run(terminal);
+    let result = app.run(terminal);
     ratatui::restore();
     result?;
 
@@ -1,61 +1,160 @@
-use std::{fs, path::Path};
+use std::path::PathBuf;
 
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    style::Stylize,
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
     symbols,
-    text::Line,
+    text::{Line, Span},
     widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState, StatefulWidget, Widget},
 };
+use tracing::error;
+use tui_textarea::TextArea;
 
-use crate::engine::{BackupRequest, EngineControl};
+use crate::{
+    engine::{backups, manifest::Manifest, EngineArgs},
+    internal::archive::{ArchiveEntry, PasswordRequired},
+};
+
+use super::{
+    conflict_picker::{self, ConflictPicker, PickerAction},
+    link::EngineLink,
+    style::{list_item_color, LIST_BORDER_COLOR, LIST_HIGHLIGHT_STYLE},
+};
 
-use super::style::{list_item_color, LIST_BORDER_COLOR, LIST_HIGHLIGHT_STYLE};
+const ENTIRE_BACKUP_ITEM: &str = "[Entire backup]";
 
 pub struct RestoreBackupView {
-    engine_control: EngineControl,
+    engine_control: EngineLink,
+    engine_args: EngineArgs,
 
     items: Vec<String>,
+    /// Metadata of each backup extracted from its save files, for display next to its name
+    item_metadata: Vec<String>,
     list_state: ListState,
+
+    /// Password of the chosen archive, if it cannot be read without one
+    password_prompt: Option<PasswordPrompt>,
+    /// Archive chosen for restoring, with the files it contains
+    file_picker: Option<FilePicker>,
+    /// Save files newer than in the chosen backup, to resolve before restoring
+    conflict_picker: Option<ConflictPicker>,
+
     is_done: bool,
 }
 
-impl RestoreBackupView {
-    pub fn new(engine_control: EngineControl, backup_path: &Path) -> Result<Self, anyhow::Error> {
-        let backup_files = fs::read_dir(backup_path)?;
-        let mut backup_files: Vec<_> = backup_files
-            .filter_map(Result::ok)
-            .filter_map(|e| {
-                let path = e.path();
-
-                if !path.is_file() || !matches!(path.extension(), Some(ext) if ext == "7z") {
-                    return None;
-                }
-
-                let metadata = path.metadata().unwrap();
-                let modified = metadata.modified().unwrap();
+struct FilePicker {
+    archive_name: String,
+    items: Vec<String>,
+    list_state: ListState,
+}
 
-                Some((path, modified))
-            })
-            .collect();
+struct PasswordPrompt {
+    archive_name: String,
+    password: TextArea<'static>,
+}
 
-        backup_files.sort_by_key(|(_, v)| *v);
-        backup_files.reverse();
+impl RestoreBackupView {
+    pub fn new(engine_control: EngineLink, engine_args: &EngineArgs) -> Result<Self, anyhow::Error> {
+        let backups = backups::list_game_backups(engine_args)?;
 
-        let items: Vec<_> = backup_files
+        let item_metadata = backups
             .iter()
-            .map(|(p, _)| p.file_name().unwrap().to_string_lossy().to_string())
+            .map(|b| {
+                let metadata = Manifest::load_for_archive(&b.path)
+                    .ok()
+                    .flatten()
+                    .map(|m| m.metadata)
+                    .unwrap_or_default();
+
+                metadata
+                    .iter()
+                    .map(|(label, value)| format!("{label}: {value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
             .collect();
 
         Ok(Self {
             engine_control,
-            items,
+            engine_args: engine_args.clone(),
+            items: backups.into_iter().map(|b| b.name).collect(),
+            item_metadata,
             list_state: ListState::default(),
+            password_prompt: None,
+            file_picker: None,
+            conflict_picker: None,
             is_done: false,
         })
     }
 
     pub fn on_key_event(&mut self, event: KeyEvent) -> Result<(), anyhow::Error> {
+        if let Some(picker) = self.conflict_picker.as_mut() {
+            match picker.on_key_event(event) {
+                PickerAction::None => {}
+                PickerAction::Cancel => self.conflict_picker = None,
+                PickerAction::Restore => {
+                    if let Some(picker) = self.conflict_picker.take() {
+                        picker.restore(&self.engine_control)?;
+                    }
+
+                    self.is_done = true;
+                }
+            }
+
+            return Ok(());
+        }
+
+        if let Some(prompt) = self.password_prompt.as_mut() {
+            match event.code {
+                KeyCode::Esc => self.password_prompt = None,
+                KeyCode::Enter => {
+                    let password = prompt.password.lines().first().cloned().unwrap_or_default();
+                    let archive_name = prompt.archive_name.clone();
+
+                    if matches!(self.engine_control, EngineLink::Remote(_)) {
+                        error!("The attached engine cannot restore password-protected archives");
+                    }
+
+                    self.engine_args.archiver.set_password(Some(password));
+                    self.open_file_picker(archive_name, true);
+                }
+                _ => {
+                    prompt.password.input(event);
+                }
+            }
+
+            return Ok(());
+        }
+
+        if let Some(file_picker) = self.file_picker.as_mut() {
+            match event.code {
+                KeyCode::Esc => self.file_picker = None,
+                KeyCode::Down => file_picker.list_state.select_next(),
+                KeyCode::Up => file_picker.list_state.select_previous(),
+                KeyCode::PageDown => file_picker.list_state.scroll_down_by(10),
+                KeyCode::PageUp => file_picker.list_state.scroll_up_by(10),
+                KeyCode::Enter => {
+                    let Some(ix) = file_picker.list_state.selected() else {
+                        return Ok(());
+                    };
+
+                    let archive_name = file_picker.archive_name.clone();
+
+                    // The first item restores the entire backup
+                    let only = match ix {
+                        0 => None,
+                        _ => file_picker.items.get(ix).map(PathBuf::from),
+                    };
+
+                    self.restore_backup(archive_name, only)?;
+                }
+                _ => {}
+            }
+
+            return Ok(());
+        }
+
         match event.code {
             KeyCode::Esc => self.is_done = true,
             KeyCode::Down => self.list_state.select_next(),
@@ -71,7 +170,7 @@ impl RestoreBackupView {
                     return Ok(());
                 };
 
-                self.restore_backup(item.to_owned())?;
+                self.open_file_picker(item.to_owned(), false);
             }
             _ => {}
         }
@@ -83,26 +182,113 @@ impl RestoreBackupView {
         self.is_done
     }
 
-    pub fn restore_backup(&mut self, archive_name: String) -> Result<(), anyhow::Error> {
+    /// Pick files from an archive to restore, asking for its password first if it is password-protected
+    fn open_file_picker(&mut self, archive_name: String, password_given: bool) {
+        self.password_prompt = None;
+
+        match backups::list_backup_contents(&self.engine_args, &archive_name) {
+            Err(err) if err.is::<PasswordRequired>() => {
+                self.password_prompt = Some(PasswordPrompt::new(archive_name, password_given));
+            }
+            contents => self.file_picker = Some(FilePicker::new(archive_name, contents)),
+        }
+    }
+
+    pub fn restore_backup(&mut self, archive_name: String, only: Option<PathBuf>) -> Result<(), anyhow::Error> {
         if self.is_done {
             return Ok(());
         }
 
-        self.is_done = true;
-
-        self.engine_control
-            .send(BackupRequest::RestoreBackup { archive_name })?;
+        self.conflict_picker =
+            conflict_picker::restore_or_pick(&self.engine_control, &self.engine_args, archive_name, only)?;
+        self.is_done = self.conflict_picker.is_none();
 
         Ok(())
     }
 }
 
+impl FilePicker {
+    fn new(archive_name: String, contents: Result<Vec<ArchiveEntry>, anyhow::Error>) -> Self {
+        let mut items = vec![ENTIRE_BACKUP_ITEM.to_owned()];
+
+        // If the archive contents cannot be listed, only a full restore is offered
+        match contents {
+            Ok(entries) => items.extend(
+                entries
+                    .into_iter()
+                    .filter(|e| !e.is_dir)
+                    .map(|e| e.path.to_string_lossy().to_string()),
+            ),
+            Err(err) => error!("Error listing backup contents: {err}"),
+        }
+
+        let mut list_state = ListState::default();
+        list_state.select_first();
+
+        Self {
+            archive_name,
+            items,
+            list_state,
+        }
+    }
+}
+
+impl PasswordPrompt {
+    fn new(archive_name: String, password_given: bool) -> Self {
+        let title = if password_given {
+            format!("Wrong password for {archive_name}")
+        } else {
+            format!("Password for {archive_name}")
+        };
+
+        let hints = Line::raw(" Enter: open | Esc: cancel ").centered();
+
+        let mut password = TextArea::default();
+        password.set_block(
+            Block::default()
+                .title(Line::raw(title))
+                .title_bottom(hints)
+                .border_set(symbols::border::ROUNDED)
+                .border_style(Style::default())
+                .borders(Borders::all()),
+        );
+        password.set_cursor_line_style(Style::default());
+        password.set_mask_char('*');
+
+        Self { archive_name, password }
+    }
+}
+
 impl Widget for &mut RestoreBackupView {
     fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
     where
         Self: Sized,
     {
-        let title = Line::raw("Restore backup");
+        if let Some(picker) = self.conflict_picker.as_mut() {
+            picker.render(area, buf);
+            return;
+        }
+
+        if let Some(prompt) = self.password_prompt.as_ref() {
+            let [password_area, _] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+            prompt.password.render(password_area, buf);
+            return;
+        }
+
+        let (title, items, metadata, list_state) = match self.file_picker.as_mut() {
+            Some(file_picker) => (
+                Line::raw(format!("Restore from {}", file_picker.archive_name)),
+                &file_picker.items,
+                None,
+                &mut file_picker.list_state,
+            ),
+            None => (
+                Line::raw("Restore backup"),
+                &self.items,
+                Some(&self.item_metadata),
+                &mut self.list_state,
+            ),
+        };
 
         let block = Block::new()
             .title(title)
@@ -110,14 +296,20 @@ impl Widget for &mut RestoreBackupView {
             .border_set(symbols::border::ROUNDED)
             .border_style(LIST_BORDER_COLOR);
 
-        let items: Vec<ListItem> = self
-            .items
+        let items: Vec<ListItem> = items
             .iter()
             .enumerate()
             .map(|(i, item)| {
                 let color = list_item_color(i);
 
-                ListItem::from(item.as_str()).bg(color)
+                match metadata.and_then(|m| m.get(i)).filter(|m| !m.is_empty()) {
+                    Some(metadata) => ListItem::from(Line::from(vec![
+                        Span::raw(item.as_str()),
+                        Span::raw(format!("  ({metadata})")).dim(),
+                    ])),
+                    None => ListItem::from(item.as_str()),
+                }
+                .bg(color)
             })
             .collect();
 
@@ -129,6 +321,6 @@ impl Widget for &mut RestoreBackupView {
 
         // We need to disambiguate this trait method as both `Widget` and `StatefulWidget` share the
         // same method name `render`.
-        StatefulWidget::render(list, area, buf, &mut self.list_state);
+        StatefulWidget::render(list, area, buf, list_state);
     }
 }
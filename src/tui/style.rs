@@ -1,5 +1,5 @@
 use ratatui::style::{
-    palette::tailwind::{BLACK, BLUE, GREEN, RED, SLATE},
+    palette::tailwind::{AMBER, BLACK, BLUE, GREEN, RED, SLATE, YELLOW},
     Color, Style,
 };
 
@@ -15,13 +15,18 @@ pub const LOG_BORDER_COLOR: Color = SLATE.c300;
 pub const MENU_HIGHLIGHT_STYLE: Style = Style::new().fg(GREEN.c600);
 
 pub const PROGRESS_BAR_STYLE: Color = BLUE.c600;
+pub const PROGRESS_BAR_DETAIL_STYLE: Color = BLUE.c800;
 pub const PROGRESS_BAR_BG_COLOR: Color = Color::Rgb(20, 20, 20);
 
 pub const FOOTER_AUTOBACKUP_ON_STYLE: Style = Style::new().bg(GREEN.c900);
 pub const FOOTER_AUTOBACKUP_OFF_STYLE: Style = Style::new().bg(RED.c900);
+pub const FOOTER_PENDING_STYLE: Style = Style::new().bg(AMBER.c900);
+pub const FOOTER_WARNING_STYLE: Style = Style::new().bg(RED.c900);
+pub const FOOTER_BACKUP_WARNINGS_STYLE: Style = Style::new().fg(BLACK).bg(YELLOW.c500);
+pub const FOOTER_CONFIRM_STYLE: Style = Style::new().bg(GREEN.c900);
 
 pub const fn list_item_color(i: usize) -> Color {
-    if i % 2 == 0 {
+    if i.is_multiple_of(2) {
         LIST_ITEM_BG
     } else {
         LIST_ITEM_ALT_BG
@@ -0,0 +1,88 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    style::Stylize,
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, StatefulWidget, Widget},
+};
+use time::OffsetDateTime;
+
+use crate::internal::format::format_duration;
+
+use super::{
+    link::EngineLink,
+    style::{list_item_color, LIST_BORDER_COLOR},
+};
+
+/// Watched save paths with their watcher backend and event counts, for diagnosing missed changes
+pub struct WatchesView {
+    engine_control: EngineLink,
+
+    list_state: ListState,
+
+    is_done: bool,
+}
+
+impl WatchesView {
+    pub fn new(engine_control: EngineLink) -> Self {
+        Self {
+            engine_control,
+            list_state: ListState::default(),
+            is_done: false,
+        }
+    }
+
+    pub fn on_key_event(&mut self, event: KeyEvent) {
+        match event.code {
+            KeyCode::Esc => self.is_done = true,
+            KeyCode::Down => self.list_state.scroll_down_by(1),
+            KeyCode::Up => self.list_state.scroll_up_by(1),
+            _ => {}
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.is_done
+    }
+}
+
+impl Widget for &mut WatchesView {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::new()
+            .title(Line::raw("Watches"))
+            .title_bottom(Line::raw(" Esc: back ").centered())
+            .borders(Borders::all())
+            .border_set(symbols::border::ROUNDED)
+            .border_style(LIST_BORDER_COLOR);
+
+        let now = OffsetDateTime::now_utc();
+
+        // Counts change while the view is open, so they are read on every render
+        let items: Vec<ListItem> = self
+            .engine_control
+            .watches()
+            .into_iter()
+            .enumerate()
+            .map(|(i, watch)| {
+                let last_event = match watch.last_event_at {
+                    Some(at) => format!("{} ago", format_duration((now - at).try_into().unwrap_or_default())),
+                    None => "never".to_owned(),
+                };
+
+                ListItem::from(vec![
+                    Line::raw(watch.path.display().to_string()),
+                    Line::raw(format!(
+                        "  {} | events: {} | ignored: {} | errors: {} | dropped: {} | last event: {last_event}",
+                        watch.backend, watch.events, watch.ignored, watch.errors, watch.dropped
+                    )),
+                ])
+                .bg(list_item_color(i))
+            })
+            .collect();
+
+        StatefulWidget::render(List::new(items).block(block), area, buf, &mut self.list_state);
+    }
+}